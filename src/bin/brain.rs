@@ -4,16 +4,20 @@ extern crate clap;
 extern crate brain;
 
 use std::process;
-use std::io::prelude::*;
+use std::iter::once;
+use std::io::{self, prelude::*, IsTerminal};
 use std::fs::File;
 use std::path::{Path, PathBuf};
 
-use clap::{Arg, App};
+use clap::{Arg, App, AppSettings, ArgMatches, SubCommand};
 
-use brain::parser::{Program, ParseError};
+use brain::parser::{Program, ParseError, Span};
+use brain::diagnostics::{Diagnostic, Level, ColorMode};
+use brain::operations::{Operations, Lint, Lints, LintLevel};
 use brain::operations::scope::ScopeStack;
-use brain::codegen::Instructions;
+use brain::codegen::{self, Instructions, LayoutStrategy};
 use brain::optimizations::{OptimizationLevel, Optimize};
+use brain::interpreter::{Interpreter, Config, CellWidth, OverflowBehaviour, EofBehaviour, TapeSize};
 use brain::prelude;
 
 macro_rules! exit_with_error(
@@ -24,11 +28,71 @@ macro_rules! exit_with_error(
 );
 
 fn main() {
-    let args = App::new(crate_name!())
+    let lint_names: Vec<_> = Lint::ALL.iter().map(|lint| lint.name()).chain(once(Lints::ALL_WARNINGS)).collect();
+
+    let args = app(&lint_names).get_matches();
+
+    if let Some(args) = args.subcommand_matches("run") {
+        let source_path = Path::new(args.value_of("input-file").unwrap());
+        let source = read_source(source_path);
+
+        let config = interpreter_config(args);
+        let layout = value_t!(args, "layout", LayoutStrategy).unwrap();
+
+        let instructions = if source_path.extension().is_some_and(|ext| ext == "bf") {
+            Instructions::from(source.as_str())
+        }
+        else {
+            let operations = compile_operations(source, source_path, color_mode(args), &lints(args), args.is_present("recursion"));
+            compile(operations, layout, config.cell_width, config.overflow)
+        };
+
+        let stdin = io::stdin();
+        let stdout = io::stdout();
+        let mut interpreter = Interpreter::with_config(config, stdin.lock(), stdout.lock());
+        interpreter.run(&instructions).unwrap_or_else(|e| {
+            exit_with_error!("Runtime Error: {}", e);
+        });
+
+        return;
+    }
+
+    let source_path = Path::new(args.value_of("input-file").unwrap());
+    let output_path = args.value_of("output-file").map_or_else(|| {
+        let mut path = PathBuf::from(source_path.file_name().and_then(|s| s.to_str()).unwrap_or(""));
+        path.set_extension("bf");
+        path
+    }, PathBuf::from);
+
+    let source = read_source(source_path);
+
+    let operations = compile_operations(source, source_path, color_mode(&args), &lints(&args), args.is_present("recursion"));
+    println!("{:#?}", operations);
+
+    let layout = value_t!(args, "layout", LayoutStrategy).unwrap();
+    let generated_code: String = compile(operations, layout, CellWidth::default(), OverflowBehaviour::default()).into();
+    println!("{:?}", generated_code);
+
+    let mut output_file = File::create(output_path).unwrap_or_else(|e| {
+        exit_with_error!("Could not create target file: {}", e);
+    });
+    output_file.write_all(generated_code.as_bytes()).and_then(|_| {
+        // Write a newline because that's how a line is defined
+        // http://stackoverflow.com/a/729795/551904
+        output_file.write(b"\n")
+    }).unwrap_or_else(|e| {
+        exit_with_error!("Could not write target file: {}", e);
+    });
+}
+
+/// The command line interface, which uses the given names for the lint arguments
+fn app<'a>(lint_names: &'a [&'a str]) -> App<'a, 'a> {
+    App::new(crate_name!())
         .version(crate_version!())
         .version_short("v")
         .author(crate_authors!())
         .about(crate_description!())
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(Arg::with_name("input-file")
             .help("The brain file to process")
             .value_name("file")
@@ -41,19 +105,153 @@ fn main() {
             .value_name("target")
             .takes_value(true)
         )
-        .get_matches();
+        .arg(Arg::with_name("layout")
+            .long("layout")
+            .help("How cells are chosen for each variable and temporary value")
+            .value_name("strategy")
+            .takes_value(true)
+            .possible_values(&["incremental", "whole-program"])
+            .default_value("incremental")
+        )
+        .arg(Arg::with_name("color")
+            .long("color")
+            .help("Whether errors are printed in colour")
+            .value_name("when")
+            .takes_value(true)
+            .possible_values(&["auto", "always", "never"])
+            .default_value("auto")
+        )
+        .arg(Arg::with_name("recursion")
+            .long("recursion")
+            .help("Allow functions to call themselves by keeping a call stack on the tape (slower)")
+        )
+        .args(&lint_args(lint_names))
+        .subcommand(SubCommand::with_name("run")
+            .about("Compiles and immediately runs a brain file using the built-in brainfuck interpreter")
+            .arg(Arg::with_name("input-file")
+                .help("The brain file to run (.bf files are run directly without compiling)")
+                .value_name("file")
+                .takes_value(true)
+                .required(true)
+            )
+            .arg(Arg::with_name("layout")
+                .long("layout")
+                .help("How cells are chosen for each variable and temporary value")
+                .value_name("strategy")
+                .takes_value(true)
+                .possible_values(&["incremental", "whole-program"])
+                .default_value("incremental")
+            )
+            .arg(Arg::with_name("color")
+                .long("color")
+                .help("Whether errors are printed in colour")
+                .value_name("when")
+                .takes_value(true)
+                .possible_values(&["auto", "always", "never"])
+                .default_value("auto")
+            )
+            .arg(Arg::with_name("recursion")
+                .long("recursion")
+                .help("Allow functions to call themselves by keeping a call stack on the tape (slower)")
+            )
+            .args(&lint_args(lint_names))
+            .arg(Arg::with_name("cell-width")
+                .long("cell-width")
+                .help("The number of bits in each cell of the tape")
+                .value_name("bits")
+                .takes_value(true)
+                .possible_values(&["8", "16", "32"])
+                .default_value("8")
+            )
+            .arg(Arg::with_name("overflow")
+                .long("overflow")
+                .help("Whether cells wrap around or stop the program when they overflow")
+                .value_name("behaviour")
+                .takes_value(true)
+                .possible_values(&["wrap", "error"])
+                .default_value("wrap")
+            )
+            .arg(Arg::with_name("eof")
+                .long("eof")
+                .help("What to store in the current cell when reading after the end of input")
+                .value_name("behaviour")
+                .takes_value(true)
+                .possible_values(&["unchanged", "zero", "max"])
+                .default_value("unchanged")
+            )
+            .arg(Arg::with_name("tape-size")
+                .long("tape-size")
+                .help("The number of cells on the tape (unbounded if not specified)")
+                .value_name("cells")
+                .takes_value(true)
+            )
+        )
+}
 
-    let source_path = Path::new(args.value_of("input-file").unwrap());
+/// The arguments that set the level of each lint
+fn lint_args<'a>(lint_names: &'a [&'a str]) -> Vec<Arg<'a, 'a>> {
+    let lint_arg = |name, short, help| Arg::with_name(name)
+        .short(short)
+        .long(name)
+        .help(help)
+        .value_name("lint")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .possible_values(lint_names);
+
+    vec![
+        lint_arg("allow", "A", "Do not report the given lint (`warnings` for every lint)"),
+        lint_arg("warn", "W", "Report the given lint as a warning (`warnings` for every lint)"),
+        lint_arg("deny", "D", "Report the given lint as an error (`warnings` for every lint)"),
+    ]
+}
+
+/// Builds the level of every lint from the arguments
+/// Arguments are applied in the order they were given so a later one overrides an earlier one,
+/// e.g. `-D warnings -A unused_mut` denies every lint except `unused_mut`
+fn lints(args: &ArgMatches) -> Lints {
+    let mut levels = Vec::new();
+    for &(arg, level) in &[("allow", LintLevel::Allow), ("warn", LintLevel::Warn), ("deny", LintLevel::Deny)] {
+        if let (Some(indices), Some(names)) = (args.indices_of(arg), args.values_of(arg)) {
+            levels.extend(indices.zip(names).map(|(index, name)| (index, name, level)));
+        }
+    }
+    levels.sort_by_key(|&(index, _, _)| index);
+
+    let mut lints = Lints::new();
+    for (_, name, level) in levels {
+        // clap has already checked these against their possible values
+        let known = lints.set(name, level);
+        debug_assert!(known, "unknown lint: {}", name);
+    }
+    lints
+}
+
+/// Builds the interpreter configuration from the arguments of the run subcommand
+fn interpreter_config(args: &ArgMatches) -> Config {
+    // clap has already checked these against their possible values
+    let cell_width = value_t!(args, "cell-width", CellWidth).unwrap();
+    let overflow = value_t!(args, "overflow", OverflowBehaviour).unwrap();
+    let eof = value_t!(args, "eof", EofBehaviour).unwrap();
+
+    let tape_size = match args.value_of("tape-size") {
+        Some(size) => match size.parse() {
+            Ok(size) if size > 0 => TapeSize::Bounded(size),
+            _ => exit_with_error!("Invalid tape size: '{}'", size),
+        },
+        None => TapeSize::Unbounded,
+    };
+
+    Config {cell_width, overflow, eof, tape_size}
+}
+
+/// Reads the entire contents of the given source file
+fn read_source(source_path: &Path) -> String {
     if !source_path.exists() || !source_path.is_file() {
         exit_with_error!("Not a valid file: '{}'", source_path.display());
     }
 
-    let output_path = args.value_of("output-file").map_or_else(|| {
-        let mut path = PathBuf::from(source_path.file_name().and_then(|s| s.to_str()).unwrap_or(""));
-        path.set_extension("bf");
-        path
-    }, |s| PathBuf::from(s));
-
     let mut source_file = File::open(source_path).unwrap_or_else(|e| {
         exit_with_error!("Could not open source file: {}", e);
     });
@@ -62,37 +260,152 @@ fn main() {
         exit_with_error!("Could not read source file: {}", e);
     });
 
-    let generated_code: String = compile(source);
-    println!("{:?}", generated_code);
+    source
+}
 
-    let mut output_file = File::create(output_path).unwrap_or_else(|e| {
-        exit_with_error!("Could not create target file: {}", e);
-    });
-    output_file.write_all(generated_code.as_bytes()).and_then(|_| {
-        // Write a newline because that's how a line is defined
-        // http://stackoverflow.com/a/729795/551904
-        output_file.write(&['\n' as u8])
-    }).unwrap_or_else(|e| {
-        exit_with_error!("Could not write target file: {}", e);
-    });
+/// Returns whether errors should be printed in colour
+fn color_mode(args: &ArgMatches) -> ColorMode {
+    match args.value_of("color") {
+        Some("always") => ColorMode::Colored,
+        Some("never") => ColorMode::Plain,
+        _ if io::stderr().is_terminal() => ColorMode::Colored,
+        _ => ColorMode::Plain,
+    }
 }
 
-/// Compile the provided source code to brainfuck
-fn compile(source: String) -> String {
-    let program: Program = source.parse().unwrap_or_else(|e: ParseError| {
-        exit_with_error!("Syntax Error: {}", e);
+/// Parse and type check the provided source code
+fn compile_operations(source: String, source_path: &Path, color: ColorMode, lints: &Lints, recursion: bool) -> Operations {
+    let path = source_path.display().to_string();
+
+    let program: Program = source.parse().unwrap_or_else(|errors: Vec<ParseError>| {
+        let diagnostics = errors.iter().map(|error| error.diagnostic()).collect();
+        exit_with_diagnostics(diagnostics, &path, &source, color);
     });
 
     let mut global_scope = ScopeStack::new();
     prelude::populate_scope(&mut global_scope);
-    let operations = program.into_operations(&mut global_scope).unwrap_or_else(|error| {
-        println!("{:?}", error);
-        // Print the error properly
-        unimplemented!();
-    });
-    println!("{:#?}", operations);
+    if recursion {
+        global_scope.enable_recursion();
+    }
+    let (operations, errors) = match program.into_operations(&mut global_scope) {
+        Ok(operations) => (Some(operations), Vec::new()),
+        Err(errors) => (None, errors.iter().map(|error| error.diagnostic()).collect()),
+    };
+
+    // Denied lints turn warnings into errors
+    let mut diagnostics = lints.diagnostics(&global_scope.take_warnings());
+    diagnostics.extend(errors);
+    // Diagnostics are printed in the order of the code that caused them
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+
+    match operations {
+        Some(operations) if diagnostics.iter().all(|diagnostic| diagnostic.level == Level::Warning) => {
+            print_warnings(&diagnostics, &path, &source, color);
+            operations
+        },
+        _ => exit_with_diagnostics(diagnostics, &path, &source, color),
+    }
+}
+
+/// Prints every warning followed by how many there were
+fn print_warnings(warnings: &[Diagnostic], path: &str, source: &str, color: ColorMode) {
+    if warnings.is_empty() {
+        return;
+    }
+
+    for warning in warnings {
+        eprintln!("{}", warning.render(path, source, color));
+    }
+    let emitted = Diagnostic::warning(format!("{} emitted", count(warnings.len(), "warning")), Span::default());
+    eprintln!("{}", emitted.render(path, source, color));
+}
+
+/// Prints every diagnostic and then exits
+fn exit_with_diagnostics(diagnostics: Vec<Diagnostic>, path: &str, source: &str, color: ColorMode) -> ! {
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic.render(path, source, color));
+    }
+
+    let errors = diagnostics.iter().filter(|diagnostic| diagnostic.level == Level::Error).count();
+    let warnings = diagnostics.len() - errors;
+
+    let mut message = if errors == 1 {
+        "aborting due to previous error".to_owned()
+    }
+    else {
+        format!("aborting due to {} previous errors", errors)
+    };
+    if warnings > 0 {
+        message.push_str(&format!("; {} emitted", count(warnings, "warning")));
+    }
+    let aborting = Diagnostic::error(message, Span::default());
+    exit_with_error!("{}", aborting.render(path, source, color));
+}
+
+/// Returns the given count followed by the given noun, made plural if necessary
+fn count(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    }
+    else {
+        format!("{} {}s", count, noun)
+    }
+}
 
-    let instructions: Instructions = operations.into();
+/// Compile the provided operations to brainfuck for cells of the given width and overflow
+/// behaviour using the given memory layout strategy
+fn compile(operations: Operations, layout: LayoutStrategy, cell_width: CellWidth, overflow: OverflowBehaviour) -> Instructions {
+    let level = OptimizationLevel::L1;
+    let ops = codegen::generate(operations, layout).optimize_for(level, cell_width, overflow);
+    let instructions: Instructions = ops.into();
 
-    instructions.optimize(OptimizationLevel::L1).into()
+    instructions.optimize(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compiles the given source and runs it the way `brain run` does with the given config
+    fn run(source: &str, config: Config) -> String {
+        let program: Program = source.parse().unwrap();
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        let operations = program.into_operations(&mut scope).unwrap();
+        let instructions = compile(operations, LayoutStrategy::Incremental, config.cell_width, config.overflow);
+
+        let mut interpreter = Interpreter::with_config(config, io::empty(), Vec::new());
+        interpreter.run(&instructions).unwrap();
+        String::from_utf8(interpreter.into_inner().1).unwrap()
+    }
+
+    /// Returns the lints set by the given command line arguments
+    fn lints_from(args: &[&str]) -> Lints {
+        let lint_names: Vec<_> = Lint::ALL.iter().map(|lint| lint.name()).chain(once(Lints::ALL_WARNINGS)).collect();
+        let args = app(&lint_names).get_matches_from(once("brain").chain(args.iter().cloned()).chain(once("f.brn")));
+        lints(&args)
+    }
+
+    #[test]
+    fn later_lint_arguments_override_earlier_ones() {
+        let lints = lints_from(&["-D", "warnings", "-A", "unused_mut"]);
+        assert_eq!(lints.level(Lint::UnusedMut), LintLevel::Allow);
+        assert_eq!(lints.level(Lint::UnusedVariables), LintLevel::Deny);
+
+        let lints = lints_from(&["-A", "unused_mut", "-D", "warnings"]);
+        assert_eq!(lints.level(Lint::UnusedMut), LintLevel::Deny);
+
+        let lints = lints_from(&["--warn", "no_effect", "-A", "warnings", "-D", "unused_mut", "-W", "unused_mut"]);
+        assert_eq!(lints.level(Lint::NoEffect), LintLevel::Allow);
+        assert_eq!(lints.level(Lint::UnusedMut), LintLevel::Warn);
+    }
+
+    #[test]
+    fn runs_large_constants_when_overflowing_is_an_error() {
+        let source = "let x: u8 = 200; stdout.println(x - 195); let y: u16 = 300; stdout.println(y);";
+        for &overflow in &[OverflowBehaviour::Wrap, OverflowBehaviour::Error] {
+            let config = Config {overflow, ..Config::default()};
+            assert_eq!(run(source, config), "5\n300\n");
+        }
+    }
 }
@@ -0,0 +1,492 @@
+use std::iter::once;
+
+use memory::{MemoryLayout, MemoryBlock, MemSize, CellIndex};
+use operations::{Operation, Operations};
+use ir::{Ops, Op, Offset};
+
+use super::{Instructions, Instruction};
+use super::layout::{LayoutStrategy, incremental_layout, planned_layout, loop_variables};
+
+impl From<Operations> for Ops {
+    fn from(ops: Operations) -> Ops {
+        generate(ops, LayoutStrategy::default())
+    }
+}
+
+/// Generates the IR for the given operations using the given strategy to lay out memory
+pub fn generate(ops: Operations, strategy: LayoutStrategy) -> Ops {
+    match strategy {
+        LayoutStrategy::Incremental => {
+            let layout = incremental_layout(&ops);
+            generate_with(ops, layout)
+        },
+        LayoutStrategy::WholeProgram => {
+            let incremental = generate_with(ops.clone(), incremental_layout(&ops));
+            let planned = {
+                let layout = planned_layout(&ops);
+                generate_with(ops, layout)
+            };
+
+            // The planned layout is a heuristic, so it does not always win
+            if moves(&planned) < moves(&incremental) {
+                planned
+            }
+            else {
+                incremental
+            }
+        },
+    }
+}
+
+pub fn generate_with(ops: Operations, mut layout: MemoryLayout) -> Ops {
+    // The call stack goes after every cell used by anything else, which is only known once the
+    // program has been generated. Where the stack is has no effect on the layout itself.
+    let stack = if uses_call_stack(&ops) {
+        let mut trial = layout.clone();
+        into_ir(ops.clone(), &mut trial, &mut 0, 0);
+        trial.peak_size()
+    }
+    else {
+        0
+    };
+
+    let mut current_cell = 0;
+    into_ir(ops, &mut layout, &mut current_cell, stack)
+}
+
+/// Returns true if the given operations push or pop anything on the call stack
+fn uses_call_stack(ops: &Operations) -> bool {
+    ops.iter().any(|op| match *op {
+        Operation::Push {..} | Operation::Pop {..} => true,
+        Operation::Block {ref body} | Operation::TempAllocate {ref body, ..} | Operation::Loop {ref body, ..} => {
+            uses_call_stack(body)
+        },
+        Operation::Branch {ref if_body, ref else_body, ..} => {
+            uses_call_stack(if_body) || uses_call_stack(else_body)
+        },
+        _ => false,
+    })
+}
+
+/// Returns the number of `<` and `>` instructions needed for the given IR
+fn moves(ops: &Ops) -> usize {
+    Instructions::from(ops).iter()
+        .filter(|&&instr| instr == Instruction::Left || instr == Instruction::Right)
+        .count()
+}
+
+/// Generates the IR for the given operations
+///
+/// `current_cell` is the position of the pointer on the tape. Cells are accessed using their
+/// offset from this position and the pointer is only moved when a loop requires it to be
+/// somewhere in particular. `stack` is the position of the start of the call stack.
+fn into_ir(
+    ops: Operations,
+    layout: &mut MemoryLayout,
+    current_cell: &mut CellIndex,
+    stack: CellIndex,
+) -> Ops {
+    use self::Operation::*;
+    ops.into_iter().flat_map(|op| match op {
+        Block {body} => into_ir(body, layout, current_cell, stack),
+        TempAllocate {temp, body, should_zero} => {
+            let mut ops = into_ir(body, layout, current_cell, stack);
+            // The temporary must be zeroed before it is removed or else it would be laid out
+            // again somewhere else
+            if should_zero {
+                ops.extend(zero(*current_cell, layout, temp));
+            }
+            layout.remove(&temp);
+            ops
+        },
+        Increment {target, amount} => {
+            once(Op::Add(offset(*current_cell, layout.position(&target)), amount as i32)).collect()
+        },
+        Decrement {target, amount} => {
+            once(Op::Add(offset(*current_cell, layout.position(&target)), -(amount as i32))).collect()
+        },
+        Read {target} => {
+            let position = layout.position(&target.position());
+            (0..target.size()).map(|i| Op::Read(offset(*current_cell, position + i))).collect()
+        },
+        Write {target} => {
+            let position = layout.position(&target.position());
+            (0..target.size()).map(|i| Op::Write(offset(*current_cell, position + i))).collect()
+        },
+        Zero {target} => zero(*current_cell, layout, target),
+        Branch {cond, if_body, else_body} => {
+            // Algorithm from: https://esolangs.org/wiki/Brainfuck_algorithms#if_.28x.29_.7B_code1_.7D_else_.7B_code2_.7D
+            //
+            // temp0 and temp1 are consecutive in memory following cond
+            // temp0[-]+
+            // temp1[-]
+            // cond[
+            //  if_block
+            //  cond>-]>
+            // [<
+            //  else_block
+            //  cond>->]
+            //
+            // Three consecutive temporary cells:
+            // 1. cond - the boolean result of the condition expression
+            // 2. temp0 - used to go into the else block when necessary
+            // 3. temp1 - used *not* to go into the else block when necessary
+            //
+            // The basic idea of the algorithm is that you can control which of two adjacent loops run
+            // by "sending" them either temp0 or temp1 based on the condition result
+            //
+            // Since the pointer ends up in a different place depending on which loop ran, the
+            // moves that depend on that are emitted without updating current_cell. Both paths
+            // end up back at cond.
+            //
+            // If the cells after cond are not free, the layout moves cond somewhere else for the
+            // duration of the branch and its value has to be moved there and back again.
+            let original = layout.position(&cond.position());
+            let size = cond.size();
+            layout.consecutive(&cond, 2, |layout, cond, temp| {
+                let mut ops = Ops::new();
+                if cond != original {
+                    ops.extend(relocate(current_cell, original, cond, size));
+                }
+                ops.push(Op::Add(offset(*current_cell, temp.position()), 1));
+
+                ops.extend(move_to(current_cell, cond));
+                let mut if_loop = into_ir(if_body, layout, current_cell, stack);
+                if_loop.extend(move_to(current_cell, cond));
+                if_loop.push(Op::Add(1, -1));
+                if_loop.push(Op::Move(1));
+                ops.push(Op::Loop(if_loop));
+
+                ops.push(Op::Move(1));
+
+                let mut else_loop = Ops::new();
+                else_loop.push(Op::Move(-1));
+                else_loop.extend(into_ir(else_body, layout, current_cell, stack));
+                else_loop.extend(move_to(current_cell, cond));
+                else_loop.push(Op::Add(1, -1));
+                else_loop.push(Op::Move(2));
+                ops.push(Op::Loop(else_loop));
+
+                ops.push(Op::Move(-2));
+
+                if cond != original {
+                    ops.extend(relocate(current_cell, cond, original, size));
+                }
+
+                ops
+            })
+        },
+        Loop {cond, body} => {
+            for mem in loop_variables(&body) {
+                layout.get(&mem);
+            }
+
+            let cond = layout.position(&cond);
+            let mut ops: Ops = move_to(current_cell, cond).into_iter().collect();
+
+            let mut body = into_ir(body, layout, current_cell, stack);
+            body.extend(move_to(current_cell, cond));
+            ops.push(Op::Loop(body));
+
+            ops
+        },
+        Copy {source, target, size} => {
+            // Copying to the same position is a no-op
+            if source == target {
+                return Ops::new();
+            }
+
+            debug_assert!(source.associated_memory().size() - source.offset() >= size);
+            debug_assert!(target.associated_memory().size() - target.offset() >= size);
+
+            // Algorithm for copying cells:
+            // 1. In a loop, decrement the source cell and increment both the target cell and a
+            //    temporary cell until the source cell is zero
+            // 2. In a loop, decrement the temporary cell and increment the source cell until the
+            //    temporary cell is zero
+            // 3. Repeat these steps for each cell to be copied
+
+            let source = layout.position(&source);
+            let target = layout.position(&target);
+            layout.temporary(1, |temp| (0..size).flat_map(|i| {
+                let mut ops: Ops = move_to(current_cell, source + i).into_iter().collect();
+                // Fill the target and the temporary with the value of the source cell
+                ops.push(Op::Loop(vec![
+                    Op::Add(offset(*current_cell, target + i), 1),
+                    Op::Add(offset(*current_cell, temp.position()), 1),
+                    Op::Add(0, -1),
+                ].into()));
+
+                // Refill the source cell with the temporary
+                ops.extend(move_to(current_cell, temp.position()));
+                ops.push(Op::Loop(vec![
+                    Op::Add(offset(*current_cell, source + i), 1),
+                    Op::Add(0, -1),
+                ].into()));
+
+                ops
+            }).collect())
+        },
+        Relocate {source, target} => {
+            debug_assert!(source.size() == target.size());
+            let size = source.size();
+
+            let source = layout.position(&source.position());
+            let target = layout.position(&target.position());
+
+            relocate(current_cell, source, target, size)
+        },
+        Push {source} => {
+            let source = layout.position(&source);
+            push(current_cell, stack, source)
+        },
+        Pop {target} => {
+            let target = layout.position(&target);
+            pop(current_cell, stack, target)
+        },
+        Recurse {..} => unreachable!("Recursive calls are replaced by a dispatch loop before code generation"),
+    }).collect()
+}
+
+// The call stack starts with a cell that is always zero. Each entry after it is a pair of cells:
+// a marker that is 1 while the entry is in use followed by the value of the entry. Since the
+// markers are two cells apart, `[>>]` finds the first free entry and `[<<]` finds the way back
+// to the start no matter how many entries there are. Values are carried between the stack and
+// the rest of the tape one at a time, which is slow but works for any depth.
+
+/// Moves the value of the source cell onto a new entry at the top of the call stack
+fn push(current_cell: &mut CellIndex, stack: CellIndex, source: CellIndex) -> Ops {
+    let mut ops: Ops = move_to(current_cell, stack).into_iter().collect();
+    // Mark the first free entry as used, then go back to the start
+    ops.push(Op::Move(2));
+    ops.push(Op::Loop(vec![Op::Move(2)].into()));
+    ops.push(Op::Add(0, 1));
+    ops.push(Op::Loop(vec![Op::Move(-2)].into()));
+
+    ops.extend(move_to(current_cell, source));
+    ops.push(Op::Loop(vec![
+        Op::Add(0, -1),
+        // The new entry is the one right before the first free entry
+        Op::Move(offset(source, stack) + 2),
+        Op::Loop(vec![Op::Move(2)].into()),
+        Op::Add(-1, 1),
+        Op::Move(-2),
+        Op::Loop(vec![Op::Move(-2)].into()),
+        Op::Move(offset(stack, source)),
+    ].into()));
+
+    ops
+}
+
+/// Moves the value of the entry at the top of the call stack into the target cell and removes
+/// that entry
+fn pop(current_cell: &mut CellIndex, stack: CellIndex, target: CellIndex) -> Ops {
+    let to_top = || vec![Op::Move(2), Op::Loop(vec![Op::Move(2)].into()), Op::Move(-1)];
+    let to_start = || vec![Op::Move(-1), Op::Loop(vec![Op::Move(-2)].into())];
+
+    let mut ops: Ops = move_to(current_cell, stack).into_iter().collect();
+    ops.extend(to_top());
+
+    let mut body = vec![Op::Add(0, -1)];
+    body.extend(to_start());
+    body.push(Op::Add(offset(stack, target), 1));
+    body.extend(to_top());
+    ops.push(Op::Loop(body.into()));
+
+    // Clear the marker of the entry, then go back to the start from the entry before it
+    ops.push(Op::Add(-1, -1));
+    ops.push(Op::Move(-2));
+    ops.extend(to_start());
+
+    ops
+}
+
+/// Moves the values of `size` cells starting at source to the cells starting at target, leaving
+/// only zeros at source
+fn relocate(current_cell: &mut CellIndex, source: CellIndex, target: CellIndex, size: MemSize) -> Ops {
+    (0..size).flat_map(|i| {
+        let mut ops: Ops = move_to(current_cell, source + i).into_iter().collect();
+        ops.push(Op::Loop(vec![
+            Op::Add(offset(*current_cell, target + i), 1),
+            Op::Add(0, -1),
+        ].into()));
+
+        ops
+    }).collect()
+}
+
+fn zero(current_cell: CellIndex, layout: &mut MemoryLayout, target: MemoryBlock) -> Ops {
+    let position = layout.position(&target.position());
+    (0..target.size()).map(|i| Op::SetZero(offset(current_cell, position + i))).collect()
+}
+
+/// Returns the offset of the target cell from the current cell
+fn offset(current_cell: CellIndex, target: CellIndex) -> Offset {
+    target as Offset - current_cell as Offset
+}
+
+/// Moves the pointer to the target cell if it isn't there already
+fn move_to(current_cell: &mut CellIndex, target: CellIndex) -> Option<Op> {
+    let distance = offset(*current_cell, target);
+    *current_cell = target;
+
+    if distance == 0 {
+        None
+    }
+    else {
+        Some(Op::Move(distance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use memory::StaticAllocator;
+    use codegen::testing::assert_output;
+
+    #[test]
+    fn branches_in_loops() {
+        assert_output(r#"
+            let mut a: u8 = 0;
+            let one: u8 = 1;
+            let mut running: bool = true;
+            while running {
+                stdout.print(b"<");
+                if a == one {
+                    stdout.print(b"y");
+                    running = false;
+                }
+                else {
+                    stdout.print(b"z");
+                    a = 1;
+                }
+            }
+        "#, b"", b"<z<y");
+    }
+
+    #[test]
+    fn nested_branches_in_loops() {
+        assert_output(r#"
+            let mut a: u8 = 0;
+            let mut b: u8 = 1;
+            let two: u8 = 2;
+            let one: u8 = 1;
+            let mut running: bool = true;
+            while running {
+                stdout.print(b"<");
+                if a == one {
+                    if b == two {
+                        stdout.print(b"x");
+                    }
+                    else {
+                        stdout.print(b"y");
+                        b = 2;
+                    }
+                }
+                else {
+                    stdout.print(b"z");
+                    a = 1;
+                    if b == one {
+                        stdout.print(b"w");
+                    }
+                }
+                let finished: bool = b == two;
+                if finished {
+                    running = false;
+                }
+            }
+        "#, b"", b"<zw<y");
+    }
+
+    #[test]
+    fn branches_on_variables_declared_earlier() {
+        // The conditions are laid out long before the branches, so other variables are placed
+        // right after them
+        assert_output(r#"
+            let mut first: bool = true;
+            let mut second: bool = false;
+            let mut third: bool = true;
+            let mut step1: bool = true;
+            let mut step2: bool = true;
+            let mut running: bool = true;
+            while running {
+                if first {
+                    stdout.print(b"a");
+                    if second {
+                        stdout.print(b"b");
+                        first = false;
+                    }
+                    else if third {
+                        stdout.print(b"c");
+                        second = true;
+                    }
+                    else {
+                        stdout.print(b"d");
+                    }
+                }
+                else {
+                    stdout.print(b"e");
+                    first = third;
+                }
+
+                if step1 {
+                    step1 = false;
+                }
+                else if step2 {
+                    step2 = false;
+                }
+                else {
+                    running = false;
+                }
+                third = false;
+            }
+        "#, b"", b"acabe");
+    }
+
+    #[test]
+    fn branches_on_input_in_loops() {
+        assert_output(r#"
+            let mut c: [u8; 1];
+            stdin.read_exact(c);
+            let mut x: bool = true;
+            let mut running: bool = true;
+            while running {
+                if x {
+                    stdout.print(c);
+                    x = false;
+                }
+                else {
+                    stdout.print(b"-");
+                    running = false;
+                }
+            }
+        "#, b"q", b"q-");
+    }
+
+    #[test]
+    fn whole_program_layout_reduces_moves() {
+        let mut allocator = StaticAllocator::new();
+        let a = allocator.allocate(1);
+        let padding = allocator.allocate(5);
+        let b = allocator.allocate(1);
+
+        // a and b are only used together, but incrementally they end up on either side of padding
+        let ops = vec![
+            Operation::Read {target: a},
+            Operation::Read {target: padding},
+            Operation::Read {target: b},
+            Operation::Loop {cond: a.position(), body: vec![
+                Operation::Decrement {target: a.position(), amount: 1},
+                Operation::Increment {target: b.position(), amount: 1},
+            ]},
+            Operation::Write {target: b},
+            Operation::Write {target: padding},
+        ];
+
+        let incremental = generate(ops.clone(), LayoutStrategy::Incremental);
+        let planned = generate(ops, LayoutStrategy::WholeProgram);
+        assert!(moves(&planned) < moves(&incremental),
+            "{} moves is not fewer than {}", moves(&planned), moves(&incremental));
+    }
+}
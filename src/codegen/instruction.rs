@@ -1,9 +1,9 @@
 use std::fmt;
-use std::iter::{once, repeat, empty, FromIterator};
+use std::iter::FromIterator;
 use std::ops::Index;
 
-use memory::{MemoryLayout, MemoryBlock, CellIndex, MemSize};
-use operations::{Operation, Operations};
+use operations::Operations;
+use ir::Ops;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Instructions(Vec<Instruction>);
@@ -13,11 +13,15 @@ impl Instructions {
         self.0.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn last(&self) -> Option<&Instruction> {
         self.0.last()
     }
 
-    pub fn iter(&self) -> ::std::slice::Iter<Instruction> {
+    pub fn iter(&self) -> ::std::slice::Iter<'_, Instruction> {
         self.0.iter()
     }
 
@@ -67,6 +71,14 @@ impl FromIterator<Instruction> for String {
     }
 }
 
+impl<'a> From<&'a str> for Instructions {
+    /// Parses raw brainfuck source code
+    /// Any characters that are not brainfuck instructions are treated as comments and ignored
+    fn from(source: &'a str) -> Instructions {
+        source.chars().filter_map(Instruction::from_char).collect()
+    }
+}
+
 impl From<Instructions> for String {
     fn from(instrs: Instructions) -> String {
         instrs.into_iter().collect()
@@ -93,6 +105,24 @@ pub enum Instruction {
     JumpBackwardUnlessZero,
 }
 
+impl Instruction {
+    /// Returns the instruction represented by the given brainfuck character, if any
+    pub fn from_char(c: char) -> Option<Instruction> {
+        use self::Instruction::*;
+        Some(match c {
+            '>' => Right,
+            '<' => Left,
+            '+' => Increment,
+            '-' => Decrement,
+            '.' => Write,
+            ',' => Read,
+            '[' => JumpForwardIfZero,
+            ']' => JumpBackwardUnlessZero,
+            _ => return None,
+        })
+    }
+}
+
 impl fmt::Display for Instruction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use self::Instruction::*;
@@ -111,203 +141,6 @@ impl fmt::Display for Instruction {
 
 impl From<Operations> for Instructions {
     fn from(ops: Operations) -> Instructions {
-        let mut current_cell = 0;
-        let mut layout = MemoryLayout::new();
-
-        into_instructions_index(ops, &mut layout, &mut current_cell)
+        Ops::from(ops).into()
     }
 }
-
-fn into_instructions_index(
-    ops: Operations,
-    layout: &mut MemoryLayout,
-    current_cell: &mut CellIndex,
-) -> Instructions {
-    use self::Operation::*;
-    ops.into_iter().flat_map(|op| match op {
-        Block {body} => into_instructions_index(body, layout, current_cell),
-        TempAllocate {temp, body, should_zero} => {
-            let instrs = into_instructions_index(body, layout, current_cell);
-            layout.remove(&temp);
-            if should_zero {
-                instrs.into_iter().chain(zero(current_cell, layout, temp)).collect()
-            }
-            else {
-                instrs
-            }
-        },
-        Increment {target, amount} => {
-            move_to(current_cell, layout.position(&target)).into_iter()
-                .chain(repeat(Instruction::Increment).take(amount as usize)).collect()
-        },
-        Decrement {target, amount} => {
-            move_to(current_cell, layout.position(&target)).into_iter()
-                .chain(repeat(Instruction::Decrement).take(amount as usize)).collect()
-        },
-        Read {target} => {
-            move_to(current_cell, layout.position(&target.position())).into_iter()
-                .chain(consecutive(vec![Instruction::Read], current_cell, target.size())).collect()
-        },
-        Write {target} => {
-            move_to(current_cell, layout.position(&target.position())).into_iter()
-                .chain(consecutive(vec![Instruction::Write], current_cell, target.size())).collect()
-        },
-        Zero {target} => zero(current_cell, layout, target),
-        Branch {cond, if_body, else_body} => {
-            // Algorithm from: https://esolangs.org/wiki/Brainfuck_algorithms#if_.28x.29_.7B_code1_.7D_else_.7B_code2_.7D
-            //
-            // temp0 and temp1 are consecutive in memory following cond
-            // temp0[-]+
-            // temp1[-]
-            // cond[
-            //  if_block
-            //  cond>-]>
-            // [<
-            //  else_block
-            //  cond>->]
-            //
-            // Three consecutive temporary cells:
-            // 1. cond - the boolean result of the condition expression
-            // 2. temp0 - used to go into the else block when necessary
-            // 3. temp1 - used *not* to go into the else block when necessary
-            //
-            // The basic idea of the algorithm is that you can control which of two adjacent loops run
-            // by "sending" them either temp0 or temp1 based on the condition result
-            layout.consecutive(&cond, 2, |layout, cond, temp| {
-                move_to(current_cell, temp.position()).into_iter()
-                    .chain(once(Instruction::Increment))
-
-                    .chain(move_to(current_cell, cond))
-                    .chain(once(Instruction::JumpForwardIfZero))
-
-                    .chain(into_instructions_index(if_body, layout, current_cell))
-                    .chain(move_to(current_cell, cond))
-                    .chain(once(Instruction::Right))
-                    .chain(once(Instruction::Decrement))
-
-                    .chain(once(Instruction::JumpBackwardUnlessZero))
-
-                    .chain(once(Instruction::Right))
-
-                    .chain(once(Instruction::JumpForwardIfZero))
-                    .chain(once(Instruction::Left))
-
-                    .chain(into_instructions_index(else_body, layout, current_cell))
-                    .chain(move_to(current_cell, cond))
-                    .chain(once(Instruction::Right))
-                    .chain(once(Instruction::Decrement))
-                    .chain(once(Instruction::Right))
-
-                    .chain(once(Instruction::JumpBackwardUnlessZero))
-                    .chain(once(Instruction::Left))
-                    .chain(once(Instruction::Left))
-
-                    .collect()
-            })
-        },
-        Loop {cond, body} => {
-            move_to(current_cell, layout.position(&cond)).into_iter()
-                .chain(once(Instruction::JumpForwardIfZero))
-                .chain(into_instructions_index(body, layout, current_cell))
-                .chain(move_to(current_cell, layout.position(&cond)))
-                .chain(once(Instruction::JumpBackwardUnlessZero))
-                .collect()
-        },
-        Copy {source, target, size} => {
-            // Copying to the same position is a no-op
-            if source == target {
-                return empty().collect();
-            }
-
-            debug_assert!(source.associated_memory().size() - source.offset() == size);
-            debug_assert!(target.associated_memory().size() - target.offset() == size);
-
-            // Algorithm for copying cells:
-            // 1. In a loop, decrement the source cell and increment both the target cell and a
-            //    temporary cell until the source cell is zero
-            // 2. In a loop, decrement the temporary cell and increment the source cell until the
-            //    temporary cell is zero
-            // 3. Repeat these steps for each cell to be copied
-
-            let source = layout.position(&source);
-            let target = layout.position(&target);
-            layout.temporary(1, |temp| (0..size).flat_map(|i| {
-                move_to(current_cell, source + i).into_iter()
-                    // Fill the target and the temporary with the value of the source cell
-                    .chain(once(Instruction::JumpForwardIfZero))
-
-                    .chain(move_to(current_cell, target + i))
-                    .chain(once(Instruction::Increment))
-
-                    .chain(move_to(current_cell, temp.position()))
-                    .chain(once(Instruction::Increment))
-
-                    .chain(move_to(current_cell, source + i))
-                    .chain(once(Instruction::Decrement))
-
-                    .chain(once(Instruction::JumpBackwardUnlessZero))
-
-                    // Refill the source cell with the temporary
-                    .chain(move_to(current_cell, temp.position()))
-                    .chain(once(Instruction::JumpForwardIfZero))
-
-                    .chain(move_to(current_cell, source + i))
-                    .chain(once(Instruction::Increment))
-
-                    .chain(move_to(current_cell, temp.position()))
-                    .chain(once(Instruction::Decrement))
-
-                    .chain(once(Instruction::JumpBackwardUnlessZero))
-            }).collect())
-        },
-        Relocate {source, target} => {
-            debug_assert!(source.size() == target.size());
-            let size = source.size();
-
-            let source = layout.position(&source.position());
-            let target = layout.position(&target.position());
-
-            (0..size).flat_map(|i| {
-                move_to(current_cell, source + i).into_iter()
-                    .chain(once(Instruction::JumpForwardIfZero))
-
-                    .chain(move_to(current_cell, target + i))
-                    .chain(once(Instruction::Increment))
-
-                    .chain(move_to(current_cell, source + i))
-                    .chain(once(Instruction::Decrement))
-
-                    .chain(once(Instruction::JumpBackwardUnlessZero))
-            }).collect()
-        },
-    }).collect()
-}
-
-fn zero(current_cell: &mut CellIndex, layout: &mut MemoryLayout, target: MemoryBlock) -> Instructions {
-    move_to(current_cell, layout.position(&target.position())).into_iter()
-        .chain(consecutive(vec![
-            Instruction::JumpForwardIfZero,
-            Instruction::Decrement,
-            Instruction::JumpBackwardUnlessZero,
-        ], current_cell, target.size())).collect()
-}
-
-fn move_to(current_cell: &mut CellIndex, target: CellIndex) -> Vec<Instruction> {
-    let current = *current_cell;
-    *current_cell = target;
-
-    if current > target {
-        repeat(Instruction::Left).take(current - target).collect()
-    }
-    else if target > current {
-        repeat(Instruction::Right).take(target - current).collect()
-    }
-    else {
-        Vec::new()
-    }
-}
-
-fn consecutive(instrs: Vec<Instruction>, current_cell: &mut CellIndex, size: MemSize) -> Vec<Instruction> {
-    *current_cell += size;
-    (0..size).flat_map(|_| instrs.clone().into_iter().chain(once(Instruction::Right))).collect()
-}
@@ -0,0 +1,313 @@
+use std::str::FromStr;
+use std::collections::HashMap;
+
+use memory::{MemoryLayout, MemoryBlock, MemId};
+use operations::{Operation, Operations};
+
+/// The number of temporary cells that a branch needs right after its condition
+pub const BRANCH_TEMPORARY_CELLS: usize = 2;
+
+/// The largest weight given to a pair of accesses no matter how deeply nested they are in loops
+const MAX_ACCESS_WEIGHT: u64 = 10_000;
+
+/// How the cells of each memory block are chosen during code generation
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum LayoutStrategy {
+    /// Memory blocks are placed in the first free cells that fit them as they are used
+    #[default]
+    Incremental,
+    /// The accesses of the entire program are analyzed first so that memory blocks that are
+    /// often used one after the other (especially in loops) are placed next to each other
+    ///
+    /// The incremental layout is still used if it turns out to need fewer moves.
+    WholeProgram,
+}
+
+impl FromStr for LayoutStrategy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "incremental" => Ok(LayoutStrategy::Incremental),
+            "whole-program" => Ok(LayoutStrategy::WholeProgram),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Returns an empty layout that places memory blocks as they are used
+///
+/// The temporary cells of every branch are reserved after its condition ahead of time so that
+/// they can never be taken by anything else.
+pub fn incremental_layout(ops: &Operations) -> MemoryLayout {
+    let mut layout = MemoryLayout::new();
+    for cond in branch_conditions(ops) {
+        layout.reserve_after(&cond, BRANCH_TEMPORARY_CELLS);
+    }
+    layout
+}
+
+/// Returns a layout with a position planned for every memory block used in the given operations
+///
+/// Every time the pointer goes from one memory block to another, the two blocks get a little
+/// closer. Accesses inside loops count ten times more for every level of nesting since they are
+/// likely to run many more times. Memory blocks are then placed one by one at either end of a
+/// chain, always picking the block that is most strongly connected to one of the ends.
+pub fn planned_layout(ops: &Operations) -> MemoryLayout {
+    let mut trace = Vec::new();
+    access_trace(ops, 0, &mut trace);
+
+    // Memory blocks in the order they are first used
+    let mut blocks: Vec<MemoryBlock> = Vec::new();
+    for &(mem, _) in &trace {
+        if !blocks.contains(&mem) {
+            blocks.push(mem);
+        }
+    }
+
+    let mut weights: HashMap<(MemId, MemId), u64> = HashMap::new();
+    for pair in trace.windows(2) {
+        let (first, first_depth) = pair[0];
+        let (second, second_depth) = pair[1];
+        if first == second {
+            continue;
+        }
+
+        let weight = 10u64.saturating_pow(first_depth.min(second_depth) as u32).min(MAX_ACCESS_WEIGHT);
+        *weights.entry((first.id(), second.id())).or_insert(0) += weight;
+        *weights.entry((second.id(), first.id())).or_insert(0) += weight;
+    }
+    let weight = |a: &MemoryBlock, b: &MemoryBlock| weights.get(&(a.id(), b.id())).cloned().unwrap_or(0);
+
+    let mut chain = Vec::new();
+    // Start with the block that is the most strongly connected to all the others. Ties always
+    // go to the block that was used first.
+    let mut start = 0;
+    let mut start_weight = 0;
+    for (i, mem) in blocks.iter().enumerate() {
+        let total = blocks.iter().map(|other| weight(mem, other)).sum();
+        if total > start_weight {
+            start = i;
+            start_weight = total;
+        }
+    }
+    if !blocks.is_empty() {
+        chain.push(blocks.remove(start));
+    }
+
+    while !blocks.is_empty() {
+        let mut best = (0, false);
+        let mut best_weight = 0;
+        for (i, mem) in blocks.iter().enumerate() {
+            for &at_start in &[false, true] {
+                let end = if at_start { &chain[0] } else { &chain[chain.len() - 1] };
+                let connection = weight(mem, end);
+                if connection > best_weight {
+                    best = (i, at_start);
+                    best_weight = connection;
+                }
+            }
+        }
+
+        let (index, at_start) = best;
+        let mem = blocks.remove(index);
+        if at_start {
+            chain.insert(0, mem);
+        }
+        else {
+            chain.push(mem);
+        }
+    }
+
+    let mut layout = MemoryLayout::new();
+    let conds = branch_conditions(ops);
+    for cond in &conds {
+        layout.reserve_after(cond, BRANCH_TEMPORARY_CELLS);
+    }
+
+    let mut position = 0;
+    for mem in chain {
+        layout.plan(&mem, position);
+        position += mem.size();
+        if conds.contains(&mem) {
+            position += BRANCH_TEMPORARY_CELLS;
+        }
+    }
+    layout
+}
+
+/// Returns the memory blocks used in the body of a loop that must keep their cells for every
+/// iteration of the loop, in the order they are first used
+///
+/// Temporary cells allocated and freed inside the loop are left out. Everything else has to be
+/// laid out before the loop starts, otherwise a memory block used for the first time partway
+/// through the body could be given cells freed by a temporary earlier in the same body and
+/// then have its value overwritten on the next iteration.
+pub fn loop_variables(body: &Operations) -> Vec<MemoryBlock> {
+    let mut trace = Vec::new();
+    access_trace(body, 0, &mut trace);
+    let temps = temporaries(body);
+
+    let mut blocks: Vec<MemoryBlock> = Vec::new();
+    for (mem, _) in trace {
+        if !temps.contains(&mem) && !blocks.contains(&mem) {
+            blocks.push(mem);
+        }
+    }
+    blocks
+}
+
+/// Returns the memory block of every temporary allocation in the given operations
+fn temporaries(ops: &Operations) -> Vec<MemoryBlock> {
+    let mut temps = Vec::new();
+    for op in ops {
+        use self::Operation::*;
+        match *op {
+            Block {ref body} | Loop {ref body, ..} => temps.extend(temporaries(body)),
+            TempAllocate {temp, ref body, ..} => {
+                temps.push(temp);
+                temps.extend(temporaries(body));
+            },
+            Branch {ref if_body, ref else_body, ..} => {
+                temps.extend(temporaries(if_body));
+                temps.extend(temporaries(else_body));
+            },
+            Increment {..} | Decrement {..} | Read {..} | Write {..} | Zero {..} |
+            Copy {..} | Relocate {..} | Push {..} | Pop {..} => {},
+            Recurse {..} => unreachable!("Recursive calls are replaced by a dispatch loop before code generation"),
+        }
+    }
+    temps
+}
+
+/// Returns the condition of every branch in the given operations
+fn branch_conditions(ops: &Operations) -> Vec<MemoryBlock> {
+    let mut conds = Vec::new();
+    for op in ops {
+        use self::Operation::*;
+        match *op {
+            Block {ref body} | TempAllocate {ref body, ..} | Loop {ref body, ..} => {
+                conds.extend(branch_conditions(body));
+            },
+            Branch {cond, ref if_body, ref else_body} => {
+                conds.push(cond);
+                conds.extend(branch_conditions(if_body));
+                conds.extend(branch_conditions(else_body));
+            },
+            Increment {..} | Decrement {..} | Read {..} | Write {..} | Zero {..} |
+            Copy {..} | Relocate {..} | Push {..} | Pop {..} => {},
+            Recurse {..} => unreachable!("Recursive calls are replaced by a dispatch loop before code generation"),
+        }
+    }
+    conds
+}
+
+/// Records the memory blocks in the order that the pointer visits them along with how deeply
+/// nested in loops each access is
+fn access_trace(ops: &Operations, depth: usize, trace: &mut Vec<(MemoryBlock, usize)>) {
+    let visit = |trace: &mut Vec<_>, mem: MemoryBlock| if mem.size() > 0 {
+        trace.push((mem, depth));
+    };
+
+    for op in ops {
+        use self::Operation::*;
+        match *op {
+            Block {ref body} | TempAllocate {ref body, ..} => access_trace(body, depth, trace),
+            Increment {target, ..} | Decrement {target, ..} => {
+                visit(trace, target.associated_memory());
+            },
+            Read {target} | Write {target} | Zero {target} => visit(trace, target),
+            Branch {cond, ref if_body, ref else_body} => {
+                visit(trace, cond);
+                access_trace(if_body, depth, trace);
+                visit(trace, cond);
+                access_trace(else_body, depth, trace);
+                visit(trace, cond);
+            },
+            Loop {cond, ref body} => {
+                visit(trace, cond.associated_memory());
+                access_trace(body, depth + 1, trace);
+                visit(trace, cond.associated_memory());
+            },
+            Copy {source, target, ..} => {
+                // The source is visited again when it is refilled from the temporary cell
+                visit(trace, source.associated_memory());
+                visit(trace, target.associated_memory());
+                visit(trace, source.associated_memory());
+            },
+            Relocate {source, target} => {
+                visit(trace, source);
+                visit(trace, target);
+            },
+            Push {source: cell} | Pop {target: cell} => visit(trace, cell.associated_memory()),
+            Recurse {..} => unreachable!("Recursive calls are replaced by a dispatch loop before code generation"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use memory::StaticAllocator;
+
+    #[test]
+    fn places_frequently_used_blocks_together() {
+        let mut allocator = StaticAllocator::new();
+        let a = allocator.allocate(1);
+        let b = allocator.allocate(1);
+        let c = allocator.allocate(1);
+
+        // a is used once, but b and c are used together in a loop
+        let ops = vec![
+            Operation::Read {target: a},
+            Operation::Read {target: b},
+            Operation::Loop {cond: b.position(), body: vec![
+                Operation::Decrement {target: b.position(), amount: 1},
+                Operation::Increment {target: c.position(), amount: 1},
+            ]},
+            Operation::Write {target: a},
+        ];
+
+        let mut layout = planned_layout(&ops);
+        let b_position = layout.position(&b.position());
+        let c_position = layout.position(&c.position());
+        assert_eq!((b_position as isize - c_position as isize).abs(), 1);
+        assert_eq!(layout.size(), 3);
+    }
+
+    #[test]
+    fn reserves_cells_after_branch_conditions() {
+        let mut allocator = StaticAllocator::new();
+        let cond = allocator.allocate(1);
+        let a = allocator.allocate(1);
+
+        let ops = vec![
+            Operation::Read {target: cond},
+            Operation::Branch {
+                cond,
+                if_body: vec![Operation::Read {target: a}],
+                else_body: vec![],
+            },
+        ];
+
+        let mut layouts = [incremental_layout(&ops), planned_layout(&ops)];
+        for layout in layouts.iter_mut() {
+            let cond_position = layout.position(&cond.position());
+            let a_position = layout.position(&a.position());
+            assert!(a_position < cond_position || a_position > cond_position + BRANCH_TEMPORARY_CELLS);
+
+            layout.consecutive(&cond, BRANCH_TEMPORARY_CELLS, |_, position, temp| {
+                assert_eq!(position, cond_position);
+                assert_eq!(temp.position(), cond_position + 1);
+            });
+        }
+    }
+
+    #[test]
+    fn parses_strategies() {
+        assert_eq!("incremental".parse(), Ok(LayoutStrategy::Incremental));
+        assert_eq!("whole-program".parse(), Ok(LayoutStrategy::WholeProgram));
+        assert_eq!("other".parse::<LayoutStrategy>(), Err(()));
+    }
+}
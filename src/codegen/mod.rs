@@ -1,3 +1,9 @@
 mod instruction;
+mod generate;
+mod layout;
+#[cfg(test)]
+pub mod testing;
 
 pub use self::instruction::*;
+pub use self::generate::generate;
+pub use self::layout::LayoutStrategy;
@@ -0,0 +1,42 @@
+//! Helpers for tests that compile a whole program and check what it outputs when it runs
+
+use parser::Program;
+use memory::MemoryLayout;
+use prelude;
+use operations::scope::ScopeStack;
+use interpreter::Interpreter;
+
+use super::{Instructions, LayoutStrategy, generate};
+use super::generate::generate_with;
+
+/// Runs the given source with every memory layout, including one without any cells reserved
+/// for branches so that conditions have to be moved
+pub fn run_with_every_layout(source: &str, input: &[u8]) -> Vec<Vec<u8>> {
+    let mut scope = ScopeStack::new();
+    prelude::populate_scope(&mut scope);
+    run_in_scope(scope, source, input)
+}
+
+/// Runs the given source with every memory layout in a scope that was already set up
+pub fn run_in_scope(mut scope: ScopeStack, source: &str, input: &[u8]) -> Vec<Vec<u8>> {
+    let program: Program = source.parse().unwrap();
+    let ops = program.into_operations(&mut scope).unwrap();
+
+    let generated = [
+        generate(ops.clone(), LayoutStrategy::Incremental),
+        generate(ops.clone(), LayoutStrategy::WholeProgram),
+        generate_with(ops, MemoryLayout::new()),
+    ];
+    generated.iter().map(|ops| {
+        let mut interpreter = Interpreter::new(input, Vec::new());
+        interpreter.run(&Instructions::from(ops)).unwrap();
+        interpreter.into_inner().1
+    }).collect()
+}
+
+/// Asserts that the given source outputs the expected bytes with every memory layout
+pub fn assert_output(source: &str, input: &[u8], expected: &[u8]) {
+    for output in run_with_every_layout(source, input) {
+        assert_eq!(String::from_utf8_lossy(&output), String::from_utf8_lossy(expected));
+    }
+}
@@ -3,7 +3,7 @@ use memory::{MemoryBlock};
 
 use operations::{Operation, expression, Target};
 use operations::item_type::{ItemType, FuncArgType};
-use operations::scope::{ScopeStack, ScopeItem, TypeId};
+use operations::scope::{ScopeStack, ScopeItem, TypeId, Mutability};
 
 pub fn define_stdin(scope: &mut ScopeStack, u8_type: TypeId) -> TypeId {
     // Taking advantage of the scope system to simulate modules
@@ -14,9 +14,9 @@ pub fn define_stdin(scope: &mut ScopeStack, u8_type: TypeId) -> TypeId {
     let type_name = Identifier::from("std::io::Stdin");
     let stdin_type = scope.declare_type(
         type_name.clone(),
-        ItemType::Struct {},
+        ItemType::Struct {fields: Vec::new()},
     );
-    scope.declare(Identifier::from("stdin"), stdin_type);
+    scope.declare(Identifier::from("stdin"), stdin_type, Mutability::Immutable);
 
     let unit_type = scope.primitives().unit();
 
@@ -34,6 +34,7 @@ pub fn define_stdin(scope: &mut ScopeStack, u8_type: TypeId) -> TypeId {
             match args[1] {
                 ScopeItem::Array {item, memory: target, ..} => {
                     debug_assert_eq!(scope.primitives().u8(), item);
+                    scope.assign(target)?;
 
                     //TODO: This is currently an unchecked operation but it really shouldn't be
                     Ok(vec![Operation::Read {target}])
@@ -55,9 +56,9 @@ pub fn define_stdout(scope: &mut ScopeStack) -> TypeId {
     let type_name = Identifier::from("std::io::Stdout");
     let stdout_type = scope.declare_type(
         type_name.clone(),
-        ItemType::Struct {},
+        ItemType::Struct {fields: Vec::new()},
     );
-    scope.declare(Identifier::from("stdout"), stdout_type);
+    scope.declare(Identifier::from("stdout"), stdout_type, Mutability::Immutable);
 
     let unit_type = scope.primitives().unit();
 
@@ -82,7 +83,7 @@ pub fn define_stdout(scope: &mut ScopeStack) -> TypeId {
                     memory: MemoryBlock::default(),
                 }
             )).collect::<Result<Vec<_>, _>>().map(|op_vecs| {
-                op_vecs.into_iter().flat_map(|ops| ops).collect()
+                op_vecs.into_iter().flatten().collect()
             })
         }
     );
@@ -1,23 +1,48 @@
-use parser::Identifier;
-use operations::item_type::ItemType;
-use operations::scope::{ScopeStack, TypeId};
+use parser::{Identifier, Number, Span};
+use operations::{Target, expression};
+use operations::item_type::{ItemType, FuncArgType};
+use operations::scope::{ScopeStack, ScopeItem, TypeId};
 
-pub fn define_array(scope: &mut ScopeStack) -> TypeId {
+pub fn define_array(scope: &mut ScopeStack, usize_type: TypeId) -> TypeId {
     // Taking advantage of the scope system to simulate modules
     // This will be replaced with something better in:
     // https://github.com/brain-lang/brain/issues/37
     scope.push_scope();
 
+    let type_name = Identifier::from("[T; N]");
     let array_type = scope.declare_type(
-        Identifier::from("[T; N]"),
+        type_name.clone(),
         ItemType::Array {item: None, size: None},
     );
     scope.register_primitive("array", array_type);
 
+    scope.declare_builtin_function(
+        type_name.concat(Identifier::from("len")),
+        ItemType::Function {
+            //TODO: This should only accept a single array of any type once generics are supported
+            // (see #45)
+            args: vec![FuncArgType::Variadic(None)],
+            return_type: usize_type,
+        },
+        move |scope, args, target| {
+            let size = match args[0] {
+                ScopeItem::Array {size, ..} => size,
+                _ => unreachable!(),
+            };
+
+            // Replaced with the location of the call by the caller
+            expression::store_number(scope, size as Number, Span::default(), Target::TypedBlock {
+                type_id: usize_type,
+                memory: target,
+            })
+        }
+    );
+
     array_type
 }
 
 #[cfg(test)]
+#[allow(clippy::infallible_destructuring_match)]
 mod tests {
     use super::*;
 
@@ -26,7 +51,8 @@ mod tests {
     #[test]
     fn defines_primitive() {
         let mut scope = ScopeStack::new();
-        define_array(&mut scope);
+        // Just put a random type ID for usize_type argument since it doesn't matter for this test
+        define_array(&mut scope, 1000);
 
         let array_type_id = match **scope.lookup_type(&Identifier::from("[T; N]")).first().unwrap() {
             ScopeType::Type(id) => id,
@@ -209,10 +209,10 @@ pub fn define_boolean(scope: &mut ScopeStack) -> TypeId {
                         }],
                     }
                 },
-                /// Thanks to certain properties of booleans, we can evaluate certain things during
-                /// compilation and avoid a lot of extra computation.
-                (&ScopeItem::Constant {type_id: const_type, ref bytes}, &ScopeItem::TypedBlock {type_id: other_type, memory}) |
-                (&ScopeItem::TypedBlock {type_id: other_type, memory}, &ScopeItem::Constant {type_id: const_type, ref bytes}) => {
+                // Thanks to certain properties of booleans, we can evaluate certain things during
+                // compilation and avoid a lot of extra computation.
+                (&ScopeItem::Constant {type_id: const_type, ref bytes}, &ScopeItem::TypedBlock {type_id: other_type, memory, ..}) |
+                (&ScopeItem::TypedBlock {type_id: other_type, memory, ..}, &ScopeItem::Constant {type_id: const_type, ref bytes}) => {
                     debug_assert_eq!(const_type, bool_type);
                     debug_assert_eq!(other_type, bool_type);
                     // This code assumes that this is 1
@@ -356,10 +356,10 @@ pub fn define_boolean(scope: &mut ScopeStack) -> TypeId {
                         }],
                     }
                 },
-                /// Thanks to certain properties of booleans, we can evaluate certain things during
-                /// compilation and avoid a lot of extra computation.
-                (&ScopeItem::Constant {type_id: const_type, ref bytes}, &ScopeItem::TypedBlock {type_id: other_type, memory}) |
-                (&ScopeItem::TypedBlock {type_id: other_type, memory}, &ScopeItem::Constant {type_id: const_type, ref bytes}) => {
+                // Thanks to certain properties of booleans, we can evaluate certain things during
+                // compilation and avoid a lot of extra computation.
+                (&ScopeItem::Constant {type_id: const_type, ref bytes}, &ScopeItem::TypedBlock {type_id: other_type, memory, ..}) |
+                (&ScopeItem::TypedBlock {type_id: other_type, memory, ..}, &ScopeItem::Constant {type_id: const_type, ref bytes}) => {
                     debug_assert_eq!(const_type, bool_type);
                     debug_assert_eq!(other_type, bool_type);
                     // This code assumes that this is 1
@@ -510,10 +510,10 @@ pub fn define_boolean(scope: &mut ScopeStack) -> TypeId {
                         }],
                     }
                 },
-                /// Thanks to certain properties of booleans, we can evaluate certain things during
-                /// compilation and avoid a lot of extra computation.
-                (&ScopeItem::Constant {type_id: const_type, ref bytes}, &ScopeItem::TypedBlock {type_id: other_type, memory}) |
-                (&ScopeItem::TypedBlock {type_id: other_type, memory}, &ScopeItem::Constant {type_id: const_type, ref bytes}) => {
+                // Thanks to certain properties of booleans, we can evaluate certain things during
+                // compilation and avoid a lot of extra computation.
+                (&ScopeItem::Constant {type_id: const_type, ref bytes}, &ScopeItem::TypedBlock {type_id: other_type, memory, ..}) |
+                (&ScopeItem::TypedBlock {type_id: other_type, memory, ..}, &ScopeItem::Constant {type_id: const_type, ref bytes}) => {
                     debug_assert_eq!(const_type, bool_type);
                     debug_assert_eq!(other_type, bool_type);
                     // This code assumes that this is 1
@@ -638,10 +638,10 @@ pub fn define_boolean(scope: &mut ScopeStack) -> TypeId {
                         }],
                     }
                 },
-                /// Thanks to certain properties of booleans, we can evaluate certain things during
-                /// compilation and avoid a lot of extra computation.
-                (&ScopeItem::Constant {type_id: const_type, ref bytes}, &ScopeItem::TypedBlock {type_id: other_type, memory}) |
-                (&ScopeItem::TypedBlock {type_id: other_type, memory}, &ScopeItem::Constant {type_id: const_type, ref bytes}) => {
+                // Thanks to certain properties of booleans, we can evaluate certain things during
+                // compilation and avoid a lot of extra computation.
+                (&ScopeItem::Constant {type_id: const_type, ref bytes}, &ScopeItem::TypedBlock {type_id: other_type, memory, ..}) |
+                (&ScopeItem::TypedBlock {type_id: other_type, memory, ..}, &ScopeItem::Constant {type_id: const_type, ref bytes}) => {
                     debug_assert_eq!(const_type, bool_type);
                     debug_assert_eq!(other_type, bool_type);
                     // This code assumes that this is 1
@@ -709,6 +709,7 @@ pub fn define_boolean(scope: &mut ScopeStack) -> TypeId {
 }
 
 #[cfg(test)]
+#[allow(clippy::infallible_destructuring_match)]
 mod tests {
     use super::*;
 
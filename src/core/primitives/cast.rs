@@ -0,0 +1,129 @@
+use parser::Identifier;
+use memory::MemoryBlock;
+use operations::{Operation, Operations};
+use operations::item_type::{ItemType, FuncArgType};
+use operations::scope::{ScopeStack, ScopeItem, TypeId};
+
+use super::integer::sign_bit;
+
+/// Defines `as` casts between every integer type and from `bool` to every integer type
+///
+/// Casting to a smaller integer keeps only its lowest bytes. Casting to a larger integer fills
+/// the extra bytes with the sign of a signed value (sign extension) or with zeros for an unsigned
+/// value. `true` becomes 1 and `false` becomes 0. Nothing can be cast to `bool` since comparing
+/// with zero says what is meant much more clearly.
+///
+/// Must be called after every integer type is defined.
+pub fn define_casts(scope: &mut ScopeStack) {
+    let bool_type = scope.primitives().bool();
+    let integers = {
+        let primitives = scope.primitives();
+        [
+            (primitives.u8(), false),
+            (primitives.u16(), false),
+            (primitives.u32(), false),
+            (primitives.usize(), false),
+            (primitives.i8(), true),
+            (primitives.i16(), true),
+        ]
+    };
+
+    for &(to, _) in &integers {
+        declare_cast(scope, bool_type, to, |_, value, target| {
+            // A bool only ever uses its lowest bit
+            vec![Operation::Copy {
+                source: value.position(),
+                target: target.position(),
+                size: 1,
+            }]
+        });
+
+        for &(from, signed) in &integers {
+            declare_cast(scope, from, to, move |scope, value, target| {
+                convert_integer(scope, value, signed, target)
+            });
+        }
+    }
+
+    declare_cast(scope, bool_type, bool_type, |_, value, target| {
+        vec![Operation::Copy {
+            source: value.position(),
+            target: target.position(),
+            size: 1,
+        }]
+    });
+}
+
+/// Declares the conversion used to cast a value of one type to another
+///
+/// The given function must store the converted value in the target without modifying the value.
+/// Constants (like `true`) are converted from their value instead.
+fn declare_cast<F>(scope: &mut ScopeStack, from: TypeId, to: TypeId, f: F)
+    where F: 'static + Fn(&mut ScopeStack, MemoryBlock, MemoryBlock) -> Operations {
+
+    // Only one item with each name can be declared per scope and every conversion has the same
+    // name, so each one needs a scope of its own
+    scope.push_scope();
+    scope.declare_builtin_function(
+        // Special method for casting with `as`
+        // This name is such that it could never be called directly
+        // from the language itself
+        Identifier::from("std::convert::As::as"),
+        ItemType::Function {
+            args: vec![FuncArgType::Arg(from)],
+            return_type: to,
+        },
+        move |scope, args, target| match args[0] {
+            ScopeItem::TypedBlock {memory, ..} => Ok(f(scope, memory, target)),
+            ScopeItem::Constant {ref bytes, ..} => {
+                let value = scope.allocate(from);
+                let mut ops = Operation::increment_to_value(value, bytes);
+                ops.extend(f(scope, value, target));
+                Ok(vec![Operation::TempAllocate {
+                    temp: value,
+                    body: ops,
+                    should_zero: true,
+                }])
+            },
+            // Literals are given the type they are cast to instead of being converted
+            _ => unreachable!(),
+        }
+    );
+}
+
+/// Stores the given integer value in the target, truncating it or extending it to fit
+fn convert_integer(scope: &mut ScopeStack, value: MemoryBlock, signed: bool, target: MemoryBlock) -> Operations {
+    let mut ops = vec![Operation::Copy {
+        source: value.position(),
+        target: target.position(),
+        size: value.size().min(target.size()),
+    }];
+
+    if signed && target.size() > value.size() {
+        let u8_type = scope.primitives().u8();
+        let negative = scope.allocate(u8_type);
+        ops.extend(sign_bit(scope, value.position_at(value.size() - 1), negative));
+
+        // Every extra byte of a negative value is all ones
+        let mut body = vec![Operation::Decrement {
+            target: negative.position(),
+            amount: 1,
+        }];
+        body.extend((value.size()..target.size()).map(|i| Operation::Increment {
+            target: target.position_at(i),
+            amount: 255,
+        }));
+        ops.push(Operation::Loop {
+            cond: negative.position(),
+            body,
+        });
+
+        ops = vec![Operation::TempAllocate {
+            temp: negative,
+            body: ops,
+            should_zero: false,
+        }];
+    }
+
+    ops
+}
@@ -0,0 +1,1106 @@
+use parser::{Identifier, Span};
+use memory::{MemSize, MemoryBlock, CellPosition};
+use operations::{Operation, Operations, OperationsResult, Error, Target, expression};
+use operations::item_type::{ItemType, FuncArgType};
+use operations::scope::{ScopeStack, ScopeItem, TypeId, FuncArgs};
+
+/// An integer type that is stored in one or more cells
+///
+/// The cells are stored from the least significant byte to the most significant byte. Signed
+/// integers use two's complement, so most operations treat them exactly like unsigned integers.
+#[derive(Debug, Clone, Copy)]
+struct Integer {
+    type_id: TypeId,
+    size: MemSize,
+    signed: bool,
+}
+
+impl Integer {
+    /// The number of bits in this integer
+    fn bits(self) -> usize {
+        self.size * 8
+    }
+
+    /// The largest value that a literal of this type can have
+    fn max_value(self) -> i64 {
+        let bits = if self.signed { self.bits() - 1 } else { self.bits() };
+        (1i64 << bits) - 1
+    }
+
+    /// The smallest value that a literal of this type can have
+    fn min_value(self) -> i64 {
+        if self.signed { -self.max_value() - 1 } else { 0 }
+    }
+
+    /// The number of decimal digits needed to display any value of this type
+    fn max_digits(self) -> usize {
+        // Signed values are displayed from their absolute value which can be one larger than the
+        // maximum value of the type
+        let largest = if self.signed { self.max_value() + 1 } else { self.max_value() };
+        largest.to_string().len()
+    }
+}
+
+/// Defines an integer type with the given name that is stored in the given number of cells
+///
+/// Provides literals, `Display::print`, equality, ordering and the arithmetic operators. Like
+/// `u8`, arithmetic wraps around and dividing by zero results in zero with the dividend left over
+/// as the remainder.
+pub fn define_integer(scope: &mut ScopeStack, name: &str, size: MemSize, signed: bool, bool_type: TypeId) -> TypeId {
+    // Taking advantage of the scope system to simulate modules
+    // This will be replaced with something better in:
+    // https://github.com/brain-lang/brain/issues/37
+    scope.push_scope();
+
+    let unit_type = scope.primitives().unit();
+
+    let type_id = scope.declare_type(
+        Identifier::from(name),
+        ItemType::Primitive(size)
+    );
+    scope.register_primitive(name, type_id);
+    let integer = Integer {type_id, size, signed};
+
+    // Only signed types can be created from a negative literal
+    let literal_types: &[&str] = if signed {
+        &["{unsigned integer}", "{signed integer}"]
+    } else {
+        &["{unsigned integer}"]
+    };
+    for literal_type in literal_types {
+        scope.declare_builtin_function(
+            // Special method for converting from literal
+            // This name is such that it could never be called directly
+            // from the language itself
+            Identifier::from(format!("std::convert::From<{}>", literal_type).as_str()),
+            ItemType::Function {
+                args: vec![FuncArgType::Arg(type_id)],
+                return_type: type_id,
+            },
+            move |scope, args, target| {
+                let value = args[0].numeric_literal_value();
+                if value < integer.min_value() || value > integer.max_value() {
+                    return Err(Error::OverflowingLiteral {
+                        typ: scope.type_name(integer.type_id),
+                        // Replaced with the location of the literal by the caller
+                        span: Span::default(),
+                    });
+                }
+
+                // Negative values are already in two's complement, so their bytes can be used as is
+                Ok((0..integer.size).map(|i| (i, (value >> (i * 8)) as u8)).filter(|&(_, byte)| byte > 0)
+                    .map(|(i, byte)| Operation::Increment {
+                        target: target.position_at(i),
+                        amount: byte,
+                    }).collect())
+            }
+        );
+    }
+
+    scope.declare_builtin_function(
+        // Special method for displaying this primitive (used from print/println)
+        // This name is such that it could never be called directly
+        // from the language itself
+        Identifier::from("std::fmt::Display::print"),
+        ItemType::Function {
+            args: vec![FuncArgType::Arg(type_id)],
+            return_type: unit_type,
+        },
+        move |scope, args, _| {
+            let value = match args[0] {
+                ScopeItem::TypedBlock {memory, ..} => memory,
+                _ => unreachable!(),
+            };
+            Ok(print(scope, integer, value))
+        }
+    );
+
+    declare_operator(scope, integer, "std::cmp::PartialEq::eq", bool_type, |scope, x, y, target| {
+        equal(scope, x, y, target, true)
+    });
+
+    declare_operator(scope, integer, "std::cmp::PartialEq::ne", bool_type, |scope, x, y, target| {
+        equal(scope, x, y, target, false)
+    });
+
+    declare_operator(scope, integer, "std::cmp::PartialOrd::lt", bool_type, move |scope, x, y, target| {
+        compare(scope, integer, x, y, target, false)
+    });
+
+    declare_operator(scope, integer, "std::cmp::PartialOrd::gt", bool_type, move |scope, x, y, target| {
+        compare(scope, integer, y, x, target, false)
+    });
+
+    declare_operator(scope, integer, "std::cmp::PartialOrd::le", bool_type, move |scope, x, y, target| {
+        compare(scope, integer, y, x, target, true)
+    });
+
+    declare_operator(scope, integer, "std::cmp::PartialOrd::ge", bool_type, move |scope, x, y, target| {
+        compare(scope, integer, x, y, target, true)
+    });
+
+    declare_operator(scope, integer, "std::ops::Add::add", type_id, |scope, x, y, target| {
+        let mut ops = vec![Operation::Copy {
+            source: x.position(),
+            target: target.position(),
+            size: x.size(),
+        }];
+        ops.extend(add(scope, &cells(target), &cells(y)));
+        ops
+    });
+
+    declare_operator(scope, integer, "std::ops::Sub::sub", type_id, |scope, x, y, target| {
+        let mut ops = vec![Operation::Copy {
+            source: x.position(),
+            target: target.position(),
+            size: x.size(),
+        }];
+        ops.extend(subtract(scope, &cells(target), &cells(y)));
+        ops
+    });
+
+    declare_operator(scope, integer, "std::ops::Mul::mul", type_id, |scope, x, y, target| {
+        multiply(scope, x, y, target)
+    });
+
+    declare_operator(scope, integer, "std::ops::Div::div", type_id, move |scope, x, y, target| {
+        let remainder = scope.allocate(integer.type_id);
+        vec![Operation::TempAllocate {
+            temp: remainder,
+            body: divide(scope, integer, x, y, target, remainder),
+            should_zero: true,
+        }]
+    });
+
+    declare_operator(scope, integer, "std::ops::Rem::rem", type_id, move |scope, x, y, target| {
+        let quotient = scope.allocate(integer.type_id);
+        vec![Operation::TempAllocate {
+            temp: quotient,
+            body: divide(scope, integer, x, y, quotient, target),
+            should_zero: true,
+        }]
+    });
+
+    type_id
+}
+
+/// Declares a binary operator that takes two values of the given integer type
+///
+/// Numeric literal operands are stored in temporary cells first so that the given function only
+/// ever has to generate operations for values stored in memory. The function must store its
+/// result in the target without modifying either operand.
+fn declare_operator<F>(scope: &mut ScopeStack, integer: Integer, name: &str, return_type: TypeId, f: F)
+    where F: 'static + Fn(&mut ScopeStack, MemoryBlock, MemoryBlock, MemoryBlock) -> Operations {
+
+    scope.declare_builtin_function(
+        Identifier::from(name),
+        ItemType::Function {
+            args: vec![FuncArgType::Arg(integer.type_id), FuncArgType::Arg(integer.type_id)],
+            return_type,
+        },
+        move |scope, args, target| with_operands(scope, integer, args, |scope, x, y| f(scope, x, y, target))
+    );
+}
+
+/// Stores any numeric literals in the given arguments in temporary memory and then generates the
+/// operations that use the memory of each argument
+fn with_operands<F>(scope: &mut ScopeStack, integer: Integer, args: FuncArgs, f: F) -> OperationsResult
+    where F: FnOnce(&mut ScopeStack, MemoryBlock, MemoryBlock) -> Operations {
+
+    let mut ops = Vec::new();
+    let mut temps = Vec::new();
+    let mut operands = Vec::new();
+    for arg in args {
+        match arg {
+            ScopeItem::TypedBlock {memory, ..} => operands.push(memory),
+            ScopeItem::NumericLiteral(value) => {
+                let temp = scope.allocate(integer.type_id);
+                // Replaced with the location of the call by the caller
+                ops.extend(expression::store_number(scope, value, Span::default(), Target::TypedBlock {
+                    type_id: integer.type_id,
+                    memory: temp,
+                })?);
+                temps.push(temp);
+                operands.push(temp);
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    debug_assert_eq!(operands.len(), 2);
+    ops.extend(f(scope, operands[0], operands[1]));
+    Ok(temporaries(&temps, ops, true))
+}
+
+/// Returns the position of every cell in the given memory block
+fn cells(memory: MemoryBlock) -> Vec<CellPosition> {
+    (0..memory.size()).map(|i| memory.position_at(i)).collect()
+}
+
+/// Allocates a single cell that can hold any byte
+fn allocate_cell(scope: &mut ScopeStack) -> MemoryBlock {
+    let u8_type = scope.primitives().u8();
+    scope.allocate(u8_type)
+}
+
+/// Frees the given temporary memory after the given operations are done with it
+fn temporaries(temps: &[MemoryBlock], body: Operations, should_zero: bool) -> Operations {
+    temps.iter().rev().fold(body, |body, &temp| vec![Operation::TempAllocate {
+        temp,
+        body,
+        should_zero,
+    }])
+}
+
+/// Runs the given operations once if the given cell is not zero
+///
+/// The operations must not modify the cell.
+fn if_nonzero(scope: &mut ScopeStack, cell: CellPosition, body: Operations) -> Operations {
+    let cond = allocate_cell(scope);
+
+    // Algorithm:
+    //
+    // c = x
+    // c[c[-] body]
+    temporaries(&[cond], vec![
+        Operation::Copy {
+            source: cell,
+            target: cond.position(),
+            size: 1,
+        },
+        Operation::Loop {
+            cond: cond.position(),
+            body: Some(Operation::Zero {target: cond}).into_iter().chain(body).collect(),
+        },
+    ], false)
+}
+
+/// Runs the given operations once if the given cell is zero
+///
+/// The operations must not modify the cell.
+fn if_zero(scope: &mut ScopeStack, cell: CellPosition, body: Operations) -> Operations {
+    let flag = allocate_cell(scope);
+
+    // Algorithm:
+    //
+    // f+
+    // (if x { f- })
+    // f[f- body]
+    let mut ops = vec![Operation::Increment {
+        target: flag.position(),
+        amount: 1,
+    }];
+    ops.extend(if_nonzero(scope, cell, vec![Operation::Decrement {
+        target: flag.position(),
+        amount: 1,
+    }]));
+    ops.push(Operation::Loop {
+        cond: flag.position(),
+        body: Some(Operation::Decrement {
+            target: flag.position(),
+            amount: 1,
+        }).into_iter().chain(body).collect(),
+    });
+
+    temporaries(&[flag], ops, false)
+}
+
+/// Adds one to the number stored in the given cells, carrying into each following cell whenever
+/// a cell wraps around to zero
+fn increment(scope: &mut ScopeStack, value: &[CellPosition]) -> Operations {
+    let mut ops = vec![Operation::Increment {
+        target: value[0],
+        amount: 1,
+    }];
+    if value.len() > 1 {
+        let carry = increment(scope, &value[1..]);
+        ops.extend(if_zero(scope, value[0], carry));
+    }
+    ops
+}
+
+/// Subtracts one from the number stored in the given cells, borrowing from each following cell
+/// whenever a cell is about to wrap around from zero
+fn decrement(scope: &mut ScopeStack, value: &[CellPosition]) -> Operations {
+    let mut ops = Vec::new();
+    if value.len() > 1 {
+        let borrow = decrement(scope, &value[1..]);
+        ops.extend(if_zero(scope, value[0], borrow));
+    }
+    ops.push(Operation::Decrement {
+        target: value[0],
+        amount: 1,
+    });
+    ops
+}
+
+/// Adds the number in the addend cells to the number in the value cells, leaving the addend
+/// cells zeroed
+///
+/// The value may have more cells than the addend, the extra cells only receive carries.
+fn add_moving(scope: &mut ScopeStack, value: &[CellPosition], addend: &[CellPosition]) -> Operations {
+    // Algorithm (for each cell i):
+    //
+    // y[i][y[i]- (x[i..] += 1)]
+    addend.iter().enumerate().map(|(i, &cell)| {
+        let mut body = vec![Operation::Decrement {
+            target: cell,
+            amount: 1,
+        }];
+        body.extend(increment(scope, &value[i..]));
+        Operation::Loop {
+            cond: cell,
+            body,
+        }
+    }).collect()
+}
+
+/// Adds the number in the addend cells to the number in the value cells
+fn add(scope: &mut ScopeStack, value: &[CellPosition], addend: &[CellPosition]) -> Operations {
+    let temp = scope.allocate_array(scope.primitives().u8(), addend.len());
+    let mut ops = vec![Operation::Copy {
+        source: addend[0],
+        target: temp.position(),
+        size: addend.len(),
+    }];
+    ops.extend(add_moving(scope, value, &cells(temp)));
+    temporaries(&[temp], ops, false)
+}
+
+/// Subtracts the number in the subtrahend cells from the number in the value cells, leaving the
+/// subtrahend cells zeroed
+///
+/// The value may have more cells than the subtrahend, the extra cells are only borrowed from.
+fn subtract_moving(scope: &mut ScopeStack, value: &[CellPosition], subtrahend: &[CellPosition]) -> Operations {
+    // Algorithm (for each cell i):
+    //
+    // y[i][y[i]- (x[i..] -= 1)]
+    subtrahend.iter().enumerate().map(|(i, &cell)| {
+        let mut body = vec![Operation::Decrement {
+            target: cell,
+            amount: 1,
+        }];
+        body.extend(decrement(scope, &value[i..]));
+        Operation::Loop {
+            cond: cell,
+            body,
+        }
+    }).collect()
+}
+
+/// Subtracts the number in the subtrahend cells from the number in the value cells
+fn subtract(scope: &mut ScopeStack, value: &[CellPosition], subtrahend: &[CellPosition]) -> Operations {
+    let temp = scope.allocate_array(scope.primitives().u8(), subtrahend.len());
+    let mut ops = vec![Operation::Copy {
+        source: subtrahend[0],
+        target: temp.position(),
+        size: subtrahend.len(),
+    }];
+    ops.extend(subtract_moving(scope, value, &cells(temp)));
+    temporaries(&[temp], ops, false)
+}
+
+/// Doubles the number stored in the given cells
+fn double(scope: &mut ScopeStack, value: &[CellPosition]) -> Operations {
+    add(scope, value, value)
+}
+
+/// Replaces the number stored in the given cells with its two's complement negation
+fn negate(scope: &mut ScopeStack, value: &[CellPosition]) -> Operations {
+    let temp = allocate_cell(scope);
+
+    // Algorithm:
+    //
+    // (for each cell i) x[i][t+x[i]-] x[i] = 255 t[x[i]-t-]
+    // x += 1
+    let mut ops: Operations = value.iter().flat_map(|&cell| vec![
+        Operation::Loop {
+            cond: cell,
+            body: vec![
+                Operation::Increment {
+                    target: temp.position(),
+                    amount: 1,
+                },
+                Operation::Decrement {
+                    target: cell,
+                    amount: 1,
+                },
+            ],
+        },
+        Operation::Increment {
+            target: cell,
+            amount: 255,
+        },
+        Operation::Loop {
+            cond: temp.position(),
+            body: vec![
+                Operation::Decrement {
+                    target: cell,
+                    amount: 1,
+                },
+                Operation::Decrement {
+                    target: temp.position(),
+                    amount: 1,
+                },
+            ],
+        },
+    ]).collect();
+    ops.extend(increment(scope, value));
+
+    temporaries(&[temp], ops, false)
+}
+
+/// Sets the flag (which must be zero) to one if the most significant bit of the given cell is set
+pub fn sign_bit(scope: &mut ScopeStack, cell: CellPosition, flag: MemoryBlock) -> Operations {
+    let temp = allocate_cell(scope);
+    let counter = allocate_cell(scope);
+
+    // Algorithm:
+    //
+    // The bit is set if the cell is still nonzero after subtracting 127 without going below zero
+    //
+    // t = x
+    // c = 127
+    // c[(if t { t- }) c-]
+    // (if t { f+ })
+    let mut body = if_nonzero(scope, temp.position(), vec![Operation::Decrement {
+        target: temp.position(),
+        amount: 1,
+    }]);
+    body.push(Operation::Decrement {
+        target: counter.position(),
+        amount: 1,
+    });
+
+    let mut ops = vec![
+        Operation::Copy {
+            source: cell,
+            target: temp.position(),
+            size: 1,
+        },
+        Operation::Increment {
+            target: counter.position(),
+            amount: 127,
+        },
+        Operation::Loop {
+            cond: counter.position(),
+            body,
+        },
+    ];
+    ops.extend(if_nonzero(scope, temp.position(), vec![Operation::Increment {
+        target: flag.position(),
+        amount: 1,
+    }]));
+
+    // The remaining value of temp is not needed
+    vec![Operation::TempAllocate {
+        temp,
+        body: temporaries(&[counter], ops, false),
+        should_zero: true,
+    }]
+}
+
+/// Stores whether x == y (if equal is true) or x != y (if equal is false) in the target
+fn equal(scope: &mut ScopeStack, x: MemoryBlock, y: MemoryBlock, target: MemoryBlock, equal: bool) -> Operations {
+    let diff = scope.allocate_array(scope.primitives().u8(), x.size());
+
+    // Algorithm:
+    //
+    // d = x - y
+    // z = equal
+    // (for each cell i) if d[i] { z = !equal }
+    let mut ops = vec![Operation::Copy {
+        source: x.position(),
+        target: diff.position(),
+        size: x.size(),
+    }];
+    ops.extend(subtract(scope, &cells(diff), &cells(y)));
+    if equal {
+        ops.push(Operation::Increment {
+            target: target.position(),
+            amount: 1,
+        });
+    }
+    for cell in cells(diff) {
+        let mut different = vec![Operation::Zero {
+            target,
+        }];
+        if !equal {
+            different.push(Operation::Increment {
+                target: target.position(),
+                amount: 1,
+            });
+        }
+        ops.extend(if_nonzero(scope, cell, different));
+    }
+
+    vec![Operation::TempAllocate {
+        temp: diff,
+        body: ops,
+        should_zero: true,
+    }]
+}
+
+/// Stores whether x >= y (if greater_or_equal is true) or x < y (if greater_or_equal is false)
+/// in the target
+fn compare(scope: &mut ScopeStack, integer: Integer, x: MemoryBlock, y: MemoryBlock, target: MemoryBlock, greater_or_equal: bool) -> Operations {
+    // One extra cell that is borrowed from if y is larger than x
+    let diff = scope.allocate_array(scope.primitives().u8(), integer.size + 1);
+    let diff_cells = cells(diff);
+    let top = diff_cells[integer.size - 1];
+    let borrow = diff_cells[integer.size];
+
+    // Algorithm:
+    //
+    // Signed values are offset so that the smallest value becomes zero, then they can be
+    // compared like unsigned values
+    //
+    // d = (1, x + offset)
+    // d -= y + offset
+    // z = d[borrow] is still 1
+    let mut ops = vec![
+        Operation::Copy {
+            source: x.position(),
+            target: diff.position(),
+            size: x.size(),
+        },
+        Operation::Increment {
+            target: borrow,
+            amount: 1,
+        },
+    ];
+    if integer.signed {
+        // Flipping the sign bit of both operands is the same as adding the offset to both
+        let offset_y = scope.allocate(integer.type_id);
+        ops.push(Operation::Increment {
+            target: top,
+            amount: 128,
+        });
+        ops.push(Operation::Copy {
+            source: y.position(),
+            target: offset_y.position(),
+            size: y.size(),
+        });
+        ops.push(Operation::Increment {
+            target: offset_y.position_at(integer.size - 1),
+            amount: 128,
+        });
+        ops.extend(subtract_moving(scope, &diff_cells, &cells(offset_y)));
+        ops = temporaries(&[offset_y], ops, false);
+    }
+    else {
+        ops.extend(subtract(scope, &diff_cells, &cells(y)));
+    }
+
+    if !greater_or_equal {
+        ops.push(Operation::Increment {
+            target: target.position(),
+            amount: 1,
+        });
+    }
+    ops.extend(if_nonzero(scope, borrow, vec![
+        if greater_or_equal {
+            Operation::Increment {
+                target: target.position(),
+                amount: 1,
+            }
+        }
+        else {
+            Operation::Decrement {
+                target: target.position(),
+                amount: 1,
+            }
+        },
+    ]));
+
+    vec![Operation::TempAllocate {
+        temp: diff,
+        body: ops,
+        should_zero: true,
+    }]
+}
+
+/// Multiplies x by y, storing the result in the target
+fn multiply(scope: &mut ScopeStack, x: MemoryBlock, y: MemoryBlock, target: MemoryBlock) -> Operations {
+    let temp_x = allocate_cell(scope);
+    let temp_y = allocate_cell(scope);
+    let target_cells = cells(target);
+    let size = x.size();
+
+    // Algorithm (for each pair of cells whose product affects the result):
+    //
+    // a = x[i]
+    // a[a- b = y[j] b[b- (z[i + j..] += 1)]]
+    let mut ops = Vec::new();
+    for i in 0..size {
+        for j in 0..size - i {
+            let mut inner = vec![Operation::Decrement {
+                target: temp_y.position(),
+                amount: 1,
+            }];
+            inner.extend(increment(scope, &target_cells[i + j..]));
+
+            ops.push(Operation::Copy {
+                source: x.position_at(i),
+                target: temp_x.position(),
+                size: 1,
+            });
+            ops.push(Operation::Loop {
+                cond: temp_x.position(),
+                body: vec![
+                    Operation::Decrement {
+                        target: temp_x.position(),
+                        amount: 1,
+                    },
+                    Operation::Copy {
+                        source: y.position_at(j),
+                        target: temp_y.position(),
+                        size: 1,
+                    },
+                    Operation::Loop {
+                        cond: temp_y.position(),
+                        body: inner,
+                    },
+                ],
+            });
+        }
+    }
+
+    temporaries(&[temp_x, temp_y], ops, false)
+}
+
+/// Divides x by y, storing the results in quotient and remainder which must both be zero
+///
+/// Signed division rounds towards zero and the remainder has the same sign as x, just like Rust.
+fn divide(scope: &mut ScopeStack, integer: Integer, x: MemoryBlock, y: MemoryBlock, quotient: MemoryBlock, remainder: MemoryBlock) -> Operations {
+    if !integer.signed {
+        return divide_unsigned(scope, integer, x, y, quotient, remainder);
+    }
+
+    let abs_x = scope.allocate(integer.type_id);
+    let abs_y = scope.allocate(integer.type_id);
+    let negative_x = allocate_cell(scope);
+    let negative_y = allocate_cell(scope);
+    let negative_quotient = allocate_cell(scope);
+    let top = integer.size - 1;
+
+    // Algorithm:
+    //
+    // a = |x|, b = |y|
+    // q, r = a / b, a % b
+    // if x < 0 != y < 0 { q = -q }
+    // if x < 0 { r = -r }
+    let mut ops = vec![
+        Operation::Copy {
+            source: x.position(),
+            target: abs_x.position(),
+            size: x.size(),
+        },
+        Operation::Copy {
+            source: y.position(),
+            target: abs_y.position(),
+            size: y.size(),
+        },
+    ];
+    ops.extend(sign_bit(scope, x.position_at(top), negative_x));
+    ops.extend(sign_bit(scope, y.position_at(top), negative_y));
+    let negate_x = negate(scope, &cells(abs_x));
+    ops.extend(if_nonzero(scope, negative_x.position(), negate_x));
+    let negate_y = negate(scope, &cells(abs_y));
+    ops.extend(if_nonzero(scope, negative_y.position(), negate_y));
+    ops.extend(divide_unsigned(scope, integer, abs_x, abs_y, quotient, remainder));
+
+    // The signs are different if exactly one of them is set
+    ops.push(Operation::Copy {
+        source: negative_x.position(),
+        target: negative_quotient.position(),
+        size: 1,
+    });
+    ops.push(Operation::Copy {
+        source: negative_y.position(),
+        target: negative_quotient.position(),
+        size: 1,
+    });
+    ops.push(Operation::Decrement {
+        target: negative_quotient.position(),
+        amount: 1,
+    });
+    let negate_quotient = negate(scope, &cells(quotient));
+    ops.extend(if_zero(scope, negative_quotient.position(), negate_quotient));
+    let negate_remainder = negate(scope, &cells(remainder));
+    ops.extend(if_nonzero(scope, negative_x.position(), negate_remainder));
+
+    temporaries(&[abs_x, abs_y, negative_x, negative_y, negative_quotient], ops, true)
+}
+
+/// Divides x by y as unsigned integers using binary long division, storing the results in
+/// quotient and remainder which must both be zero
+fn divide_unsigned(scope: &mut ScopeStack, integer: Integer, x: MemoryBlock, y: MemoryBlock, quotient: MemoryBlock, remainder: MemoryBlock) -> Operations {
+    let u8_type = scope.primitives().u8();
+    let size = integer.size;
+    // Each has an extra cell to hold the bit that is shifted out of the rest of the cells
+    let dividend = scope.allocate_array(u8_type, size + 1);
+    let partial = scope.allocate_array(u8_type, size + 1);
+    // Has yet another cell that is borrowed from if the divisor is larger
+    let diff = scope.allocate_array(u8_type, size + 2);
+    let counter = allocate_cell(scope);
+    let nonzero = allocate_cell(scope);
+    let dividend_cells = cells(dividend);
+    let partial_cells = cells(partial);
+    let diff_cells = cells(diff);
+    let quotient_cells = cells(quotient);
+
+    // Algorithm:
+    //
+    // a = x
+    // for each bit of x from highest to lowest {
+    //     a, bit = a << 1
+    //     p = (p << 1) + bit
+    //     q = q << 1
+    //     if p >= y {
+    //         p -= y
+    //         q += 1
+    //     }
+    // }
+    // r = p
+    let mut body = double(scope, &dividend_cells);
+    body.extend(double(scope, &partial_cells));
+    body.push(Operation::Loop {
+        cond: dividend_cells[size],
+        body: vec![
+            Operation::Decrement {
+                target: dividend_cells[size],
+                amount: 1,
+            },
+            // The lowest bit of a doubled number is always zero, so this never carries
+            Operation::Increment {
+                target: partial_cells[0],
+                amount: 1,
+            },
+        ],
+    });
+    body.extend(double(scope, &quotient_cells));
+
+    // p >= y if nothing was borrowed from the extra cell of p - y
+    body.push(Operation::Copy {
+        source: partial.position(),
+        target: diff.position(),
+        size: size + 1,
+    });
+    body.push(Operation::Increment {
+        target: diff_cells[size + 1],
+        amount: 1,
+    });
+    body.extend(subtract(scope, &diff_cells, &cells(y)));
+    let mut found = subtract(scope, &partial_cells, &cells(y));
+    found.push(Operation::Increment {
+        target: quotient_cells[0],
+        amount: 1,
+    });
+    body.extend(if_nonzero(scope, diff_cells[size + 1], found));
+    body.push(Operation::Zero {
+        target: diff,
+    });
+    body.push(Operation::Decrement {
+        target: counter.position(),
+        amount: 1,
+    });
+
+    let mut division = vec![
+        Operation::Zero {
+            target: nonzero,
+        },
+        Operation::Copy {
+            source: x.position(),
+            target: dividend.position(),
+            size,
+        },
+        Operation::Increment {
+            target: counter.position(),
+            amount: integer.bits() as u8,
+        },
+        Operation::Loop {
+            cond: counter.position(),
+            body,
+        },
+    ];
+    division.extend(partial_cells[..size].iter().zip(cells(remainder)).map(|(&source, target)| Operation::Loop {
+        cond: source,
+        body: vec![
+            Operation::Decrement {
+                target: source,
+                amount: 1,
+            },
+            Operation::Increment {
+                target,
+                amount: 1,
+            },
+        ],
+    }));
+
+    // Dividing by zero leaves the quotient as zero and the remainder as x
+    let mut ops = Vec::new();
+    for cell in cells(y) {
+        ops.extend(if_nonzero(scope, cell, vec![
+            Operation::Zero {
+                target: nonzero,
+            },
+            Operation::Increment {
+                target: nonzero.position(),
+                amount: 1,
+            },
+        ]));
+    }
+    ops.extend(if_zero(scope, nonzero.position(), vec![Operation::Copy {
+        source: x.position(),
+        target: remainder.position(),
+        size,
+    }]));
+    ops.push(Operation::Loop {
+        cond: nonzero.position(),
+        body: division,
+    });
+
+    temporaries(&[dividend, partial, diff, counter, nonzero], ops, true)
+}
+
+/// Writes the decimal digits of the given value
+fn print(scope: &mut ScopeStack, integer: Integer, value: MemoryBlock) -> Operations {
+    let digits: Vec<_> = (0..integer.max_digits()).map(|_| allocate_cell(scope)).collect();
+    let temp = scope.allocate(integer.type_id);
+    let started = allocate_cell(scope);
+    let temp_cells = cells(temp);
+
+    let mut ops = vec![Operation::Copy {
+        source: value.position(),
+        target: temp.position(),
+        size: value.size(),
+    }];
+
+    if integer.signed {
+        let negative = allocate_cell(scope);
+        let mut minus = vec![
+            Operation::Increment {
+                target: started.position(),
+                amount: b'-',
+            },
+            Operation::Write {
+                target: started,
+            },
+            Operation::Decrement {
+                target: started.position(),
+                amount: b'-',
+            },
+        ];
+        minus.extend(negate(scope, &temp_cells));
+
+        let mut sign = sign_bit(scope, value.position_at(integer.size - 1), negative);
+        sign.extend(if_nonzero(scope, negative.position(), minus));
+        ops.extend(temporaries(&[negative], sign, true));
+    }
+
+    // Algorithm:
+    //
+    // Every digit is found from lowest to highest, then they are written from highest to lowest
+    // once the first digit that is not zero is found
+    //
+    // (for each digit d) t, d = t / 10, t % 10
+    // (for each digit d except the last) if d { s = 1 } if s { write(d) }
+    // write(last digit)
+    for &digit in &digits {
+        ops.extend(divide_by_ten(scope, &temp_cells, digit));
+    }
+    for (i, &digit) in digits.iter().enumerate().rev() {
+        let write = vec![
+            Operation::Increment {
+                target: digit.position(),
+                amount: b'0',
+            },
+            Operation::Write {
+                target: digit,
+            },
+            Operation::Decrement {
+                target: digit.position(),
+                amount: b'0',
+            },
+        ];
+
+        if i == 0 {
+            ops.extend(write);
+        }
+        else {
+            ops.extend(if_nonzero(scope, digit.position(), vec![
+                Operation::Zero {
+                    target: started,
+                },
+                Operation::Increment {
+                    target: started.position(),
+                    amount: 1,
+                },
+            ]));
+            ops.extend(if_nonzero(scope, started.position(), write));
+        }
+    }
+
+    let temps: Vec<_> = digits.into_iter().chain(vec![temp, started]).collect();
+    temporaries(&temps, ops, true)
+}
+
+/// Divides the given value by ten in place, storing the remainder in the given cell which must
+/// be zero
+fn divide_by_ten(scope: &mut ScopeStack, value: &[CellPosition], remainder: MemoryBlock) -> Operations {
+    let temp = allocate_cell(scope);
+    let carried = allocate_cell(scope);
+
+    // Algorithm:
+    //
+    // Long division from the highest cell to the lowest where each cell is a digit in base 256.
+    // Every cell is replaced with its part of the quotient. Since 256 = 25 * 10 + 6, each unit
+    // of the remainder carried into a cell adds 25 to that cell and 6 to the new remainder.
+    //
+    // (for each cell i from highest to lowest)
+    // t = x[i], x[i] = 0
+    // c = r, r = 0
+    // c[c- x[i] += 25 (6 times: add one)]
+    // t[t- (add one)]
+    //
+    // Where "add one" is: r+ if r == 10 { r = 0 x[i]+ }
+    let mut ops = Vec::new();
+    for &cell in value.iter().rev() {
+        let mut carry = vec![
+            Operation::Decrement {
+                target: carried.position(),
+                amount: 1,
+            },
+            Operation::Increment {
+                target: cell,
+                amount: 25,
+            },
+        ];
+        for _ in 0..6 {
+            carry.extend(add_digit_unit(scope, remainder, cell));
+        }
+
+        let mut rest = vec![Operation::Decrement {
+            target: temp.position(),
+            amount: 1,
+        }];
+        rest.extend(add_digit_unit(scope, remainder, cell));
+
+        ops.push(Operation::Relocate {
+            source: remainder,
+            target: carried,
+        });
+        ops.push(Operation::Loop {
+            cond: cell,
+            body: vec![
+                Operation::Decrement {
+                    target: cell,
+                    amount: 1,
+                },
+                Operation::Increment {
+                    target: temp.position(),
+                    amount: 1,
+                },
+            ],
+        });
+        ops.push(Operation::Loop {
+            cond: carried.position(),
+            body: carry,
+        });
+        ops.push(Operation::Loop {
+            cond: temp.position(),
+            body: rest,
+        });
+    }
+
+    temporaries(&[temp, carried], ops, false)
+}
+
+/// Adds one to the remainder of a division by ten, moving it into the quotient once it reaches
+/// ten
+fn add_digit_unit(scope: &mut ScopeStack, remainder: MemoryBlock, quotient: CellPosition) -> Operations {
+    let cond = allocate_cell(scope);
+
+    let mut ops = vec![
+        Operation::Increment {
+            target: remainder.position(),
+            amount: 1,
+        },
+        Operation::Copy {
+            source: remainder.position(),
+            target: cond.position(),
+            size: 1,
+        },
+        Operation::Decrement {
+            target: cond.position(),
+            amount: 10,
+        },
+    ];
+    ops.extend(if_zero(scope, cond.position(), vec![
+        Operation::Zero {
+            target: remainder,
+        },
+        Operation::Increment {
+            target: quotient,
+            amount: 1,
+        },
+    ]));
+
+    temporaries(&[cond], ops, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use codegen::testing::assert_output;
+
+    #[test]
+    fn limits() {
+        let u16_type = Integer {type_id: 0, size: 2, signed: false};
+        assert_eq!(u16_type.min_value(), 0);
+        assert_eq!(u16_type.max_value(), 65535);
+        assert_eq!(u16_type.max_digits(), 5);
+
+        let i8_type = Integer {type_id: 0, size: 1, signed: true};
+        assert_eq!(i8_type.min_value(), -128);
+        assert_eq!(i8_type.max_value(), 127);
+        assert_eq!(i8_type.max_digits(), 3);
+
+        let u32_type = Integer {type_id: 0, size: 4, signed: false};
+        assert_eq!(u32_type.max_value(), 4294967295);
+        assert_eq!(u32_type.max_digits(), 10);
+    }
+
+    #[test]
+    fn wider_integers_in_loops() {
+        assert_output(r#"
+            let mut i: i16 = 0 - 300;
+            while i <= 300 {
+                stdout.print(i, b"|", i * 3, b"|", i > 0, b" ");
+                i += 250;
+            }
+            let mut n: u16 = 65530;
+            stdout.print(n + 7, b" ", n - 65535);
+        "#, b"", b"-300|-900|0 -50|-150|0 200|600|1 1 65531");
+    }
+
+    #[test]
+    fn negative_literals() {
+        assert_output(r#"
+            let min: i8 = -128;
+            let mut i: i16 = -32768;
+            stdout.print(min, b" ", i, b" ", min + 127, b" ");
+            i = -2;
+            while i < 2 {
+                stdout.print(i + -3, b"|", i as i8 == -1, b" ");
+                i += 1;
+            }
+            match min {
+                -128 => stdout.print(b"min"),
+                _ => stdout.print(b"other"),
+            }
+        "#, b"", b"-128 -32768 -1 -5|0 -4|1 -3|0 -2|0 min");
+    }
+}
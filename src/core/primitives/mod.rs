@@ -1,3 +1,5 @@
 pub mod array;
 pub mod boolean;
+pub mod cast;
+pub mod integer;
 pub mod u8;
@@ -1,7 +1,8 @@
-use parser::Identifier;
-use operations::{Operation, Error};
+use parser::{Identifier, Span};
+use memory::MemoryBlock;
+use operations::{Operation, Operations, OperationsResult, Error, Target, expression};
 use operations::item_type::{ItemType, FuncArgType};
-use operations::scope::{ScopeStack, ScopeItem, TypeId};
+use operations::scope::{ScopeStack, ScopeItem, TypeId, FuncArgs};
 
 pub fn define_u8(scope: &mut ScopeStack, bool_type: TypeId) -> TypeId {
     // Taking advantage of the scope system to simulate modules
@@ -37,7 +38,9 @@ pub fn define_u8(scope: &mut ScopeStack, bool_type: TypeId) -> TypeId {
             // have (2^size - 1) numbers available
             if value >= (1 << 8) {
                 Err(Error::OverflowingLiteral {
-                    typ: scope.get_type(u8_type).clone()
+                    typ: scope.type_name(u8_type),
+                    // Replaced with the location of the literal by the caller
+                    span: Span::default(),
                 })
             }
             else {
@@ -92,13 +95,14 @@ pub fn define_u8(scope: &mut ScopeStack, bool_type: TypeId) -> TypeId {
             args: vec![FuncArgType::Arg(u8_type)],
             return_type: unit_type,
         },
-        move |_scope, args, _target| {
+        move |scope, args, _target| {
             let mem = match args[0] {
                 ScopeItem::TypedBlock {memory, ..} => memory,
                 _ => unreachable!(),
             };
 
             debug_assert!(mem.size() == 1);
+            scope.mutate(mem)?;
 
             Ok(vec![
                 Operation::Increment {
@@ -115,13 +119,14 @@ pub fn define_u8(scope: &mut ScopeStack, bool_type: TypeId) -> TypeId {
             args: vec![FuncArgType::Arg(u8_type)],
             return_type: unit_type,
         },
-        move |_scope, args, _target| {
+        move |scope, args, _target| {
             let mem = match args[0] {
                 ScopeItem::TypedBlock {memory, ..} => memory,
                 _ => unreachable!(),
             };
 
             debug_assert!(mem.size() == 1);
+            scope.mutate(mem)?;
 
             Ok(vec![
                 Operation::Decrement {
@@ -139,69 +144,65 @@ pub fn define_u8(scope: &mut ScopeStack, bool_type: TypeId) -> TypeId {
             return_type: bool_type,
         },
         move |scope, args, target| {
-            let u8_type = scope.primitives().u8();
+            with_operands(scope, args, |scope, x, y| {
+                let u8_type = scope.primitives().u8();
+                let temp_x = scope.allocate(u8_type);
+                let temp_y = scope.allocate(u8_type);
 
-            Ok(match (&args[0], &args[1]) {
-                (&ScopeItem::TypedBlock {memory: x, ..}, &ScopeItem::TypedBlock {memory: y, ..}) => {
-                    let temp_x = scope.allocate(u8_type);
-                    let temp_y = scope.allocate(u8_type);
+                vec![Operation::TempAllocate {
+                    temp: temp_x,
+                    body: vec![Operation::TempAllocate {
+                        temp: temp_y,
+                        body: vec![
+                            Operation::Copy {
+                                source: x.position(),
+                                target: temp_x.position(),
+                                size: x.size(),
+                            },
+                            Operation::Copy {
+                                source: y.position(),
+                                target: temp_y.position(),
+                                size: y.size(),
+                            },
 
-                    vec![Operation::TempAllocate {
-                        temp: temp_x,
-                        body: vec![Operation::TempAllocate {
-                            temp: temp_y,
-                            body: vec![
-                                Operation::Copy {
-                                    source: x.position(),
-                                    target: temp_x.position(),
-                                    size: x.size(),
-                                },
-                                Operation::Copy {
-                                    source: y.position(),
-                                    target: temp_y.position(),
-                                    size: y.size(),
-                                },
-
-                                // Algorithm:
-                                //
-                                // z = x == y
-                                // x[y-x-]z+y[z-y[-]]
-                                Operation::Loop {
-                                    cond: temp_x.position(),
-                                    body: vec![
-                                        Operation::Decrement {
-                                            target: temp_y.position(),
-                                            amount: 1,
-                                        },
-                                        Operation::Decrement {
-                                            target: temp_x.position(),
-                                            amount: 1,
-                                        },
-                                    ],
-                                },
-                                Operation::Increment {
-                                    target: target.position(),
-                                    amount: 1,
-                                },
-                                Operation::Loop {
-                                    cond: temp_y.position(),
-                                    body: vec![
-                                        Operation::Decrement {
-                                            target: target.position(),
-                                            amount: 1,
-                                        },
-                                        Operation::Zero {
-                                            target: temp_y,
-                                        },
-                                    ],
-                                },
-                            ],
-                            should_zero: false,
-                        }],
+                            // Algorithm:
+                            //
+                            // z = x == y
+                            // x[y-x-]z+y[z-y[-]]
+                            Operation::Loop {
+                                cond: temp_x.position(),
+                                body: vec![
+                                    Operation::Decrement {
+                                        target: temp_y.position(),
+                                        amount: 1,
+                                    },
+                                    Operation::Decrement {
+                                        target: temp_x.position(),
+                                        amount: 1,
+                                    },
+                                ],
+                            },
+                            Operation::Increment {
+                                target: target.position(),
+                                amount: 1,
+                            },
+                            Operation::Loop {
+                                cond: temp_y.position(),
+                                body: vec![
+                                    Operation::Decrement {
+                                        target: target.position(),
+                                        amount: 1,
+                                    },
+                                    Operation::Zero {
+                                        target: temp_y,
+                                    },
+                                ],
+                            },
+                        ],
                         should_zero: false,
-                    }]
-                },
-                _ => unreachable!(),
+                    }],
+                    should_zero: false,
+                }]
             })
         }
     );
@@ -213,69 +214,391 @@ pub fn define_u8(scope: &mut ScopeStack, bool_type: TypeId) -> TypeId {
             return_type: bool_type,
         },
         move |scope, args, target| {
-            let u8_type = scope.primitives().bool();
-
-            Ok(match (&args[0], &args[1]) {
-                (&ScopeItem::TypedBlock {memory: x, ..}, &ScopeItem::TypedBlock {memory: y, ..}) => {
-                    let temp_x = scope.allocate(u8_type);
-                    let temp_y = scope.allocate(u8_type);
+            with_operands(scope, args, |scope, x, y| {
+                let u8_type = scope.primitives().u8();
+                let temp_x = scope.allocate(u8_type);
+                let temp_y = scope.allocate(u8_type);
 
-                    vec![Operation::TempAllocate {
-                        temp: temp_x,
-                        body: vec![Operation::TempAllocate {
-                            temp: temp_y,
-                            body: vec![
-                                Operation::Copy {
-                                    source: x.position(),
-                                    target: temp_x.position(),
-                                    size: x.size(),
-                                },
-                                Operation::Copy {
-                                    source: y.position(),
-                                    target: temp_y.position(),
-                                    size: y.size(),
-                                },
+                vec![Operation::TempAllocate {
+                    temp: temp_x,
+                    body: vec![Operation::TempAllocate {
+                        temp: temp_y,
+                        body: vec![
+                            Operation::Copy {
+                                source: x.position(),
+                                target: temp_x.position(),
+                                size: x.size(),
+                            },
+                            Operation::Copy {
+                                source: y.position(),
+                                target: temp_y.position(),
+                                size: y.size(),
+                            },
 
-                                // Algorithm:
-                                //
-                                // z = x != y
-                                // x[y-x-]y[z+y[-]]
-                                Operation::Loop {
-                                    cond: temp_x.position(),
-                                    body: vec![
-                                        Operation::Decrement {
-                                            target: temp_y.position(),
-                                            amount: 1,
-                                        },
-                                        Operation::Decrement {
-                                            target: temp_x.position(),
-                                            amount: 1,
-                                        },
-                                    ],
-                                },
-                                Operation::Loop {
-                                    cond: temp_y.position(),
-                                    body: vec![
-                                        Operation::Increment {
-                                            target: target.position(),
-                                            amount: 1,
-                                        },
-                                        Operation::Zero {
-                                            target: temp_y,
-                                        },
-                                    ],
-                                },
-                            ],
-                            should_zero: false,
-                        }],
+                            // Algorithm:
+                            //
+                            // z = x != y
+                            // x[y-x-]y[z+y[-]]
+                            Operation::Loop {
+                                cond: temp_x.position(),
+                                body: vec![
+                                    Operation::Decrement {
+                                        target: temp_y.position(),
+                                        amount: 1,
+                                    },
+                                    Operation::Decrement {
+                                        target: temp_x.position(),
+                                        amount: 1,
+                                    },
+                                ],
+                            },
+                            Operation::Loop {
+                                cond: temp_y.position(),
+                                body: vec![
+                                    Operation::Increment {
+                                        target: target.position(),
+                                        amount: 1,
+                                    },
+                                    Operation::Zero {
+                                        target: temp_y,
+                                    },
+                                ],
+                            },
+                        ],
                         should_zero: false,
-                    }]
-                },
-                _ => unreachable!(),
+                    }],
+                    should_zero: false,
+                }]
             })
         }
     );
 
+    declare_operator(scope, "std::ops::Add::add", u8_type, u8_type, |_, x, y, target| vec![
+        Operation::Copy {
+            source: x.position(),
+            target: target.position(),
+            size: x.size(),
+        },
+        Operation::Copy {
+            source: y.position(),
+            target: target.position(),
+            size: y.size(),
+        },
+    ]);
+
+    declare_operator(scope, "std::ops::Sub::sub", u8_type, u8_type, |scope, x, y, target| {
+        let u8_type = scope.primitives().u8();
+        let temp_y = scope.allocate(u8_type);
+
+        vec![Operation::TempAllocate {
+            temp: temp_y,
+            body: vec![
+                Operation::Copy {
+                    source: x.position(),
+                    target: target.position(),
+                    size: x.size(),
+                },
+                Operation::Copy {
+                    source: y.position(),
+                    target: temp_y.position(),
+                    size: y.size(),
+                },
+
+                // Algorithm:
+                //
+                // z = x - y
+                // (z = x) y[z-y-]
+                Operation::Loop {
+                    cond: temp_y.position(),
+                    body: vec![
+                        Operation::Decrement {
+                            target: target.position(),
+                            amount: 1,
+                        },
+                        Operation::Decrement {
+                            target: temp_y.position(),
+                            amount: 1,
+                        },
+                    ],
+                },
+            ],
+            should_zero: false,
+        }]
+    });
+
+    declare_operator(scope, "std::ops::Mul::mul", u8_type, u8_type, |scope, x, y, target| {
+        let u8_type = scope.primitives().u8();
+        let temp_x = scope.allocate(u8_type);
+
+        vec![Operation::TempAllocate {
+            temp: temp_x,
+            body: vec![
+                Operation::Copy {
+                    source: x.position(),
+                    target: temp_x.position(),
+                    size: x.size(),
+                },
+
+                // Algorithm:
+                //
+                // z = x * y
+                // x[(z += y)x-]
+                Operation::Loop {
+                    cond: temp_x.position(),
+                    body: vec![
+                        Operation::Copy {
+                            source: y.position(),
+                            target: target.position(),
+                            size: y.size(),
+                        },
+                        Operation::Decrement {
+                            target: temp_x.position(),
+                            amount: 1,
+                        },
+                    ],
+                },
+            ],
+            should_zero: false,
+        }]
+    });
+
+    declare_operator(scope, "std::ops::Div::div", u8_type, u8_type, |scope, x, y, target| {
+        let u8_type = scope.primitives().u8();
+        let remainder = scope.allocate(u8_type);
+
+        vec![Operation::TempAllocate {
+            temp: remainder,
+            body: divmod(scope, x, y, target, remainder),
+            should_zero: true,
+        }]
+    });
+
+    declare_operator(scope, "std::ops::Rem::rem", u8_type, u8_type, |scope, x, y, target| {
+        let u8_type = scope.primitives().u8();
+        let quotient = scope.allocate(u8_type);
+
+        vec![Operation::TempAllocate {
+            temp: quotient,
+            body: divmod(scope, x, y, quotient, target),
+            should_zero: true,
+        }]
+    });
+
+    // Every ordering is decided by whether one operand minus the other (without going below
+    // zero) is zero. For example, x < y is the same as y - x > 0.
+    declare_operator(scope, "std::cmp::PartialOrd::lt", u8_type, bool_type, |scope, x, y, target| {
+        compare(scope, y, x, target, true)
+    });
+
+    declare_operator(scope, "std::cmp::PartialOrd::gt", u8_type, bool_type, |scope, x, y, target| {
+        compare(scope, x, y, target, true)
+    });
+
+    declare_operator(scope, "std::cmp::PartialOrd::le", u8_type, bool_type, |scope, x, y, target| {
+        compare(scope, x, y, target, false)
+    });
+
+    declare_operator(scope, "std::cmp::PartialOrd::ge", u8_type, bool_type, |scope, x, y, target| {
+        compare(scope, y, x, target, false)
+    });
+
+    declare_operator(scope, "std::ops::BitAnd::bitand", u8_type, u8_type, |scope, x, y, target| {
+        // Algorithm:
+        //
+        // x[y[z += weight y[-]]x[-]]
+        bitwise(scope, x, y, |bit_x, bit_y, weight| vec![
+            Operation::Loop {
+                cond: bit_x.position(),
+                body: vec![
+                    Operation::Loop {
+                        cond: bit_y.position(),
+                        body: vec![
+                            Operation::Increment {
+                                target: target.position(),
+                                amount: weight,
+                            },
+                            Operation::Zero {
+                                target: bit_y,
+                            },
+                        ],
+                    },
+                    Operation::Zero {
+                        target: bit_x,
+                    },
+                ],
+            },
+            Operation::Zero {
+                target: bit_y,
+            },
+        ])
+    });
+
+    declare_operator(scope, "std::ops::BitOr::bitor", u8_type, u8_type, |scope, x, y, target| {
+        // Algorithm:
+        //
+        // x += y
+        // x[z += weight x[-]]
+        bitwise(scope, x, y, |bit_x, bit_y, weight| vec![
+            Operation::Relocate {
+                source: bit_y,
+                target: bit_x,
+            },
+            Operation::Loop {
+                cond: bit_x.position(),
+                body: vec![
+                    Operation::Increment {
+                        target: target.position(),
+                        amount: weight,
+                    },
+                    Operation::Zero {
+                        target: bit_x,
+                    },
+                ],
+            },
+        ])
+    });
+
+    declare_operator(scope, "std::ops::BitXor::bitxor", u8_type, u8_type, |scope, x, y, target| {
+        // Algorithm:
+        //
+        // x += y
+        // x[x- z += weight x[z -= weight x-]]
+        bitwise(scope, x, y, |bit_x, bit_y, weight| vec![
+            Operation::Relocate {
+                source: bit_y,
+                target: bit_x,
+            },
+            Operation::Loop {
+                cond: bit_x.position(),
+                body: vec![
+                    Operation::Decrement {
+                        target: bit_x.position(),
+                        amount: 1,
+                    },
+                    Operation::Increment {
+                        target: target.position(),
+                        amount: weight,
+                    },
+                    // Only runs if both bits were set
+                    Operation::Loop {
+                        cond: bit_x.position(),
+                        body: vec![
+                            Operation::Decrement {
+                                target: target.position(),
+                                amount: weight,
+                            },
+                            Operation::Decrement {
+                                target: bit_x.position(),
+                                amount: 1,
+                            },
+                        ],
+                    },
+                ],
+            },
+        ])
+    });
+
+    declare_operator(scope, "std::ops::Shl::shl", u8_type, u8_type, |scope, x, y, target| {
+        let u8_type = scope.primitives().u8();
+        let temp_y = scope.allocate(u8_type);
+        let doubled = scope.allocate(u8_type);
+
+        // Algorithm:
+        //
+        // z = x
+        // y[z[d++z-] (z = d) y-]
+        vec![Operation::TempAllocate {
+            temp: temp_y,
+            body: vec![Operation::TempAllocate {
+                temp: doubled,
+                body: vec![
+                    Operation::Copy {
+                        source: x.position(),
+                        target: target.position(),
+                        size: x.size(),
+                    },
+                    Operation::Copy {
+                        source: y.position(),
+                        target: temp_y.position(),
+                        size: y.size(),
+                    },
+                    Operation::Loop {
+                        cond: temp_y.position(),
+                        body: vec![
+                            Operation::Loop {
+                                cond: target.position(),
+                                body: vec![
+                                    Operation::Increment {
+                                        target: doubled.position(),
+                                        amount: 2,
+                                    },
+                                    Operation::Decrement {
+                                        target: target.position(),
+                                        amount: 1,
+                                    },
+                                ],
+                            },
+                            Operation::Relocate {
+                                source: doubled,
+                                target,
+                            },
+                            Operation::Decrement {
+                                target: temp_y.position(),
+                                amount: 1,
+                            },
+                        ],
+                    },
+                ],
+                should_zero: false,
+            }],
+            should_zero: false,
+        }]
+    });
+
+    declare_operator(scope, "std::ops::Shr::shr", u8_type, u8_type, |scope, x, y, target| {
+        let u8_type = scope.primitives().u8();
+        let temp_y = scope.allocate(u8_type);
+        let bit = scope.allocate(u8_type);
+
+        // Algorithm:
+        //
+        // z = x
+        // y[(z, b = z / 2, z % 2) b[-] y-]
+        let mut body = halve(scope, target, bit);
+        body.push(Operation::Zero {
+            target: bit,
+        });
+        body.push(Operation::Decrement {
+            target: temp_y.position(),
+            amount: 1,
+        });
+
+        vec![Operation::TempAllocate {
+            temp: temp_y,
+            body: vec![Operation::TempAllocate {
+                temp: bit,
+                body: vec![
+                    Operation::Copy {
+                        source: x.position(),
+                        target: target.position(),
+                        size: x.size(),
+                    },
+                    Operation::Copy {
+                        source: y.position(),
+                        target: temp_y.position(),
+                        size: y.size(),
+                    },
+                    Operation::Loop {
+                        cond: temp_y.position(),
+                        body,
+                    },
+                ],
+                should_zero: false,
+            }],
+            should_zero: false,
+        }]
+    });
+
     // Need this so that this next method definition does not overwrite the previous one
     scope.push_scope();
 
@@ -300,22 +623,22 @@ pub fn define_u8(scope: &mut ScopeStack, bool_type: TypeId) -> TypeId {
                     let u8_type = scope.primitives().u8();
                     let mem = scope.allocate(u8_type);
 
+                    // Each byte is written by changing the cell from the previous byte instead
+                    // of starting from zero every time, so `b"ll"` only needs one `.` for the
+                    // second `l`. The cell is changed back to zero after the last byte.
+                    let mut body = Vec::new();
+                    let mut previous = 0;
+                    for &ch in bytes.iter() {
+                        body.extend(change_by(mem, ch.wrapping_sub(previous)));
+                        body.push(Operation::Write {target: mem});
+                        previous = ch;
+                    }
+                    body.extend(change_by(mem, 0u8.wrapping_sub(previous)));
+
                     Ok(vec![
                         Operation::TempAllocate {
                             temp: mem,
-                            body: bytes.iter().flat_map(|&ch| vec![
-                                Operation::Increment {
-                                    target: mem.position(),
-                                    amount: ch,
-                                },
-                                Operation::Write {
-                                    target: mem,
-                                },
-                                Operation::Decrement {
-                                    target: mem.position(),
-                                    amount: ch,
-                                },
-                            ]).collect(),
+                            body,
                             should_zero: false,
                         },
                     ])
@@ -328,10 +651,374 @@ pub fn define_u8(scope: &mut ScopeStack, bool_type: TypeId) -> TypeId {
     u8_type
 }
 
+/// Generates the operation that adds the given wrapping amount to a cell, subtracting instead
+/// when that is shorter, or nothing if the amount is zero
+fn change_by(mem: MemoryBlock, amount: u8) -> Option<Operation> {
+    match amount {
+        0 => None,
+        1..=128 => Some(Operation::Increment {target: mem.position(), amount}),
+        _ => Some(Operation::Decrement {target: mem.position(), amount: 0u8.wrapping_sub(amount)}),
+    }
+}
+
+/// Declares a binary operator that takes two u8 values and returns a value of the given type
+///
+/// Numeric literal operands are stored in temporary cells first so that the given function only
+/// ever has to generate operations for values stored in memory. The function must store its
+/// result in the target without modifying either operand.
+fn declare_operator<F>(scope: &mut ScopeStack, name: &str, u8_type: TypeId, return_type: TypeId, f: F)
+    where F: 'static + Fn(&mut ScopeStack, MemoryBlock, MemoryBlock, MemoryBlock) -> Operations {
+
+    scope.declare_builtin_function(
+        Identifier::from(name),
+        ItemType::Function {
+            args: vec![FuncArgType::Arg(u8_type), FuncArgType::Arg(u8_type)],
+            return_type,
+        },
+        move |scope, args, target| with_operands(scope, args, |scope, x, y| f(scope, x, y, target))
+    );
+}
+
+/// Stores any numeric literals in the given u8 arguments in temporary cells and then generates
+/// the operations that use the memory of each argument
+fn with_operands<F>(scope: &mut ScopeStack, args: FuncArgs, f: F) -> OperationsResult
+    where F: FnOnce(&mut ScopeStack, MemoryBlock, MemoryBlock) -> Operations {
+
+    let u8_type = scope.primitives().u8();
+    let mut ops = Vec::new();
+    let mut temps = Vec::new();
+    let mut operands = Vec::new();
+    for arg in args {
+        match arg {
+            ScopeItem::TypedBlock {memory, ..} => operands.push(memory),
+            ScopeItem::NumericLiteral(value) => {
+                let temp = scope.allocate(u8_type);
+                // Replaced with the location of the call by the caller
+                ops.extend(expression::store_number(scope, value, Span::default(), Target::TypedBlock {
+                    type_id: u8_type,
+                    memory: temp,
+                })?);
+                temps.push(temp);
+                operands.push(temp);
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    debug_assert_eq!(operands.len(), 2);
+    ops.extend(f(scope, operands[0], operands[1]));
+    Ok(temps.into_iter().rev().fold(ops, |body, temp| vec![Operation::TempAllocate {
+        temp,
+        body,
+        should_zero: true,
+    }]))
+}
+
+/// Divides x by y, storing the results in quotient and remainder which must both be zero
+///
+/// Dividing by zero results in a quotient of zero and a remainder of x since the remainder never
+/// reaches the divisor. There is no way to stop the program like Rust would.
+fn divmod(scope: &mut ScopeStack, x: MemoryBlock, y: MemoryBlock, quotient: MemoryBlock, remainder: MemoryBlock) -> Operations {
+    let u8_type = scope.primitives().u8();
+    let dividend = scope.allocate(u8_type);
+    // The amount that remainder still needs to be incremented by before it reaches y
+    let left = scope.allocate(u8_type);
+    let cond = scope.allocate(u8_type);
+
+    // Algorithm:
+    //
+    // dividend = x
+    // left = y
+    // while dividend {
+    //     dividend -= 1
+    //     remainder += 1
+    //     left -= 1
+    //     if left == 0 {
+    //         quotient += 1
+    //         remainder = 0
+    //         left = y
+    //     }
+    // }
+    let body = vec![
+        Operation::Copy {
+            source: x.position(),
+            target: dividend.position(),
+            size: x.size(),
+        },
+        Operation::Copy {
+            source: y.position(),
+            target: left.position(),
+            size: y.size(),
+        },
+        Operation::Loop {
+            cond: dividend.position(),
+            body: vec![
+                Operation::Decrement {
+                    target: dividend.position(),
+                    amount: 1,
+                },
+                Operation::Increment {
+                    target: remainder.position(),
+                    amount: 1,
+                },
+                Operation::Decrement {
+                    target: left.position(),
+                    amount: 1,
+                },
+                // The condition is a copy so that left can be reset in the branch
+                Operation::Copy {
+                    source: left.position(),
+                    target: cond.position(),
+                    size: left.size(),
+                },
+                Operation::Branch {
+                    cond,
+                    if_body: Vec::new(),
+                    else_body: vec![
+                        Operation::Increment {
+                            target: quotient.position(),
+                            amount: 1,
+                        },
+                        Operation::Zero {
+                            target: remainder,
+                        },
+                        Operation::Copy {
+                            source: y.position(),
+                            target: left.position(),
+                            size: y.size(),
+                        },
+                    ],
+                },
+                Operation::Zero {
+                    target: cond,
+                },
+            ],
+        },
+    ];
+
+    vec![Operation::TempAllocate {
+        temp: dividend,
+        body: vec![Operation::TempAllocate {
+            temp: left,
+            body: vec![Operation::TempAllocate {
+                temp: cond,
+                body,
+                should_zero: false,
+            }],
+            should_zero: true,
+        }],
+        should_zero: false,
+    }]
+}
+
+/// Stores whether x - y is greater than zero (if nonzero is true) or whether it is zero (if
+/// nonzero is false) in the target without modifying either operand
+///
+/// The subtraction stops at zero instead of wrapping around, so x - y is only greater than zero
+/// when x > y.
+fn compare(scope: &mut ScopeStack, x: MemoryBlock, y: MemoryBlock, target: MemoryBlock, nonzero: bool) -> Operations {
+    let u8_type = scope.primitives().u8();
+    let diff = scope.allocate(u8_type);
+    let temp_y = scope.allocate(u8_type);
+    let cond = scope.allocate(u8_type);
+
+    // Algorithm:
+    //
+    // d = x
+    // y[c = d c[d- c[-]] y-]
+    // (z = 0) d[z+ d[-]]  or  (z = 1) d[z- d[-]]
+    let mut body = vec![
+        Operation::Copy {
+            source: x.position(),
+            target: diff.position(),
+            size: x.size(),
+        },
+        Operation::Copy {
+            source: y.position(),
+            target: temp_y.position(),
+            size: y.size(),
+        },
+        Operation::Loop {
+            cond: temp_y.position(),
+            body: vec![
+                Operation::Copy {
+                    source: diff.position(),
+                    target: cond.position(),
+                    size: diff.size(),
+                },
+                // Only decrements the difference if it is not already zero
+                Operation::Loop {
+                    cond: cond.position(),
+                    body: vec![
+                        Operation::Decrement {
+                            target: diff.position(),
+                            amount: 1,
+                        },
+                        Operation::Zero {
+                            target: cond,
+                        },
+                    ],
+                },
+                Operation::Decrement {
+                    target: temp_y.position(),
+                    amount: 1,
+                },
+            ],
+        },
+    ];
+
+    if !nonzero {
+        body.push(Operation::Increment {
+            target: target.position(),
+            amount: 1,
+        });
+    }
+    body.push(Operation::Loop {
+        cond: diff.position(),
+        body: vec![
+            if nonzero {
+                Operation::Increment {
+                    target: target.position(),
+                    amount: 1,
+                }
+            }
+            else {
+                Operation::Decrement {
+                    target: target.position(),
+                    amount: 1,
+                }
+            },
+            Operation::Zero {
+                target: diff,
+            },
+        ],
+    });
+
+    vec![Operation::TempAllocate {
+        temp: diff,
+        body: vec![Operation::TempAllocate {
+            temp: temp_y,
+            body: vec![Operation::TempAllocate {
+                temp: cond,
+                body,
+                should_zero: false,
+            }],
+            should_zero: false,
+        }],
+        should_zero: false,
+    }]
+}
+
+/// Goes through the bits of x and y from lowest to highest, storing each pair of bits in
+/// temporary cells before generating the given operations to combine them into the result
+///
+/// The operations are given the cells of each bit and the value of that bit in the target. They
+/// must leave both cells zeroed.
+fn bitwise<F>(scope: &mut ScopeStack, x: MemoryBlock, y: MemoryBlock, combine: F) -> Operations
+    where F: Fn(MemoryBlock, MemoryBlock, u8) -> Operations {
+    let u8_type = scope.primitives().u8();
+    let temp_x = scope.allocate(u8_type);
+    let temp_y = scope.allocate(u8_type);
+    let bit_x = scope.allocate(u8_type);
+    let bit_y = scope.allocate(u8_type);
+
+    let mut body = vec![
+        Operation::Copy {
+            source: x.position(),
+            target: temp_x.position(),
+            size: x.size(),
+        },
+        Operation::Copy {
+            source: y.position(),
+            target: temp_y.position(),
+            size: y.size(),
+        },
+    ];
+    for i in 0..8 {
+        body.extend(halve(scope, temp_x, bit_x));
+        body.extend(halve(scope, temp_y, bit_y));
+        body.extend(combine(bit_x, bit_y, 1 << i));
+    }
+
+    // Every bit has been taken out of both operands, so there is nothing left to zero
+    [temp_x, temp_y, bit_x, bit_y].iter().rev().fold(body, |body, &temp| vec![Operation::TempAllocate {
+        temp,
+        body,
+        should_zero: false,
+    }])
+}
+
+/// Divides the value by two, storing the remainder in bit which must be zero
+fn halve(scope: &mut ScopeStack, value: MemoryBlock, bit: MemoryBlock) -> Operations {
+    let u8_type = scope.primitives().u8();
+    let half = scope.allocate(u8_type);
+    let cond = scope.allocate(u8_type);
+
+    // Algorithm:
+    //
+    // v[v- b+ c = v c[v- h+ b- c[-]]]
+    // v = h
+    vec![Operation::TempAllocate {
+        temp: half,
+        body: vec![Operation::TempAllocate {
+            temp: cond,
+            body: vec![
+                Operation::Loop {
+                    cond: value.position(),
+                    body: vec![
+                        Operation::Decrement {
+                            target: value.position(),
+                            amount: 1,
+                        },
+                        Operation::Increment {
+                            target: bit.position(),
+                            amount: 1,
+                        },
+                        Operation::Copy {
+                            source: value.position(),
+                            target: cond.position(),
+                            size: value.size(),
+                        },
+                        // Only runs if there was a second unit to take away
+                        Operation::Loop {
+                            cond: cond.position(),
+                            body: vec![
+                                Operation::Decrement {
+                                    target: value.position(),
+                                    amount: 1,
+                                },
+                                Operation::Increment {
+                                    target: half.position(),
+                                    amount: 1,
+                                },
+                                Operation::Decrement {
+                                    target: bit.position(),
+                                    amount: 1,
+                                },
+                                Operation::Zero {
+                                    target: cond,
+                                },
+                            ],
+                        },
+                    ],
+                },
+                Operation::Relocate {
+                    source: half,
+                    target: value,
+                },
+            ],
+            should_zero: false,
+        }],
+        should_zero: false,
+    }]
+}
+
 #[cfg(test)]
+#[allow(clippy::infallible_destructuring_match)]
 mod tests {
     use super::*;
 
+    use codegen::testing::assert_output;
     use operations::scope::{ScopeType};
 
     #[test]
@@ -345,4 +1032,60 @@ mod tests {
         };
         assert_eq!(scope.primitives().u8(), u8_type_id);
     }
+
+    #[test]
+    fn prints_byte_literals_from_previous_byte() {
+        let mut scope = ScopeStack::new();
+        define_u8(&mut scope, 1000);
+
+        let unit_type = scope.primitives().unit();
+        let ops = expression::call(
+            &mut scope,
+            Identifier::from("std::fmt::Display::print"),
+            vec![ScopeItem::ByteLiteral(b"Hee\x01".to_vec())],
+            Target::TypedBlock {type_id: unit_type, memory: MemoryBlock::default()},
+        ).unwrap();
+
+        let (mem, body) = match ops[0] {
+            Operation::TempAllocate {temp, ref body, ..} => (temp, body.clone()),
+            _ => panic!("Expected the bytes to be written using a temporary cell"),
+        };
+        assert_eq!(body, vec![
+            Operation::Increment {target: mem.position(), amount: b'H'},
+            Operation::Write {target: mem},
+            Operation::Increment {target: mem.position(), amount: b'e' - b'H'},
+            Operation::Write {target: mem},
+            Operation::Write {target: mem},
+            // Going down is shorter than wrapping all the way around
+            Operation::Decrement {target: mem.position(), amount: b'e' - 1},
+            Operation::Write {target: mem},
+            Operation::Decrement {target: mem.position(), amount: 1},
+        ]);
+    }
+
+    #[test]
+    fn arithmetic_in_loops() {
+        // None of these values are known at compile time since they change in the loop
+        assert_output(r#"
+            let mut i: u8 = 0;
+            let mut running: bool = true;
+            while running {
+                let x: u8 = i * 7 + 3;
+                stdout.print(x / 10, x % 10, b" ", 5 / i, 5 % i, b" ");
+                i += 1;
+                running = i != 3;
+            }
+        "#, b"", b"03 05 10 50 17 21 ");
+    }
+
+    #[test]
+    fn comparisons_and_bitwise_in_loops() {
+        assert_output(r#"
+            let mut i: u8 = 0;
+            while i < 6 {
+                stdout.print(i <= 2, i > 3, i & 3, i | 4, i ^ 5, i << 1, i >> 1, b" ");
+                i += 1;
+            }
+        "#, b"", b"1004500 1015420 1026741 0037661 0104182 01150:2 ");
+    }
 }
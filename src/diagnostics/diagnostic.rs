@@ -0,0 +1,78 @@
+use std::fmt;
+
+use parser::Span;
+
+/// How serious a diagnostic is
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Level {
+    /// The program could not be compiled
+    Error,
+    /// The program compiled but probably does not do what was intended
+    Warning,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match *self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        })
+    }
+}
+
+/// A message about a specific piece of the source code
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub level: Level,
+    /// A short description of the problem
+    pub message: String,
+    /// The code that the diagnostic is about, may be unknown if no code is responsible
+    pub span: Span,
+    /// Shown right next to the highlighted code
+    pub label: Option<String>,
+    /// Extra information about the problem
+    pub notes: Vec<String>,
+    /// Suggestions for fixing the problem
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error<S: Into<String>>(message: S, span: Span) -> Diagnostic {
+        Diagnostic::new(Level::Error, message, span)
+    }
+
+    pub fn warning<S: Into<String>>(message: S, span: Span) -> Diagnostic {
+        Diagnostic::new(Level::Warning, message, span)
+    }
+
+    fn new<S: Into<String>>(level: Level, message: S, span: Span) -> Diagnostic {
+        Diagnostic {
+            level,
+            message: message.into(),
+            span,
+            label: None,
+            notes: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+
+    /// Returns this diagnostic with the given label next to the highlighted code
+    pub fn with_label<S: Into<String>>(self, label: S) -> Diagnostic {
+        Diagnostic {
+            label: Some(label.into()),
+            ..self
+        }
+    }
+
+    /// Returns this diagnostic with the given note added to it
+    pub fn with_note<S: Into<String>>(mut self, note: S) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Returns this diagnostic with the given help text added to it
+    pub fn with_help<S: Into<String>>(mut self, help: S) -> Diagnostic {
+        self.help.push(help.into());
+        self
+    }
+}
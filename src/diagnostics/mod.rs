@@ -0,0 +1,11 @@
+//! Errors reported to the user along with the source code that caused them
+//!
+//! Both syntax errors and errors found while generating operations are converted into a
+//! `Diagnostic` which can then be rendered with a snippet of the source code, similar to the
+//! errors produced by rustc.
+
+mod diagnostic;
+mod render;
+
+pub use self::diagnostic::*;
+pub use self::render::*;
@@ -0,0 +1,171 @@
+use super::{Diagnostic, Level};
+
+/// The number of columns that a tab takes up in a rendered snippet
+const TAB_WIDTH: usize = 4;
+
+// ANSI escape codes for each part of a rendered diagnostic
+const BOLD: &str = "1";
+const BOLD_RED: &str = "1;31";
+const BOLD_YELLOW: &str = "1;33";
+const BOLD_BLUE: &str = "1;34";
+
+/// Whether rendered diagnostics use ANSI escape codes to add colour
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ColorMode {
+    Plain,
+    Colored,
+}
+
+impl ColorMode {
+    fn paint(self, style: &str, text: &str) -> String {
+        match self {
+            ColorMode::Plain => text.to_owned(),
+            ColorMode::Colored => format!("\x1b[{}m{}\x1b[0m", style, text),
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Renders this diagnostic with the snippet of the given source code that it refers to
+    ///
+    /// ```text
+    /// error: cannot find `b` in this scope
+    ///  --> hello.brn:2:14
+    ///   |
+    /// 2 | stdout.print(b);
+    ///   |              ^ not found in this scope
+    /// ```
+    ///
+    /// Spans that continue onto later lines are only highlighted until the end of their first
+    /// line. Diagnostics without a known span are rendered without a snippet.
+    pub fn render(&self, path: &str, source: &str, color: ColorMode) -> String {
+        let level_style = match self.level {
+            Level::Error => BOLD_RED,
+            Level::Warning => BOLD_YELLOW,
+        };
+
+        let mut rendered = format!("{}{}\n",
+            color.paint(level_style, &self.level.to_string()),
+            color.paint(BOLD, &format!(": {}", self.message)));
+
+        let gutter = if self.span.is_unknown() {
+            String::new()
+        }
+        else {
+            let line_number = self.span.line.to_string();
+            let gutter = " ".repeat(line_number.len());
+            let bar = color.paint(BOLD_BLUE, "|");
+
+            let start = self.span.start.min(source.len());
+            let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = source[start..].find('\n').map_or(source.len(), |i| start + i);
+            let line = source[line_start..line_end].trim_end_matches('\r');
+            let end = self.span.end.min(line_start + line.len()).max(start);
+
+            let column = display_width(&source[line_start..start]);
+            let width = display_width(&source[start..end]).max(1);
+            let underline = format!("{}{}", "^".repeat(width), self.label.as_ref()
+                .map_or_else(String::new, |label| format!(" {}", label)));
+
+            rendered.push_str(&format!("{}{} {}:{}:{}\n",
+                gutter, color.paint(BOLD_BLUE, "-->"), path, self.span.line, self.span.col));
+            rendered.push_str(&format!("{} {}\n", gutter, bar));
+            rendered.push_str(&format!("{} {} {}\n",
+                color.paint(BOLD_BLUE, &line_number), bar, line.replace('\t', &" ".repeat(TAB_WIDTH))));
+            rendered.push_str(&format!("{} {} {}{}\n",
+                gutter, bar, " ".repeat(column), color.paint(level_style, &underline)));
+
+            if !self.notes.is_empty() || !self.help.is_empty() {
+                rendered.push_str(&format!("{} {}\n", gutter, bar));
+            }
+            gutter
+        };
+
+        let notes = self.notes.iter().map(|note| ("note", note));
+        let help = self.help.iter().map(|help| ("help", help));
+        for (kind, text) in notes.chain(help) {
+            rendered.push_str(&format!("{} {} {} {}\n",
+                gutter, color.paint(BOLD_BLUE, "="), color.paint(BOLD, &format!("{}:", kind)), text));
+        }
+
+        rendered
+    }
+}
+
+/// Returns the number of columns that the given text takes up in a rendered snippet
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c == '\t' { TAB_WIDTH } else { 1 }).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parser::Span;
+
+    #[test]
+    fn renders_snippet() {
+        let source = "let a: u8 = 1;\nstdout.print(bc);\n";
+        let diagnostic = Diagnostic::error("cannot find `bc` in this scope", Span::new(28, 30, 2, 14))
+            .with_label("not found in this scope")
+            .with_help("declare `bc` before using it");
+
+        assert_eq!(diagnostic.render("test.brn", source, ColorMode::Plain), "\
+error: cannot find `bc` in this scope
+ --> test.brn:2:14
+  |
+2 | stdout.print(bc);
+  |              ^^ not found in this scope
+  |
+  = help: declare `bc` before using it
+");
+    }
+
+    #[test]
+    fn renders_multiline_spans_and_tabs() {
+        let source = "\twhile a {\n\tb;\n}";
+        let diagnostic = Diagnostic::error("mismatched types", Span::new(1, source.len(), 1, 2));
+
+        assert_eq!(diagnostic.render("test.brn", source, ColorMode::Plain), "\
+error: mismatched types
+ --> test.brn:1:2
+  |
+1 |     while a {
+  |     ^^^^^^^^^
+");
+    }
+
+    #[test]
+    fn renders_unknown_spans() {
+        let diagnostic = Diagnostic::error("something went wrong", Span::default())
+            .with_note("this is a note");
+
+        assert_eq!(diagnostic.render("test.brn", "", ColorMode::Plain), "\
+error: something went wrong
+ = note: this is a note
+");
+    }
+
+    #[test]
+    fn renders_warnings() {
+        let source = "let a: u8 = 1;\n";
+        let diagnostic = Diagnostic::warning("unused variable: `a`", Span::new(4, 5, 1, 5))
+            .with_label("help: if this is intentional, prefix it with an underscore: `_a`");
+
+        assert_eq!(diagnostic.render("test.brn", source, ColorMode::Plain), "\
+warning: unused variable: `a`
+ --> test.brn:1:5
+  |
+1 | let a: u8 = 1;
+  |     ^ help: if this is intentional, prefix it with an underscore: `_a`
+");
+        assert!(diagnostic.render("test.brn", source, ColorMode::Colored).starts_with("\x1b[1;33mwarning"));
+    }
+
+    #[test]
+    fn renders_colors() {
+        let diagnostic = Diagnostic::error("oops", Span::default());
+        assert_eq!(diagnostic.render("test.brn", "", ColorMode::Colored),
+            "\x1b[1;31merror\x1b[0m\x1b[1m: oops\x1b[0m\n");
+    }
+}
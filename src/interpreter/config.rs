@@ -0,0 +1,105 @@
+use std::str::FromStr;
+
+/// The number of bits in each cell of the tape
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum CellWidth {
+    #[default]
+    U8,
+    U16,
+    U32,
+}
+
+impl CellWidth {
+    /// Returns the largest value that can be stored in a cell of this width
+    pub fn max_value(self) -> u32 {
+        match self {
+            CellWidth::U8 => u8::MAX as u32,
+            CellWidth::U16 => u16::MAX as u32,
+            CellWidth::U32 => u32::MAX,
+        }
+    }
+}
+
+impl FromStr for CellWidth {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "8" => Ok(CellWidth::U8),
+            "16" => Ok(CellWidth::U16),
+            "32" => Ok(CellWidth::U32),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What happens when a cell is incremented past its maximum value or decremented below zero
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum OverflowBehaviour {
+    /// The value wraps around to the other end of the range of the cell
+    #[default]
+    Wrap,
+    /// The program is stopped with an error
+    Error,
+}
+
+impl FromStr for OverflowBehaviour {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wrap" => Ok(OverflowBehaviour::Wrap),
+            "error" => Ok(OverflowBehaviour::Error),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What is stored in the current cell when a read instruction is run after the end of the
+/// input has been reached
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum EofBehaviour {
+    /// The cell is left as it was before the read
+    #[default]
+    Unchanged,
+    /// The cell is set to zero
+    Zero,
+    /// The cell is set to its maximum value (255 for 8-bit cells, or -1 in two's complement)
+    Max,
+}
+
+impl FromStr for EofBehaviour {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unchanged" => Ok(EofBehaviour::Unchanged),
+            "zero" => Ok(EofBehaviour::Zero),
+            "max" => Ok(EofBehaviour::Max),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The number of cells available on the tape
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum TapeSize {
+    /// The tape grows to the right whenever the pointer moves past its end
+    #[default]
+    Unbounded,
+    /// The tape has exactly this many cells and moving past the last one is an error
+    Bounded(usize),
+}
+
+/// The semantics of the brainfuck machine used to run a program
+///
+/// Different brainfuck interpreters make different choices about these details. The default
+/// configuration matches the brainfuck specification targeted by the brain compiler: 8-bit
+/// wrapping cells, unchanged cells on EOF and an unbounded tape.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Config {
+    pub cell_width: CellWidth,
+    pub overflow: OverflowBehaviour,
+    pub eof: EofBehaviour,
+    pub tape_size: TapeSize,
+}
@@ -0,0 +1,101 @@
+use std::io;
+use std::fmt;
+use std::error;
+
+/// A problem with the tape encountered while running a single instruction
+///
+/// Use `Fault::at` to attach the index of the instruction that caused it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Fault {
+    /// The pointer was moved before the first cell of the tape
+    PointerUnderflow,
+    /// A cell was incremented past its maximum value
+    CellOverflow,
+    /// A cell was decremented below zero
+    CellUnderflow,
+    /// The pointer was moved past the last cell of a bounded tape
+    TapeExhausted,
+}
+
+impl Fault {
+    /// Converts this fault into an error that occurred at the given instruction index
+    pub fn at(self, instruction: usize) -> Error {
+        match self {
+            Fault::PointerUnderflow => Error::PointerUnderflow {instruction},
+            Fault::CellOverflow => Error::CellOverflow {instruction},
+            Fault::CellUnderflow => Error::CellUnderflow {instruction},
+            Fault::TapeExhausted => Error::TapeExhausted {instruction},
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// A `[` or `]` at the given instruction index has no matching bracket
+    UnmatchedJump {
+        instruction: usize,
+    },
+
+    /// The instruction at the given index moved the pointer before the start of the tape
+    PointerUnderflow {
+        instruction: usize,
+    },
+
+    /// The instruction at the given index incremented a cell past its maximum value while
+    /// overflow was configured to be an error
+    CellOverflow {
+        instruction: usize,
+    },
+
+    /// The instruction at the given index decremented a cell below zero while overflow was
+    /// configured to be an error
+    CellUnderflow {
+        instruction: usize,
+    },
+
+    /// The instruction at the given index moved the pointer past the end of a bounded tape
+    TapeExhausted {
+        instruction: usize,
+    },
+
+    /// Reading input or writing output failed
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnmatchedJump {instruction} => {
+                write!(f, "unmatched jump instruction at instruction {}", instruction)
+            },
+            Error::PointerUnderflow {instruction} => {
+                write!(f, "pointer moved before the start of the tape at instruction {}", instruction)
+            },
+            Error::CellOverflow {instruction} => {
+                write!(f, "cell value overflowed at instruction {}", instruction)
+            },
+            Error::CellUnderflow {instruction} => {
+                write!(f, "cell value underflowed at instruction {}", instruction)
+            },
+            Error::TapeExhausted {instruction} => {
+                write!(f, "pointer moved past the end of the tape at instruction {}", instruction)
+            },
+            Error::Io(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
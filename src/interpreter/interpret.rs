@@ -0,0 +1,307 @@
+use std::io::{self, Read, Write};
+
+use codegen::Instructions;
+use ir::Ops;
+
+use super::{Tape, Config, EofBehaviour, Error, Fault};
+use super::step::{self, Step};
+
+/// Executes brainfuck instructions on a tape, reading input from `stdin` and writing output to
+/// `stdout`
+///
+/// Any `Read` and `Write` implementations can be used which makes it easy to run programs
+/// against fixed input and capture their output.
+pub struct Interpreter<R: Read, W: Write> {
+    tape: Tape,
+    stdin: R,
+    stdout: W,
+}
+
+impl<R: Read, W: Write> Interpreter<R, W> {
+    /// Creates an interpreter with an empty tape using the default configuration
+    pub fn new(stdin: R, stdout: W) -> Interpreter<R, W> {
+        Interpreter::with_tape(Tape::new(), stdin, stdout)
+    }
+
+    /// Creates an interpreter with an empty tape that follows the given configuration
+    pub fn with_config(config: Config, stdin: R, stdout: W) -> Interpreter<R, W> {
+        Interpreter::with_tape(Tape::with_config(config), stdin, stdout)
+    }
+
+    /// Creates an interpreter that will run on the given tape
+    ///
+    /// The interpreter follows the configuration the tape was created with.
+    pub fn with_tape(tape: Tape, stdin: R, stdout: W) -> Interpreter<R, W> {
+        Interpreter {
+            tape,
+            stdin,
+            stdout,
+        }
+    }
+
+    /// Returns the tape in the state left behind by the programs run so far
+    pub fn tape(&self) -> &Tape {
+        &self.tape
+    }
+
+    /// Consumes the interpreter and returns its input and output handles
+    pub fn into_inner(self) -> (R, W) {
+        (self.stdin, self.stdout)
+    }
+
+    /// Parses and runs raw brainfuck source code
+    /// Any characters that are not brainfuck instructions are treated as comments
+    pub fn run_source(&mut self, source: &str) -> Result<(), Error> {
+        self.run(&Instructions::from(source))
+    }
+
+    /// Runs the given instructions until the end of the program is reached
+    ///
+    /// The tape is not reset before running, so running several programs in a row on the same
+    /// interpreter will continue from wherever the last program left off.
+    pub fn run(&mut self, instructions: &Instructions) -> Result<(), Error> {
+        let steps = step::from_instructions(instructions)?;
+        self.execute(&steps)
+    }
+
+    /// Runs the given IR operations until the end of the program is reached
+    ///
+    /// Any runtime errors report the index of the operation that caused them as if every loop
+    /// was replaced by a jump, its body, and then another jump.
+    pub fn run_ir(&mut self, ops: &Ops) -> Result<(), Error> {
+        let steps = step::from_ir(ops);
+        self.execute(&steps)
+    }
+
+    fn execute(&mut self, steps: &[Step]) -> Result<(), Error> {
+        let mut pc = 0;
+        while pc < steps.len() {
+            match steps[pc] {
+                Step::JumpForwardIfZero(end) if self.tape.get() == 0 => pc = end,
+                Step::JumpBackwardUnlessZero(start) if self.tape.get() != 0 => pc = start,
+                Step::JumpForwardIfZero(_) | Step::JumpBackwardUnlessZero(_) => {},
+                step => self.step(step).map_err(|err| match err {
+                    StepError::Fault(fault) => fault.at(pc),
+                    StepError::Io(err) => Error::Io(err),
+                })?,
+            }
+
+            pc += 1;
+        }
+
+        self.stdout.flush()?;
+
+        Ok(())
+    }
+
+    /// Runs the effect of a single step on the tape and IO handles. Jumps have no effect
+    /// here since they only affect control flow.
+    fn step(&mut self, step: Step) -> Result<(), StepError> {
+        match step {
+            Step::Add(offset, amount) => self.tape.add_at(offset, amount as i64)?,
+            Step::Move(amount) => self.tape.move_by(amount)?,
+            Step::SetZero(offset) => self.tape.set_at(offset, 0)?,
+            Step::MulAdd {src, dst, factor} => {
+                let value = self.tape.get_at(src)?;
+                self.tape.add_at(dst, value as i64 * factor as i64)?;
+            },
+            // Only the lowest byte of cells wider than 8 bits is written
+            Step::Write(offset) => {
+                let value = self.tape.get_at(offset)?;
+                self.stdout.write_all(&[value as u8])?;
+            },
+            Step::Read(offset) => {
+                let mut buf = [0];
+                if self.stdin.read(&mut buf)? == 1 {
+                    self.tape.set_at(offset, buf[0] as u32)?;
+                }
+                else {
+                    match self.tape.config().eof {
+                        EofBehaviour::Unchanged => {},
+                        EofBehaviour::Zero => self.tape.set_at(offset, 0)?,
+                        EofBehaviour::Max => {
+                            let max = self.tape.config().cell_width.max_value();
+                            self.tape.set_at(offset, max)?;
+                        },
+                    }
+                }
+            },
+            Step::JumpForwardIfZero(_) | Step::JumpBackwardUnlessZero(_) => {},
+        }
+
+        Ok(())
+    }
+}
+
+/// An error from a single step before it has been given the index of its instruction
+enum StepError {
+    Fault(Fault),
+    Io(io::Error),
+}
+
+impl From<Fault> for StepError {
+    fn from(fault: Fault) -> StepError {
+        StepError::Fault(fault)
+    }
+}
+
+impl From<io::Error> for StepError {
+    fn from(err: io::Error) -> StepError {
+        StepError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{CellWidth, OverflowBehaviour, TapeSize};
+
+    #[test]
+    fn hello_world() {
+        let output = run(concat!(
+            "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.",
+            "------.--------.>>+.>++.",
+        ), b"");
+
+        assert_eq!(output, b"Hello World!\n");
+    }
+
+    #[test]
+    fn reads_input() {
+        // Reverses its input
+        let output = run(">,[>,]<[.<]", b"abc");
+        assert_eq!(output, b"cba");
+    }
+
+    #[test]
+    fn eof_leaves_cell_unchanged() {
+        let output = run("+++,.", b"");
+        assert_eq!(output, &[3]);
+    }
+
+    #[test]
+    fn eof_behaviours() {
+        let config = Config {eof: EofBehaviour::Zero, ..Config::default()};
+        assert_eq!(run_with_config(config, "+++,.", b""), &[0]);
+
+        let config = Config {eof: EofBehaviour::Max, ..Config::default()};
+        assert_eq!(run_with_config(config, "+++,.", b""), &[255]);
+
+        // Only the lowest byte of a wide cell is written
+        let config = Config {eof: EofBehaviour::Max, cell_width: CellWidth::U16, ..Config::default()};
+        let mut interpreter = Interpreter::with_config(config, &b""[..], Vec::new());
+        interpreter.run_source(",.").unwrap();
+        assert_eq!(interpreter.tape().get(), 65535);
+        assert_eq!(interpreter.into_inner().1, &[255]);
+    }
+
+    #[test]
+    fn wide_cells() {
+        // 256 only fits in cells wider than 8 bits, so this loop only runs with 16-bit cells
+        let source = "++++++++++++++++[>++++++++++++++++<-]>[>+<[-]]>.";
+        assert_eq!(run(source, b""), &[0]);
+
+        let config = Config {cell_width: CellWidth::U16, ..Config::default()};
+        assert_eq!(run_with_config(config, source, b""), &[1]);
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let config = Config {overflow: OverflowBehaviour::Error, ..Config::default()};
+        let mut interpreter = Interpreter::with_config(config, &b""[..], Vec::new());
+        match interpreter.run_source("+>-") {
+            Err(Error::CellUnderflow {instruction: 2}) => {},
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let mut interpreter = Interpreter::with_config(config, &b""[..], Vec::new());
+        match interpreter.run_source("-[-]") {
+            Err(Error::CellUnderflow {instruction: 0}) => {},
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let mut interpreter = Interpreter::with_config(config, &b""[..], Vec::new());
+        match interpreter.run_source("+[+]") {
+            Err(Error::CellOverflow {instruction: 2}) => {},
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let mut interpreter = Interpreter::new(&b""[..], Vec::new());
+        match interpreter.run_source(">+<<") {
+            Err(Error::PointerUnderflow {instruction: 3}) => {},
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        let config = Config {tape_size: TapeSize::Bounded(4), ..Config::default()};
+        let mut interpreter = Interpreter::with_config(config, &b""[..], Vec::new());
+        match interpreter.run_source("+[>+]") {
+            Err(Error::TapeExhausted {instruction: 2}) => {},
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        assert_eq!(interpreter.tape().cells(), &[1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn runs_ir() {
+        use ir::Op;
+
+        // Doubles the input and writes it out twice
+        let ops: Ops = vec![
+            Op::Read(0),
+            Op::MulAdd {src: 0, dst: 1, factor: 2},
+            Op::SetZero(0),
+            Op::Move(1),
+            Op::Write(0),
+            Op::Loop(vec![Op::Write(0), Op::Add(0, -1), Op::SetZero(0)].into()),
+            Op::Add(-1, 5),
+            Op::Write(-1),
+        ].into();
+
+        let mut interpreter = Interpreter::new(&b"\x21"[..], Vec::new());
+        interpreter.run_ir(&ops).unwrap();
+        assert_eq!(interpreter.tape().cells(), &[5, 0]);
+        assert_eq!(interpreter.into_inner().1, &[0x42, 0x42, 5]);
+
+        // Errors report the index of the operation with loops flattened into jumps
+        let ops: Ops = vec![
+            Op::Add(0, 1),
+            Op::Loop(vec![Op::Add(0, -1)].into()),
+            Op::Move(-1),
+        ].into();
+        let mut interpreter = Interpreter::new(&b""[..], Vec::new());
+        match interpreter.run_ir(&ops) {
+            Err(Error::PointerUnderflow {instruction: 4}) => {},
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn ignores_comments() {
+        let output = run("this is a comment +++ that adds three.", b"");
+        assert_eq!(output, &[3]);
+    }
+
+    #[test]
+    fn unmatched_jumps() {
+        let mut interpreter = Interpreter::new(&b""[..], Vec::new());
+        match interpreter.run_source("+[[-]") {
+            Err(Error::UnmatchedJump {instruction: 1}) => {},
+            res => panic!("Unexpected result: {:?}", res),
+        }
+
+        match interpreter.run_source("+[-]]") {
+            Err(Error::UnmatchedJump {instruction: 4}) => {},
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
+    fn run(source: &str, input: &[u8]) -> Vec<u8> {
+        run_with_config(Config::default(), source, input)
+    }
+
+    fn run_with_config(config: Config, source: &str, input: &[u8]) -> Vec<u8> {
+        let mut interpreter = Interpreter::with_config(config, input, Vec::new());
+        interpreter.run_source(source).unwrap();
+        interpreter.into_inner().1
+    }
+}
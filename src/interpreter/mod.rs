@@ -0,0 +1,15 @@
+//! A brainfuck interpreter that can execute the instructions generated by the compiler
+//!
+//! This makes it possible to run brain programs without depending on a separate brainfuck
+//! interpreter being installed.
+
+mod config;
+mod tape;
+mod interpret;
+mod error;
+mod step;
+
+pub use self::config::*;
+pub use self::tape::*;
+pub use self::interpret::*;
+pub use self::error::*;
@@ -0,0 +1,89 @@
+use codegen::{Instructions, Instruction};
+use ir::{Ops, Op, Offset};
+
+use super::Error;
+
+/// A single step of a program that has been flattened so that it can be run by the interpreter
+///
+/// Both instructions and IR operations are converted into steps so that there is only one way
+/// to run a program. Jumps store the index of the step they jump to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Step {
+    Add(Offset, i32),
+    Move(Offset),
+    SetZero(Offset),
+    MulAdd {
+        src: Offset,
+        dst: Offset,
+        factor: i32,
+    },
+    Read(Offset),
+    Write(Offset),
+    JumpForwardIfZero(usize),
+    JumpBackwardUnlessZero(usize),
+}
+
+/// Converts each instruction into exactly one step so that the index of each step is the same as
+/// the index of the instruction it came from
+pub fn from_instructions(instructions: &Instructions) -> Result<Vec<Step>, Error> {
+    let mut steps = Vec::with_capacity(instructions.len());
+    let mut stack = Vec::new();
+
+    for (i, &instr) in instructions.iter().enumerate() {
+        let step = match instr {
+            Instruction::Right => Step::Move(1),
+            Instruction::Left => Step::Move(-1),
+            Instruction::Increment => Step::Add(0, 1),
+            Instruction::Decrement => Step::Add(0, -1),
+            Instruction::Write => Step::Write(0),
+            Instruction::Read => Step::Read(0),
+            Instruction::JumpForwardIfZero => {
+                stack.push(i);
+                // Filled in once the matching jump is found
+                Step::JumpForwardIfZero(0)
+            },
+            Instruction::JumpBackwardUnlessZero => {
+                let start = stack.pop().ok_or(Error::UnmatchedJump {instruction: i})?;
+                steps[start] = Step::JumpForwardIfZero(i);
+                Step::JumpBackwardUnlessZero(start)
+            },
+        };
+        steps.push(step);
+    }
+
+    match stack.pop() {
+        Some(start) => Err(Error::UnmatchedJump {instruction: start}),
+        None => Ok(steps),
+    }
+}
+
+/// Flattens the given IR operations so that each loop becomes a pair of jumps around its body
+pub fn from_ir(ops: &Ops) -> Vec<Step> {
+    let mut steps = Vec::new();
+    flatten(ops, &mut steps);
+    steps
+}
+
+fn flatten(ops: &Ops, steps: &mut Vec<Step>) {
+    for op in ops.iter() {
+        let step = match *op {
+            Op::Add(offset, amount) => Step::Add(offset, amount),
+            Op::Move(amount) => Step::Move(amount),
+            Op::SetZero(offset) => Step::SetZero(offset),
+            Op::MulAdd {src, dst, factor} => Step::MulAdd {src, dst, factor},
+            Op::Read(offset) => Step::Read(offset),
+            Op::Write(offset) => Step::Write(offset),
+            Op::Loop(ref body) => {
+                let start = steps.len();
+                // Filled in once the body has been flattened
+                steps.push(Step::JumpForwardIfZero(0));
+                flatten(body, steps);
+
+                let end = steps.len();
+                steps[start] = Step::JumpForwardIfZero(end);
+                Step::JumpBackwardUnlessZero(start)
+            },
+        };
+        steps.push(step);
+    }
+}
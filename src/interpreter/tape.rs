@@ -0,0 +1,286 @@
+use memory::CellIndex;
+use ir::Offset;
+
+use super::{Config, OverflowBehaviour, TapeSize, Fault};
+
+/// The brainfuck "turing machine" tape
+///
+/// The tape starts with a single zeroed cell and grows to the right as the pointer moves past
+/// its end. Every new cell is zero. How far the tape can grow and what values its cells can
+/// hold are determined by the `Config` it was created with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tape {
+    cells: Vec<u32>,
+    pointer: CellIndex,
+    config: Config,
+}
+
+impl Default for Tape {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tape {
+    /// Creates an empty tape with the pointer at the first cell using the default configuration
+    pub fn new() -> Tape {
+        Tape::with_config(Config::default())
+    }
+
+    /// Creates an empty tape with room for `capacity` cells before it needs to grow
+    pub fn with_capacity(capacity: usize) -> Tape {
+        let mut tape = Tape::new();
+        tape.cells.reserve(capacity);
+        tape
+    }
+
+    /// Creates an empty tape with the pointer at the first cell that follows the given
+    /// configuration
+    ///
+    /// # Panics
+    /// Panics if the configuration specifies a bounded tape with no cells
+    pub fn with_config(config: Config) -> Tape {
+        if let TapeSize::Bounded(size) = config.tape_size {
+            assert!(size > 0, "A bounded tape must have at least one cell");
+        }
+
+        Tape {
+            cells: vec![0],
+            pointer: 0,
+            config,
+        }
+    }
+
+    /// Returns the configuration this tape was created with
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the index of the cell currently being pointed to
+    pub fn pointer(&self) -> CellIndex {
+        self.pointer
+    }
+
+    /// Returns all the cells that have been used so far
+    pub fn cells(&self) -> &[u32] {
+        &self.cells
+    }
+
+    /// Returns the value of the cell currently being pointed to
+    pub fn get(&self) -> u32 {
+        self.cells[self.pointer]
+    }
+
+    /// Replaces the value of the cell currently being pointed to
+    ///
+    /// Any bits that do not fit in the configured cell width are discarded.
+    pub fn set(&mut self, value: u32) {
+        self.cells[self.pointer] = value & self.config.cell_width.max_value();
+    }
+
+    /// Adds the given amount to the current cell
+    ///
+    /// Returns an error if the value overflows and the tape is not configured to wrap
+    pub fn add(&mut self, amount: u32) -> Result<(), Fault> {
+        self.add_at(0, amount as i64)
+    }
+
+    /// Subtracts the given amount from the current cell
+    ///
+    /// Returns an error if the value underflows and the tape is not configured to wrap
+    pub fn sub(&mut self, amount: u32) -> Result<(), Fault> {
+        self.add_at(0, -(amount as i64))
+    }
+
+    /// Returns the value of the cell at the given offset from the pointer
+    pub fn get_at(&mut self, offset: Offset) -> Result<u32, Fault> {
+        let index = self.index(offset)?;
+        Ok(self.cells[index])
+    }
+
+    /// Replaces the value of the cell at the given offset from the pointer
+    ///
+    /// Any bits that do not fit in the configured cell width are discarded.
+    pub fn set_at(&mut self, offset: Offset, value: u32) -> Result<(), Fault> {
+        let index = self.index(offset)?;
+        self.cells[index] = value & self.config.cell_width.max_value();
+        Ok(())
+    }
+
+    /// Adds the given amount to the cell at the given offset from the pointer. A negative amount
+    /// subtracts from the cell.
+    ///
+    /// Returns an error if the value overflows or underflows and the tape is not configured to
+    /// wrap
+    pub fn add_at(&mut self, offset: Offset, amount: i64) -> Result<(), Fault> {
+        let index = self.index(offset)?;
+        let modulus = self.config.cell_width.max_value() as i64 + 1;
+        let value = self.cells[index] as i64 + amount;
+
+        if self.config.overflow == OverflowBehaviour::Error {
+            if value >= modulus {
+                return Err(Fault::CellOverflow);
+            }
+            else if value < 0 {
+                return Err(Fault::CellUnderflow);
+            }
+        }
+
+        self.cells[index] = value.rem_euclid(modulus) as u32;
+        Ok(())
+    }
+
+    /// Moves the pointer right by the given number of cells, growing the tape if necessary
+    ///
+    /// Returns an error if this would move the pointer past the end of a bounded tape
+    pub fn move_right(&mut self, amount: usize) -> Result<(), Fault> {
+        self.move_by(amount as Offset)
+    }
+
+    /// Moves the pointer left by the given number of cells
+    ///
+    /// Returns an error if this would move the pointer before the start of the tape
+    pub fn move_left(&mut self, amount: usize) -> Result<(), Fault> {
+        self.move_by(-(amount as Offset))
+    }
+
+    /// Moves the pointer by the given number of cells (right if positive, left if negative)
+    ///
+    /// Returns an error if this would move the pointer off either end of the tape
+    pub fn move_by(&mut self, amount: Offset) -> Result<(), Fault> {
+        self.pointer = self.index(amount)?;
+        Ok(())
+    }
+
+    /// Returns the index of the cell at the given offset from the pointer, growing the tape if
+    /// necessary
+    fn index(&mut self, offset: Offset) -> Result<CellIndex, Fault> {
+        let index = if offset < 0 {
+            self.pointer.checked_sub(offset.unsigned_abs()).ok_or(Fault::PointerUnderflow)?
+        }
+        else {
+            self.pointer.checked_add(offset as usize).ok_or(Fault::TapeExhausted)?
+        };
+
+        if let TapeSize::Bounded(size) = self.config.tape_size {
+            if index >= size {
+                return Err(Fault::TapeExhausted);
+            }
+        }
+
+        if index >= self.cells.len() {
+            self.cells.resize(index + 1, 0);
+        }
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::CellWidth;
+
+    #[test]
+    fn grows_when_moving_right() {
+        let mut tape = Tape::new();
+        assert_eq!(tape.cells(), &[0]);
+
+        tape.move_right(3).unwrap();
+        assert_eq!(tape.pointer(), 3);
+        assert_eq!(tape.cells(), &[0, 0, 0, 0]);
+
+        tape.move_left(2).unwrap();
+        tape.add(5).unwrap();
+        tape.move_right(1).unwrap();
+        assert_eq!(tape.cells(), &[0, 5, 0, 0]);
+    }
+
+    #[test]
+    fn wraps_values() {
+        let mut tape = Tape::new();
+        tape.sub(1).unwrap();
+        assert_eq!(tape.get(), 255);
+        tape.add(2).unwrap();
+        assert_eq!(tape.get(), 1);
+    }
+
+    #[test]
+    fn wraps_wider_cells() {
+        let mut tape = Tape::with_config(Config {cell_width: CellWidth::U16, ..Config::default()});
+        tape.add(256).unwrap();
+        assert_eq!(tape.get(), 256);
+        tape.sub(257).unwrap();
+        assert_eq!(tape.get(), 65535);
+
+        let mut tape = Tape::with_config(Config {cell_width: CellWidth::U32, ..Config::default()});
+        tape.sub(1).unwrap();
+        assert_eq!(tape.get(), u32::MAX);
+        tape.add(1).unwrap();
+        assert_eq!(tape.get(), 0);
+    }
+
+    #[test]
+    fn overflow_errors() {
+        let mut tape = Tape::with_config(Config {overflow: OverflowBehaviour::Error, ..Config::default()});
+        assert_eq!(tape.sub(1), Err(Fault::CellUnderflow));
+        assert_eq!(tape.get(), 0);
+
+        tape.add(255).unwrap();
+        assert_eq!(tape.add(1), Err(Fault::CellOverflow));
+        assert_eq!(tape.get(), 255);
+    }
+
+    #[test]
+    fn overflow_errors_before_wrapping() {
+        // Amounts that are a multiple of the cell width would leave the cell unchanged if they
+        // were wrapped before being checked
+        let mut tape = Tape::with_config(Config {overflow: OverflowBehaviour::Error, ..Config::default()});
+        tape.add(5).unwrap();
+        assert_eq!(tape.sub(256), Err(Fault::CellUnderflow));
+        assert_eq!(tape.add(256), Err(Fault::CellOverflow));
+        assert_eq!(tape.add_at(0, -261), Err(Fault::CellUnderflow));
+        assert_eq!(tape.get(), 5);
+
+        let mut tape = Tape::with_config(Config {
+            cell_width: CellWidth::U16,
+            overflow: OverflowBehaviour::Error,
+            ..Config::default()
+        });
+        assert_eq!(tape.sub(65536), Err(Fault::CellUnderflow));
+        assert_eq!(tape.add(65536), Err(Fault::CellOverflow));
+        assert_eq!(tape.get(), 0);
+    }
+
+    #[test]
+    fn cannot_move_before_start() {
+        let mut tape = Tape::new();
+        tape.move_right(1).unwrap();
+        assert_eq!(tape.move_left(2), Err(Fault::PointerUnderflow));
+        assert_eq!(tape.pointer(), 1);
+    }
+
+    #[test]
+    fn offsets() {
+        let mut tape = Tape::new();
+        tape.move_right(2).unwrap();
+        tape.add_at(1, 3).unwrap();
+        tape.add_at(-2, -1).unwrap();
+        assert_eq!(tape.pointer(), 2);
+        assert_eq!(tape.cells(), &[255, 0, 0, 3]);
+        assert_eq!(tape.get_at(-2), Ok(255));
+        assert_eq!(tape.get_at(-3), Err(Fault::PointerUnderflow));
+
+        tape.move_by(-1).unwrap();
+        tape.set_at(2, 7).unwrap();
+        assert_eq!(tape.cells(), &[255, 0, 0, 7]);
+    }
+
+    #[test]
+    fn bounded_tape() {
+        let mut tape = Tape::with_config(Config {tape_size: TapeSize::Bounded(3), ..Config::default()});
+        tape.move_right(2).unwrap();
+        assert_eq!(tape.move_right(1), Err(Fault::TapeExhausted));
+        assert_eq!(tape.pointer(), 2);
+        assert_eq!(tape.cells(), &[0, 0, 0]);
+    }
+}
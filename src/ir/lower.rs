@@ -0,0 +1,146 @@
+use std::iter::repeat_n;
+
+use codegen::{Instructions, Instruction};
+
+use super::{Ops, Op, Offset};
+
+impl From<Ops> for Instructions {
+    fn from(ops: Ops) -> Instructions {
+        Instructions::from(&ops)
+    }
+}
+
+impl<'a> From<&'a Ops> for Instructions {
+    fn from(ops: &'a Ops) -> Instructions {
+        let mut instrs = Vec::new();
+        // Movement is deferred until the pointer actually needs to be somewhere so that
+        // consecutive operations at different offsets only move as much as necessary
+        let mut pending = 0;
+        lower(ops, &mut instrs, &mut pending);
+        instrs.into_iter().collect()
+    }
+}
+
+/// Lowers the given operations into instructions
+///
+/// `pending` is the offset of the position of the IR pointer relative to the cell that the
+/// brainfuck pointer is actually on
+fn lower(ops: &[Op], instrs: &mut Vec<Instruction>, pending: &mut Offset) {
+    let mut i = 0;
+    while i < ops.len() {
+        match ops[i] {
+            Op::Add(offset, amount) => {
+                move_to(instrs, pending, offset);
+                add(instrs, amount);
+            },
+            Op::Move(amount) => *pending += amount,
+            Op::SetZero(offset) => {
+                move_to(instrs, pending, offset);
+                instrs.extend_from_slice(&[
+                    Instruction::JumpForwardIfZero,
+                    Instruction::Decrement,
+                    Instruction::JumpBackwardUnlessZero,
+                ]);
+            },
+            Op::MulAdd {src, ..} => {
+                // All of the MulAdds with this source are generated as a single loop which
+                // consumes the source cell, so it must be zeroed right after them
+                let end = i + ops[i..].iter()
+                    .take_while(|op| matches!(**op, Op::MulAdd {src: s, ..} if s == src))
+                    .count();
+                assert_eq!(ops.get(end), Some(&Op::SetZero(src)),
+                    "MulAdd must be followed by a SetZero of its source cell to be lowered");
+
+                move_to(instrs, pending, src);
+                instrs.push(Instruction::JumpForwardIfZero);
+                instrs.push(Instruction::Decrement);
+                for op in &ops[i..end] {
+                    if let Op::MulAdd {dst, factor, ..} = *op {
+                        move_to(instrs, pending, dst);
+                        add(instrs, factor);
+                    }
+                }
+                move_to(instrs, pending, src);
+                instrs.push(Instruction::JumpBackwardUnlessZero);
+
+                // Skip the SetZero since the loop already zeroed the source
+                i = end;
+            },
+            Op::Loop(ref body) => {
+                move_to(instrs, pending, 0);
+                instrs.push(Instruction::JumpForwardIfZero);
+                lower(body, instrs, pending);
+                move_to(instrs, pending, 0);
+                instrs.push(Instruction::JumpBackwardUnlessZero);
+            },
+            Op::Read(offset) => {
+                move_to(instrs, pending, offset);
+                instrs.push(Instruction::Read);
+            },
+            Op::Write(offset) => {
+                move_to(instrs, pending, offset);
+                instrs.push(Instruction::Write);
+            },
+        }
+
+        i += 1;
+    }
+}
+
+/// Moves the brainfuck pointer to the cell at the given offset from the IR pointer
+fn move_to(instrs: &mut Vec<Instruction>, pending: &mut Offset, offset: Offset) {
+    let distance = *pending + offset;
+    let instr = if distance < 0 { Instruction::Left } else { Instruction::Right };
+    instrs.extend(repeat_n(instr, distance.unsigned_abs()));
+    *pending = -offset;
+}
+
+fn add(instrs: &mut Vec<Instruction>, amount: i32) {
+    let instr = if amount < 0 { Instruction::Decrement } else { Instruction::Increment };
+    instrs.extend(repeat_n(instr, amount.unsigned_abs() as usize));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lower_str(ops: Vec<Op>) -> String {
+        Instructions::from(Ops::from(ops)).into()
+    }
+
+    #[test]
+    fn defers_movement() {
+        assert_eq!(lower_str(vec![
+            Op::Add(2, 3),
+            Op::Add(-1, -2),
+            Op::Move(1),
+            Op::Write(1),
+        ]), ">>+++<<<-->>>.");
+    }
+
+    #[test]
+    fn loops_move_to_pointer() {
+        assert_eq!(lower_str(vec![
+            Op::Add(1, 1),
+            Op::Loop(vec![Op::Add(0, -1), Op::Move(1)].into()),
+            Op::Read(0),
+        ]), ">+<[->],");
+    }
+
+    #[test]
+    fn mul_add() {
+        assert_eq!(lower_str(vec![
+            Op::Move(1),
+            Op::MulAdd {src: 0, dst: 1, factor: 2},
+            Op::MulAdd {src: 0, dst: -1, factor: -1},
+            Op::SetZero(0),
+            Op::SetZero(1),
+        ]), ">[->++<<->]>[-]");
+    }
+
+    #[test]
+    #[should_panic(expected = "MulAdd must be followed by a SetZero of its source cell")]
+    fn mul_add_without_set_zero() {
+        lower_str(vec![Op::MulAdd {src: 0, dst: 1, factor: 1}]);
+    }
+}
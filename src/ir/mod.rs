@@ -0,0 +1,10 @@
+//! The intermediate representation (IR) used between the high-level operations and brainfuck
+//!
+//! Code generation lowers `Operations` into IR operations, optimizations run on the IR, and the
+//! result is then lowered into brainfuck `Instructions`. The interpreter can also run the IR
+//! directly.
+
+mod op;
+mod lower;
+
+pub use self::op::*;
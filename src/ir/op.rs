@@ -0,0 +1,116 @@
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
+
+/// A distance in cells relative to the current position of the pointer
+pub type Offset = isize;
+
+/// A sequence of IR operations run one after the other
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Ops(Vec<Op>);
+
+impl Ops {
+    /// Creates an empty sequence of operations
+    pub fn new() -> Ops {
+        Ops(Vec::new())
+    }
+
+    /// Returns true if this sequence contains a Read or Write anywhere, including inside loops
+    pub fn has_side_effects(&self) -> bool {
+        self.iter().any(Op::has_side_effects)
+    }
+}
+
+impl Deref for Ops {
+    type Target = Vec<Op>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Ops {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<Vec<Op>> for Ops {
+    fn from(ops: Vec<Op>) -> Ops {
+        Ops(ops)
+    }
+}
+
+impl IntoIterator for Ops {
+    type Item = Op;
+    type IntoIter = ::std::vec::IntoIter<Op>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl FromIterator<Op> for Ops {
+    fn from_iter<I: IntoIterator<Item=Op>>(iter: I) -> Self {
+        Ops(iter.into_iter().collect())
+    }
+}
+
+impl Extend<Op> for Ops {
+    fn extend<I: IntoIterator<Item=Op>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+/// A single operation of the intermediate representation
+///
+/// Operations address cells using an offset from the current position of the pointer. Only
+/// `Move` and loops whose bodies move the pointer change that position. This means that a run of
+/// brainfuck instructions like `>>+<<` can be represented as a single `Add(2, 1)`.
+///
+/// Amounts are added to cells modulo the cell width, so a negative amount subtracts from the
+/// cell.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Op {
+    /// Add the given amount to the cell at the given offset
+    Add(Offset, i32),
+
+    /// Move the pointer by the given number of cells (right if positive, left if negative)
+    Move(Offset),
+
+    /// Set the cell at the given offset to zero
+    SetZero(Offset),
+
+    /// Add the value of the cell at `src` multiplied by `factor` to the cell at `dst`
+    ///
+    /// The `src` cell is left unchanged. Brainfuck can only express this with a loop that
+    /// consumes the source cell, so when lowering to instructions, every run of `MulAdd`
+    /// operations must be directly followed by a `SetZero` of their shared `src` cell.
+    MulAdd {
+        src: Offset,
+        dst: Offset,
+        factor: i32,
+    },
+
+    /// Run the body repeatedly as long as the cell at the pointer is non-zero
+    ///
+    /// The condition is checked before every iteration. The body may leave the pointer
+    /// somewhere other than where it started.
+    Loop(Ops),
+
+    /// Read a byte of input into the cell at the given offset
+    Read(Offset),
+
+    /// Write the cell at the given offset as a byte of output
+    Write(Offset),
+}
+
+impl Op {
+    /// Returns true if this operation reads input or writes output
+    pub fn has_side_effects(&self) -> bool {
+        match *self {
+            Op::Read(_) | Op::Write(_) => true,
+            Op::Loop(ref body) => body.has_side_effects(),
+            Op::Add(..) | Op::Move(_) | Op::SetZero(_) | Op::MulAdd {..} => false,
+        }
+    }
+}
@@ -4,9 +4,12 @@
 extern crate pest;
 
 pub mod parser;
+pub mod diagnostics;
 pub mod operations;
 pub mod memory;
 pub mod core;
 pub mod prelude;
 pub mod codegen;
+pub mod ir;
 pub mod optimizations;
+pub mod interpreter;
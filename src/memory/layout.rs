@@ -28,13 +28,34 @@ impl Cells {
     pub fn size(&self) -> MemSize {
         self.size
     }
+
+    fn end(&self) -> CellIndex {
+        self.position + self.size
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MemoryLayout {
     table: HashMap<MemId, Cells>,
+    // Positions decided ahead of time for memory blocks that have not been laid out yet
+    planned: HashMap<MemId, CellIndex>,
+    // The number of cells to keep free right after a memory block once it is laid out
+    trailing: HashMap<MemId, MemSize>,
+    // Cells being kept free after a memory block so that they can be used by `consecutive`
+    reserved: HashMap<MemId, Cells>,
+    // Holes left behind by removed cells, sorted by position. Adjacent holes are always merged
+    // and there is never a hole at the very end of the layout.
+    free: Vec<Cells>,
     // The total size of the entire memory layout
     size: MemSize,
+    // The largest that size has ever been
+    peak: MemSize,
+}
+
+impl Default for MemoryLayout {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MemoryLayout {
@@ -42,7 +63,12 @@ impl MemoryLayout {
     pub fn new() -> MemoryLayout {
         MemoryLayout {
             table: HashMap::new(),
+            planned: HashMap::new(),
+            trailing: HashMap::new(),
+            reserved: HashMap::new(),
+            free: Vec::new(),
             size: 0,
+            peak: 0,
         }
     }
 
@@ -51,6 +77,40 @@ impl MemoryLayout {
         self.size
     }
 
+    /// Returns the largest total size that the memory layout has had at any point, so no cell
+    /// at or after this position has ever been used
+    pub fn peak_size(&self) -> MemSize {
+        self.peak
+    }
+
+    /// Decides ahead of time where the given memory block will be placed once it is used
+    ///
+    /// The cells are set aside for the memory block immediately along with any cells reserved
+    /// after it with `reserve_after`, so that nothing else can be placed there in the meantime.
+    /// Planned positions must not overlap.
+    pub fn plan(&mut self, mem: &MemoryBlock, position: CellIndex) {
+        debug_assert!(!self.table.contains_key(&mem.id()), "Cannot plan a memory block that was already laid out");
+
+        let end = position + mem.size() + self.trailing.get(&mem.id()).cloned().unwrap_or(0);
+        if position > self.size {
+            let gap = Cells {position: self.size, size: position - self.size};
+            self.set_size(end);
+            self.remove_cells(gap);
+        }
+        let size = self.size.max(end);
+        self.set_size(size);
+        self.planned.insert(mem.id(), position);
+    }
+
+    /// Keeps the given number of cells right after the given memory block free once it is laid
+    /// out so that they are available to `consecutive`
+    ///
+    /// This must be called before the memory block is first used.
+    pub fn reserve_after(&mut self, mem: &MemoryBlock, size: MemSize) {
+        debug_assert!(!self.table.contains_key(&mem.id()), "Cannot reserve cells after a memory block that was already laid out");
+        self.trailing.insert(mem.id(), size);
+    }
+
     /// Removes a memory block from the memory layout
     ///
     /// NOTE: This **DOES NOT** guarantee that the associated cells have been zeroed. That is up
@@ -59,6 +119,9 @@ impl MemoryLayout {
         let cells = self.table.remove(&mem.id()).expect("Removed memory block that was already removed or never present");
 
         self.remove_cells(cells);
+        if let Some(reserved) = self.reserved.remove(&mem.id()) {
+            self.remove_cells(reserved);
+        }
     }
 
     /// Gets the brainfuck cells associated to the given memory block
@@ -85,22 +148,48 @@ impl MemoryLayout {
     }
 
     /// Allocates temporary cells that are placed consecutively after the given memory block
+    ///
+    /// The callback is given the position of the target and the temporary cells. If the cells
+    /// right after the target are already in use, the target is moved to a place with enough free
+    /// cells after it for the duration of the callback. The cells it was moved from are kept
+    /// aside so that it can be moved back afterwards. Moving the value of the target to the
+    /// position given to the callback and back again is up to the caller. Use `reserve_after`
+    /// to avoid needing to move the target at all.
     pub fn consecutive<F, T>(&mut self, target: &MemoryBlock, size: MemSize, callback: F) -> T
         where F: FnOnce(&mut MemoryLayout, CellIndex, Cells) -> T {
         // allocate the target first, if it hasn't already been allocated
-        let (target_position, target_size) = {
-            let target_cells = self.get(target);
-            (target_cells.position(), target_cells.size())
+        let target_cells = *self.get(target);
+        let target_size = target_cells.size();
+        // any cells reserved after the target are exactly the ones we are looking for
+        let reserved = self.reserved.remove(&target.id());
+        if let Some(reserved) = reserved {
+            self.remove_cells(reserved);
+        }
+
+        let res = if self.allocate_at(target_cells.end(), size) {
+            let cells = Cells {position: target_cells.end(), size};
+            let res = callback(self, target_cells.position(), cells);
+            self.remove_cells(cells);
+            res
+        }
+        else {
+            let position = self.allocate(target_size + size);
+            let moved = Cells {position, size: target_size};
+            self.table.insert(target.id(), moved);
+
+            let cells = Cells {position: moved.end(), size};
+            let res = callback(self, position, cells);
+            self.table.insert(target.id(), target_cells);
+            self.remove_cells(Cells {position, size: target_size + size});
+            res
         };
-        // allocate the requested temporary cells
-        let position = self.allocate(size);
-        // assert that the cells are consecutive
-        //TODO: See if there is a better way to deal with the cells not being consecutive
-        assert_eq!(position - target_position, target_size);
 
-        let cells = Cells {position, size};
-        let res = callback(self, target_position, cells);
-        self.remove_cells(cells);
+        // Keep the reservation so that the target can be used with `consecutive` again
+        if let Some(reserved) = reserved {
+            if self.allocate_at(reserved.position(), reserved.size()) {
+                self.reserved.insert(target.id(), reserved);
+            }
+        }
         res
     }
 
@@ -113,26 +202,209 @@ impl MemoryLayout {
         }
         else {
             let size = mem.size();
-            // This should ONLY be incremented the **first** time this is inserted
-            let position = self.allocate(size);
+            let trailing = self.trailing.get(&key).cloned().unwrap_or(0);
+            // This should ONLY be allocated the **first** time this is inserted
+            let position = match self.planned.remove(&key) {
+                Some(position) => position,
+                None => self.allocate(size + trailing),
+            };
             self.table.insert(key, Cells {position, size});
+
+            if trailing > 0 {
+                self.reserved.insert(key, Cells {position: position + size, size: trailing});
+            }
         }
     }
 
+    /// Finds space for the given number of cells, reusing the first hole that is big enough
     fn allocate(&mut self, size: MemSize) -> CellIndex {
-        let position = self.size;
-        self.size += size;
-        position
+        if size == 0 {
+            return self.size;
+        }
+
+        match self.free.iter().position(|hole| hole.size() >= size) {
+            Some(index) => {
+                let position = self.free[index].position();
+                self.take(index, position, size);
+                position
+            },
+            None => {
+                let position = self.size;
+                self.set_size(position + size);
+                position
+            },
+        }
+    }
+
+    /// Attempts to allocate the cells starting at the given position. Returns false if any of
+    /// those cells are already in use.
+    fn allocate_at(&mut self, position: CellIndex, size: MemSize) -> bool {
+        if position >= self.size {
+            let previous_size = self.size;
+            self.set_size(position + size);
+            // Any cells skipped over are left as a hole
+            if position > previous_size {
+                self.remove_cells(Cells {position: previous_size, size: position - previous_size});
+            }
+            return true;
+        }
+
+        let hole = self.free.iter().position(|hole| {
+            hole.position() <= position && position + size <= hole.end()
+        });
+        match hole {
+            Some(index) => {
+                self.take(index, position, size);
+                true
+            },
+            // There is never a hole at the end, so the cells must be in use
+            None => size == 0,
+        }
+    }
+
+    /// Removes the given cells from the hole at the given index, splitting it if necessary
+    fn take(&mut self, index: usize, position: CellIndex, size: MemSize) {
+        let hole = self.free.remove(index);
+        let after = Cells {position: position + size, size: hole.end() - (position + size)};
+        if after.size() > 0 {
+            self.free.insert(index, after);
+        }
+        let before = Cells {position: hole.position(), size: position - hole.position()};
+        if before.size() > 0 {
+            self.free.insert(index, before);
+        }
+    }
+
+    /// Grows the layout to the given size
+    fn set_size(&mut self, size: MemSize) {
+        self.size = size;
+        self.peak = self.peak.max(size);
     }
 
     fn remove_cells(&mut self, cells: Cells) {
-        // We currently just free up that space for reuse if its at the end of the buffer
-        // This means that there can be "holes" leftover in the layout after the removal
-        // Ideally this memory layout would be implemented as some sort of efficient memory pool
-        // where we could find the nearest space and put the cells there
-        // Then this remove operation would just remove that item in the memory pool
-        if cells.position() + cells.size() == self.size {
-            self.size -= cells.size();
+        if cells.size() == 0 {
+            return;
+        }
+
+        // Freed cells become a hole that can be reused by the next allocation that fits
+        let mut index = self.free.iter().position(|hole| hole.position() > cells.position())
+            .unwrap_or(self.free.len());
+        self.free.insert(index, cells);
+
+        if index + 1 < self.free.len() && self.free[index].end() == self.free[index + 1].position() {
+            let next = self.free.remove(index + 1);
+            self.free[index].size += next.size();
+        }
+        if index > 0 && self.free[index - 1].end() == self.free[index].position() {
+            let hole = self.free.remove(index);
+            index -= 1;
+            self.free[index].size += hole.size();
+        }
+
+        // Free cells at the end of the layout are not a hole, the layout just gets smaller
+        if self.free[index].end() == self.size {
+            self.size = self.free.remove(index).position();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use memory::StaticAllocator;
+
+    #[test]
+    fn reuses_holes() {
+        let mut allocator = StaticAllocator::new();
+        let a = allocator.allocate(2);
+        let b = allocator.allocate(1);
+        let c = allocator.allocate(1);
+        let d = allocator.allocate(3);
+
+        let mut layout = MemoryLayout::new();
+        assert_eq!(layout.get(&a).position(), 0);
+        assert_eq!(layout.get(&b).position(), 2);
+        layout.remove(&a);
+        // Only the hole is left behind, the layout does not shrink
+        assert_eq!(layout.size(), 3);
+
+        assert_eq!(layout.get(&c).position(), 0);
+        // d does not fit in what is left of the hole
+        assert_eq!(layout.get(&d).position(), 3);
+        assert_eq!(layout.size(), 6);
+
+        // Freeing everything merges the holes and the layout shrinks back to nothing
+        layout.remove(&b);
+        layout.remove(&d);
+        layout.remove(&c);
+        assert_eq!(layout.size(), 0);
+        // The peak size is kept so that everything after it is known to be unused
+        assert_eq!(layout.peak_size(), 6);
+    }
+
+    #[test]
+    fn consecutive_uses_reserved_cells() {
+        let mut allocator = StaticAllocator::new();
+        let cond = allocator.allocate(1);
+        let a = allocator.allocate(1);
+
+        let mut layout = MemoryLayout::new();
+        layout.reserve_after(&cond, 2);
+        assert_eq!(layout.get(&cond).position(), 0);
+        // The reserved cells are never given to anything else
+        assert_eq!(layout.get(&a).position(), 3);
+
+        for _ in 0..2 {
+            layout.consecutive(&cond, 2, |layout, position, temp| {
+                assert_eq!(position, 0);
+                assert_eq!(temp.position(), 1);
+                // Temporary cells inside the callback cannot overlap the consecutive cells
+                layout.temporary(1, |cells| assert_eq!(cells.position(), 4));
+            });
         }
+
+        layout.remove(&cond);
+        assert_eq!(layout.temporary(3, |cells| cells.position()), 0);
+    }
+
+    #[test]
+    fn consecutive_moves_target_when_cells_are_taken() {
+        let mut allocator = StaticAllocator::new();
+        let cond = allocator.allocate(1);
+        let a = allocator.allocate(1);
+
+        let mut layout = MemoryLayout::new();
+        assert_eq!(layout.get(&cond).position(), 0);
+        assert_eq!(layout.get(&a).position(), 1);
+
+        layout.consecutive(&cond, 2, |layout, position, temp| {
+            assert_eq!(position, 2);
+            assert_eq!(temp.position(), 3);
+            // Anything generated in the callback uses the new position
+            assert_eq!(layout.get(&cond).position(), 2);
+            // The original cells are kept aside until the target is moved back
+            layout.temporary(1, |cells| assert_eq!(cells.position(), 5));
+        });
+
+        assert_eq!(layout.get(&cond).position(), 0);
+        assert_eq!(layout.size(), 2);
+    }
+
+    #[test]
+    fn planned_positions() {
+        let mut allocator = StaticAllocator::new();
+        let a = allocator.allocate(1);
+        let b = allocator.allocate(2);
+        let c = allocator.allocate(1);
+
+        let mut layout = MemoryLayout::new();
+        layout.plan(&a, 3);
+        assert_eq!(layout.size(), 4);
+        // The cells before the planned block can still be used
+        assert_eq!(layout.get(&b).position(), 0);
+        assert_eq!(layout.get(&c).position(), 2);
+        assert_eq!(layout.get(&a).position(), 3);
+        assert_eq!(layout.size(), 4);
     }
 }
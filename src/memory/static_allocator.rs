@@ -14,7 +14,7 @@ pub type Index = usize;
 
 /// MemoryBlock of the first cell in a memory block of the given size
 /// Use CellPositions to represent locations within a memory block pointed to by an MemoryBlock
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub struct MemoryBlock {
     id: MemId,
     size: MemSize,
@@ -68,7 +68,7 @@ impl Default for MemoryBlock {
 
 /// The position of a cell within an MemoryBlock
 /// Index = 0 indicates the start of the memory block
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub struct CellPosition(MemoryBlock, Index);
 
 impl CellPosition {
@@ -89,6 +89,12 @@ pub struct StaticAllocator {
     next_id: usize,
 }
 
+impl Default for StaticAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StaticAllocator {
     pub fn new() -> StaticAllocator {
         StaticAllocator {
@@ -105,7 +111,7 @@ impl StaticAllocator {
 
         let blk = MemoryBlock {
             id: MemId(self.next_id),
-            size: size,
+            size,
         };
         self.next_id += 1;
 
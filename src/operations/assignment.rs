@@ -1,10 +1,11 @@
 use std::iter::once;
 
-use parser::{Identifier, Expression};
+use parser::{Identifier, Expression, Span};
 
 use super::{Error};
 use super::{Operation, OperationsResult, expression, Target};
 use super::scope::{ScopeStack, ScopeItem};
+use super::expression::{resolve_field, allocate_field, copy_to_field};
 
 pub fn into_operations(
     scope: &mut ScopeStack,
@@ -12,25 +13,122 @@ pub fn into_operations(
     expr: Expression,
 ) -> OperationsResult {
     scope.lookup(&lhs).first().ok_or_else(|| {
-        Error::UnresolvedName(lhs.clone())
+        Error::UnresolvedName {name: lhs.clone(), span: lhs.span()}
     }).map(|item| (**item).clone()).and_then(|item| match item {
         // There is a non-lexical lifetimes issue here which was introduced by calling into_operations*() below
         // The clone() above is completely unnecssary and is a hack to work around this problem
         // in the Rust compiler
         // http://smallcultfollowing.com/babysteps/blog/2016/04/27/non-lexical-lifetimes-introduction/#problem-case-2-conditional-control-flow
 
-        ScopeItem::TypedBlock {type_id, memory} => {
-            Ok(once(Operation::Zero {target: memory}).chain(
-                expression::into_operations(scope, expr, Target::TypedBlock {type_id, memory})?.into_iter()
-            ).collect())
+        ScopeItem::TypedBlock {type_id, memory, ..} => {
+            replace_value(scope, lhs, expr, Target::TypedBlock {type_id, memory})
         },
-        ScopeItem::Array {item, size, memory} => {
-            Ok(once(Operation::Zero {target: memory}).chain(
-                expression::into_operations(scope, expr, Target::Array {item, size, memory})?.into_iter()
-            ).collect())
+        ScopeItem::Array {item, size, memory, ..} => {
+            replace_value(scope, lhs, expr, Target::Array {item, size, memory})
         },
         ScopeItem::Constant {..} | ScopeItem::NumericLiteral(..) | ScopeItem::ByteLiteral(..) | ScopeItem::BuiltInFunction {..} => {
-            Err(Error::InvalidLeftHandSide(lhs))
+            Err(Error::InvalidLeftHandSide {span: lhs.span(), name: lhs})
         },
+        ScopeItem::Poisoned => Err(Error::Poisoned {span: lhs.span(), name: lhs}),
     })
 }
+
+/// Generates operations for an assignment to a single field of a struct like `a.b = c`, where
+/// the field may be inside of other fields like in `a.b.c = d`
+///
+/// The struct must be mutable but does not need to have a value yet, so a struct can be given a
+/// value one field at a time.
+pub fn field_into_operations(
+    scope: &mut ScopeStack,
+    target: Identifier,
+    fields: Vec<Identifier>,
+    expr: Expression,
+) -> OperationsResult {
+    match scope.lookup(&target).first() {
+        Some(&&ScopeItem::TypedBlock {..}) => {},
+        Some(&&ScopeItem::Poisoned) => return Err(Error::Poisoned {span: target.span(), name: target}),
+        Some(_) => return Err(Error::InvalidLeftHandSide {span: target.span(), name: target}),
+        None => return Err(Error::UnresolvedName {span: target.span(), name: target}),
+    }
+    let (memory, typ, offset) = resolve_field(scope, &target, &fields)?;
+
+    // The value is stored somewhere else first since the expression may read the field
+    let value = allocate_field(scope, typ);
+    let mut body = expression::into_operations(scope, expr, value)?;
+    scope.assign_field(memory, &fields).map_err(|err| err.or_span(target.span()))?;
+
+    let size = typ.required_size(scope);
+    body.extend((offset..offset + size).map(|i| {
+        let cell = memory.position_at(i);
+        Operation::Loop {
+            cond: cell,
+            body: vec![Operation::Decrement {target: cell, amount: 1}],
+        }
+    }));
+    body.extend(copy_to_field(scope, value.memory(), memory, typ, offset));
+
+    Ok(vec![Operation::TempAllocate {
+        temp: value.memory(),
+        body,
+        should_zero: true,
+    }])
+}
+
+/// Generates operations for an assignment like `a += b` by treating it as `a = a + b`, or like
+/// `a.b += c` by treating it as `a.b = a.b + c`
+pub fn compound_into_operations(
+    scope: &mut ScopeStack,
+    lhs: Identifier,
+    fields: Vec<Identifier>,
+    operator: Identifier,
+    expr: Expression,
+    span: Span,
+) -> OperationsResult {
+    let current = fields.iter().fold(Expression::Identifier(lhs.clone()), |target, field| {
+        let span = target.span().to(field.span());
+        Expression::Access {target: Box::new(target), field: field.clone(), span}
+    });
+    let expr = Expression::Call {
+        method: Box::new(Expression::Identifier(operator)),
+        args: vec![current, expr],
+        span,
+    };
+
+    if fields.is_empty() {
+        into_operations(scope, lhs, expr)
+    }
+    else {
+        field_into_operations(scope, lhs, fields, expr)
+    }
+}
+
+/// Replaces the value stored in the target with the result of the expression
+fn replace_value(
+    scope: &mut ScopeStack,
+    lhs: Identifier,
+    expr: Expression,
+    target: Target,
+) -> OperationsResult {
+    let memory = target.memory();
+
+    // The expression is generated first since the variable is only initialized after it runs
+    if !expression::reads(&expr, &lhs) {
+        let ops = expression::into_operations(scope, expr, target)?;
+        scope.assign(memory).map_err(|err| err.or_span(lhs.span()))?;
+        return Ok(once(Operation::Zero {target: memory}).chain(ops).collect());
+    }
+
+    // Zeroing the variable first would change the value that the expression reads, so the result
+    // is stored somewhere else until the expression is done with the variable
+    let temp = target.allocate_like(scope);
+    let ops = expression::into_operations(scope, expr, temp)?;
+    scope.assign(memory).map_err(|err| err.or_span(lhs.span()))?;
+    Ok(vec![Operation::TempAllocate {
+        temp: temp.memory(),
+        body: ops.into_iter().chain(vec![
+            Operation::Zero {target: memory},
+            Operation::Relocate {source: temp.memory(), target: memory},
+        ]).collect(),
+        should_zero: false,
+    }])
+}
@@ -1,8 +1,18 @@
-use parser::{Statement, Block};
+use parser::{Statement, Expression, Block, Span};
 
-use super::{Operation, OperationsResult, statement, expression, Target};
+use super::{Operation, OperationsResult, Warning, statement, expression, function, implementation, Target};
 use super::scope::ScopeStack;
 
+/// Whether the statements in a block will ever run
+#[derive(Debug, Clone, Copy)]
+enum Reachability {
+    Reachable,
+    /// Every statement after the infinite loop with this span is unreachable
+    AfterInfiniteLoop(Span),
+    /// The first unreachable statement has already been reported
+    Reported,
+}
+
 pub fn into_operations(scope: &mut ScopeStack, mut block: Block, target: Target) -> OperationsResult {
     scope.push_scope();
 
@@ -10,15 +20,27 @@ pub fn into_operations(scope: &mut ScopeStack, mut block: Block, target: Target)
     // This works because we automatically insert a UnitLiteral at the end of blocks terminated
     // by a semicolon in the parser
     let last = block.pop().expect("The parser did not fulfill its guarantee of a last statement");
+    let block = declare_items(scope, block);
 
     let mut ops = Vec::new();
+    let mut reachability = Reachability::Reachable;
 
+    // Statements that fail are skipped so that the errors in the rest of the block are
+    // reported too
     for stmt in block.into_iter() {
-        ops.extend(statement::into_operations(scope, stmt)?);
+        reachability = check_reachable(scope, &stmt, reachability);
+        match statement::into_operations(scope, stmt) {
+            Ok(stmt_ops) => ops.extend(stmt_ops),
+            Err(error) => scope.report_error(error),
+        }
     }
 
+    check_reachable(scope, &last, reachability);
     if let Statement::Expression {expr} = last {
-        ops.extend(expression::into_operations(scope, expr, target)?);
+        match expression::into_operations(scope, expr, target) {
+            Ok(expr_ops) => ops.extend(expr_ops),
+            Err(error) => scope.report_error(error),
+        }
     }
     else {
         // The parser guarantees that the last statement will always be an expression
@@ -32,17 +54,85 @@ pub fn into_operations(scope: &mut ScopeStack, mut block: Block, target: Target)
     }])
 }
 
+/// Declares every item (function, struct, enum and impl block) in the given statements and
+/// returns the rest of the statements
+///
+/// Items can be used anywhere in the block they are declared in. Types are declared first so that
+/// any function can use them, then the signature of every function so that any function can call
+/// any other. The bodies are only checked once every function has been declared.
+fn declare_items(scope: &mut ScopeStack, statements: Vec<Statement>) -> Vec<Statement> {
+    let (types, statements): (Vec<_>, Vec<_>) = statements.into_iter().partition(|stmt| {
+        matches!(*stmt, Statement::Struct {..} | Statement::Enum {..})
+    });
+    for stmt in types {
+        if let Err(error) = statement::into_operations(scope, stmt) {
+            scope.report_error(error);
+        }
+    }
+
+    let (functions, statements): (Vec<_>, Vec<_>) = statements.into_iter().partition(|stmt| {
+        matches!(*stmt, Statement::Function {..} | Statement::Impl {..})
+    });
+    let mut declared = Vec::new();
+    for stmt in functions {
+        let result = match stmt {
+            Statement::Function {name, params, return_type, body, ..} => {
+                function::declare(scope, name, None, params, return_type, body).map(|function| vec![function])
+            },
+            Statement::Impl {type_name, methods, ..} => implementation::declare(scope, type_name, methods),
+            _ => unreachable!(),
+        };
+        match result {
+            Ok(functions) => declared.extend(functions),
+            Err(error) => scope.report_error(error),
+        }
+    }
+
+    for function in declared {
+        if let Err(error) = function::check(scope, &function) {
+            scope.report_error(error);
+        }
+    }
+
+    statements
+}
+
+/// Reports the given statement if it comes after an infinite loop and returns whether the
+/// statements after it are reachable
+///
+/// Only the first unreachable statement is reported since the rest are unreachable for the same
+/// reason.
+fn check_reachable(scope: &mut ScopeStack, stmt: &Statement, reachability: Reachability) -> Reachability {
+    match (reachability, stmt) {
+        // The unit literal at the end of every block is not written in the source
+        (_, &Statement::Comment(..)) | (_, &Statement::Expression {expr: Expression::UnitLiteral(_)}) => {
+            reachability
+        },
+        (Reachability::AfterInfiniteLoop(infinite_loop), _) => {
+            scope.report_warning(Warning::UnreachableCode {infinite_loop, span: stmt.span()});
+            Reachability::Reported
+        },
+        (Reachability::Reachable, &Statement::WhileLoop {ref condition, span, ..}) => {
+            match expression::constant_condition(scope, condition) {
+                Some(true) => Reachability::AfterInfiniteLoop(span),
+                _ => reachability,
+            }
+        },
+        _ => reachability,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use parser::{Statement, Expression};
+    use parser::{Statement, Expression, Span};
     use memory::MemoryBlock;
 
     #[test]
     fn empty_block() {
         let mut scope = ScopeStack::new();
         let unit_type = scope.primitives().unit();
-        let block = vec![Statement::Expression {expr: Expression::UnitLiteral}];
+        let block = vec![Statement::Expression {expr: Expression::UnitLiteral(Span::default())}];
 
         let ops = into_operations(&mut scope, block, Target::TypedBlock {
             type_id: unit_type,
@@ -0,0 +1,693 @@
+use std::collections::{HashMap, HashSet};
+
+use memory::{MemoryBlock, CellPosition, MemSize};
+
+use super::{Operation, Operations};
+
+/// The maximum number of loop iterations that will be evaluated at compile time before giving
+/// up and leaving the loop to be run when the program runs
+const EVALUATION_LIMIT: usize = 10_000;
+
+/// What is known about the value of a cell at a given point in the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value {
+    /// The value of the cell is known at compile time
+    ///
+    /// `tape` is the value actually stored in the cell when the program runs. The two only
+    /// differ when operations on the cell were evaluated at compile time and have not been
+    /// materialized yet.
+    Known {value: u8, tape: u8},
+    /// The value of the cell depends on something that only happens at runtime
+    Unknown,
+}
+
+/// Tracks the value of every cell that is known at compile time
+///
+/// Every cell starts at zero, so any cell that has never been used is known to be zero.
+#[derive(Debug, Clone, Default)]
+struct KnownValues {
+    cells: HashMap<CellPosition, Value>,
+}
+
+impl KnownValues {
+    fn get(&self, cell: CellPosition) -> Value {
+        self.cells.get(&cell).cloned().unwrap_or(Value::Known {value: 0, tape: 0})
+    }
+
+    fn is_known(&self, cell: CellPosition) -> bool {
+        self.get(cell) != Value::Unknown
+    }
+
+    /// Changes the known value of a cell without changing what is stored on the tape
+    ///
+    /// # Panics
+    /// Panics if the value of the cell was not known
+    fn set(&mut self, cell: CellPosition, value: u8) {
+        match self.get(cell) {
+            Value::Known {tape, ..} => self.cells.insert(cell, Value::Known {value, tape}),
+            Value::Unknown => unreachable!("Attempt to change the value of an unknown cell"),
+        };
+    }
+
+    fn add(&mut self, cell: CellPosition, amount: u8) {
+        match self.get(cell) {
+            Value::Known {value, ..} => self.set(cell, value.wrapping_add(amount)),
+            Value::Unknown => unreachable!("Attempt to change the value of an unknown cell"),
+        }
+    }
+
+    fn set_unknown(&mut self, cell: CellPosition) {
+        self.cells.insert(cell, Value::Unknown);
+    }
+
+    /// Records that the cell is zero both at compile time and on the tape
+    fn set_zero(&mut self, cell: CellPosition) {
+        self.cells.insert(cell, Value::Known {value: 0, tape: 0});
+    }
+
+    /// Generates the operations that update the tape so that it stores the known value of the
+    /// given cell
+    fn materialize(&mut self, cell: CellPosition, ops: &mut Operations) {
+        if let Value::Known {value, tape} = self.get(cell) {
+            // Wrapping around is avoided so that the generated code works for any cell width
+            if value > tape {
+                ops.push(Operation::Increment {target: cell, amount: value - tape});
+            }
+            else if value < tape {
+                ops.push(Operation::Decrement {target: cell, amount: tape - value});
+            }
+
+            self.cells.insert(cell, Value::Known {value, tape: value});
+        }
+    }
+
+    fn materialize_all(&mut self, cells: &[CellPosition], ops: &mut Operations) {
+        for &cell in cells {
+            self.materialize(cell, ops);
+        }
+    }
+
+    /// Stops tracking the given memory block because it is no longer in use
+    fn forget(&mut self, mem: MemoryBlock) {
+        for cell in block_cells(mem) {
+            self.cells.remove(&cell);
+        }
+    }
+}
+
+/// Evaluates the given operations as much as possible at compile time
+///
+/// The value of each cell is tracked until it depends on input or on a loop that cannot be
+/// evaluated at compile time. Operations on known values are evaluated during compilation and
+/// only the final value is written to the tape right before it is needed. Loops and branches
+/// whose condition is known are evaluated at compile time, so comparisons of constants produce
+/// no code at all.
+pub fn fold(ops: Operations) -> Operations {
+    let mut values = KnownValues::default();
+    fold_with(ops, &mut values)
+}
+
+fn fold_with(ops: Operations, values: &mut KnownValues) -> Operations {
+    let mut folded = Vec::new();
+    for op in ops {
+        fold_op(op, values, &mut folded);
+    }
+    folded
+}
+
+fn fold_op(op: Operation, values: &mut KnownValues, out: &mut Operations) {
+    match op {
+        Operation::Block {body} => {
+            out.push(Operation::Block {body: fold_with(body, values)});
+        },
+
+        Operation::TempAllocate {temp, body, should_zero} => {
+            let mut body = fold_with(body, values);
+
+            // The temporary must be zero on the tape once it is freed so that it can be reused
+            let any_unknown = block_cells(temp).any(|cell| !values.is_known(cell));
+            let should_zero = should_zero && any_unknown;
+            if !should_zero {
+                for cell in block_cells(temp) {
+                    if values.is_known(cell) {
+                        values.set(cell, 0);
+                        values.materialize(cell, &mut body);
+                    }
+                }
+            }
+            values.forget(temp);
+
+            // If every use of the temporary was evaluated at compile time, it never needs to be
+            // allocated at all
+            if should_zero || touched_cells(&body).iter().any(|cell| cell.id() == temp.id()) {
+                out.push(Operation::TempAllocate {temp, body, should_zero});
+            }
+            else {
+                out.extend(body);
+            }
+        },
+
+        Operation::Increment {target, amount} => {
+            if values.is_known(target) {
+                values.add(target, amount);
+            }
+            else {
+                out.push(op);
+            }
+        },
+
+        Operation::Decrement {target, amount} => {
+            if values.is_known(target) {
+                values.add(target, amount.wrapping_neg());
+            }
+            else {
+                out.push(op);
+            }
+        },
+
+        Operation::Read {target} => {
+            // Reading at the end of input can leave the cell unchanged, so it still needs to
+            // have the right value beforehand
+            for cell in block_cells(target) {
+                values.materialize(cell, out);
+                values.set_unknown(cell);
+            }
+            out.push(op);
+        },
+
+        Operation::Write {target} => {
+            for cell in block_cells(target) {
+                values.materialize(cell, out);
+            }
+            out.push(op);
+        },
+
+        Operation::Zero {target} => {
+            if block_cells(target).all(|cell| values.is_known(cell)) {
+                for cell in block_cells(target) {
+                    values.set(cell, 0);
+                }
+            }
+            else {
+                for cell in block_cells(target) {
+                    values.set_zero(cell);
+                }
+                out.push(op);
+            }
+        },
+
+        Operation::Branch {cond, if_body, else_body} => match values.get(cond.position()) {
+            Value::Known {value, ..} => {
+                let body = if value != 0 { if_body } else { else_body };
+                out.extend(fold_with(body, values));
+            },
+            Value::Unknown => {
+                let mut touched = touched_cells(&if_body);
+                extend_unique(&mut touched, touched_cells(&else_body));
+
+                // Whichever branch runs needs to leave the tape in the same state, so every cell
+                // used by either branch is materialized at the start and end of both
+                let mut if_values = values.clone();
+                let if_body = fold_branch(if_body, &touched, &mut if_values);
+                let mut else_values = values.clone();
+                let else_body = fold_branch(else_body, &touched, &mut else_values);
+
+                for &cell in &touched {
+                    let value = if_values.get(cell);
+                    if value == else_values.get(cell) {
+                        values.cells.insert(cell, value);
+                    }
+                    else {
+                        values.set_unknown(cell);
+                    }
+                }
+
+                out.push(Operation::Branch {cond, if_body, else_body});
+            },
+        },
+
+        Operation::Loop {cond, body} => {
+            match values.get(cond) {
+                // The loop never runs
+                Value::Known {value: 0, ..} => return,
+                Value::Known {..} => {
+                    let mut result = values.clone();
+                    let mut fuel = EVALUATION_LIMIT;
+                    if evaluate_loop(cond, &body, &mut result, &mut fuel).is_some() {
+                        *values = result;
+                        return;
+                    }
+                },
+                Value::Unknown => {},
+            }
+
+            let mut touched = touched_cells(&body);
+            extend_unique(&mut touched, vec![cond]);
+            values.materialize_all(&touched, out);
+
+            // Only cells that are never changed by the loop keep the same value on every iteration
+            let written = written_cells(&body);
+            for &cell in &written {
+                values.set_unknown(cell);
+            }
+
+            let mut body = fold_with(body, values);
+            values.materialize_all(&touched, &mut body);
+
+            for &cell in &written {
+                values.set_unknown(cell);
+            }
+            // A loop only stops once its condition is zero
+            values.set_zero(cond);
+
+            out.push(Operation::Loop {cond, body});
+        },
+
+        Operation::Copy {source, target, size} => {
+            // Copying to the same position does nothing
+            if source == target {
+                return;
+            }
+
+            let sources: Vec<_> = cells_from(source, size).collect();
+            let targets: Vec<_> = cells_from(target, size).collect();
+            if sources.iter().all(|&cell| values.is_known(cell)) {
+                add_known(&sources, &targets, values, out);
+            }
+            else {
+                values.materialize_all(&sources, out);
+                values.materialize_all(&targets, out);
+                for &cell in &targets {
+                    values.set_unknown(cell);
+                }
+                out.push(op);
+            }
+        },
+
+        Operation::Relocate {source, target} => {
+            let sources: Vec<_> = block_cells(source).collect();
+            let targets: Vec<_> = block_cells(target).collect();
+            if sources.iter().all(|&cell| values.is_known(cell)) {
+                add_known(&sources, &targets, values, out);
+                for &cell in &sources {
+                    values.set(cell, 0);
+                }
+            }
+            else {
+                values.materialize_all(&sources, out);
+                values.materialize_all(&targets, out);
+                for &cell in &targets {
+                    values.set_unknown(cell);
+                }
+                for &cell in &sources {
+                    values.set_zero(cell);
+                }
+                out.push(op);
+            }
+        },
+
+        Operation::Push {source} => {
+            values.materialize(source, out);
+            out.push(op);
+            values.set_zero(source);
+        },
+
+        Operation::Pop {target} => {
+            values.materialize(target, out);
+            out.push(op);
+            values.set_unknown(target);
+        },
+
+        Operation::Recurse {..} => unreachable!("Recursive calls are replaced by a dispatch loop before folding"),
+    }
+}
+
+/// Folds the body of a branch whose condition is only known at runtime
+fn fold_branch(body: Operations, touched: &[CellPosition], values: &mut KnownValues) -> Operations {
+    let mut ops = Vec::new();
+    values.materialize_all(touched, &mut ops);
+    ops.extend(fold_with(body, values));
+    values.materialize_all(touched, &mut ops);
+    ops
+}
+
+/// Adds the known values of the source cells to the target cells
+fn add_known(
+    sources: &[CellPosition],
+    targets: &[CellPosition],
+    values: &mut KnownValues,
+    out: &mut Operations,
+) {
+    for (&source, &target) in sources.iter().zip(targets) {
+        let amount = match values.get(source) {
+            Value::Known {value, ..} => value,
+            Value::Unknown => unreachable!(),
+        };
+
+        if values.is_known(target) {
+            values.add(target, amount);
+        }
+        else if amount != 0 {
+            out.push(Operation::Increment {target, amount});
+        }
+    }
+}
+
+/// Evaluates the given operations entirely at compile time
+///
+/// Returns None if any of the operations depend on a value that is not known or have an effect
+/// that can only happen at runtime. The values may have been partially updated when that
+/// happens, so this should be run on a copy.
+fn evaluate(ops: &[Operation], values: &mut KnownValues, fuel: &mut usize) -> Option<()> {
+    for op in ops {
+        match *op {
+            Operation::Block {ref body} => evaluate(body, values, fuel)?,
+            Operation::TempAllocate {temp, ref body, should_zero} => {
+                evaluate(body, values, fuel)?;
+                if should_zero {
+                    for cell in block_cells(temp) {
+                        values.set(cell, 0);
+                    }
+                }
+                values.forget(temp);
+            },
+            Operation::Increment {target, amount} => {
+                known(values, target)?;
+                values.add(target, amount);
+            },
+            Operation::Decrement {target, amount} => {
+                known(values, target)?;
+                values.add(target, amount.wrapping_neg());
+            },
+            Operation::Read {..} | Operation::Write {..} | Operation::Push {..} | Operation::Pop {..} |
+            Operation::Recurse {..} => return None,
+            Operation::Zero {target} => {
+                for cell in block_cells(target) {
+                    known(values, cell)?;
+                    values.set(cell, 0);
+                }
+            },
+            Operation::Branch {cond, ref if_body, ref else_body} => {
+                let body = if known(values, cond.position())? != 0 { if_body } else { else_body };
+                evaluate(body, values, fuel)?;
+            },
+            Operation::Loop {cond, ref body} => evaluate_loop(cond, body, values, fuel)?,
+            Operation::Copy {source, target, size} => {
+                if source != target {
+                    for (source, target) in cells_from(source, size).zip(cells_from(target, size)) {
+                        let amount = known(values, source)?;
+                        known(values, target)?;
+                        values.add(target, amount);
+                    }
+                }
+            },
+            Operation::Relocate {source, target} => {
+                for (source, target) in block_cells(source).zip(block_cells(target)) {
+                    let amount = known(values, source)?;
+                    known(values, target)?;
+                    values.add(target, amount);
+                    values.set(source, 0);
+                }
+            },
+        }
+    }
+
+    Some(())
+}
+
+fn evaluate_loop(
+    cond: CellPosition,
+    body: &[Operation],
+    values: &mut KnownValues,
+    fuel: &mut usize,
+) -> Option<()> {
+    while known(values, cond)? != 0 {
+        if *fuel == 0 {
+            return None;
+        }
+        *fuel -= 1;
+
+        evaluate(body, values, fuel)?;
+    }
+
+    Some(())
+}
+
+fn known(values: &KnownValues, cell: CellPosition) -> Option<u8> {
+    match values.get(cell) {
+        Value::Known {value, ..} => Some(value),
+        Value::Unknown => None,
+    }
+}
+
+/// Returns every cell that may be changed by the given operations
+fn written_cells(ops: &[Operation]) -> Vec<CellPosition> {
+    let mut cells = Vec::new();
+    for op in ops {
+        let written = match *op {
+            Operation::Block {ref body} | Operation::Loop {ref body, ..} => written_cells(body),
+            Operation::TempAllocate {temp, ref body, ..} => {
+                let mut written = written_cells(body);
+                extend_unique(&mut written, block_cells(temp).collect());
+                written
+            },
+            Operation::Increment {target, ..} | Operation::Decrement {target, ..} => vec![target],
+            Operation::Read {target} | Operation::Zero {target} => block_cells(target).collect(),
+            Operation::Write {..} => Vec::new(),
+            Operation::Branch {ref if_body, ref else_body, ..} => {
+                let mut written = written_cells(if_body);
+                extend_unique(&mut written, written_cells(else_body));
+                written
+            },
+            Operation::Copy {target, size, ..} => cells_from(target, size).collect(),
+            Operation::Relocate {source, target} => {
+                block_cells(source).chain(block_cells(target)).collect()
+            },
+            Operation::Push {source: cell} | Operation::Pop {target: cell} => vec![cell],
+            Operation::Recurse {target, ..} => block_cells(target).collect(),
+        };
+        extend_unique(&mut cells, written);
+    }
+    cells
+}
+
+/// Returns every cell that is used in any way by the given operations
+fn touched_cells(ops: &[Operation]) -> Vec<CellPosition> {
+    let mut cells = Vec::new();
+    for op in ops {
+        let touched = match *op {
+            Operation::Block {ref body} => touched_cells(body),
+            Operation::TempAllocate {temp, ref body, should_zero} => {
+                let mut touched = touched_cells(body);
+                if should_zero {
+                    extend_unique(&mut touched, block_cells(temp).collect());
+                }
+                touched
+            },
+            Operation::Increment {target, ..} | Operation::Decrement {target, ..} => vec![target],
+            Operation::Read {target} | Operation::Write {target} | Operation::Zero {target} => {
+                block_cells(target).collect()
+            },
+            Operation::Branch {cond, ref if_body, ref else_body} => {
+                let mut touched: Vec<_> = block_cells(cond).collect();
+                extend_unique(&mut touched, touched_cells(if_body));
+                extend_unique(&mut touched, touched_cells(else_body));
+                touched
+            },
+            Operation::Loop {cond, ref body} => {
+                let mut touched = vec![cond];
+                extend_unique(&mut touched, touched_cells(body));
+                touched
+            },
+            Operation::Copy {source, target, size} => {
+                cells_from(source, size).chain(cells_from(target, size)).collect()
+            },
+            Operation::Relocate {source, target} => {
+                block_cells(source).chain(block_cells(target)).collect()
+            },
+            Operation::Push {source: cell} | Operation::Pop {target: cell} => vec![cell],
+            Operation::Recurse {target, ..} => block_cells(target).collect(),
+        };
+        extend_unique(&mut cells, touched);
+    }
+    cells
+}
+
+/// Adds the given cells to the list, skipping any that are already in it so that the order in
+/// which cells are first used is preserved
+fn extend_unique(cells: &mut Vec<CellPosition>, other: Vec<CellPosition>) {
+    let mut seen: HashSet<_> = cells.iter().cloned().collect();
+    for cell in other {
+        if seen.insert(cell) {
+            cells.push(cell);
+        }
+    }
+}
+
+fn block_cells(mem: MemoryBlock) -> impl Iterator<Item=CellPosition> {
+    (0..mem.size()).map(move |i| mem.position_at(i))
+}
+
+fn cells_from(start: CellPosition, size: MemSize) -> impl Iterator<Item=CellPosition> {
+    let mem = start.associated_memory();
+    (start.offset()..start.offset() + size).map(move |i| mem.position_at(i))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use memory::StaticAllocator;
+    use parser::Program;
+    use prelude;
+    use operations::scope::ScopeStack;
+    use codegen::Instructions;
+    use interpreter::Interpreter;
+
+    fn compile(source: &str) -> Operations {
+        let program: Program = source.parse().unwrap();
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        program.into_operations(&mut scope).unwrap()
+    }
+
+    fn has_control_flow(ops: &[Operation]) -> bool {
+        ops.iter().any(|op| match *op {
+            Operation::Loop {..} | Operation::Branch {..} | Operation::Copy {..} => true,
+            Operation::Block {ref body} | Operation::TempAllocate {ref body, ..} => has_control_flow(body),
+            _ => false,
+        })
+    }
+
+    #[test]
+    fn materializes_final_values() {
+        let mut allocator = StaticAllocator::new();
+        let a = allocator.allocate(1);
+        let b = allocator.allocate(1);
+
+        let ops = fold(vec![
+            Operation::Increment {target: a.position(), amount: 5},
+            Operation::Copy {source: a.position(), target: b.position(), size: 1},
+            Operation::Decrement {target: a.position(), amount: 2},
+            Operation::Write {target: b},
+            Operation::Increment {target: b.position(), amount: 1},
+            Operation::Write {target: b},
+            Operation::Write {target: a},
+        ]);
+        assert_eq!(ops, vec![
+            Operation::Increment {target: b.position(), amount: 5},
+            Operation::Write {target: b},
+            Operation::Increment {target: b.position(), amount: 1},
+            Operation::Write {target: b},
+            Operation::Increment {target: a.position(), amount: 3},
+            Operation::Write {target: a},
+        ]);
+    }
+
+    #[test]
+    fn stops_at_input() {
+        let mut allocator = StaticAllocator::new();
+        let a = allocator.allocate(1);
+        let b = allocator.allocate(1);
+
+        let ops = fold(vec![
+            Operation::Increment {target: a.position(), amount: 2},
+            Operation::Read {target: a},
+            Operation::Increment {target: a.position(), amount: 1},
+            Operation::Copy {source: a.position(), target: b.position(), size: 1},
+            Operation::Write {target: b},
+        ]);
+        assert_eq!(ops, vec![
+            Operation::Increment {target: a.position(), amount: 2},
+            Operation::Read {target: a},
+            Operation::Increment {target: a.position(), amount: 1},
+            Operation::Copy {source: a.position(), target: b.position(), size: 1},
+            Operation::Write {target: b},
+        ]);
+    }
+
+    #[test]
+    fn runtime_loops() {
+        let mut allocator = StaticAllocator::new();
+        let cond = allocator.allocate(1);
+        let a = allocator.allocate(1);
+        let b = allocator.allocate(1);
+
+        // Only the cells changed by the loop become unknown
+        let ops = fold(vec![
+            Operation::Increment {target: a.position(), amount: 3},
+            Operation::Increment {target: b.position(), amount: 4},
+            Operation::Read {target: cond},
+            Operation::Loop {cond: cond.position(), body: vec![
+                Operation::Increment {target: a.position(), amount: 1},
+                Operation::Decrement {target: cond.position(), amount: 1},
+            ]},
+            Operation::Increment {target: a.position(), amount: 1},
+            Operation::Increment {target: b.position(), amount: 1},
+            Operation::Write {target: a},
+            Operation::Write {target: b},
+        ]);
+        assert_eq!(ops, vec![
+            Operation::Read {target: cond},
+            Operation::Increment {target: a.position(), amount: 3},
+            Operation::Loop {cond: cond.position(), body: vec![
+                Operation::Increment {target: a.position(), amount: 1},
+                Operation::Decrement {target: cond.position(), amount: 1},
+            ]},
+            Operation::Increment {target: a.position(), amount: 1},
+            Operation::Write {target: a},
+            Operation::Increment {target: b.position(), amount: 5},
+            Operation::Write {target: b},
+        ]);
+    }
+
+    #[test]
+    fn folds_comparisons_of_constants() {
+        let ops = compile(r#"
+            let mut counter: u8 = 200;
+            let limit: u8 = 200;
+            if counter == limit {
+                stdout.print(b"a");
+            }
+            else {
+                stdout.print(b"b");
+            }
+
+            let three: u8 = 3;
+            let mut flag: bool = counter != three;
+            while flag {
+                flag = false;
+            }
+            stdout.print(flag);
+        "#);
+        assert!(!has_control_flow(&ops), "comparisons were not evaluated: {:#?}", ops);
+    }
+
+    #[test]
+    fn keeps_comparisons_after_runtime_loops() {
+        // The loop writes output, so it has to run when the program runs
+        let ops = compile(r#"
+            let mut n: u8 = 1;
+            let mut running: bool = true;
+            while running {
+                stdout.print(b"x");
+                n = 3;
+                running = false;
+            }
+
+            let three: u8 = 3;
+            if n == three {
+                stdout.print(b"y");
+            }
+            else {
+                stdout.print(b"n");
+            }
+        "#);
+        assert!(has_control_flow(&ops));
+
+        let instructions = Instructions::from(ops);
+        let mut interpreter = Interpreter::new(&b""[..], Vec::new());
+        interpreter.run(&instructions).unwrap();
+        assert_eq!(interpreter.into_inner().1, b"xy");
+    }
+}
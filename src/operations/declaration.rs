@@ -1,41 +1,57 @@
-use parser::{Identifier, Pattern, TypeDefinition, Expression};
+use std::iter::once;
 
-use super::{OperationsResult, expression, Target};
+use parser::{Identifier, Pattern, TypeDefinition, Expression, Span};
+use memory::MemoryBlock;
+
+use super::{Operation, Operations, OperationsResult, expression, Target};
 use super::item_type::{ItemType};
-use super::scope::{TypeId, ScopeStack, ScopeItem, ScopeType, ArraySize};
+use super::scope::{TypeId, ScopeStack, ScopeItem, ScopeType, ArraySize, Mutability};
 use super::Error;
 
 pub fn into_operations(
     scope: &mut ScopeStack,
     pattern: Pattern,
+    mutable: bool,
     type_def: TypeDefinition,
     expr: Option<Expression>,
 ) -> OperationsResult {
-    let name = match pattern {
-        Pattern::Identifier(name) => name,
-    };
+    let Pattern::Identifier(name) = pattern;
+
+    let mutability = if mutable { Mutability::Mutable } else { Mutability::Immutable };
+    let initialized = expr.is_some();
 
     // Need to always declare the variable in the scope before operating on it
-    match type_def {
+    let ops = match type_def {
         // We return the first declaration found because we want to use the latest definition
         // of the type that we are defining
-        TypeDefinition::Name {name: type_name} => declare_name(scope, name, type_name, expr),
-        TypeDefinition::Array {type_def, size} => declare_array(scope, name, *type_def, size, expr),
+        TypeDefinition::Name {name: type_name} => declare_name(scope, name.clone(), type_name, mutability, expr),
+        TypeDefinition::Array {type_def, size, span} => {
+            declare_array(scope, name.clone(), *type_def, size, mutability, expr, span)
+        },
+    };
+
+    // Only tracked once it is initialized so that the initializer does not count as a use
+    if ops.is_ok() {
+        scope.track_variable(name, mutable, initialized);
     }
+    ops
 }
 
 fn declare_name(
     scope: &mut ScopeStack,
     name: Identifier,
     type_name: Identifier,
+    mutability: Mutability,
     expr: Option<Expression>,
 ) -> OperationsResult {
-    resolve_type_id(scope, &type_name).and_then(|type_id| {
-        let memory = scope.declare(name, type_id);
+    let type_id = match resolve_type_id(scope, &type_name) {
+        Ok(type_id) => type_id,
+        Err(error) => return poison(scope, name, error),
+    };
+    let memory = scope.declare(name, type_id, mutability);
 
-        expr.map_or(Ok(Vec::new()), |expr| {
-            expression::into_operations(scope, expr, Target::TypedBlock {type_id, memory})
-        })
+    expr.map_or(Ok(Vec::new()), |expr| {
+        initialize(memory, expression::into_operations(scope, expr, Target::TypedBlock {type_id, memory})?)
     })
 }
 
@@ -44,22 +60,44 @@ fn declare_array(
     name: Identifier,
     item_type_def: TypeDefinition,
     size_expr: Option<Expression>,
+    mutability: Mutability,
     expr: Option<Expression>,
+    span: Span,
 ) -> OperationsResult {
-    match item_type_def {
-        TypeDefinition::Name {name: ref item_name} => resolve_type_id(scope, item_name).and_then(|item| {
-            let size = infer_size(scope, item, size_expr, &expr, &name)?;
-            let memory = scope.declare_array(name, item, size);
-
-            expr.map_or(Ok(Vec::new()), |expr| {
-                expression::into_operations(scope, expr, Target::Array {item, size, memory})
-            })
-        }),
+    let item = match item_type_def {
+        TypeDefinition::Name {name: ref item_name} => resolve_type_id(scope, item_name),
         //TODO: Deal with infinitely sized (self-referential) types
-        TypeDefinition::Array { .. } => {
-            Err(Error::UnsupportedArrayType {name: name})
-        },
-    }
+        TypeDefinition::Array {span, ..} => Err(Error::UnsupportedArrayType {name: name.clone(), span}),
+    };
+    let declared = item.and_then(|item| {
+        infer_size(scope, item, size_expr, &expr, &name, span).map(|size| (item, size))
+    });
+    let (item, size) = match declared {
+        Ok(declared) => declared,
+        Err(error) => return poison(scope, name, error),
+    };
+    let memory = scope.declare_array(name, item, size, mutability);
+
+    expr.map_or(Ok(Vec::new()), |expr| {
+        initialize(memory, expression::into_operations(scope, expr, Target::Array {item, size, memory})?)
+    })
+}
+
+/// Declares the name as poisoned since its type could not be determined and returns the error
+/// that caused that
+///
+/// Later uses of the name are not reported as errors since they would only be caused by this one.
+fn poison(scope: &mut ScopeStack, name: Identifier, error: Error) -> OperationsResult {
+    scope.declare_poisoned(name);
+    Err(error)
+}
+
+/// Zeroes the memory of a declaration before running the operations that initialize it
+///
+/// Declarations in loops reuse the same memory every iteration, so it may still contain the value
+/// from the previous iteration. Zeroing memory that is already zero is optimized away.
+fn initialize(memory: MemoryBlock, ops: Operations) -> OperationsResult {
+    Ok(once(Operation::Zero {target: memory}).chain(ops).collect())
 }
 
 /// Attempts to infer the size of the array from various pieces of information
@@ -69,57 +107,76 @@ fn infer_size(
     size_expr: Option<Expression>,
     expr: &Option<Expression>,
     name: &Identifier,
+    span: Span,
 ) -> Result<ArraySize, Error> {
     //TODO: Do this better. Ideally, this kind of inference would be done in a separate
     // pass with all the other inference that needs to be done.
     match size_expr {
-        Some(Expression::Number(value)) if value > 0 => Ok(value as ArraySize),
+        Some(Expression::Number(value, _)) if value > 0 => Ok(value as ArraySize),
         // Since no size was declared, try to infer it from the expression
         None if expr.is_some() => match *expr.as_ref().unwrap() {
-            Expression::ByteLiteral(ref literal) => Ok(literal.len()),
+            Expression::ByteLiteral(ref literal, _) => Ok(literal.len()),
             Expression::Identifier(ref name) => scope.lookup(name).first().ok_or_else(|| {
-                Error::UnresolvedName(name.clone())
+                Error::UnresolvedName {name: name.clone(), span: name.span()}
             }).and_then(|item| match **item {
                 ScopeItem::ByteLiteral(ref bytes) => Ok(bytes.len()),
                 ScopeItem::Array {item, size, ..} if item == item_type => Ok(size),
+                ScopeItem::Poisoned => Err(Error::Poisoned {name: name.clone(), span: name.span()}),
                 ref item => Err(Error::MismatchedTypes {
-                    expected: ItemType::Array {
+                    expected: scope.describe_type(&ItemType::Array {
                         item: Some(item_type),
                         size: None,
-                    },
-                    found: match *item {
-                        //TODO: Update this when more numeric types are added
-                        ScopeItem::NumericLiteral(..) => scope.get_type(scope.primitives().u8()).clone(),
-                        ScopeItem::ByteLiteral(..) => unreachable!(),
-                        ScopeItem::Array {item, size, ..} => ItemType::Array {item: Some(item), size: Some(size)},
-                        ref arg => scope.get_type(arg.type_id()).clone(),
-                    },
+                    }),
+                    found: expression::describe_item(scope, item),
+                    span: name.span(),
+                }),
+            }),
+            Expression::Number(_, number_span) => Err(Error::MismatchedTypes {
+                expected: scope.describe_type(&ItemType::Array {
+                    item: Some(item_type),
+                    size: None,
                 }),
+                found: "{integer}".to_owned(),
+                span: number_span,
             }),
-            Expression::Number(..) => Err(Error::MismatchedTypes {
-                expected: ItemType::Array {
+            Expression::StructLiteral {ref name, span, ..} => Err(Error::MismatchedTypes {
+                expected: scope.describe_type(&ItemType::Array {
                     item: Some(item_type),
                     size: None,
+                }),
+                found: name.to_string(),
+                span,
+            }),
+            Expression::Cast {ref type_def, span, ..} => Err(match **type_def {
+                TypeDefinition::Name {name: ref type_name} => Error::MismatchedTypes {
+                    expected: scope.describe_type(&ItemType::Array {
+                        item: Some(item_type),
+                        size: None,
+                    }),
+                    found: type_name.to_string(),
+                    span,
                 },
-                //TODO: Update this when more numeric types are added
-                found: scope.get_type(scope.primitives().u8()).clone(),
+                TypeDefinition::Array {..} => Error::UnsupportedArrayType {name: name.clone(), span},
             }),
+            //TODO: The size could be inferred from the arms once they are checked to agree
+            Expression::Match {span, ..} => Err(Error::UnsupportedArrayType {name: name.clone(), span}),
             // These are unimplemented until a more robust static analysis is implemented
             //TODO: These can all be inferred, it would just be way too messy to do it here
-            Expression::UnitLiteral | Expression::Call {..} | Expression::Access {..} | Expression::Branch {..} => unimplemented!(),
+            Expression::UnitLiteral(_) | Expression::Call {..} | Expression::Access {..} |
+            Expression::Branch {..} => unimplemented!(),
         },
-        _ => Err(Error::UnsupportedArrayType {name: name.clone()}),
+        _ => Err(Error::UnsupportedArrayType {name: name.clone(), span}),
     }
 }
 
-fn resolve_type_id(
+pub fn resolve_type_id(
     scope: &ScopeStack,
     name: &Identifier,
 ) -> Result<TypeId, Error> {
-    scope.lookup_type(&name).first().ok_or_else(|| {
-        Error::UnresolvedName(name.clone())
-    }).and_then(|it| match **it {
-        ScopeType::Type(id) => Ok(id),
+    scope.lookup_type(name).first().ok_or_else(|| {
+        Error::UnresolvedName {name: name.clone(), span: name.span()}
+    }).map(|it| match **it {
+        ScopeType::Type(id) => id,
     })
 }
 
@@ -140,6 +197,7 @@ mod tests {
         let ops = into_operations(
             &mut scope,
             Pattern::Identifier(Identifier::from("foo")),
+            false,
             TypeDefinition::Name {name: Identifier::from("u8")},
             None
         ).unwrap();
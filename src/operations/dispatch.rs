@@ -0,0 +1,469 @@
+use parser::{Identifier, Span};
+use memory::{MemoryBlock, CellPosition};
+
+use super::{Error, Operation, Operations, Target};
+use super::scope::ScopeStack;
+
+/// The program counter is a single cell, so this is as many states as a dispatch loop can have
+const MAX_STATES: usize = 255;
+
+/// The memory used by the dispatch loop of a recursive function
+#[derive(Debug, Clone)]
+pub struct Dispatch {
+    /// The number of the state that runs next, states are numbered from 1
+    pub pc: MemoryBlock,
+    /// Where the body of the function stores its result before returning
+    pub result: MemoryBlock,
+    /// The memory of each parameter of the function
+    pub params: Vec<MemoryBlock>,
+    /// Where the arguments of a recursive call are kept until the call starts, since computing
+    /// them may still need the parameters of the caller
+    pub args: Vec<Target>,
+}
+
+/// Turns the body of a recursive function into a loop that runs the function one state at a time
+///
+/// Brainfuck cannot jump back to the code that made a call, so the body is split into states at
+/// every `Recurse` operation and at every loop and branch around one. A call pushes every
+/// variable of the function onto the call stack followed by the number of the state that
+/// continues after the call, then starts the body again with the new arguments. Returning pops
+/// that number into the program counter and the state it names pops the variables of the caller
+/// back off of the stack. The first call returns to a state that stores the result in the target
+/// and stops the loop.
+///
+/// A function that is only called again through a function around it (like the second of two
+/// functions that call each other) never goes back to the start of its own loop. Those calls are
+/// made by the loop around it instead, so no loop is generated for it at all.
+///
+/// `entry` must store the arguments of the first call in the parameters.
+pub fn dispatch_loop(
+    scope: &mut ScopeStack,
+    name: &Identifier,
+    dispatch: &Dispatch,
+    entry: Operations,
+    body: Operations,
+    target: MemoryBlock,
+) -> Result<Operations, Error> {
+    let pc = dispatch.pc;
+    if !body.iter().any(|op| contains_recursion(op, Some(pc))) {
+        let mut ops = entry;
+        ops.extend(body);
+        ops.push(Operation::Relocate {source: dispatch.result, target});
+        return Ok(ops);
+    }
+
+    let bool_type = scope.primitives().bool();
+    let running = scope.allocate(bool_type);
+
+    let variables = variables(&body, dispatch);
+    let mut states = States {
+        scope,
+        name,
+        dispatch,
+        ops: Vec::new(),
+        variables,
+        hoisted: Vec::new(),
+        enclosing: Vec::new(),
+    };
+    let start = states.add()?;
+    let exit = states.add()?;
+    let end = states.flatten(body, start)?;
+
+    // Returning continues with the state on the top of the call stack
+    states.ops[end].push(Operation::Zero {target: pc});
+    states.ops[end].push(Operation::Pop {target: pc.position()});
+
+    states.ops[exit].extend(vec![
+        Operation::Relocate {source: dispatch.result, target},
+        Operation::Zero {target: pc},
+        Operation::Decrement {target: running.position(), amount: 1},
+    ]);
+
+    let States {scope, ops: state_ops, hoisted, ..} = states;
+    let mut loop_body = Vec::new();
+    for (index, state) in state_ops.into_iter().enumerate() {
+        loop_body.extend(run_state(scope, pc, index, state));
+    }
+
+    let mut ops = entry;
+    // The first call returns to the state that stops the loop
+    ops.extend(goto(pc, exit));
+    ops.push(Operation::Push {source: pc.position()});
+    ops.extend(goto(pc, start));
+    ops.push(Operation::Increment {target: running.position(), amount: 1});
+    ops.push(Operation::Loop {cond: running.position(), body: loop_body});
+
+    // Temporaries used across states keep their memory for the entire loop. They are already
+    // zero by the time the loop stops.
+    Ok(hoisted.into_iter().rev().fold(ops, |body, temp| vec![Operation::TempAllocate {
+        temp,
+        body,
+        should_zero: false,
+    }]))
+}
+
+/// The states of a dispatch loop while the body of the function is being split into them
+struct States<'a> {
+    scope: &'a mut ScopeStack,
+    name: &'a Identifier,
+    dispatch: &'a Dispatch,
+    /// The operations of each state, the state at index `i` runs when the program counter is
+    /// `i + 1`
+    ops: Vec<Operations>,
+    /// Every memory block other than temporaries used by the body
+    variables: Vec<MemoryBlock>,
+    /// Temporaries that are used by more than one state
+    hoisted: Vec<MemoryBlock>,
+    /// The temporaries still in use at the operation currently being split
+    enclosing: Vec<MemoryBlock>,
+}
+
+impl<'a> States<'a> {
+    /// Adds an empty state and returns its index
+    fn add(&mut self) -> Result<usize, Error> {
+        if self.ops.len() >= MAX_STATES {
+            return Err(Error::RecursionTooComplex {
+                name: self.name.clone(),
+                // Replaced with the location of the call by the caller
+                span: Span::default(),
+            });
+        }
+
+        self.ops.push(Vec::new());
+        Ok(self.ops.len() - 1)
+    }
+
+    /// Adds the given operations to the given state, splitting them into new states wherever
+    /// needed. Returns the index of the state that the last of the operations ends up in.
+    fn flatten(&mut self, ops: Operations, state: usize) -> Result<usize, Error> {
+        let mut current = state;
+        for op in ops {
+            if !contains_recursion(&op, None) {
+                self.ops[current].push(op);
+                continue;
+            }
+
+            current = match op {
+                Operation::Block {body} => self.flatten(body, current)?,
+
+                Operation::TempAllocate {temp, body, should_zero} => {
+                    self.hoisted.push(temp);
+                    self.enclosing.push(temp);
+                    let end = self.flatten(body, current)?;
+                    self.enclosing.pop();
+
+                    if should_zero {
+                        self.ops[end].push(Operation::Zero {target: temp});
+                    }
+                    end
+                },
+
+                Operation::Branch {cond, if_body, else_body} => {
+                    let if_start = self.add()?;
+                    let else_start = self.add()?;
+                    let after = self.add()?;
+
+                    let pc = self.dispatch.pc;
+                    self.ops[current].push(Operation::Branch {
+                        cond,
+                        if_body: goto(pc, if_start),
+                        else_body: goto(pc, else_start),
+                    });
+
+                    let if_end = self.flatten(if_body, if_start)?;
+                    self.ops[if_end].extend(goto(pc, after));
+                    let else_end = self.flatten(else_body, else_start)?;
+                    self.ops[else_end].extend(goto(pc, after));
+
+                    after
+                },
+
+                Operation::Loop {cond, body} => {
+                    // The condition is checked in a state of its own so that the end of the body
+                    // can go back to it
+                    let check = self.add()?;
+                    let start = self.add()?;
+                    let after = self.add()?;
+
+                    let pc = self.dispatch.pc;
+                    self.ops[current].extend(goto(pc, check));
+
+                    let u8_type = self.scope.primitives().u8();
+                    let flag = self.scope.allocate(u8_type);
+                    self.ops[check].push(Operation::TempAllocate {
+                        temp: flag,
+                        body: vec![
+                            Operation::Copy {source: cond, target: flag.position(), size: 1},
+                            Operation::Branch {
+                                cond: flag,
+                                if_body: goto(pc, start),
+                                else_body: goto(pc, after),
+                            },
+                        ],
+                        should_zero: true,
+                    });
+
+                    let end = self.flatten(body, start)?;
+                    self.ops[end].extend(goto(pc, check));
+
+                    after
+                },
+
+                Operation::Recurse {pc, target} => {
+                    // A call can only go back to the start of the loop it is in
+                    if pc != self.dispatch.pc {
+                        return Err(Error::NestedRecursion {
+                            name: self.name.clone(),
+                            span: Span::default(),
+                        });
+                    }
+
+                    let resume = self.add()?;
+                    let frame = self.frame();
+
+                    let mut call: Operations = frame_cells(&frame)
+                        .map(|source| Operation::Push {source})
+                        .collect();
+                    // The state to resume is pushed last so that returning finds it first
+                    call.extend(goto(pc, resume));
+                    call.push(Operation::Push {source: pc.position()});
+                    for (arg, &param) in self.dispatch.args.iter().zip(&self.dispatch.params) {
+                        call.push(Operation::Relocate {source: arg.memory(), target: param});
+                    }
+                    call.extend(goto(pc, 0));
+                    self.ops[current].extend(call);
+
+                    // The variables still hold whatever the call left in them
+                    let mut resume_ops: Operations = frame.iter()
+                        .map(|&target| Operation::Zero {target})
+                        .collect();
+                    let cells: Vec<_> = frame_cells(&frame).collect();
+                    resume_ops.extend(cells.into_iter().rev().map(|target| Operation::Pop {target}));
+                    resume_ops.push(Operation::Relocate {source: self.dispatch.result, target});
+                    self.ops[resume].extend(resume_ops);
+
+                    resume
+                },
+
+                _ => unreachable!("Only operations with a body can contain a recursive call"),
+            };
+        }
+
+        Ok(current)
+    }
+
+    /// Returns the memory blocks that have to be saved on the call stack during a call made at
+    /// the operation currently being split
+    fn frame(&self) -> Vec<MemoryBlock> {
+        let mut frame = Vec::new();
+        let blocks = self.dispatch.params.iter().chain(&self.variables).chain(&self.enclosing);
+        for &mem in blocks {
+            if mem.size() > 0 && !frame.contains(&mem) {
+                frame.push(mem);
+            }
+        }
+        frame
+    }
+}
+
+/// Generates the operations that run the given state when the program counter is its number
+fn run_state(scope: &mut ScopeStack, pc: MemoryBlock, index: usize, body: Operations) -> Operations {
+    let u8_type = scope.primitives().u8();
+    let flag = scope.allocate(u8_type);
+    let difference = scope.allocate(u8_type);
+
+    let mut state = vec![Operation::Zero {target: flag}];
+    state.extend(body);
+    vec![Operation::TempAllocate {
+        temp: flag,
+        body: vec![
+            Operation::TempAllocate {
+                temp: difference,
+                body: vec![
+                    Operation::Copy {source: pc.position(), target: difference.position(), size: 1},
+                    Operation::Decrement {target: difference.position(), amount: state_number(index)},
+                    Operation::Increment {target: flag.position(), amount: 1},
+                    // The flag is cleared if the program counter is anything else
+                    Operation::Loop {
+                        cond: difference.position(),
+                        body: vec![
+                            Operation::Zero {target: difference},
+                            Operation::Decrement {target: flag.position(), amount: 1},
+                        ],
+                    },
+                ],
+                should_zero: false,
+            },
+            Operation::Loop {cond: flag.position(), body: state},
+        ],
+        should_zero: false,
+    }]
+}
+
+/// Generates the operations that make the state at the given index run next
+fn goto(pc: MemoryBlock, index: usize) -> Operations {
+    vec![
+        Operation::Zero {target: pc},
+        Operation::Increment {target: pc.position(), amount: state_number(index)},
+    ]
+}
+
+fn state_number(index: usize) -> u8 {
+    // Zero is never used so that a program counter that is zero runs nothing
+    (index + 1) as u8
+}
+
+fn frame_cells<'a>(frame: &'a [MemoryBlock]) -> impl Iterator<Item=CellPosition> + 'a {
+    frame.iter().flat_map(|mem| (0..mem.size()).map(move |i| mem.position_at(i)))
+}
+
+/// Returns every memory block used by the given body that keeps its value between states,
+/// leaving out the memory of the dispatch loop itself
+fn variables(body: &Operations, dispatch: &Dispatch) -> Vec<MemoryBlock> {
+    let mut blocks = Vec::new();
+    used_blocks(body, &mut blocks);
+
+    let mut temps = vec![dispatch.pc, dispatch.result];
+    temps.extend(dispatch.args.iter().map(Target::memory));
+    temporaries(body, &mut temps);
+
+    blocks.retain(|mem| !temps.contains(mem));
+    blocks
+}
+
+fn used_blocks(ops: &Operations, blocks: &mut Vec<MemoryBlock>) {
+    for op in ops {
+        use self::Operation::*;
+        let used = match *op {
+            Block {ref body} | TempAllocate {ref body, ..} => {
+                used_blocks(body, blocks);
+                continue;
+            },
+            Loop {cond, ref body} => {
+                used_blocks(body, blocks);
+                vec![cond.associated_memory()]
+            },
+            Branch {cond, ref if_body, ref else_body} => {
+                used_blocks(if_body, blocks);
+                used_blocks(else_body, blocks);
+                vec![cond]
+            },
+            Increment {target, ..} | Decrement {target, ..} | Pop {target} => vec![target.associated_memory()],
+            Push {source} => vec![source.associated_memory()],
+            Read {target} | Write {target} | Zero {target} | Recurse {target, ..} => vec![target],
+            Copy {source, target, ..} => vec![source.associated_memory(), target.associated_memory()],
+            Relocate {source, target} => vec![source, target],
+        };
+
+        for mem in used {
+            if !blocks.contains(&mem) {
+                blocks.push(mem);
+            }
+        }
+    }
+}
+
+fn temporaries(ops: &Operations, temps: &mut Vec<MemoryBlock>) {
+    for op in ops {
+        match *op {
+            Operation::Block {ref body} | Operation::Loop {ref body, ..} => temporaries(body, temps),
+            Operation::TempAllocate {temp, ref body, ..} => {
+                temps.push(temp);
+                temporaries(body, temps);
+            },
+            Operation::Branch {ref if_body, ref else_body, ..} => {
+                temporaries(if_body, temps);
+                temporaries(else_body, temps);
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Returns true if the given operation contains a call that goes back to the start of the dispatch
+/// loop with the given program counter, or of any dispatch loop if there is none
+fn contains_recursion(op: &Operation, to: Option<MemoryBlock>) -> bool {
+    match *op {
+        Operation::Recurse {pc, ..} => to.is_none() || to == Some(pc),
+        Operation::Block {ref body} | Operation::TempAllocate {ref body, ..} | Operation::Loop {ref body, ..} => {
+            body.iter().any(|op| contains_recursion(op, to))
+        },
+        Operation::Branch {ref if_body, ref else_body, ..} => {
+            if_body.iter().chain(else_body).any(|op| contains_recursion(op, to))
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude;
+    use operations::scope::ScopeStack;
+    use codegen::testing::run_in_scope;
+
+    #[test]
+    fn recursive_functions() {
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        scope.enable_recursion();
+
+        let outputs = run_in_scope(scope, r#"
+            fn factorial(n: u16) -> u16 {
+                if n == 0 {
+                    1
+                } else {
+                    n * factorial(n - 1)
+                }
+            }
+            // Every call in the loop has to resume the loop where it left off
+            fn subsets(n: u16) -> u16 {
+                let mut total: u16 = 1;
+                let mut i: u16 = 0;
+                while i < n {
+                    total += subsets(i);
+                    i += 1;
+                }
+                total
+            }
+            fn countdown(n: u16) {
+                if n > 0 {
+                    stdout.print(n);
+                    countdown(n - 1);
+                    stdout.print(n);
+                }
+            }
+
+            let mut i: u16 = 3;
+            while i < 6 {
+                stdout.print(factorial(i), b" ");
+                i += 1;
+            }
+            stdout.print(subsets(5), b" ");
+            countdown(3);
+        "#, b"");
+        for output in outputs {
+            assert_eq!(String::from_utf8_lossy(&output), "6 24 120 32 321123");
+        }
+    }
+
+    #[test]
+    fn mutually_recursive_functions() {
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        scope.enable_recursion();
+
+        // Only the function called first gets a dispatch loop, the other one is inlined into it
+        let outputs = run_in_scope(scope, r#"
+            stdout.print(is_even(4), is_odd(4), is_even(7), is_odd(7));
+            fn is_even(n: u8) -> bool {
+                if n == 0 { true } else { is_odd(n - 1) }
+            }
+            fn is_odd(n: u8) -> bool {
+                if n == 0 { false } else { is_even(n - 1) }
+            }
+        "#, b"");
+        for output in outputs {
+            assert_eq!(String::from_utf8_lossy(&output), "1001");
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use parser::{Identifier, VariantDefinition, Span};
+use memory::{MemoryBlock, MemSize};
+
+use super::{Error, Operation, OperationsResult, expression};
+use super::item_type::{ItemType, Variant, FieldType, FuncArgType};
+use super::scope::{ScopeStack, FuncArgs};
+use super::structure::field_type;
+
+/// Declares an enum type so that it can be used anywhere in the block it is declared in
+///
+/// A variant without fields is declared as a constant, like `Op::Add`, and a variant with fields
+/// is declared as a function that creates it, like `Op::Num(2)`. Either way, the tag cell of the
+/// value holds the index of its variant and the fields are laid out one after the other after it.
+pub fn into_operations(
+    scope: &mut ScopeStack,
+    name: Identifier,
+    variant_defs: Vec<VariantDefinition>,
+) -> OperationsResult {
+    if scope.is_type_declared_here(&name) {
+        return Err(Error::DuplicateDefinition {span: name.span(), name});
+    }
+
+    let mut variants: Vec<Variant> = Vec::new();
+    for VariantDefinition {name: variant_name, fields: type_defs} in variant_defs {
+        if variants.iter().any(|variant| variant.name == variant_name) {
+            return Err(Error::DuplicateVariant {span: variant_name.span(), variant: variant_name});
+        }
+
+        let fields = type_defs.into_iter()
+            .map(|type_def| field_type(scope, &variant_name, type_def))
+            .collect::<Result<Vec<_>, _>>()?;
+        variants.push(Variant {name: variant_name, fields});
+    }
+
+    //TODO: The tag is a single cell, so only the first 256 variants can be told apart
+    let type_id = scope.declare_type(name.clone(), ItemType::Enum {variants: variants.clone()});
+    let size = scope.get_type(type_id).required_size(scope);
+    for (tag, variant) in variants.into_iter().enumerate() {
+        let path = name.clone().concat(variant.name.clone()).with_span(variant.name.span());
+        if variant.fields.is_empty() {
+            let mut bytes = vec![0; size];
+            bytes[0] = tag as u8;
+            scope.declare_constant(path, type_id, bytes);
+            continue;
+        }
+
+        let args = variant.fields.iter().map(|&field| match field {
+            FieldType::Value(type_id) => FuncArgType::Arg(type_id),
+            FieldType::Array {item, size} => FuncArgType::Array {item, size: Some(size)},
+        }).collect();
+        let fields = variant.field_offsets(scope);
+        scope.declare_builtin_function(path, ItemType::Function {args, return_type: type_id}, move |scope, args, target| {
+            construct_variant(scope, tag as u8, &fields, args, target)
+        });
+    }
+
+    Ok(Vec::new())
+}
+
+/// Stores the variant with the given tag in the target with each argument copied into the field
+/// at the same position
+fn construct_variant(
+    scope: &mut ScopeStack,
+    tag: u8,
+    fields: &[(FieldType, MemSize)],
+    args: FuncArgs,
+    target: MemoryBlock,
+) -> OperationsResult {
+    let mut ops = vec![Operation::Increment {target: target.position_at(0), amount: tag}];
+    for (arg, &(typ, offset)) in args.into_iter().zip(fields) {
+        let value = expression::allocate_field(scope, typ);
+        // Replaced with the location of the call by the caller
+        let mut body = expression::store_item(scope, arg, Span::default(), value)?;
+        body.extend(expression::copy_to_field(scope, value.memory(), target, typ, offset));
+        ops.push(Operation::TempAllocate {
+            temp: value.memory(),
+            body,
+            should_zero: true,
+        });
+    }
+
+    Ok(ops)
+}
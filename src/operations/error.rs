@@ -1,37 +1,123 @@
-use super::item_type::ItemType;
 use super::operation::Operations;
+use super::PathStep;
 
-use parser::Identifier;
+use parser::{Identifier, Span};
+use diagnostics::Diagnostic;
 
 pub type OperationsResult = Result<Operations, Error>;
 
+/// Every error stores the span of the code that caused it so that it can be reported to the user
 #[derive(Debug, Clone, PartialEq)]
 pub enum Error {
     /// Unresolved name: `name`
-    UnresolvedName(Identifier),
+    UnresolvedName {
+        name: Identifier,
+        span: Span,
+    },
+
+    /// The name `name` is defined multiple times in the same scope
+    DuplicateDefinition {
+        name: Identifier,
+        span: Span,
+    },
 
     /// No field `field` on type `target_type`
     UnresolvedField {
         /// The target of the field acccess
-        target_type: ItemType,
+        target_type: String,
         /// The field name that was attempted
         field: Identifier,
+        span: Span,
+    },
+
+    /// Field `field` is declared or given a value more than once
+    DuplicateField {
+        field: Identifier,
+        span: Span,
+    },
+
+    /// A struct literal of type `type_name` does not give a value to every field
+    MissingFields {
+        type_name: String,
+        /// The fields without a value in the order they are declared
+        fields: Vec<Identifier>,
+        span: Span,
+    },
+
+    /// `name` is used to create a struct but it is not a struct type
+    NotAStruct {
+        name: Identifier,
+        span: Span,
+    },
+
+    /// Variant `variant` is declared more than once in the same enum
+    DuplicateVariant {
+        variant: Identifier,
+        span: Span,
+    },
+
+    /// The type `type_name` has no variant named `variant`
+    UnresolvedVariant {
+        type_name: String,
+        variant: Identifier,
+        span: Span,
+    },
+
+    /// A pattern for the variant `variant` does not have a name or `_` for each of its fields
+    MismatchedPatternFields {
+        variant: Identifier,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+
+    /// The arms of a `match` do not cover every possible value
+    NonExhaustivePatterns {
+        /// Patterns for the values that are not covered, as they would be written in the source
+        missing: Vec<String>,
+        span: Span,
     },
 
     /// `name` is not a valid type
-    InvalidType(Identifier),
+    InvalidType {
+        name: Identifier,
+        span: Span,
+    },
 
     /// Invalid left-hand side expression used in assignment
     /// Usually because `name` is not a variable
     /// It might be a type or function or something
-    InvalidLeftHandSide(Identifier),
+    InvalidLeftHandSide {
+        name: Identifier,
+        span: Span,
+    },
+
+    /// Cannot modify immutable variable `name`
+    /// Variables must be declared with `let mut` to be modified after they are initialized
+    ImmutableVariable {
+        name: Identifier,
+        /// The span of the name in the declaration of the variable
+        declared: Span,
+        span: Span,
+    },
+
+    /// Used binding `name` is possibly-uninitialized
+    /// Variables must be given a value on every path before they are read
+    UninitializedVariable {
+        name: Identifier,
+        /// The decisions that lead to the variable not being initialized, empty if it is never
+        /// initialized before it is used
+        path: Vec<PathStep>,
+        span: Span,
+    },
 
     /// Mismatched types:
     ///     expected type: `expected`
     ///     found type: `found`
     MismatchedTypes {
-        expected: ItemType,
-        found: ItemType,
+        expected: String,
+        found: String,
+        span: Span,
     },
 
     /// Same as MismatchedTypes but for literals
@@ -40,13 +126,61 @@ pub enum Error {
     ///     expected type: `expected`
     ///     found type: `found`
     MismatchedLiteral {
-        expected: ItemType,
+        expected: String,
         found: String,
+        span: Span,
     },
 
     /// Overflowing literal: literal out of range for `typ`
     OverflowingLiteral {
-        typ: ItemType,
+        typ: String,
+        span: Span,
+    },
+
+    /// Cannot cast `from` as `to`
+    /// Only numeric types and `bool` can be cast and nothing can be cast to `bool`
+    InvalidCast {
+        from: String,
+        to: String,
+        span: Span,
+    },
+
+    /// A function declared with `fn` calls itself through another function without recursion
+    /// being enabled
+    MutualRecursion {
+        name: Identifier,
+        span: Span,
+    },
+
+    /// A function declared with `fn` calls itself without recursion being enabled
+    /// Functions are inlined wherever they are called, so recursion would never stop
+    RecursiveCall {
+        name: Identifier,
+        span: Span,
+    },
+
+    /// `self` is declared somewhere other than the first parameter of a method
+    UnexpectedSelf {
+        span: Span,
+    },
+
+    /// The recursive function `name` has more states than its dispatch loop can tell apart
+    RecursionTooComplex {
+        name: Identifier,
+        span: Span,
+    },
+
+    /// The recursive function `name` calls a recursive function that is still running from
+    /// further out, which would need to leave the dispatch loop of `name` partway through
+    NestedRecursion {
+        name: Identifier,
+        span: Span,
+    },
+
+    /// The function `name` returns an array, which is not supported yet
+    UnsupportedReturnType {
+        name: Identifier,
+        span: Span,
     },
 
     /// Unsupported array type declaration
@@ -56,5 +190,363 @@ pub enum Error {
     /// * Cannot infer without expression
     UnsupportedArrayType {
         name: Identifier,
+        span: Span,
+    },
+
+    /// The type of a conditional or `match` that is passed to a call could not be found from the
+    /// values of its arms
+    TypeAnnotationsNeeded {
+        span: Span,
+    },
+
+    /// A name whose declaration failed was used
+    /// This error is never reported since the declaration already was
+    Poisoned {
+        name: Identifier,
+        span: Span,
     },
 }
+
+impl Error {
+    /// Returns the location of the code that caused this error
+    pub fn span(&self) -> Span {
+        use self::Error::*;
+        match *self {
+            UnresolvedName {span, ..} |
+            DuplicateDefinition {span, ..} |
+            UnresolvedField {span, ..} |
+            DuplicateField {span, ..} |
+            MissingFields {span, ..} |
+            NotAStruct {span, ..} |
+            DuplicateVariant {span, ..} |
+            UnresolvedVariant {span, ..} |
+            MismatchedPatternFields {span, ..} |
+            NonExhaustivePatterns {span, ..} |
+            InvalidType {span, ..} |
+            InvalidLeftHandSide {span, ..} |
+            ImmutableVariable {span, ..} |
+            UninitializedVariable {span, ..} |
+            MismatchedTypes {span, ..} |
+            MismatchedLiteral {span, ..} |
+            OverflowingLiteral {span, ..} |
+            InvalidCast {span, ..} |
+            RecursiveCall {span, ..} |
+            MutualRecursion {span, ..} |
+            UnexpectedSelf {span} |
+            RecursionTooComplex {span, ..} |
+            NestedRecursion {span, ..} |
+            UnsupportedReturnType {span, ..} |
+            UnsupportedArrayType {span, ..} |
+            TypeAnnotationsNeeded {span} |
+            Poisoned {span, ..} => span,
+        }
+    }
+
+    /// Converts this error into a diagnostic that can be rendered with the source code
+    pub fn diagnostic(&self) -> Diagnostic {
+        use self::Error::*;
+        match *self {
+            UnresolvedName {ref name, span} => {
+                Diagnostic::error(format!("cannot find `{}` in this scope", name), span)
+                    .with_label("not found in this scope")
+            },
+            DuplicateDefinition {ref name, span} => {
+                Diagnostic::error(format!("the name `{}` is defined multiple times", name), span)
+                    .with_label(format!("`{}` redefined here", name))
+            },
+            UnresolvedField {ref target_type, ref field, span} => {
+                Diagnostic::error(format!("no field `{}` on type `{}`", field, target_type), span)
+                    .with_label("unknown field")
+            },
+            DuplicateField {ref field, span} => {
+                Diagnostic::error(format!("field `{}` is used more than once", field), span)
+                    .with_label("duplicate field")
+            },
+            MissingFields {ref type_name, ref fields, span} => {
+                let fields: Vec<_> = fields.iter().map(|field| format!("`{}`", field)).collect();
+                Diagnostic::error(format!("missing {} {} in initializer of `{}`",
+                    if fields.len() == 1 { "field" } else { "fields" }, fields.join(", "), type_name), span)
+                    .with_label(format!("missing {}", fields.join(", ")))
+            },
+            NotAStruct {ref name, span} => {
+                Diagnostic::error(format!("`{}` is not a struct", name), span)
+                    .with_label("not a struct")
+                    .with_note("only struct types can be created with a struct literal like `Name {field: value}`")
+            },
+            DuplicateVariant {ref variant, span} => {
+                Diagnostic::error(format!("variant `{}` is declared more than once", variant), span)
+                    .with_label("duplicate variant")
+            },
+            UnresolvedVariant {ref type_name, ref variant, span} => {
+                Diagnostic::error(format!("no variant named `{}` found for `{}`", variant, type_name), span)
+                    .with_label("unknown variant")
+            },
+            MismatchedPatternFields {ref variant, expected, found, span} => {
+                let fields = |count| if count == 1 { "field" } else { "fields" };
+                Diagnostic::error(format!("this pattern has {} {}, but `{}` has {} {}",
+                    found, fields(found), variant, expected, fields(expected)), span)
+                    .with_label(format!("expected {} {}, found {}", expected, fields(expected), found))
+                    .with_help("use `_` for each field that is not needed")
+            },
+            NonExhaustivePatterns {ref missing, span} => {
+                let mut missing: Vec<_> = missing.iter().map(|pattern| format!("`{}`", pattern)).collect();
+                let last = missing.pop().expect("Expected at least one missing pattern");
+                let patterns = if missing.is_empty() { last } else { format!("{} and {}", missing.join(", "), last) };
+                Diagnostic::error(format!("non-exhaustive patterns: {} not covered", patterns), span)
+                    .with_label(format!("{} not covered", patterns))
+                    .with_help("add an arm for each missing pattern or a wildcard arm like `_ => {}`")
+            },
+            InvalidType {ref name, span} => {
+                Diagnostic::error(format!("`{}` is not a type", name), span)
+                    .with_label("not a type")
+            },
+            InvalidLeftHandSide {ref name, span} => {
+                Diagnostic::error("invalid left-hand side of assignment", span)
+                    .with_label(format!("cannot assign to `{}`", name))
+                    .with_note("only variables can be assigned to")
+            },
+            ImmutableVariable {ref name, declared, span} if *name == Identifier::from("self") => {
+                Diagnostic::error("cannot modify immutable variable `self`", span)
+                    .with_label("cannot modify")
+                    .with_note(format!("`self` is declared without `mut` on line {}", declared.line))
+                    .with_help("make the method take a mutable copy of the instance: `mut self`")
+            },
+            ImmutableVariable {ref name, declared, span} => {
+                Diagnostic::error(format!("cannot modify immutable variable `{}`", name), span)
+                    .with_label("cannot modify")
+                    .with_note(format!("`{}` is declared without `mut` on line {}", name, declared.line))
+                    .with_help(format!("make the variable mutable: `let mut {}`", name))
+            },
+            UninitializedVariable {ref name, ref path, span} if path.is_empty() => {
+                Diagnostic::error(format!("used binding `{}` isn't initialized", name), span)
+                    .with_label(format!("`{}` used here but it isn't initialized", name))
+                    .with_help(format!("give `{}` a value when it is declared or assign to it before \
+                        using it", name))
+            },
+            UninitializedVariable {ref name, ref path, span} => {
+                let path: Vec<_> = path.iter().map(|step| step.to_string()).collect();
+                Diagnostic::error(format!("used binding `{}` is possibly-uninitialized", name), span)
+                    .with_label(format!("`{}` used here but it is possibly-uninitialized", name))
+                    .with_note(format!("`{}` is not initialized if {}", name, path.join(" and ")))
+            },
+            MismatchedTypes {ref expected, ref found, span} |
+            MismatchedLiteral {ref expected, ref found, span} => {
+                Diagnostic::error("mismatched types", span)
+                    .with_label(format!("expected `{}`, found `{}`", expected, found))
+            },
+            OverflowingLiteral {ref typ, span} => {
+                Diagnostic::error(format!("literal out of range for `{}`", typ), span)
+                    .with_label("does not fit")
+            },
+            InvalidCast {ref from, ref to, span} => {
+                let diagnostic = Diagnostic::error(format!("cannot cast `{}` as `{}`", from, to), span)
+                    .with_label("unsupported cast")
+                    .with_note("only numeric types and `bool` can be cast with `as`");
+                if to == "bool" {
+                    diagnostic.with_help("compare with zero instead, like `x != 0`")
+                }
+                else {
+                    diagnostic
+                }
+            },
+            RecursiveCall {ref name, span} => {
+                Diagnostic::error(format!("`{}` cannot call itself", name), span)
+                    .with_label("recursive call")
+                    .with_note("functions are inlined wherever they are called, so a function that calls \
+                        itself would never stop being inlined")
+                    .with_help("compile with `--recursion` to call functions that call themselves using a \
+                        call stack instead")
+            },
+            UnexpectedSelf {span} => {
+                Diagnostic::error("unexpected `self` parameter in function", span)
+                    .with_label("must be the first parameter of a method")
+                    .with_note("methods are declared in an `impl` block, like `impl Point { fn show(self) {} }`")
+            },
+            RecursionTooComplex {ref name, span} => {
+                Diagnostic::error(format!("`{}` is too complex to be called recursively", name), span)
+                    .with_label("recursive function called here")
+                    .with_note("a recursive function can have at most 255 states, one for each recursive \
+                        call and a few for each loop or branch around one")
+            },
+            MutualRecursion {ref name, span} => {
+                Diagnostic::error(format!("`{}` calls itself through another function", name), span)
+                    .with_label("recursive function")
+                    .with_note("functions are inlined wherever they are called, so a function that calls \
+                        itself would never stop being inlined")
+                    .with_help("compile with `--recursion` to call functions that call themselves using a \
+                        call stack instead")
+            },
+            NestedRecursion {ref name, span} => {
+                Diagnostic::error(format!("`{}` calls a recursive function that called it", name), span)
+                    .with_label("recursive function called here")
+                    .with_note("a recursive function can only call itself from its own body, not from the \
+                        body of another recursive function")
+            },
+            UnsupportedReturnType {ref name, span} => {
+                Diagnostic::error(format!("`{}` cannot return an array", name), span)
+                    .with_label("unsupported return type")
+                    .with_note("functions can only return a single value like `u8` or `bool` for now")
+            },
+            UnsupportedArrayType {ref name, span} => {
+                Diagnostic::error(format!("unsupported array type for `{}`", name), span)
+                    .with_label("unsupported array type")
+                    .with_note("arrays cannot contain other arrays")
+                    .with_help("give the array a positive numeric size like `[u8; 3]` or initialize it \
+                        so the size can be inferred")
+            },
+            TypeAnnotationsNeeded {span} => {
+                Diagnostic::error("type annotations needed", span)
+                    .with_label("cannot infer the type of this value")
+                    .with_help("store the value in a variable with a type first, like `let x: u8 = ...;`")
+            },
+            Poisoned {ref name, span} => {
+                Diagnostic::error(format!("`{}` could not be declared", name), span)
+                    .with_label("declaration failed")
+            },
+        }
+    }
+
+    /// Returns this error with the given span if it does not have one already
+    ///
+    /// Built-in functions do not know where they were called from, so errors from them are
+    /// given the span of the call.
+    pub fn or_span(mut self, new_span: Span) -> Error {
+        use self::Error::*;
+        match self {
+            UnresolvedName {ref mut span, ..} |
+            DuplicateDefinition {ref mut span, ..} |
+            UnresolvedField {ref mut span, ..} |
+            DuplicateField {ref mut span, ..} |
+            MissingFields {ref mut span, ..} |
+            NotAStruct {ref mut span, ..} |
+            DuplicateVariant {ref mut span, ..} |
+            UnresolvedVariant {ref mut span, ..} |
+            MismatchedPatternFields {ref mut span, ..} |
+            NonExhaustivePatterns {ref mut span, ..} |
+            InvalidType {ref mut span, ..} |
+            InvalidLeftHandSide {ref mut span, ..} |
+            ImmutableVariable {ref mut span, ..} |
+            UninitializedVariable {ref mut span, ..} |
+            MismatchedTypes {ref mut span, ..} |
+            MismatchedLiteral {ref mut span, ..} |
+            OverflowingLiteral {ref mut span, ..} |
+            InvalidCast {ref mut span, ..} |
+            RecursiveCall {ref mut span, ..} |
+            MutualRecursion {ref mut span, ..} |
+            UnexpectedSelf {ref mut span} |
+            RecursionTooComplex {ref mut span, ..} |
+            NestedRecursion {ref mut span, ..} |
+            UnsupportedReturnType {ref mut span, ..} |
+            UnsupportedArrayType {ref mut span, ..} |
+            TypeAnnotationsNeeded {ref mut span} |
+            Poisoned {ref mut span, ..} => if span.is_unknown() {
+                *span = new_span;
+            },
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::Program;
+    use operations::scope::ScopeStack;
+    use diagnostics::ColorMode;
+    use prelude;
+
+    #[test]
+    fn renders_type_names() {
+        let source = "let a: [u8; 2] = b\"ab\";\nlet b: u8 = a;\n";
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        let program: Program = source.parse().unwrap();
+
+        let error = program.into_operations(&mut scope).unwrap_err().remove(0);
+        assert_eq!(error.diagnostic().render("test.brn", source, ColorMode::Plain), "\
+error: mismatched types
+ --> test.brn:2:13
+  |
+2 | let b: u8 = a;
+  |             ^ expected `u8`, found `[u8; 2]`
+");
+    }
+
+    #[test]
+    fn renders_mismatched_signatures() {
+        let source = "fn f(x: u8) -> u8 { x }\nlet a: u16 = 1;\nlet b: u8 = 2;\nlet c: u16 = a + b;\nlet d: u16 = f(b);\n";
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        let program: Program = source.parse().unwrap();
+
+        // The return type is only part of the expected signature when it is the reason the
+        // function does not match
+        let errors = program.into_operations(&mut scope).unwrap_err();
+        let labels: Vec<_> = errors.iter().map(|error| error.diagnostic().render("test.brn", source, ColorMode::Plain))
+            .map(|rendered| rendered.lines().nth(4).unwrap().trim().to_owned())
+            .collect();
+        assert_eq!(labels, vec![
+            "|                ^ expected `fn(u16, u8)`, found `fn(u16, u16) -> u16`",
+            "|              ^ expected `u16`, found `u8`",
+        ]);
+    }
+
+    #[test]
+    fn renders_invalid_casts() {
+        let source = "let a: u8 = 1;\nlet b: bool = a as bool;\n";
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        let program: Program = source.parse().unwrap();
+
+        let error = program.into_operations(&mut scope).unwrap_err().remove(0);
+        assert_eq!(error.diagnostic().render("test.brn", source, ColorMode::Plain), "\
+error: cannot cast `u8` as `bool`
+ --> test.brn:2:15
+  |
+2 | let b: bool = a as bool;
+  |               ^^^^^^^^^ unsupported cast
+  |
+  = note: only numeric types and `bool` can be cast with `as`
+  = help: compare with zero instead, like `x != 0`
+");
+    }
+
+    #[test]
+    fn renders_recursive_calls_once() {
+        let source = "fn forever(a: u8) -> u8 {\n    forever(a)\n}\nlet b: u8 = forever(1) + forever(2);\n";
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        let program: Program = source.parse().unwrap();
+
+        let errors = program.into_operations(&mut scope).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].diagnostic().render("test.brn", source, ColorMode::Plain), "\
+error: `forever` cannot call itself
+ --> test.brn:2:5
+  |
+2 |     forever(a)
+  |     ^^^^^^^ recursive call
+  |
+  = note: functions are inlined wherever they are called, so a function that calls itself would never stop being inlined
+  = help: compile with `--recursion` to call functions that call themselves using a call stack instead
+");
+    }
+
+    #[test]
+    fn renders_non_exhaustive_patterns() {
+        let source = "enum Op { Add, Sub, Num(u8) }\nlet a: Op = Op::Add;\nmatch a { Op::Sub => {} }\n";
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        let program: Program = source.parse().unwrap();
+
+        let error = program.into_operations(&mut scope).unwrap_err().remove(0);
+        assert_eq!(error.diagnostic().render("test.brn", source, ColorMode::Plain), "\
+error: non-exhaustive patterns: `Op::Add` and `Op::Num(_)` not covered
+ --> test.brn:3:1
+  |
+3 | match a { Op::Sub => {} }
+  | ^^^^^^^^^^^^^^^^^^^^^^^^^ `Op::Add` and `Op::Num(_)` not covered
+  |
+  = help: add an arm for each missing pattern or a wildcard arm like `_ => {}`
+");
+    }
+}
@@ -2,7 +2,7 @@ use std::iter::once;
 
 use parser::{Expression, Block};
 
-use operations::{Operation, OperationsResult, expression, block};
+use operations::{Operation, OperationsResult, Warning, InitializationState, expression, block};
 use operations::scope::{ScopeStack};
 
 use super::Target;
@@ -17,15 +17,74 @@ pub fn branch(
     let bool_type = scope.primitives().bool();
     let cond = scope.allocate(bool_type);
 
+    let cond_span = condition.span();
+    if let Some(value) = expression::constant_condition(scope, &condition) {
+        scope.report_warning(Warning::ConstantCondition {value, span: cond_span});
+    }
+
+    // The bodies are still checked when the condition fails so that their errors are reported too
     let cond_ops = expression::into_operations(scope, condition, Target::TypedBlock {
         type_id: bool_type,
         memory: cond,
-    })?;
-    let if_body = block::into_operations(scope, body, target)?;
+    }).unwrap_or_else(|error| {
+        scope.report_error(error);
+        Vec::new()
+    });
+
+    // Each body starts with what was initialized before the branch and a variable is only
+    // initialized after the branch if both bodies initialize it
+    let before = scope.initialization();
+    let if_body = block::into_operations(scope, body, target);
+    let after_if = scope.initialization();
+    scope.set_initialization(before);
     let else_body = match otherwise {
-        Some(else_body) => block::into_operations(scope, else_body, target)?,
-        None => Vec::new(),
+        Some(else_body) => block::into_operations(scope, else_body, target),
+        None => Ok(Vec::new()),
     };
+    let after_else = scope.initialization();
+    scope.set_initialization(InitializationState::merge_branches(after_if, after_else, cond_span));
+    let (if_body, else_body) = (if_body?, else_body?);
+
+    // The condition is zeroed once the branch is done so that the branch can run again (e.g. in
+    // a loop) with a freshly evaluated condition
+    Ok(vec![Operation::TempAllocate {
+        temp: cond,
+        body: cond_ops.into_iter().chain(once(Operation::Branch {cond, if_body, else_body})).collect(),
+        should_zero: true,
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use codegen::testing::assert_output;
+
+    #[test]
+    fn conditional_as_an_operand() {
+        assert_output(r#"
+            let a: u8 = 5;
+            let x: u8 = if a > 1 { 1 } else { 2 } + 1;
+            let y: u8 = 2 * if a > 9 { a } else { 3 };
+            stdout.print(x, b" ", y, b" ", if a == 5 { b"ok" } else { b"no" });
+        "#, b"", b"2 6 ok");
+    }
 
-    Ok(cond_ops.into_iter().chain(once(Operation::Branch {cond, if_body, else_body})).collect())
+    #[test]
+    fn statements_after_a_conditional_at_the_end_of_a_block() {
+        assert_output(r#"
+            fn g(n: u8) -> u8 {
+                let mut r: u8 = 0;
+                if n > 0 {
+                    r = n + 1;
+                }
+                r
+            }
+            let x: bool = true;
+            if x {
+                if x {
+                    stdout.print(b"a");
+                }
+                stdout.print(b"b", g(5))
+            }
+        "#, b"", b"ab6");
+    }
 }
@@ -1,3 +1,5 @@
+use parser::Span;
+
 use operations::{Error, Operation, OperationsResult};
 use operations::scope::ScopeStack;
 use operations::item_type::{ItemType};
@@ -7,15 +9,17 @@ use super::Target;
 pub fn store_byte_literal(
     scope: &mut ScopeStack,
     bytes: &[u8],
+    span: Span,
     target: Target,
 ) -> OperationsResult {
     match target {
         Target::TypedBlock {type_id, ..} => Err(Error::MismatchedTypes {
-            expected: scope.get_type(type_id).clone(),
-            found: ItemType::Array {
+            expected: scope.type_name(type_id),
+            found: scope.describe_type(&ItemType::Array {
                 item: Some(scope.primitives().u8()),
                 size: Some(bytes.len()),
-            },
+            }),
+            span,
         }),
 
         Target::Array {item, size, memory} => {
@@ -23,14 +27,15 @@ pub fn store_byte_literal(
 
             if item != u8_type || bytes.len() != size {
                 return Err(Error::MismatchedTypes {
-                    expected: ItemType::Array {
+                    expected: scope.describe_type(&ItemType::Array {
                         item: Some(item),
                         size: Some(size),
-                    },
-                    found: ItemType::Array {
+                    }),
+                    found: scope.describe_type(&ItemType::Array {
                         item: Some(u8_type),
                         size: Some(bytes.len()),
-                    },
+                    }),
+                    span,
                 });
             }
 
@@ -1,10 +1,15 @@
-use parser::{Expression, CallArgs, Identifier};
+use parser::{Expression, Statement, Block, CallArgs, Identifier, TypeDefinition, Span};
+use memory::MemoryBlock;
 
-use operations::{Error, OperationsResult};
+use operations::{Error, Operation, Operations, OperationsResult};
 use operations::item_type::{ItemType, FuncArgType};
-use operations::scope::{ScopeStack, ScopeItem, FuncArgs};
+use operations::scope::{TypeId, ScopeStack, ScopeItem, FuncArgs, Mutability, ArraySize};
+use operations::declaration::resolve_type_id;
 
-use super::Target;
+use super::{Target, into_operations};
+use super::cast::cast;
+use super::field::{field_path, resolve_field, allocate_field};
+use operations::item_type::FieldType;
 
 /// Evaluates the arguments first, then supplies them to the given method
 pub fn call_with_exprs(
@@ -13,23 +18,145 @@ pub fn call_with_exprs(
     arg_exprs: CallArgs,
     target: Target,
 ) -> OperationsResult {
-    let (mut args, ops): (Vec<_>, Vec<_>) = arg_exprs.into_iter().map(|expr| match expr {
-        Expression::UnitLiteral => unimplemented!(),
-        Expression::ByteLiteral(bytes) => Ok((ScopeItem::ByteLiteral(bytes), Vec::new())),
-        Expression::Number(bytes) => Ok((ScopeItem::NumericLiteral(bytes), Vec::new())),
-        Expression::Identifier(name) => scope.lookup(&name).first().ok_or_else(|| {
-            Error::UnresolvedName(name.clone())
-        }).map(|item| ((**item).clone(), Vec::new())),
-        Expression::Call {..} => unimplemented!(),
-        Expression::Access {..} => unimplemented!(),
-        Expression::Branch {..} => unimplemented!(),
-    }).collect::<Result<Vec<_>, _>>()?.into_iter().unzip();
+    let arguments = evaluate_arguments(scope, method, arg_exprs)?;
+    let variables = &arguments.variables;
+    let call_ops = call(scope, arguments.method_name, arguments.args, target)
+        .map_err(|err| at_argument(scope, err, variables))?;
+    check_initialized(scope, variables)?;
 
-    let (target_instance, method_name): (Option<ScopeItem>, Identifier) = match method {
-        Expression::Identifier(name) => (None, name),
-        Expression::Access {target, field} => {
-            let (target, method_name) = resolve_field_name(scope, *target, field)?;
-            (Some(target), method_name)
+    Ok(allocate_temporaries(arguments.temporaries, arguments.ops.into_iter().chain(call_ops).collect()))
+}
+
+/// Evaluates a call that is an argument of another call into a new temporary value
+///
+/// Returns the argument and the temporary memory that it is stored in. That memory must be
+/// freed once the call it is passed to is done with it.
+fn call_into_temporary(
+    scope: &mut ScopeStack,
+    method: Expression,
+    arg_exprs: CallArgs,
+) -> Result<(ScopeItem, Operations, MemoryBlock), Error> {
+    let arguments = evaluate_arguments(scope, method, arg_exprs)?;
+
+    // If no function matches, calling with any return type will report why
+    let type_id = return_type(scope, &arguments.method_name, &arguments.args)
+        .unwrap_or_else(|| scope.primitives().unit());
+    let memory = scope.allocate(type_id);
+    let variables = &arguments.variables;
+    let call_ops = call(scope, arguments.method_name, arguments.args, Target::TypedBlock {type_id, memory})
+        .map_err(|err| at_argument(scope, err, variables))?;
+    check_initialized(scope, variables)?;
+
+    let ops = allocate_temporaries(arguments.temporaries, arguments.ops.into_iter().chain(call_ops).collect());
+    Ok((ScopeItem::TypedBlock {type_id, memory, mutability: Mutability::Immutable}, ops, memory))
+}
+
+/// Evaluates a cast that is an argument of a call into a new temporary value
+///
+/// Returns the argument and the temporary memory that it is stored in. That memory must be
+/// freed once the call it is passed to is done with it.
+fn cast_into_temporary(
+    scope: &mut ScopeStack,
+    expr: Expression,
+    type_def: TypeDefinition,
+    span: Span,
+) -> Result<(ScopeItem, Operations, MemoryBlock), Error> {
+    // If the type cannot be cast to, the cast will report why before it uses the memory
+    let type_id = match type_def {
+        TypeDefinition::Name {ref name} => resolve_type_id(scope, name).ok(),
+        TypeDefinition::Array {..} => None,
+    }.unwrap_or_else(|| scope.primitives().unit());
+    let memory = scope.allocate(type_id);
+    let ops = cast(scope, expr, type_def, span, Target::TypedBlock {type_id, memory})?;
+
+    Ok((ScopeItem::TypedBlock {type_id, memory, mutability: Mutability::Immutable}, ops, memory))
+}
+
+/// Evaluates a field access that is an argument of a call into a new temporary value
+///
+/// Returns the argument and the temporary memory that it is stored in. That memory must be
+/// freed once the call it is passed to is done with it.
+fn field_into_temporary(
+    scope: &mut ScopeStack,
+    target: Expression,
+    field: Identifier,
+    span: Span,
+) -> Result<(ScopeItem, Operations, MemoryBlock), Error> {
+    let (name, fields) = field_path(&target, &field);
+    let typ = resolve_field(scope, &name, &fields)?.1;
+    let value = allocate_field(scope, typ);
+    let ops = into_operations(scope, Expression::Access {target: Box::new(target), field, span}, value)?;
+
+    Ok((temporary_item(value), ops, value.memory()))
+}
+
+/// Evaluates a struct literal that is an argument of a call into a new temporary value
+///
+/// Returns the argument and the temporary memory that it is stored in. That memory must be
+/// freed once the call it is passed to is done with it.
+fn struct_into_temporary(
+    scope: &mut ScopeStack,
+    name: Identifier,
+    fields: Vec<(Identifier, Expression)>,
+    span: Span,
+) -> Result<(ScopeItem, Operations, MemoryBlock), Error> {
+    // If the name is not a struct, the struct literal will report why before it uses the memory
+    let type_id = resolve_type_id(scope, &name)?;
+    let memory = scope.allocate(type_id);
+    let ops = into_operations(scope, Expression::StructLiteral {name, fields, span}, Target::TypedBlock {type_id, memory})?;
+
+    Ok((ScopeItem::TypedBlock {type_id, memory, mutability: Mutability::Immutable}, ops, memory))
+}
+
+/// Returns the item that refers to a temporary value stored in the given target
+pub fn temporary_item(target: Target) -> ScopeItem {
+    match target {
+        Target::TypedBlock {type_id, memory} => ScopeItem::TypedBlock {type_id, memory, mutability: Mutability::Immutable},
+        Target::Array {item, size, memory} => ScopeItem::Array {item, size, memory, mutability: Mutability::Immutable},
+    }
+}
+
+/// The method and arguments of a call after every argument has been evaluated
+pub struct Arguments {
+    pub method_name: Identifier,
+    pub args: FuncArgs,
+    /// The operations that evaluate the arguments
+    pub ops: Operations,
+    /// Memory that holds the values of arguments that were calls or casts themselves
+    pub temporaries: Vec<MemoryBlock>,
+    /// Variables that were passed as arguments and must have a value after the call
+    pub variables: Vec<Identifier>,
+}
+
+pub fn evaluate_arguments(
+    scope: &mut ScopeStack,
+    method: Expression,
+    arg_exprs: CallArgs,
+) -> Result<Arguments, Error> {
+    // Every argument and the method are resolved before giving up so that all of their errors
+    // are reported at once
+    let mut errors = Vec::new();
+    let mut args = Vec::new();
+    let mut ops = Vec::new();
+    let mut temporaries = Vec::new();
+    let mut variables = Vec::new();
+    for expr in arg_exprs {
+        match evaluate_argument(scope, expr, &mut temporaries, &mut variables) {
+            Ok((arg, arg_ops)) => {
+                args.push(arg);
+                ops.extend(arg_ops);
+            },
+            Err(error) => errors.push(error),
+        }
+    }
+
+    let method = match method {
+        Expression::Identifier(name) => Ok((None, name)),
+        Expression::Access {target, field, ..} => {
+            if let Expression::Identifier(ref name) = *target {
+                scope.mark_read(name);
+            }
+            resolve_field_name(scope, *target, field).map(|(target, method_name)| (Some(target), method_name))
         },
         // The grammar should prevent any other expressions from
         // ending up here
@@ -37,14 +164,327 @@ pub fn call_with_exprs(
         _ => unreachable!(),
     };
 
+    let (target_instance, method_name): (Option<ScopeItem>, Identifier) = match method {
+        Ok(method) if errors.is_empty() => method,
+        Ok(_) => return Err(last_error(scope, errors)),
+        // The method comes before its arguments in the source
+        Err(error) => {
+            errors.insert(0, error);
+            return Err(last_error(scope, errors));
+        },
+    };
+
     // If the method operates on some type, the instance of that type is the first argument
     if let Some(target_instance) = target_instance {
         args.insert(0, target_instance);
     }
 
-    Ok(ops.into_iter().flat_map(|o| o.into_iter()).chain(
-        call(scope, method_name, args, target)?
-    ).collect())
+    Ok(Arguments {method_name, args, ops, temporaries, variables})
+}
+
+/// Evaluates a single argument of a call
+///
+/// Arguments that had to be stored in new temporary memory add that memory to `temporaries` and
+/// variables that were passed directly are added to `variables`.
+pub fn evaluate_argument(
+    scope: &mut ScopeStack,
+    expr: Expression,
+    temporaries: &mut Vec<MemoryBlock>,
+    variables: &mut Vec<Identifier>,
+) -> Result<(ScopeItem, Operations), Error> {
+    match expr {
+        Expression::UnitLiteral(_) => unimplemented!(),
+        Expression::ByteLiteral(bytes, _) => Ok((ScopeItem::ByteLiteral(bytes), Vec::new())),
+        Expression::Number(bytes, _) => Ok((ScopeItem::NumericLiteral(bytes), Vec::new())),
+        Expression::Identifier(name) => {
+            scope.mark_read(&name);
+            variables.push(name.clone());
+            scope.lookup(&name).first().ok_or_else(|| {
+                Error::UnresolvedName {name: name.clone(), span: name.span()}
+            }).map(|item| ((**item).clone(), Vec::new()))
+        },
+        Expression::Call {method, args, ..} => {
+            call_into_temporary(scope, *method, args).map(|(arg, arg_ops, memory)| {
+                temporaries.push(memory);
+                (arg, arg_ops)
+            })
+        },
+        Expression::Cast {expr, type_def, span} => {
+            cast_into_temporary(scope, *expr, *type_def, span).map(|(arg, arg_ops, memory)| {
+                temporaries.push(memory);
+                (arg, arg_ops)
+            })
+        },
+        Expression::Access {target, field, span} => {
+            field_into_temporary(scope, *target, field, span).map(|(arg, arg_ops, memory)| {
+                temporaries.push(memory);
+                (arg, arg_ops)
+            })
+        },
+        Expression::StructLiteral {name, fields, span} => {
+            struct_into_temporary(scope, name, fields, span).map(|(arg, arg_ops, memory)| {
+                temporaries.push(memory);
+                (arg, arg_ops)
+            })
+        },
+        Expression::Branch {..} | Expression::Match {..} => {
+            arms_into_temporary(scope, expr).map(|(arg, arg_ops, memory)| {
+                temporaries.push(memory);
+                (arg, arg_ops)
+            })
+        },
+    }
+}
+
+/// Evaluates a conditional or a `match` that is an argument of a call into a new temporary value
+///
+/// The type of the value is taken from the first arm whose value has a type that can be found
+/// without generating any code. Returns the argument and the temporary memory that it is stored
+/// in. That memory must be freed once the call it is passed to is done with it.
+fn arms_into_temporary(
+    scope: &mut ScopeStack,
+    expr: Expression,
+) -> Result<(ScopeItem, Operations, MemoryBlock), Error> {
+    let value = match arms_type(scope, &expr) {
+        Some(FuncArgType::Arg(type_id)) => Target::TypedBlock {type_id, memory: scope.allocate(type_id)},
+        Some(FuncArgType::Array {item, size: Some(size)}) => Target::Array {item, size, memory: scope.allocate_array(item, size)},
+        _ => return Err(Error::TypeAnnotationsNeeded {span: expr.span()}),
+    };
+    let ops = into_operations(scope, expr, value)?;
+
+    Ok((temporary_item(value), ops, value.memory()))
+}
+
+/// Returns the type of the value of a conditional or a `match` from the values of its arms
+///
+/// Numeric literals have no type of their own, so they only make the value a `u8` (like a
+/// literal argument) when no other arm gives it a type.
+fn arms_type(scope: &ScopeStack, expr: &Expression) -> Option<FuncArgType> {
+    // A conditional without an `else` is `()` when its condition fails
+    let unit = Expression::UnitLiteral(expr.span());
+    let values: Vec<_> = match *expr {
+        Expression::Branch {ref body, ref otherwise, ..} => {
+            vec![block_value(body), otherwise.as_ref().map_or(&unit, block_value)]
+        },
+        Expression::Match {ref arms, ..} => arms.iter().map(|arm| block_value(&arm.body)).collect(),
+        _ => unreachable!(),
+    };
+
+    values.iter().filter_map(|value| value_type(scope, value)).next()
+        .or_else(|| literal_type(scope, &values))
+}
+
+/// Returns the type that numeric literals are given when some of the given values are literals
+fn literal_type(scope: &ScopeStack, values: &[&Expression]) -> Option<FuncArgType> {
+    if values.iter().any(|value| matches!(**value, Expression::Number(..))) {
+        Some(FuncArgType::Arg(scope.primitives().u8()))
+    }
+    else {
+        None
+    }
+}
+
+/// Returns the expression at the end of a block, which is the value of the block
+fn block_value(block: &Block) -> &Expression {
+    match block.last() {
+        Some(Statement::Expression {expr}) => expr,
+        // Every block ends with its value, the parser adds `()` if it is not written
+        _ => unreachable!(),
+    }
+}
+
+/// Returns the type of the value of the given expression if it can be found without generating
+/// any code
+///
+/// Names declared inside of the arm that the expression is the value of are not in scope yet,
+/// so their types cannot be found.
+fn value_type(scope: &ScopeStack, expr: &Expression) -> Option<FuncArgType> {
+    match *expr {
+        Expression::UnitLiteral(_) => Some(FuncArgType::Arg(scope.primitives().unit())),
+        Expression::Number(..) => None,
+        Expression::ByteLiteral(ref bytes, _) => Some(FuncArgType::Array {
+            item: scope.primitives().u8(),
+            size: Some(bytes.len() as ArraySize),
+        }),
+        Expression::Identifier(ref name) => match scope.lookup(name).first().map(|item| &**item) {
+            Some(&ScopeItem::Constant {type_id, ..}) | Some(&ScopeItem::TypedBlock {type_id, ..}) => {
+                Some(FuncArgType::Arg(type_id))
+            },
+            Some(&ScopeItem::Array {item, size, ..}) => Some(FuncArgType::Array {item, size: Some(size)}),
+            _ => None,
+        },
+        Expression::Cast {ref type_def, ..} => match **type_def {
+            TypeDefinition::Name {ref name} => resolve_type_id(scope, name).ok().map(FuncArgType::Arg),
+            TypeDefinition::Array {..} => None,
+        },
+        Expression::StructLiteral {ref name, ..} => resolve_type_id(scope, name).ok().map(FuncArgType::Arg),
+        Expression::Access {ref target, ref field, ..} => {
+            let (name, fields) = field_path(target, field);
+            resolve_field(scope, &name, &fields).ok().map(|(_, typ, _)| match typ {
+                FieldType::Value(type_id) => FuncArgType::Arg(type_id),
+                FieldType::Array {item, size} => FuncArgType::Array {item, size: Some(size)},
+            })
+        },
+        Expression::Call {ref method, ref args, ..} => call_type(scope, method, args),
+        Expression::Branch {..} | Expression::Match {..} => arms_type(scope, expr),
+    }
+}
+
+/// Returns the return type of a call if the types of all of its arguments can be found without
+/// generating any code
+fn call_type(scope: &ScopeStack, method: &Expression, arg_exprs: &CallArgs) -> Option<FuncArgType> {
+    let mut args = Vec::new();
+    for expr in arg_exprs {
+        // Only the types of the arguments matter for finding the function, so the values are
+        // never stored anywhere
+        let arg = match *expr {
+            Expression::Number(value, _) => ScopeItem::NumericLiteral(value),
+            Expression::ByteLiteral(ref bytes, _) => ScopeItem::ByteLiteral(bytes.clone()),
+            _ => match value_type(scope, expr)? {
+                FuncArgType::Arg(type_id) => ScopeItem::TypedBlock {
+                    type_id,
+                    memory: MemoryBlock::default(),
+                    mutability: Mutability::Immutable,
+                },
+                FuncArgType::Array {item, size} => ScopeItem::Array {
+                    item,
+                    size: size?,
+                    memory: MemoryBlock::default(),
+                    mutability: Mutability::Immutable,
+                },
+                FuncArgType::Variadic(_) => return None,
+            },
+        };
+        args.push(arg);
+    }
+
+    let method_name = match *method {
+        Expression::Identifier(ref name) => name.clone(),
+        Expression::Access {ref target, ref field, ..} => {
+            let (target, method_name) = resolve_field_name(scope, (**target).clone(), field.clone()).ok()?;
+            args.insert(0, target);
+            method_name
+        },
+        _ => return None,
+    };
+    return_type(scope, &method_name, &args).map(FuncArgType::Arg)
+}
+
+/// Checks that every variable passed to a call has a value
+///
+/// Checked after the call since some methods (like `stdin.read_exact`) initialize their arguments
+/// instead of reading them.
+pub fn check_initialized(scope: &mut ScopeStack, variables: &[Identifier]) -> Result<(), Error> {
+    let errors: Vec<_> = variables.iter().filter_map(|name| scope.check_initialized(name).err()).collect();
+    if errors.is_empty() {
+        Ok(())
+    }
+    else {
+        Err(last_error(scope, errors))
+    }
+}
+
+/// Frees the given temporary memory after the given operations are done with it
+pub fn allocate_temporaries(temporaries: Vec<MemoryBlock>, ops: Operations) -> Operations {
+    temporaries.into_iter().rev().fold(ops, |body, temp| vec![Operation::TempAllocate {
+        temp,
+        body,
+        should_zero: true,
+    }])
+}
+
+/// Points an error about modifying a variable that was passed to a call at that argument instead
+/// of at the function that modified it
+///
+/// The variable must be the same declaration that the argument refers to, since a function that
+/// was called may have modified its own variable with the same name.
+fn at_argument(scope: &ScopeStack, error: Error, variables: &[Identifier]) -> Error {
+    match error {
+        Error::ImmutableVariable {name, declared, span} => {
+            let argument = variables.iter().find(|&variable| scope.declared_name(variable).is_some_and(|declaration| {
+                *declaration == name && (declaration.span().start, declaration.span().end) == (declared.start, declared.end)
+            }));
+            let span = argument.map_or(span, |argument| argument.span());
+            Error::ImmutableVariable {name, declared, span}
+        },
+        error => error,
+    }
+}
+
+/// Reports every error except for the last one which is returned
+pub fn last_error(scope: &mut ScopeStack, mut errors: Vec<Error>) -> Error {
+    let last = errors.pop().expect("Expected at least one error");
+    for error in errors {
+        scope.report_error(error);
+    }
+    last
+}
+
+/// Returns the return type of the latest function with the given name that can be called with
+/// the given arguments
+///
+/// Numeric literals are only given another type if no function accepts them as `u8`.
+fn return_type(scope: &ScopeStack, method_name: &Identifier, args: &FuncArgs) -> Option<TypeId> {
+    let method_args_types = argument_types(scope, args);
+    let find_return_type = |infer_literals: bool| scope.lookup(method_name).into_iter().filter_map(|item| match *item {
+        ScopeItem::BuiltInFunction {type_id, ..} => match *scope.get_type(type_id) {
+            ref method_type @ ItemType::Function {return_type, ..} => {
+                let matches = if infer_literals {
+                    method_type.matches_args(&inferred_argument_types(scope, args, method_type))
+                }
+                else {
+                    method_type.matches_args(&method_args_types)
+                };
+                if matches { Some(return_type) } else { None }
+            },
+            _ => None,
+        },
+        _ => None,
+    }).next();
+
+    find_return_type(false).or_else(|| find_return_type(true))
+}
+
+/// Returns the types that the given arguments can be passed as
+fn argument_types(scope: &ScopeStack, args: &FuncArgs) -> Vec<FuncArgType> {
+    args.iter().map(|arg| match *arg {
+        // `inferred_argument_types` gives literals another type if nothing accepts them as `u8`
+        ScopeItem::NumericLiteral(..) => FuncArgType::Arg(scope.primitives().u8()),
+        ScopeItem::ByteLiteral(ref bytes) => FuncArgType::Array {
+            item: scope.primitives().u8(),
+            size: Some(bytes.len() as ArraySize),
+        },
+        ScopeItem::Array {item, size, ..} => FuncArgType::Array {item, size: Some(size)},
+        ref arg => FuncArgType::Arg(arg.type_id()),
+    }).collect()
+}
+
+/// Returns the types of the given arguments with every numeric literal given the type of the
+/// corresponding argument of the given function, as long as that type can be created from a
+/// literal
+fn inferred_argument_types(scope: &ScopeStack, args: &FuncArgs, method_type: &ItemType) -> Vec<FuncArgType> {
+    let expected_args = match *method_type {
+        ItemType::Function {ref args, ..} => args,
+        _ => unreachable!(),
+    };
+
+    argument_types(scope, args).into_iter().zip(args).enumerate().map(|(i, (arg_type, arg))| match (arg, expected_args.get(i)) {
+        (&ScopeItem::NumericLiteral(..), Some(&FuncArgType::Arg(type_id))) if accepts_literals(scope, type_id) => {
+            FuncArgType::Arg(type_id)
+        },
+        _ => arg_type,
+    }).collect()
+}
+
+/// Returns true if the given type can be created from a numeric literal
+pub fn accepts_literals(scope: &ScopeStack, type_id: TypeId) -> bool {
+    scope.lookup(&Identifier::from("std::convert::From<{unsigned integer}>")).into_iter().any(|item| match *item {
+        ScopeItem::BuiltInFunction {type_id: converter_type, ..} => match *scope.get_type(converter_type) {
+            ItemType::Function {return_type, ..} => return_type == type_id,
+            _ => false,
+        },
+        _ => false,
+    })
 }
 
 /// Call the provided method with the given arguments
@@ -54,15 +494,16 @@ pub fn call(
     args: FuncArgs,
     target: Target,
 ) -> OperationsResult {
+    let span = method_name.span();
+
+    // Nothing can be said about the signature if one of the arguments is poisoned
+    if args.iter().any(|arg| matches!(*arg, ScopeItem::Poisoned)) {
+        return Err(Error::Poisoned {name: method_name, span});
+    }
+
     // The first stage of calling a function is finding an implementation that matches the correct
     // function signature.
-    let method_args_types = args.iter().map(|arg| match *arg {
-        //TODO: Update this when more numeric types are added
-        ScopeItem::NumericLiteral(..) => FuncArgType::Arg(scope.primitives().u8()),
-        ScopeItem::ByteLiteral(..) => FuncArgType::Array {item: scope.primitives().u8(), size: None},
-        ScopeItem::Array {item, ..} => FuncArgType::Array {item: item, size: None},
-        ref arg => FuncArgType::Arg(arg.type_id()),
-    }).collect();
+    let method_args_types = argument_types(scope, &args);
 
     let (target_type, target_memory) = match target {
         Target::TypedBlock {type_id, memory} => (type_id, memory),
@@ -74,46 +515,141 @@ pub fn call(
     // with the given name to see if its arguments match. This is more similar to what C++ does
     // than Rust, but it works for the timebeing.
     // We keep searching until we find something that matches or we return the first error.
-    scope.lookup(&method_name).into_iter().fold(Err(Error::UnresolvedName(method_name.clone())), |acc, item| acc.or_else(|err| match *item {
-        ScopeItem::BuiltInFunction {type_id, ref operations} => {
-            let method_type = scope.get_type(type_id);
-            if method_type.matches_signature(&method_args_types, target_type) {
-                Ok(operations.clone())
-            }
-            else {
-                // If we have at least one matching item, the error should not be UnresolvedName
-                // anymore. Only the latest (first) match is considered for this.
-                Err(match err {
-                    Error::UnresolvedName(..) => Error::MismatchedTypes {
-                        expected: ItemType::Function {
-                            args: method_args_types.clone(),
-                            return_type: target_type,
-                        },
-                        found: method_type.clone(),
-                    },
-                    _ => err,
-                })
-            }
-        },
-        // If we have at least one matching item, the error should not be UnresolvedName
-        // anymore. Only the latest (first) match is considered for this.
-        ref item => Err(match err {
-            Error::UnresolvedName(..) => Error::MismatchedTypes {
-                expected: ItemType::Function {
-                    args: method_args_types.clone(),
-                    return_type: target_type,
-                },
-                found: match *item {
-                    //TODO: Update this when more numeric types are added
-                    ScopeItem::NumericLiteral(..) => scope.get_type(scope.primitives().u8()).clone(),
-                    ScopeItem::ByteLiteral(ref bytes) => ItemType::Array {item: Some(scope.primitives().u8()), size: Some(bytes.len())},
-                    ScopeItem::Array {item, size, ..} => ItemType::Array {item: Some(item), size: Some(size)},
-                    ref arg => scope.get_type(arg.type_id()).clone(),
+    let mut result = Err(Error::UnresolvedName {name: method_name.clone(), span});
+    for item in scope.lookup(&method_name) {
+        if result.is_ok() {
+            break;
+        }
+
+        result = result.or_else(|err| match *item {
+            ScopeItem::BuiltInFunction {type_id, ref operations} => {
+                if scope.get_type(type_id).matches_signature(&method_args_types, target_type) {
+                    Ok(operations.clone())
+                }
+                else {
+                    // If we have at least one matching item, the error should not be UnresolvedName
+                    // anymore. Only the latest (first) match is considered for this.
+                    Err(match err {
+                        Error::UnresolvedName {..} => mismatched_signature(scope, &method_name, &args, target_type, span),
+                        _ => err,
+                    })
+                }
+            },
+            ScopeItem::Poisoned => Err(Error::Poisoned {name: method_name.clone(), span}),
+            // If we have at least one matching item, the error should not be UnresolvedName
+            // anymore. Only the latest (first) match is considered for this.
+            ref item => Err(match err {
+                Error::UnresolvedName {..} => Error::MismatchedTypes {
+                    expected: describe_arguments(scope, &args),
+                    found: describe_item(scope, item),
+                    span,
                 },
+                _ => err,
+            }),
+        });
+    }
+
+    // Numeric literals are `u8` unless that leaves nothing to call, in which case they can take
+    // on the type that a function expects, like the `2` in `x * 2` where x is a `u16`
+    if result.is_err() {
+        let inferred = scope.lookup(&method_name).into_iter().filter_map(|item| match *item {
+            ScopeItem::BuiltInFunction {type_id, ref operations} => {
+                let method_type = scope.get_type(type_id);
+                let args_types = inferred_argument_types(scope, &args, method_type);
+                if method_type.matches_signature(&args_types, target_type) {
+                    Some(operations.clone())
+                }
+                else {
+                    None
+                }
             },
-            _ => err,
-        }),
-    })).and_then(|operations| (*operations)(scope, args, target_memory))
+            _ => None,
+        }).next();
+
+        if let Some(operations) = inferred {
+            result = Ok(operations);
+        }
+    }
+
+    // Built-in functions do not know where they were called from
+    result.and_then(|operations| (*operations)(scope, args, target_memory).map_err(|err| err.or_span(span)))
+}
+
+/// Returns the error for calling the function with the given name with the given arguments to
+/// store a value of the target type
+///
+/// If one of the functions can be called with these arguments, its return type is the reason it
+/// does not match. Otherwise the function that accepts the most of the arguments is the one that
+/// is expected, preferring literals as `u8` and then the latest function.
+fn mismatched_signature(
+    scope: &ScopeStack,
+    method_name: &Identifier,
+    args: &FuncArgs,
+    target_type: TypeId,
+    span: Span,
+) -> Error {
+    let args_types = argument_types(scope, args);
+    let functions: Vec<_> = scope.lookup(method_name).into_iter().filter_map(|item| match *item {
+        ScopeItem::BuiltInFunction {type_id, ..} => Some(type_id),
+        _ => None,
+    }).collect();
+
+    let callable = functions.iter().filter_map(|&type_id| match *scope.get_type(type_id) {
+        ref method_type @ ItemType::Function {return_type, ..} => {
+            let matches = method_type.matches_args(&args_types) ||
+                method_type.matches_args(&inferred_argument_types(scope, args, method_type));
+            if matches { Some(return_type) } else { None }
+        },
+        _ => None,
+    }).next();
+    if let Some(return_type) = callable {
+        return Error::MismatchedTypes {
+            expected: scope.type_name(target_type),
+            found: scope.type_name(return_type),
+            span,
+        };
+    }
+
+    // The latest function comes first, so searching in reverse keeps it when there is a tie
+    let closest = functions.iter().rev().cloned().max_by_key(|&type_id| {
+        let method_type = scope.get_type(type_id);
+        (
+            matching_arguments(&inferred_argument_types(scope, args, method_type), method_type),
+            matching_arguments(&args_types, method_type),
+        )
+    }).expect("Only called after a function with this name was found");
+    Error::MismatchedTypes {
+        expected: describe_arguments(scope, args),
+        found: scope.type_name(closest),
+        span,
+    }
+}
+
+/// Returns how many of the given argument types are the same as the type of the corresponding
+/// argument of the given function
+fn matching_arguments(args_types: &[FuncArgType], method_type: &ItemType) -> usize {
+    let expected_args = match *method_type {
+        ItemType::Function {ref args, ..} => args,
+        _ => unreachable!(),
+    };
+
+    args_types.iter().zip(expected_args).filter(|&(arg, expected)| arg == expected).count()
+}
+
+/// Returns a function signature that accepts the given arguments, like `fn(u8, {integer})`
+fn describe_arguments(scope: &ScopeStack, args: &FuncArgs) -> String {
+    format!("fn({})", args.iter().map(|arg| describe_item(scope, arg)).collect::<Vec<_>>().join(", "))
+}
+
+/// Returns the type of the given item as it would be written in the source code
+pub fn describe_item(scope: &ScopeStack, item: &ScopeItem) -> String {
+    match *item {
+        // A literal does not have a type until it is stored somewhere
+        ScopeItem::NumericLiteral(..) => "{integer}".to_owned(),
+        ScopeItem::ByteLiteral(ref bytes) => scope.describe_type(&ItemType::Array {item: Some(scope.primitives().u8()), size: Some(bytes.len())}),
+        ScopeItem::Array {item, size, ..} => scope.describe_type(&ItemType::Array {item: Some(item), size: Some(size)}),
+        ref arg => scope.type_name(arg.type_id()),
+    }
 }
 
 /// Returns the full path of the target type with the field appended to it
@@ -122,18 +658,22 @@ pub fn call(
 /// Since this is a field access, it needs to operate on the target object that the `target`
 /// expression refers to
 fn resolve_field_name(scope: &ScopeStack, target: Expression, field: Identifier) -> Result<(ScopeItem, Identifier), Error> {
+    let span = target.span().to(field.span());
+
     // The full path of the target type
     // Something like `std::foo::Foo` or `u8` or `()`
     let (target_instance, target_type_path) = match target {
         Expression::Identifier(target_name) => scope.lookup(&target_name).first().ok_or_else(|| {
-            Error::UnresolvedName(target_name.clone())
+            Error::UnresolvedName {name: target_name.clone(), span: target_name.span()}
         }).and_then(|item| match **item {
             ScopeItem::Constant {type_id, ..} => Ok(((*item).clone(), type_id)),
             ScopeItem::TypedBlock {type_id, ..} => Ok(((*item).clone(), type_id)),
-            ScopeItem::Array {..} => unimplemented!(),
+            ScopeItem::Array {..} => Ok(((*item).clone(), scope.primitives().array())),
+            ScopeItem::Poisoned => Err(Error::Poisoned {name: target_name.clone(), span: target_name.span()}),
             ScopeItem::BuiltInFunction {type_id, ..} => Err(Error::UnresolvedField {
-                target_type: scope.get_type(type_id).clone(),
+                target_type: scope.type_name(type_id),
                 field: field.clone(),
+                span: field.span(),
             }),
             // These are unreachable because numeric literals and byte literals are never stored
             // directly
@@ -143,7 +683,7 @@ fn resolve_field_name(scope: &ScopeStack, target: Expression, field: Identifier)
         //TODO: It's likely that the below line will just not work at all
         // because the scope item returned in the tuple is not the target we should return.
         // I think we need to map on this result and return the field as the target...not sure.
-        //Expression::Access {target, field} => resolve_field_name(scope, *target, field),
+        //Expression::Access {target, field, ..} => resolve_field_name(scope, *target, field),
         Expression::Access {..} => unimplemented!(),
 
         //TODO: ByteLiterals are valid targets for field access
@@ -162,6 +702,20 @@ fn resolve_field_name(scope: &ScopeStack, target: Expression, field: Identifier)
         // the ops necessary for that
         Expression::Call {..} => unimplemented!(),
 
+        //TODO: Struct literals are valid targets for field access
+        // In this case, we need to return the struct type and store the struct into a temporary
+        // variable
+        Expression::StructLiteral {..} => unimplemented!(),
+
+        //TODO: Match expressions are valid targets for field access
+        // In this case, we need to return the type name of the arms, just like branches
+        Expression::Match {..} => unimplemented!(),
+
+        //TODO: Casts are valid targets for field access
+        // In this case, we need to return the type name of the type being cast to
+        // This also needs the ops that perform the cast, just like calls
+        Expression::Cast {..} => unimplemented!(),
+
         //TODO: Branch expressions are valid targets for field access
         // In this case, we need to return the type name of the branches
         // Implementing this will be a bit more complicated since we need to do the branching and
@@ -169,8 +723,8 @@ fn resolve_field_name(scope: &ScopeStack, target: Expression, field: Identifier)
         Expression::Branch {..} => unimplemented!(),
 
         //TODO: This is not supported, probably want a good error here though
-        Expression::UnitLiteral => unreachable!(),
+        Expression::UnitLiteral(_) => unreachable!(),
     }?;
 
-    Ok((target_instance, target_type_path.concat(field)))
+    Ok((target_instance, target_type_path.concat(field).with_span(span)))
 }
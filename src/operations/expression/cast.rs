@@ -0,0 +1,135 @@
+use parser::{Expression, TypeDefinition, Identifier, Span};
+
+use operations::{Error, OperationsResult};
+use operations::item_type::ItemType;
+use operations::scope::{TypeId, ScopeStack, ScopeItem, ArraySize};
+use operations::declaration::resolve_type_id;
+
+use super::Target;
+use super::number::store_number;
+use super::call::{evaluate_arguments, call, check_initialized, allocate_temporaries, accepts_literals, describe_item};
+
+/// Converts the value of the given expression to the given type and stores it in the target
+///
+/// The conversion is done by the built-in `std::convert::As::as` function that takes the type of
+/// the value and returns the given type. If there is no such function, the cast is not supported.
+pub fn cast(
+    scope: &mut ScopeStack,
+    expr: Expression,
+    type_def: TypeDefinition,
+    span: Span,
+    target: Target,
+) -> OperationsResult {
+    let type_id = match type_def {
+        TypeDefinition::Name {ref name} => Some(resolve_type_id(scope, name)?),
+        // Nothing can be cast to an array, that is reported once the type of the value is known
+        TypeDefinition::Array {..} => None,
+    };
+
+    // Numeric literals can take on any integer type, so casting one is the same as giving it
+    // that type in the first place
+    if let Expression::Number(value, literal_span) = expr {
+        if let Some(type_id) = type_id.filter(|&type_id| accepts_literals(scope, type_id)) {
+            check_target(scope, type_id, span, target)?;
+            return store_number(scope, value, literal_span, target);
+        }
+    }
+
+    let converter = Identifier::from("std::convert::As::as").with_span(span);
+    let arguments = evaluate_arguments(scope, Expression::Identifier(converter.clone()), vec![expr])?;
+    let value = arguments.args[0].clone();
+    if let ScopeItem::Poisoned = value {
+        return Err(Error::Poisoned {name: converter, span});
+    }
+
+    let cast_ops = match type_id {
+        Some(type_id) => {
+            check_target(scope, type_id, span, target)?;
+            call(scope, converter, arguments.args, target).map_err(|err| match err {
+                // Every conversion has the same name, so finding none that match means that there
+                // is no conversion between these types
+                Error::MismatchedTypes {..} | Error::UnresolvedName {..} => Error::InvalidCast {
+                    from: describe_item(scope, &value),
+                    to: scope.type_name(type_id),
+                    span,
+                },
+                err => err,
+            })?
+        },
+        None => return Err(Error::InvalidCast {
+            from: describe_item(scope, &value),
+            to: describe_type_def(scope, &type_def),
+            span,
+        }),
+    };
+    check_initialized(scope, &arguments.variables)?;
+
+    Ok(allocate_temporaries(arguments.temporaries, arguments.ops.into_iter().chain(cast_ops).collect()))
+}
+
+/// Checks that the result of a cast to the given type can be stored in the target
+fn check_target(scope: &ScopeStack, type_id: TypeId, span: Span, target: Target) -> Result<(), Error> {
+    match target {
+        Target::TypedBlock {type_id: target_type, ..} if target_type == type_id => Ok(()),
+        Target::TypedBlock {type_id: target_type, ..} => Err(Error::MismatchedTypes {
+            expected: scope.type_name(target_type),
+            found: scope.type_name(type_id),
+            span,
+        }),
+        Target::Array {item, size, ..} => Err(Error::MismatchedTypes {
+            expected: scope.describe_type(&ItemType::Array {
+                item: Some(item),
+                size: Some(size),
+            }),
+            found: scope.type_name(type_id),
+            span,
+        }),
+    }
+}
+
+/// Returns the given type definition as it would be written in the source code
+fn describe_type_def(scope: &ScopeStack, type_def: &TypeDefinition) -> String {
+    match *type_def {
+        TypeDefinition::Name {ref name} => name.to_string(),
+        TypeDefinition::Array {ref type_def, ref size, ..} => {
+            let item = match **type_def {
+                TypeDefinition::Name {ref name} => resolve_type_id(scope, name).ok(),
+                TypeDefinition::Array {..} => None,
+            };
+            let size = match *size {
+                Some(Expression::Number(size, _)) if size > 0 => Some(size as ArraySize),
+                _ => None,
+            };
+            scope.describe_type(&ItemType::Array {item, size})
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codegen::testing::assert_output;
+
+    #[test]
+    fn casts_in_loops() {
+        assert_output(r#"
+            let mut i: u8 = 0;
+            while i < 3 {
+                let n: i8 = (i as i8) - 1;
+                stdout.print(n as i16, b"|", n as u16, b"|", (i as u16) * 300, b"|", (i == 1) as u16, b" ");
+                i += 1;
+            }
+            let big: u16 = 300;
+            stdout.print(big as u8 as u16);
+        "#, b"", b"-1|65535|0|0 0|0|300|1 1|1|600|0 44");
+    }
+
+    #[test]
+    fn casting_conditionals_and_matches() {
+        assert_output(r#"
+            let a: u8 = 5;
+            let x: u16 = (if a > 1 { 1 } else { 2 }) as u16;
+            let y: u16 = (match a { 5 => a, _ => 0 }) as u16 * 100;
+            stdout.print(x, b" ", y);
+        "#, b"", b"1 500");
+    }
+}
@@ -0,0 +1,168 @@
+use parser::{Identifier, Expression, Span};
+use memory::{MemoryBlock, MemSize};
+
+use operations::{Error, Operation, OperationsResult};
+use operations::item_type::{ItemType, FieldType};
+use operations::scope::{ScopeStack, ScopeItem};
+
+use super::Target;
+use super::call::describe_item;
+
+/// Stores the value of a field of a struct in the target
+pub fn store_field(
+    scope: &mut ScopeStack,
+    target_expr: Expression,
+    field: Identifier,
+    span: Span,
+    target: Target,
+) -> OperationsResult {
+    let (name, fields) = field_path(&target_expr, &field);
+    scope.mark_read(&name);
+    scope.check_field_initialized(&name, &fields)?;
+
+    let (memory, typ, offset) = resolve_field(scope, &name, &fields)?;
+    let matches = match (typ, target) {
+        (FieldType::Value(field_type), Target::TypedBlock {type_id, ..}) => field_type == type_id,
+        (FieldType::Array {item, size}, Target::Array {item: target_item, size: target_size, ..}) => {
+            item == target_item && size == target_size
+        },
+        _ => false,
+    };
+    if !matches {
+        return Err(Error::MismatchedTypes {
+            expected: describe_target(scope, &target),
+            found: describe_field_type(scope, typ),
+            span,
+        });
+    }
+
+    Ok(copy_from_field(scope, memory, typ, offset, target.memory()).into_iter().collect())
+}
+
+/// Returns the name of the variable that a field access starts from along with the fields that
+/// lead from that variable to the accessed field, like `q` and `[p, a]` for `q.p.a`
+pub fn field_path(target: &Expression, field: &Identifier) -> (Identifier, Vec<Identifier>) {
+    match *target {
+        Expression::Identifier(ref name) => (name.clone(), vec![field.clone()]),
+        Expression::Access {ref target, field: ref inner, ..} => {
+            let (name, mut fields) = field_path(target, inner);
+            fields.push(field.clone());
+            (name, fields)
+        },
+        // The grammar only allows the fields of names to be accessed
+        _ => unreachable!(),
+    }
+}
+
+/// Returns the memory of the struct stored in the variable with the given name along with the
+/// type of the last of the given fields and the offset of its first cell in that memory
+///
+/// Each field is looked up in the type of the field before it, starting from the type of the
+/// variable.
+pub fn resolve_field(
+    scope: &ScopeStack,
+    name: &Identifier,
+    fields: &[Identifier],
+) -> Result<(MemoryBlock, FieldType, MemSize), Error> {
+    let item = scope.lookup(name).first().map(|item| (**item).clone()).ok_or_else(|| {
+        Error::UnresolvedName {name: name.clone(), span: name.span()}
+    })?;
+
+    let (memory, mut typ) = match item {
+        ScopeItem::TypedBlock {type_id, memory, ..} => (memory, FieldType::Value(type_id)),
+        ScopeItem::Poisoned => return Err(Error::Poisoned {name: name.clone(), span: name.span()}),
+        // Only structs have fields
+        ref item => return Err(Error::UnresolvedField {
+            target_type: describe_item(scope, item),
+            field: fields[0].clone(),
+            span: fields[0].span(),
+        }),
+    };
+
+    let mut offset = 0;
+    for field in fields {
+        let found = match typ {
+            FieldType::Value(type_id) => scope.get_type(type_id).field(scope, field),
+            FieldType::Array {..} => None,
+        };
+        match found {
+            Some((field_type, field_offset)) => {
+                typ = field_type;
+                offset += field_offset;
+            },
+            None => return Err(Error::UnresolvedField {
+                target_type: describe_field_type(scope, typ),
+                field: field.clone(),
+                span: field.span(),
+            }),
+        }
+    }
+
+    Ok((memory, typ, offset))
+}
+
+/// Allocates memory for a value of the given field type
+pub fn allocate_field(scope: &mut ScopeStack, typ: FieldType) -> Target {
+    match typ {
+        FieldType::Value(type_id) => Target::TypedBlock {type_id, memory: scope.allocate(type_id)},
+        FieldType::Array {item, size} => Target::Array {item, size, memory: scope.allocate_array(item, size)},
+    }
+}
+
+/// Generates the operation that adds the value of a field to the memory of a value of the same
+/// type, or nothing if the field does not use any cells
+pub fn copy_from_field(
+    scope: &ScopeStack,
+    memory: MemoryBlock,
+    typ: FieldType,
+    offset: MemSize,
+    target: MemoryBlock,
+) -> Option<Operation> {
+    let size = typ.required_size(scope);
+    if size == 0 {
+        return None;
+    }
+
+    Some(Operation::Copy {
+        source: memory.position_at(offset),
+        target: target.position(),
+        size,
+    })
+}
+
+/// Generates the operation that adds the value in the given memory to a field of the same type,
+/// or nothing if the field does not use any cells
+pub fn copy_to_field(
+    scope: &ScopeStack,
+    source: MemoryBlock,
+    memory: MemoryBlock,
+    typ: FieldType,
+    offset: MemSize,
+) -> Option<Operation> {
+    let size = typ.required_size(scope);
+    if size == 0 {
+        return None;
+    }
+
+    Some(Operation::Copy {
+        source: source.position(),
+        target: memory.position_at(offset),
+        size,
+    })
+}
+
+/// Returns the given field type as it would be written in the source code
+pub fn describe_field_type(scope: &ScopeStack, typ: FieldType) -> String {
+    match typ {
+        FieldType::Value(type_id) => scope.type_name(type_id),
+        FieldType::Array {item, size} => scope.describe_type(&ItemType::Array {item: Some(item), size: Some(size)}),
+    }
+}
+
+/// Returns the type of the given target as it would be written in the source code
+pub fn describe_target(scope: &ScopeStack, target: &Target) -> String {
+    match *target {
+        Target::TypedBlock {type_id, ..} => scope.type_name(type_id),
+        Target::Array {item, size, ..} => scope.describe_type(&ItemType::Array {item: Some(item), size: Some(size)}),
+    }
+}
@@ -1,4 +1,4 @@
-use parser::Identifier;
+use parser::{Identifier, Span};
 use memory::MemoryBlock;
 
 use operations::{Error, Operation, OperationsResult};
@@ -8,36 +8,59 @@ use operations::scope::{TypeId, ScopeStack, ScopeItem, ArraySize};
 use super::Target;
 use super::number::store_number;
 use super::byte_literal::store_byte_literal;
+use super::field::describe_target;
 
 pub fn store_identifier(
     scope: &mut ScopeStack,
     name: Identifier,
     target: Target,
 ) -> OperationsResult {
+    let span = name.span();
+    scope.mark_read(&name);
+    scope.check_initialized(&name)?;
     scope.lookup(&name).first().ok_or_else(|| {
-        Error::UnresolvedName(name.clone())
+        Error::UnresolvedName {name: name.clone(), span}
     }).map(|item| (**item).clone()).and_then(|item| match item {
         // There is a non-lexical lifetimes issue here which was introduced by calling store_number() below
         // The clone() above is completely unnecssary and is a hack to work around this problem
         // in the Rust compiler
         // http://smallcultfollowing.com/babysteps/blog/2016/04/27/non-lexical-lifetimes-introduction/#problem-case-2-conditional-control-flow
 
-        ScopeItem::Constant {type_id, ref bytes} => store_identifier_constant(scope, type_id, bytes, target),
-        ScopeItem::NumericLiteral(value) => store_number(scope, value, target),
-        ScopeItem::ByteLiteral(ref bytes) => store_byte_literal(scope, bytes, target),
-        ScopeItem::TypedBlock {type_id, memory} => store_identifier_typed_block(scope, type_id, memory, target),
-        ScopeItem::Array {item, size, memory} => store_identifier_array(scope, item, size, memory, target),
-        ScopeItem::BuiltInFunction { .. } => {
-            // This is not supported yet in the syntax so it should be unreachable
-            unreachable!();
-        },
+        ScopeItem::Poisoned => Err(Error::Poisoned {name: name.clone(), span}),
+        item => store_item(scope, item, span, target),
     })
 }
 
+/// Stores the value of the given item in the given target
+pub fn store_item(
+    scope: &mut ScopeStack,
+    item: ScopeItem,
+    span: Span,
+    target: Target,
+) -> OperationsResult {
+    match item {
+        ScopeItem::Constant {type_id, ref bytes} => store_identifier_constant(scope, type_id, bytes, span, target),
+        ScopeItem::NumericLiteral(value) => store_number(scope, value, span, target),
+        ScopeItem::ByteLiteral(ref bytes) => store_byte_literal(scope, bytes, span, target),
+        ScopeItem::TypedBlock {type_id, memory, ..} => store_identifier_typed_block(scope, type_id, memory, span, target),
+        ScopeItem::Array {item, size, memory, ..} => store_identifier_array(scope, item, size, memory, span, target),
+        // Poisoned items are never stored since using them is always an error
+        ScopeItem::Poisoned => unreachable!(),
+        // Functions are not values, so a variant of an enum with fields cannot be stored
+        // without its fields (e.g. `Op::Num` instead of `Op::Num(1)`)
+        ScopeItem::BuiltInFunction {type_id, ..} => Err(Error::MismatchedTypes {
+            expected: describe_target(scope, &target),
+            found: scope.type_name(type_id),
+            span,
+        }),
+    }
+}
+
 fn store_identifier_constant(
     scope: &mut ScopeStack,
     source_type: TypeId,
     bytes: &[u8],
+    span: Span,
     target: Target,
 ) -> OperationsResult {
     match target {
@@ -46,16 +69,17 @@ fn store_identifier_constant(
                 Ok(Operation::increment_to_value(memory, bytes))
             }
             else {
-                mismatched_types(scope, type_id, source_type)
+                mismatched_types(scope, type_id, source_type, span)
             }
         },
 
         Target::Array {item, size, ..} => Err(Error::MismatchedTypes {
-            expected: ItemType::Array {
+            expected: scope.describe_type(&ItemType::Array {
                 item: Some(item),
                 size: Some(size),
-            },
-            found: scope.get_type(source_type).clone(),
+            }),
+            found: scope.type_name(source_type),
+            span,
         }),
     }
 }
@@ -64,6 +88,7 @@ fn store_identifier_typed_block(
     scope: &mut ScopeStack,
     source_type: TypeId,
     source_memory: MemoryBlock,
+    span: Span,
     target: Target,
 ) -> OperationsResult {
     match target {
@@ -80,16 +105,17 @@ fn store_identifier_typed_block(
                 }])
             }
             else {
-                mismatched_types(scope, type_id, source_type)
+                mismatched_types(scope, type_id, source_type, span)
             }
         },
 
         Target::Array {item, size, ..} => Err(Error::MismatchedTypes {
-            expected: ItemType::Array {
+            expected: scope.describe_type(&ItemType::Array {
                 item: Some(item),
                 size: Some(size),
-            },
-            found: scope.get_type(source_type).clone(),
+            }),
+            found: scope.type_name(source_type),
+            span,
         }),
     }
 }
@@ -99,15 +125,17 @@ fn store_identifier_array(
     source_item: TypeId,
     source_size: ArraySize,
     source_memory: MemoryBlock,
+    span: Span,
     target: Target,
 ) -> OperationsResult {
     match target {
         Target::TypedBlock {type_id, ..} => Err(Error::MismatchedTypes {
-            expected: scope.get_type(type_id).clone(),
-            found: ItemType::Array {
+            expected: scope.type_name(type_id),
+            found: scope.describe_type(&ItemType::Array {
                 item: Some(source_item),
                 size: Some(source_size),
-            },
+            }),
+            span,
         }),
 
         Target::Array {item, size, memory} => {
@@ -124,23 +152,25 @@ fn store_identifier_array(
              }
              else {
                  Err(Error::MismatchedTypes {
-                     expected: ItemType::Array {
+                     expected: scope.describe_type(&ItemType::Array {
                          item: Some(item),
                          size: Some(size),
-                     },
-                     found: ItemType::Array {
+                     }),
+                     found: scope.describe_type(&ItemType::Array {
                          item: Some(source_item),
                          size: Some(source_size),
-                     },
+                     }),
+                     span,
                  })
              }
         },
     }
 }
 
-fn mismatched_types(scope: &ScopeStack, expected: TypeId, found: TypeId) -> OperationsResult {
+fn mismatched_types(scope: &ScopeStack, expected: TypeId, found: TypeId, span: Span) -> OperationsResult {
     Err(Error::MismatchedTypes {
-        expected: scope.get_type(expected).clone(),
-        found: scope.get_type(found).clone(),
+        expected: scope.type_name(expected),
+        found: scope.type_name(found),
+        span,
     })
 }
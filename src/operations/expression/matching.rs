@@ -0,0 +1,457 @@
+use std::iter::once;
+
+use parser::{Identifier, Expression, Block, MatchArm, MatchPattern, Number, Span};
+use memory::{MemoryBlock, MemSize};
+
+use operations::{Error, Operation, OperationsResult, InitializationState, block};
+use operations::item_type::{ItemType, FieldType};
+use operations::scope::{TypeId, ScopeStack, ScopeItem, Mutability};
+use operations::declaration::resolve_type_id;
+
+use super::Target;
+use super::call::{
+    call,
+    evaluate_argument,
+    check_initialized,
+    allocate_temporaries,
+    accepts_literals,
+    describe_item,
+    temporary_item,
+    last_error,
+};
+use super::field::copy_from_field;
+use super::identifier::store_item;
+
+/// A pattern whose names have been resolved against the type of the value being matched
+enum Resolved {
+    /// Matches any value
+    Wildcard,
+    /// Matches values equal to the given literal
+    Literal(ScopeItem, Span),
+    /// Matches the variant with the given tag and binds each of its fields that has a name
+    Variant {
+        tag: usize,
+        fields: Vec<(Option<Identifier>, FieldType, MemSize)>,
+        span: Span,
+    },
+}
+
+/// Stores the value of the first arm whose pattern matches the given value in the target
+///
+/// The arms are compiled to a chain of branches that each check one pattern and move on to the
+/// next arm if it does not match. Every possible value must be covered by some arm, so the last
+/// arm never has to check its pattern.
+pub fn store_match(
+    scope: &mut ScopeStack,
+    value_expr: Expression,
+    arms: Vec<MatchArm>,
+    span: Span,
+    target: Target,
+) -> OperationsResult {
+    let value_name = match value_expr {
+        Expression::Identifier(ref name) => Some(name.clone()),
+        _ => None,
+    };
+    let value_span = value_expr.span();
+
+    let mut temporaries = Vec::new();
+    let mut variables = Vec::new();
+    let (value, mut ops) = evaluate_argument(scope, value_expr, &mut temporaries, &mut variables)?;
+    check_initialized(scope, &variables)?;
+
+    // Literals and constants are stored in memory so that their parts can be read like any
+    // other value
+    let stored = match value {
+        ScopeItem::Poisoned => return Err(Error::Poisoned {
+            name: value_name.expect("Only names can be poisoned"),
+            span: value_span,
+        }),
+        ScopeItem::Constant {type_id, ..} => Some(Target::TypedBlock {type_id, memory: scope.allocate(type_id)}),
+        ScopeItem::NumericLiteral(number) => {
+            let type_id = literal_type(scope, number, value_span);
+            Some(Target::TypedBlock {type_id, memory: scope.allocate(type_id)})
+        },
+        ScopeItem::ByteLiteral(ref bytes) => {
+            let item = scope.primitives().u8();
+            Some(Target::Array {item, size: bytes.len(), memory: scope.allocate_array(item, bytes.len())})
+        },
+        _ => None,
+    };
+    let value = match stored {
+        Some(stored) => {
+            ops.extend(store_item(scope, value, value_span, stored)?);
+            temporaries.push(stored.memory());
+            temporary_item(stored)
+        },
+        None => value,
+    };
+    let memory = match value {
+        ScopeItem::TypedBlock {memory, ..} | ScopeItem::Array {memory, ..} => Some(memory),
+        _ => None,
+    };
+
+    let mut errors = Vec::new();
+    let mut patterns = Vec::new();
+    let mut bodies = Vec::new();
+    for MatchArm {pattern, body, ..} in arms {
+        let pattern_span = pattern.span();
+        match resolve_pattern(scope, pattern, &value) {
+            Ok(pattern) => {
+                patterns.push(pattern);
+                bodies.push((body, pattern_span));
+            },
+            Err(error) => errors.push(error),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(last_error(scope, errors));
+    }
+
+    // Arms after a wildcard can never run
+    if let Some(wildcard) = patterns.iter().position(|pattern| matches!(*pattern, Resolved::Wildcard)) {
+        patterns.truncate(wildcard + 1);
+        bodies.truncate(wildcard + 1);
+    }
+    check_exhaustive(scope, &value, &patterns, span)?;
+
+    // Each arm starts with what was initialized before the match and a variable is only
+    // initialized after the match if every arm initializes it
+    let bool_type = scope.primitives().bool();
+    let before = scope.initialization();
+    let last = patterns.len().saturating_sub(1);
+    let mut states = Vec::new();
+    let mut compiled = Vec::new();
+    for (i, (pattern, (body, pattern_span))) in patterns.into_iter().zip(bodies).enumerate() {
+        scope.set_initialization(before.clone());
+
+        let check = if i == last {
+            None
+        }
+        else {
+            // The body is still checked when the pattern fails so that its errors are reported too
+            let cond = scope.allocate(bool_type);
+            let cond_ops = condition(scope, &value, &pattern, cond).unwrap_or_else(|error| {
+                scope.report_error(error);
+                Vec::new()
+            });
+            Some((cond, cond_ops))
+        };
+
+        let body = arm_body(scope, memory, pattern, body, target);
+        states.push((scope.initialization(), pattern_span));
+        compiled.push((check, body));
+    }
+    scope.set_initialization(InitializationState::merge_arms(before, states));
+
+    let compiled = compiled.into_iter()
+        .map(|(check, body)| body.map(|body| (check, body)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let chain = compiled.into_iter().rev().fold(Vec::new(), |else_body, (check, if_body)| match check {
+        Some((cond, cond_ops)) => vec![Operation::TempAllocate {
+            temp: cond,
+            body: cond_ops.into_iter().chain(once(Operation::Branch {cond, if_body, else_body})).collect(),
+            should_zero: true,
+        }],
+        None => if_body,
+    });
+    ops.extend(chain);
+
+    Ok(allocate_temporaries(temporaries, ops))
+}
+
+/// Returns the smallest integer type that can hold the given literal
+///
+/// Nothing else decides the type of a literal that is matched on since the patterns are literals
+/// too. If no type is large enough, the largest one is used so that storing the literal reports
+/// that it is out of range.
+fn literal_type(scope: &mut ScopeStack, number: Number, span: Span) -> TypeId {
+    let primitives = scope.primitives();
+    let candidates = if number < 0 {
+        vec![primitives.i8(), primitives.i16()]
+    }
+    else {
+        vec![primitives.u8(), primitives.u16(), primitives.u32()]
+    };
+
+    // The operations are thrown away, so the memory is never used
+    let fits = candidates.iter().cloned().find(|&type_id| {
+        let target = Target::TypedBlock {type_id, memory: scope.allocate(type_id)};
+        store_item(scope, ScopeItem::NumericLiteral(number), span, target).is_ok()
+    });
+    fits.unwrap_or_else(|| *candidates.last().unwrap())
+}
+
+/// Checks that the given pattern can match the given value and resolves the names in it
+fn resolve_pattern(scope: &ScopeStack, pattern: MatchPattern, value: &ScopeItem) -> Result<Resolved, Error> {
+    let value_type = match *value {
+        ScopeItem::Array {..} => None,
+        ref value => Some(value.type_id()),
+    };
+
+    match pattern {
+        MatchPattern::Wildcard(_) => Ok(Resolved::Wildcard),
+        MatchPattern::Literal(Expression::Number(number, span)) => {
+            if value_type.is_some_and(|type_id| accepts_literals(scope, type_id)) {
+                Ok(Resolved::Literal(ScopeItem::NumericLiteral(number), span))
+            }
+            else {
+                Err(Error::MismatchedLiteral {
+                    expected: describe_item(scope, value),
+                    found: "{unsigned integer}".to_string(),
+                    span,
+                })
+            }
+        },
+        MatchPattern::Literal(Expression::Identifier(name)) => {
+            let span = name.span();
+            let bool_type = scope.primitives().bool();
+            if value_type != Some(bool_type) {
+                return Err(Error::MismatchedTypes {
+                    expected: describe_item(scope, value),
+                    found: scope.type_name(bool_type),
+                    span,
+                });
+            }
+
+            scope.lookup(&name).first().map(|item| Resolved::Literal((**item).clone(), span))
+                .ok_or(Error::UnresolvedName {name, span})
+        },
+        // The grammar only allows numbers, `true` and `false` as literal patterns
+        MatchPattern::Literal(_) => unreachable!(),
+        MatchPattern::Variant {path, fields, span} => {
+            // The path is the path of the enum followed by the name of the variant
+            let mut segments: Vec<String> = path.clone().into_iter().collect();
+            let variant = segments.pop().expect("The grammar guarantees at least two segments in a path");
+            let variant = Identifier::from(variant.as_str()).with_span(span);
+            let type_path: Identifier = segments.into_iter().collect();
+            let type_id = resolve_type_id(scope, &type_path.with_span(path.span()))?;
+
+            if value_type != Some(type_id) {
+                return Err(Error::MismatchedTypes {
+                    expected: describe_item(scope, value),
+                    found: scope.type_name(type_id),
+                    span,
+                });
+            }
+
+            let (tag, declared) = scope.get_type(type_id).variant(&variant).ok_or_else(|| Error::UnresolvedVariant {
+                type_name: scope.type_name(type_id),
+                variant: variant.clone(),
+                span,
+            })?;
+            if declared.fields.len() != fields.len() {
+                return Err(Error::MismatchedPatternFields {
+                    variant: path,
+                    expected: declared.fields.len(),
+                    found: fields.len(),
+                    span,
+                });
+            }
+
+            Ok(Resolved::Variant {
+                tag,
+                fields: fields.into_iter().zip(declared.field_offsets(scope))
+                    .map(|(name, (typ, offset))| (name, typ, offset))
+                    .collect(),
+                span,
+            })
+        },
+    }
+}
+
+/// Checks that every possible value of the given value's type is matched by at least one of the
+/// given patterns
+fn check_exhaustive(scope: &ScopeStack, value: &ScopeItem, patterns: &[Resolved], span: Span) -> Result<(), Error> {
+    if patterns.iter().any(|pattern| matches!(*pattern, Resolved::Wildcard)) {
+        return Ok(());
+    }
+
+    let bool_type = scope.primitives().bool();
+    let missing: Vec<String> = match *value {
+        ScopeItem::TypedBlock {type_id, ..} => match *scope.get_type(type_id) {
+            ItemType::Enum {ref variants} => variants.iter().enumerate().filter(|&(tag, _)| {
+                !patterns.iter().any(|pattern| matches!(*pattern, Resolved::Variant {tag: covered, ..} if covered == tag))
+            }).map(|(_, variant)| {
+                let path = scope.type_name(type_id);
+                if variant.fields.is_empty() {
+                    format!("{}::{}", path, variant.name)
+                }
+                else {
+                    format!("{}::{}({})", path, variant.name, vec!["_"; variant.fields.len()].join(", "))
+                }
+            }).collect(),
+            _ if type_id == bool_type => [true, false].iter().filter(|&&value| {
+                !patterns.iter().any(|pattern| matches!(*pattern,
+                    Resolved::Literal(ScopeItem::Constant {ref bytes, ..}, _) if (bytes[0] != 0) == value))
+            }).map(|value| value.to_string()).collect(),
+            _ => vec!["_".to_string()],
+        },
+        _ => vec!["_".to_string()],
+    };
+
+    if missing.is_empty() {
+        Ok(())
+    }
+    else {
+        Err(Error::NonExhaustivePatterns {missing, span})
+    }
+}
+
+/// Generates the operations that store whether the value matches the given pattern in `cond`
+fn condition(scope: &mut ScopeStack, value: &ScopeItem, pattern: &Resolved, cond: MemoryBlock) -> OperationsResult {
+    let bool_type = scope.primitives().bool();
+    let cond = Target::TypedBlock {type_id: bool_type, memory: cond};
+    let eq = |span| Identifier::from("std::cmp::PartialEq::eq").with_span(span);
+
+    match *pattern {
+        Resolved::Literal(ref literal, span) => call(scope, eq(span), vec![value.clone(), literal.clone()], cond),
+        Resolved::Variant {tag, span, ..} => {
+            let memory = match *value {
+                ScopeItem::TypedBlock {memory, ..} => memory,
+                // Only enums have variants and they are always stored in memory before matching
+                _ => unreachable!(),
+            };
+
+            // The tag is copied so that it can be compared like any other u8
+            let u8_type = scope.primitives().u8();
+            let found = scope.allocate(u8_type);
+            let found_item = ScopeItem::TypedBlock {type_id: u8_type, memory: found, mutability: Mutability::Immutable};
+            let mut body = vec![Operation::Copy {source: memory.position_at(0), target: found.position(), size: 1}];
+            body.extend(call(scope, eq(span), vec![found_item, ScopeItem::NumericLiteral(tag as Number)], cond)?);
+            Ok(vec![Operation::TempAllocate {temp: found, body, should_zero: true}])
+        },
+        // A wildcard always matches, so it never needs a condition
+        Resolved::Wildcard => unreachable!(),
+    }
+}
+
+/// Generates the body of an arm with the fields bound by its pattern copied out of the value
+fn arm_body(
+    scope: &mut ScopeStack,
+    memory: Option<MemoryBlock>,
+    pattern: Resolved,
+    body: Block,
+    target: Target,
+) -> OperationsResult {
+    scope.push_scope();
+
+    let mut ops = Vec::new();
+    if let Resolved::Variant {fields, ..} = pattern {
+        let memory = memory.expect("Only enums have variants and they are always stored in memory");
+        for (name, typ, offset) in fields {
+            let name = match name {
+                Some(name) => name,
+                None => continue,
+            };
+
+            let binding = match typ {
+                FieldType::Value(type_id) => scope.declare(name.clone(), type_id, Mutability::Immutable),
+                FieldType::Array {item, size} => scope.declare_array(name.clone(), item, size, Mutability::Immutable),
+            };
+            scope.track_variable(name, false, true);
+            // A match in a loop uses the same memory for its bindings every time
+            ops.push(Operation::Zero {target: binding});
+            ops.extend(copy_from_field(scope, memory, typ, offset, binding));
+        }
+    }
+
+    let body = block::into_operations(scope, body, target);
+    scope.pop_scope();
+    ops.extend(body?);
+    Ok(ops)
+}
+
+#[cfg(test)]
+mod tests {
+    use codegen::testing::assert_output;
+
+    #[test]
+    fn enums_and_match() {
+        assert_output(r#"
+            enum Op { Add, Sub, Num(u16), Pair(u8, [u8; 2]) }
+
+            fn apply(op: Op, total: u16) -> u16 {
+                match op {
+                    Op::Add => total + 1,
+                    Op::Sub => total - 1,
+                    Op::Num(n) => n,
+                    Op::Pair(_, _) => total,
+                }
+            }
+
+            let mut total: u16 = apply(Op::Num(300), 0);
+            let mut op: Op = Op::Add;
+            let mut i: u8 = 0;
+            while i < 4 {
+                total = apply(op, total);
+                if i == 1 {
+                    op = Op::Sub;
+                }
+                i += 1;
+            }
+            stdout.print(total, b" ");
+
+            let pair: Op = Op::Pair(7, b"ok");
+            match pair {
+                Op::Pair(digit, word) => stdout.print(digit, word, b" "),
+                _ => {},
+            }
+
+            match i {
+                3 => stdout.print(b"three "),
+                4 => stdout.print(b"four "),
+                _ => stdout.print(b"more "),
+            }
+
+            let even: bool = total % 2 == 0;
+            let word: u8 = match even { true => 2, false => 1 };
+            stdout.print(word);
+        "#, b"", b"300 7ok four 2");
+    }
+
+    #[test]
+    fn match_as_an_argument() {
+        assert_output(r#"
+            enum E { A, B }
+            let e: E = E::B;
+            stdout.print(match e { E::A => 1, E::B => 2 }, b" ");
+            let x: u8 = match e { E::A => 1, E::B => 2 } + 1;
+            let wide: u16 = 300;
+            let y: u16 = 2 * match e { E::A => 0, E::B => wide };
+            stdout.print(x, b" ", y, b" ", match e { E::A => b"no", E::B => b"ok" });
+        "#, b"", b"2 3 600 ok");
+    }
+
+    #[test]
+    fn matching_large_literals() {
+        assert_output(r#"
+            match 300 { 300 => stdout.print(b"a"), _ => stdout.print(b"b") }
+            let x: u16 = match 70000 { 70000 => 7, _ => 0 };
+            stdout.print(x);
+            match -200 { -200 => stdout.print(b"c"), _ => {} }
+            match 4 { 4 => stdout.print(b"d"), _ => {} }
+        "#, b"", b"a7cd");
+    }
+
+    #[test]
+    fn statements_after_a_match_at_the_end_of_a_block() {
+        assert_output(r#"
+            fn g(n: u8) -> u8 {
+                let mut r: u8 = 0;
+                match n {
+                    0 => {},
+                    _ => { r = n + 1; },
+                }
+                r
+            }
+            let x: bool = true;
+            if x {
+                match x {
+                    true => stdout.print(b"a"),
+                    false => {},
+                }
+                stdout.print(b"b", g(5))
+            }
+        "#, b"", b"ab6");
+    }
+}
@@ -3,20 +3,30 @@ mod identifier;
 mod call;
 mod byte_literal;
 mod branch;
+mod cast;
+mod field;
+mod struct_literal;
+mod matching;
 
-pub use self::call::call;
+pub use self::call::{call, describe_item};
+pub use self::number::store_number;
+pub use self::identifier::store_item;
+pub use self::field::{resolve_field, allocate_field, copy_to_field};
 
-use parser::Expression;
+use parser::{Identifier, Expression, Statement};
 use memory::MemoryBlock;
 
 use operations::OperationsResult;
-use operations::scope::{TypeId, ScopeStack, ArraySize};
+use operations::scope::{TypeId, ScopeStack, ScopeItem, ArraySize};
 
 use self::identifier::{store_identifier};
-use self::number::store_number;
 use self::byte_literal::store_byte_literal;
 use self::call::call_with_exprs;
 use self::branch::branch;
+use self::cast::cast;
+use self::field::store_field;
+use self::struct_literal::store_struct_literal;
+use self::matching::store_match;
 
 #[derive(Debug, Clone, Copy)]
 pub enum Target {
@@ -31,6 +41,23 @@ pub enum Target {
     },
 }
 
+impl Target {
+    /// Returns the memory that the value is stored in
+    pub fn memory(&self) -> MemoryBlock {
+        match *self {
+            Target::TypedBlock {memory, ..} | Target::Array {memory, ..} => memory,
+        }
+    }
+
+    /// Returns a target for the same type stored in newly allocated memory
+    pub fn allocate_like(&self, scope: &mut ScopeStack) -> Target {
+        match *self {
+            Target::TypedBlock {type_id, ..} => Target::TypedBlock {type_id, memory: scope.allocate(type_id)},
+            Target::Array {item, size, ..} => Target::Array {item, size, memory: scope.allocate_array(item, size)},
+        }
+    }
+}
+
 /// Generates operations for evaluating the given expression
 /// and storing its result in the given target memory block
 /// NOTE: Assumes that the target memory block is **zero**
@@ -41,14 +68,66 @@ pub fn into_operations(
     target: Target,
 ) -> OperationsResult {
     match expr {
-        Expression::UnitLiteral => Ok(Vec::new()),
+        Expression::UnitLiteral(_) => Ok(Vec::new()),
         Expression::Identifier(name) => store_identifier(scope, name, target),
-        Expression::Number(value) => store_number(scope, value, target),
-        Expression::Call {method, args} => call_with_exprs(scope, *method, args, target),
-        Expression::ByteLiteral(ref bytes) => store_byte_literal(scope, bytes, target),
-        Expression::Branch {condition, body, otherwise} => {
+        Expression::Number(value, span) => store_number(scope, value, span, target),
+        Expression::Call {method, args, ..} => call_with_exprs(scope, *method, args, target),
+        Expression::ByteLiteral(ref bytes, span) => store_byte_literal(scope, bytes, span, target),
+        Expression::Branch {condition, body, otherwise, ..} => {
             branch(scope, *condition, body, otherwise, target)
         },
-        _ => unimplemented!(),
+        Expression::Cast {expr, type_def, span} => cast(scope, *expr, *type_def, span, target),
+        Expression::Access {target: target_expr, field, span} => store_field(scope, *target_expr, field, span, target),
+        Expression::StructLiteral {name, fields, span} => store_struct_literal(scope, name, fields, span, target),
+        Expression::Match {expr, arms, span} => store_match(scope, *expr, arms, span, target),
+    }
+}
+
+/// Returns true if the given expression could read the value of the given name
+///
+/// Names declared inside of the expression are not taken into account, so this may return true
+/// for an expression that only reads a different variable with the same name.
+pub fn reads(expr: &Expression, name: &Identifier) -> bool {
+    let block_reads = |block: &[Statement]| block.iter().any(|statement| statement_reads(statement, name));
+
+    match *expr {
+        Expression::UnitLiteral(_) | Expression::ByteLiteral(..) | Expression::Number(..) => false,
+        Expression::Identifier(ref ident) => ident == name,
+        Expression::Call {ref method, ref args, ..} => reads(method, name) || args.iter().any(|arg| reads(arg, name)),
+        Expression::Access {ref target, ..} => reads(target, name),
+        Expression::StructLiteral {ref fields, ..} => fields.iter().any(|(_, value)| reads(value, name)),
+        Expression::Cast {ref expr, ..} => reads(expr, name),
+        Expression::Branch {ref condition, ref body, ref otherwise, ..} => {
+            reads(condition, name) || block_reads(body) || otherwise.as_ref().is_some_and(|block| block_reads(block))
+        },
+        Expression::Match {ref expr, ref arms, ..} => reads(expr, name) || arms.iter().any(|arm| block_reads(&arm.body)),
+    }
+}
+
+fn statement_reads(statement: &Statement, name: &Identifier) -> bool {
+    match *statement {
+        Statement::Comment(..) => false,
+        Statement::Declaration {ref expr, ..} => expr.as_ref().is_some_and(|expr| reads(expr, name)),
+        Statement::Assignment {ref expr, ..} | Statement::FieldAssignment {ref expr, ..} => reads(expr, name),
+        Statement::CompoundAssignment {ref lhs, ref expr, ..} => lhs == name || reads(expr, name),
+        Statement::WhileLoop {ref condition, ref body, ..} => {
+            reads(condition, name) || body.iter().any(|statement| statement_reads(statement, name))
+        },
+        Statement::Expression {ref expr} => reads(expr, name),
+        // The body of a function cannot read the variables declared outside of it
+        Statement::Function {..} | Statement::Struct {..} | Statement::Enum {..} | Statement::Impl {..} => false,
+    }
+}
+
+/// Returns the value of the given condition if it is always the same, like `true` or `false`
+pub fn constant_condition(scope: &ScopeStack, condition: &Expression) -> Option<bool> {
+    match *condition {
+        Expression::Identifier(ref name) => match scope.lookup(name).first() {
+            Some(&&ScopeItem::Constant {type_id, ref bytes}) if type_id == scope.primitives().bool() => {
+                Some(bytes.iter().any(|&byte| byte != 0))
+            },
+            _ => None,
+        },
+        _ => None,
     }
 }
@@ -1,4 +1,4 @@
-use parser::{Identifier, Number};
+use parser::{Identifier, Number, Span};
 use memory::MemoryBlock;
 
 use operations::{Error, OperationsResult};
@@ -10,13 +10,14 @@ use super::{Target, call};
 pub fn store_number(
     scope: &mut ScopeStack,
     value: Number,
+    span: Span,
     target: Target,
 ) -> OperationsResult {
     match target {
         Target::TypedBlock {type_id, memory} => {
-            store_numeric_literal(scope, value, type_id, memory, "{signed integer}").or_else(|err| {
+            store_numeric_literal(scope, value, span, type_id, memory, "{signed integer}").or_else(|err| {
                 if value >= 0 {
-                    store_numeric_literal(scope, value, type_id, memory, "{unsigned integer}")
+                    store_numeric_literal(scope, value, span, type_id, memory, "{unsigned integer}")
                 }
                 else {
                     Err(err)
@@ -25,12 +26,12 @@ pub fn store_number(
         },
 
         Target::Array {item, size, ..} => Err(Error::MismatchedTypes {
-            expected: ItemType::Array {
+            expected: scope.describe_type(&ItemType::Array {
                 item: Some(item),
                 size: Some(size),
-            },
-            //TODO: Update this when more numeric types are added
-            found: scope.get_type(scope.primitives().u8()).clone(),
+            }),
+            found: "{integer}".to_owned(),
+            span,
         }),
     }
 }
@@ -39,11 +40,13 @@ pub fn store_number(
 fn store_numeric_literal(
     scope: &mut ScopeStack,
     value: Number,
+    span: Span,
     target_type: TypeId,
     target_memory: MemoryBlock,
     literal_type: &'static str,
 ) -> OperationsResult {
-    let converter_name = Identifier::from(format!("std::convert::From<{}>", literal_type).as_str());
+    let converter_name = Identifier::from(format!("std::convert::From<{}>", literal_type).as_str())
+        .with_span(span);
 
     call(
         scope,
@@ -51,16 +54,18 @@ fn store_numeric_literal(
         vec![ScopeItem::NumericLiteral(value)],
         Target::TypedBlock {type_id: target_type, memory: target_memory},
     ).map_err(|err| match err {
-        // No literal converter defined, so the literal must not match the type
-        Error::UnresolvedName(ref name) if *name == converter_name => {
+        // No literal converter defined for the target type, so the literal must not match it
+        Error::UnresolvedName {ref name, ..} if *name == converter_name => {
             Error::MismatchedLiteral {
-                expected: scope.get_type(target_type).clone(),
+                expected: scope.type_name(target_type),
                 found: literal_type.into(),
+                span,
             }
         },
-        Error::MismatchedTypes {found, ..} => match found {
-            ItemType::Function {..} => unreachable!("A literal converter was defined but did not have the correct type"),
-            _ => unreachable!("A literal converter was defined that was not a function"),
+        Error::MismatchedTypes {..} => Error::MismatchedLiteral {
+            expected: scope.type_name(target_type),
+            found: literal_type.into(),
+            span,
         },
         err => err,
     })
@@ -0,0 +1,82 @@
+use parser::{Identifier, Expression, Span};
+
+use operations::{Error, Operation, OperationsResult};
+use operations::item_type::{ItemType, Field};
+use operations::scope::ScopeStack;
+use operations::declaration::resolve_type_id;
+
+use super::{Target, into_operations};
+use super::field::{allocate_field, copy_to_field};
+
+/// Stores a new value of a struct type with the given fields in the target
+///
+/// Every field must be given a value exactly once. The values are evaluated in the order they are
+/// written, each one into a temporary value that is then copied into its field.
+pub fn store_struct_literal(
+    scope: &mut ScopeStack,
+    name: Identifier,
+    field_exprs: Vec<(Identifier, Expression)>,
+    span: Span,
+    target: Target,
+) -> OperationsResult {
+    let type_id = resolve_type_id(scope, &name)?;
+    let declared: Vec<Field> = match *scope.get_type(type_id) {
+        ItemType::Struct {ref fields} => fields.clone(),
+        _ => return Err(Error::NotAStruct {span: name.span(), name}),
+    };
+
+    let memory = match target {
+        Target::TypedBlock {type_id: target_type, memory} if target_type == type_id => memory,
+        Target::TypedBlock {type_id: target_type, ..} => return Err(Error::MismatchedTypes {
+            expected: scope.type_name(target_type),
+            found: scope.type_name(type_id),
+            span,
+        }),
+        Target::Array {item, size, ..} => return Err(Error::MismatchedTypes {
+            expected: scope.describe_type(&ItemType::Array {item: Some(item), size: Some(size)}),
+            found: scope.type_name(type_id),
+            span,
+        }),
+    };
+
+    let mut ops = Vec::new();
+    let mut initialized: Vec<Identifier> = Vec::new();
+    for (field, expr) in field_exprs {
+        if initialized.contains(&field) {
+            return Err(Error::DuplicateField {span: field.span(), field});
+        }
+
+        let (typ, offset) = match scope.get_type(type_id).field(scope, &field) {
+            Some(found) => found,
+            None => return Err(Error::UnresolvedField {
+                target_type: scope.type_name(type_id),
+                span: field.span(),
+                field,
+            }),
+        };
+
+        let value = allocate_field(scope, typ);
+        let mut body = into_operations(scope, expr, value)?;
+        body.extend(copy_to_field(scope, value.memory(), memory, typ, offset));
+        ops.push(Operation::TempAllocate {
+            temp: value.memory(),
+            body,
+            should_zero: true,
+        });
+        initialized.push(field);
+    }
+
+    let missing: Vec<_> = declared.into_iter()
+        .map(|field| field.name)
+        .filter(|name| !initialized.contains(name))
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::MissingFields {
+            type_name: scope.type_name(type_id),
+            fields: missing,
+            span,
+        });
+    }
+
+    Ok(ops)
+}
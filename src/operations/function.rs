@@ -0,0 +1,370 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use parser::{Identifier, Parameter, Pattern, TypeDefinition, Expression, Block, Span};
+use memory::MemoryBlock;
+
+use super::{Error, Operation, Operations, OperationsResult, Target, block, expression};
+use super::dispatch::{Dispatch, dispatch_loop};
+use super::item_type::{ItemType, FuncArgType};
+use super::scope::{TypeId, ScopeStack, ArraySize, FuncArgs, Mutability};
+use super::declaration::resolve_type_id;
+
+/// A function declared with `fn` whose body is inlined wherever it is called
+pub struct Function {
+    name: Identifier,
+    params: Vec<Param>,
+    return_type: TypeId,
+    body: Block,
+    /// The type that `Self` refers to if this is a method declared in an `impl` block
+    self_type: Option<TypeId>,
+    /// True while the body is being generated so that recursive calls can be found
+    inlining: Cell<bool>,
+    /// The number of function bodies that the body is inside of while it is being generated, which
+    /// tells a call from the body itself apart from a call from a function that it called
+    depth: Cell<usize>,
+    /// True if the body calls the function itself, which is only allowed with `--recursion`
+    recursive: Cell<bool>,
+    /// The dispatch loop that recursive calls belong to while the body of a recursive function is
+    /// being generated
+    dispatch: RefCell<Option<Dispatch>>,
+}
+
+/// A parameter of a function with its type resolved
+struct Param {
+    name: Identifier,
+    mutable: bool,
+    typ: FuncArgType,
+}
+
+/// Declares a function so that it can be called from anywhere in the block it is declared in
+///
+/// Brainfuck has no way to jump back to the code that called a function, so the body is inlined
+/// into every call with fresh memory for its parameters and variables. The body is checked once
+/// by `check` so that its errors and warnings are only reported once no matter how often it is
+/// called. Checking the body is also what finds out whether the function calls itself, in which
+/// case it is compiled into a dispatch loop instead (only allowed with `--recursion`).
+///
+/// Methods are given the type of the `impl` block they are declared in as `self_type`, which is
+/// what `Self` refers to in their signature and body.
+pub fn declare(
+    scope: &mut ScopeStack,
+    name: Identifier,
+    self_type: Option<TypeId>,
+    params: Vec<Parameter>,
+    return_type: Option<TypeDefinition>,
+    body: Block,
+) -> Result<Rc<Function>, Error> {
+    // The first definition is kept so that the calls to it are still checked against it
+    if scope.is_declared_here(&name) {
+        return Err(Error::DuplicateDefinition {span: name.span(), name});
+    }
+
+    let (params, return_type) = match signature(scope, &name, self_type, params, return_type) {
+        Ok(signature) => signature,
+        Err(error) => {
+            // Calls to the function are not reported since they would only be caused by this
+            scope.declare_poisoned(name);
+            return Err(error);
+        },
+    };
+
+    let function = Rc::new(Function {
+        name: name.clone(),
+        params,
+        return_type,
+        body,
+        self_type,
+        inlining: Cell::new(false),
+        depth: Cell::new(0),
+        recursive: Cell::new(false),
+        dispatch: RefCell::new(None),
+    });
+
+    let called = function.clone();
+    scope.declare_builtin_function(
+        name,
+        ItemType::Function {
+            args: function.params.iter().map(|param| param.typ.clone()).collect(),
+            return_type,
+        },
+        move |scope, args, target| scope.silently(|scope| call(scope, &called, args, target))
+    );
+
+    Ok(function)
+}
+
+/// Checks the body of a declared function
+///
+/// This must be done once every function it could call has been declared so that recursive calls
+/// can be found.
+pub fn check(scope: &mut ScopeStack, function: &Function) -> Result<(), Error> {
+    // The operations are only generated to check the body, the function does nothing until it
+    // is called
+    let result = scope.allocate(function.return_type);
+    inline(scope, function, None, result)?;
+
+    // A call back to this function from a function that it called is found while the errors of
+    // that function are silenced, so it is reported here instead of where the call is made
+    if function.recursive.get() && !scope.recursion_enabled() {
+        return Err(Error::MutualRecursion {
+            name: function.name.clone(),
+            span: function.name.span(),
+        });
+    }
+    Ok(())
+}
+
+/// Resolves the types of the parameters and the return type of a function
+fn signature(
+    scope: &ScopeStack,
+    name: &Identifier,
+    self_type: Option<TypeId>,
+    params: Vec<Parameter>,
+    return_type: Option<TypeDefinition>,
+) -> Result<(Vec<Param>, TypeId), Error> {
+    let params = params.into_iter().enumerate().map(|(i, Parameter {pattern: Pattern::Identifier(param_name), mutable, type_def})| {
+        // The instance a method is called on is always passed as the first argument
+        if param_name == Identifier::from("self") && (self_type.is_none() || i > 0) {
+            return Err(Error::UnexpectedSelf {span: param_name.span()});
+        }
+
+        let typ = match type_def {
+            TypeDefinition::Name {name: ref type_name} => FuncArgType::Arg(resolve_type(scope, self_type, type_name)?),
+            TypeDefinition::Array {type_def, size: Some(Expression::Number(size, _)), span} if size > 0 => match *type_def {
+                TypeDefinition::Name {name: ref item_name} => FuncArgType::Array {
+                    item: resolve_type(scope, self_type, item_name)?,
+                    size: Some(size as ArraySize),
+                },
+                TypeDefinition::Array {..} => return Err(Error::UnsupportedArrayType {name: param_name, span}),
+            },
+            // Parameters are never initialized where they are declared, so their size can never
+            // be inferred
+            TypeDefinition::Array {span, ..} => return Err(Error::UnsupportedArrayType {name: param_name, span}),
+        };
+        Ok(Param {name: param_name, mutable, typ})
+    }).collect::<Result<Vec<_>, _>>()?;
+
+    let return_type = match return_type {
+        None => scope.primitives().unit(),
+        Some(TypeDefinition::Name {name: ref type_name}) => resolve_type(scope, self_type, type_name)?,
+        Some(TypeDefinition::Array {span, ..}) => return Err(Error::UnsupportedReturnType {
+            name: name.clone(),
+            span,
+        }),
+    };
+
+    Ok((params, return_type))
+}
+
+/// Resolves the type with the given name, which may be `Self` if this is a method
+fn resolve_type(scope: &ScopeStack, self_type: Option<TypeId>, name: &Identifier) -> Result<TypeId, Error> {
+    match self_type {
+        Some(type_id) if *name == Identifier::from("Self") => Ok(type_id),
+        _ => resolve_type_id(scope, name),
+    }
+}
+
+/// Pushes the scope of the body of the given function, in which `Self` refers to the type of a
+/// method
+fn push_function_scope(scope: &mut ScopeStack, function: &Function) {
+    scope.push_function_scope();
+    function.depth.set(scope.function_depth());
+    if let Some(type_id) = function.self_type {
+        scope.declare_type_alias(Identifier::from("Self"), type_id);
+    }
+}
+
+/// Generates the operations for a call of the given function, storing its result in the target
+fn call(scope: &mut ScopeStack, function: &Function, args: FuncArgs, target: MemoryBlock) -> OperationsResult {
+    if function.inlining.get() {
+        recursive_call(scope, function, args, target)
+    }
+    else if function.recursive.get() {
+        dispatch(scope, function, args, target)
+    }
+    else {
+        inline(scope, function, Some(args), target)
+    }
+}
+
+/// Generates the operations for a single call of the given function by inlining its body
+///
+/// The parameters are declared without a value if there are no arguments, which is only useful
+/// for checking the body.
+fn inline(scope: &mut ScopeStack, function: &Function, args: Option<FuncArgs>, target: MemoryBlock) -> OperationsResult {
+    function.inlining.set(true);
+    push_function_scope(scope, function);
+    let ops = declare_params(scope, function, args).and_then(|(mut ops, _)| {
+        ops.extend(body(scope, function, target)?);
+        Ok(ops)
+    });
+    scope.pop_scope();
+    function.inlining.set(false);
+
+    ops
+}
+
+/// Generates the operations for a call of a recursive function from outside of its own body
+///
+/// The body is compiled into a dispatch loop that uses the call stack for the calls it makes to
+/// itself, see `dispatch::dispatch_loop`.
+fn dispatch(scope: &mut ScopeStack, function: &Function, args: FuncArgs, target: MemoryBlock) -> OperationsResult {
+    let u8_type = scope.primitives().u8();
+    let pc = scope.allocate(u8_type);
+    let result = scope.allocate(function.return_type);
+
+    function.inlining.set(true);
+    push_function_scope(scope, function);
+    let ops = declare_params(scope, function, Some(args)).and_then(|(entry, params)| {
+        let dispatch = Dispatch {
+            pc,
+            result,
+            params: params.iter().map(Target::memory).collect(),
+            args: params.iter().map(|param| param.allocate_like(scope)).collect(),
+        };
+
+        *function.dispatch.borrow_mut() = Some(dispatch.clone());
+        let body = body(scope, function, result);
+        *function.dispatch.borrow_mut() = None;
+
+        dispatch_loop(scope, &function.name, &dispatch, entry, body?, target)
+    });
+    scope.pop_scope();
+    function.inlining.set(false);
+
+    ops
+}
+
+/// Generates the operations for a call of a function from inside of its own body
+fn recursive_call(scope: &mut ScopeStack, function: &Function, args: FuncArgs, target: MemoryBlock) -> OperationsResult {
+    let dispatch = function.dispatch.borrow().clone();
+    match dispatch {
+        Some(dispatch) => {
+            let mut ops = Vec::new();
+            for (arg, &arg_target) in args.into_iter().zip(&dispatch.args) {
+                // Replaced with the location of the call by the caller
+                ops.extend(expression::store_item(scope, arg, Span::default(), arg_target)?);
+            }
+            ops.push(Operation::Recurse {pc: dispatch.pc, target});
+            Ok(ops)
+        },
+
+        // The body is only being checked, every call after this uses a dispatch loop
+        None if scope.recursion_enabled() => {
+            function.recursive.set(true);
+            Ok(Vec::new())
+        },
+
+        None if scope.function_depth() == function.depth.get() => Err(Error::RecursiveCall {
+            name: function.name.clone(),
+            // Replaced with the location of the call by the caller
+            span: Span::default(),
+        }),
+
+        // Called from the body of another function, reported once the body is checked
+        None => {
+            function.recursive.set(true);
+            Ok(Vec::new())
+        },
+    }
+}
+
+/// Declares the parameters of the given function in the current scope and stores the arguments
+/// in them if there are any
+///
+/// Returns the operations that store the arguments along with where each parameter is stored.
+fn declare_params(
+    scope: &mut ScopeStack,
+    function: &Function,
+    args: Option<FuncArgs>,
+) -> Result<(Operations, Vec<Target>), Error> {
+    let mut ops = Vec::new();
+    let mut params = Vec::new();
+    for (i, param) in function.params.iter().enumerate() {
+        let mutability = if param.mutable { Mutability::Mutable } else { Mutability::Immutable };
+        let param_target = match param.typ {
+            FuncArgType::Arg(type_id) => Target::TypedBlock {
+                type_id,
+                memory: scope.declare(param.name.clone(), type_id, mutability),
+            },
+            FuncArgType::Array {item, size: Some(size)} => Target::Array {
+                item,
+                size,
+                memory: scope.declare_array(param.name.clone(), item, size, mutability),
+            },
+            FuncArgType::Array {size: None, ..} | FuncArgType::Variadic(..) => unreachable!(),
+        };
+        scope.track_variable(param.name.clone(), param.mutable, true);
+
+        if let Some(ref args) = args {
+            // A call in a loop uses the same memory every time, so it may still contain the
+            // arguments from the previous time
+            ops.push(Operation::Zero {target: param_target.memory()});
+            // Replaced with the location of the call by the caller
+            ops.extend(expression::store_item(scope, args[i].clone(), Span::default(), param_target)?);
+        }
+        params.push(param_target);
+    }
+
+    Ok((ops, params))
+}
+
+/// Generates the operations for the body of the given function, storing its result in the target
+fn body(scope: &mut ScopeStack, function: &Function, target: MemoryBlock) -> OperationsResult {
+    block::into_operations(scope, function.body.clone(), Target::TypedBlock {
+        type_id: function.return_type,
+        memory: target,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use codegen::testing::assert_output;
+
+    #[test]
+    fn functions() {
+        assert_output(r#"
+            fn sum_to(mut n: u8) -> u16 {
+                let mut total: u16 = 0;
+                while n > 0 {
+                    total += n as u16;
+                    n -= 1;
+                }
+                total
+            }
+            fn greet(name: [u8; 3]) {
+                stdout.print(b"hi ", name, b" ");
+            }
+            // Overloads the built-in increment for u8
+            fn increment(x: u16) -> u16 {
+                x + 1
+            }
+
+            greet(b"bob");
+            let mut i: u8 = 1;
+            while i < 4 {
+                stdout.print(sum_to(i * 10), b"|");
+                increment(i);
+            }
+            stdout.print(increment(sum_to(30)));
+        "#, b"", b"hi bob 55|210|465|466");
+    }
+
+    #[test]
+    fn functions_declared_later() {
+        assert_output(r#"
+            stdout.print(double_plus_one(3), b" ");
+            fn double_plus_one(x: u8) -> u8 {
+                double(x) + 1
+            }
+            fn double(x: u8) -> u8 {
+                x * 2
+            }
+            if true {
+                stdout.print(triple(2));
+                fn triple(x: u8) -> u8 { x * 3 }
+            }
+        "#, b"", b"7 6");
+    }
+}
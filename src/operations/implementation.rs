@@ -0,0 +1,103 @@
+use std::rc::Rc;
+
+use parser::{Identifier, Statement};
+
+use super::{Error, function};
+use super::function::Function;
+use super::scope::ScopeStack;
+use super::declaration::resolve_type_id;
+
+/// Declares the methods of a type so that they can be called from anywhere in the block the
+/// `impl` block is in
+///
+/// Each method is declared as a function named after the type, like `Point::shift`, which is
+/// exactly the name that a call like `point.shift(1)` looks for. The instance the method was
+/// called on is passed as the first argument, so it is stored in `self` like any other argument.
+/// Since arguments are copied, a method can never change the instance it was called on.
+///
+/// Returns the methods that were declared so that their bodies can be checked with
+/// `function::check`.
+pub fn declare(
+    scope: &mut ScopeStack,
+    type_name: Identifier,
+    methods: Vec<Statement>,
+) -> Result<Vec<Rc<Function>>, Error> {
+    let self_type = resolve_type_id(scope, &type_name)?;
+    let type_path = scope.get_type_name(self_type).clone();
+
+    // Methods that fail are skipped so that the errors in the rest of the methods are reported too
+    let mut declared = Vec::new();
+    for method in methods {
+        match method {
+            Statement::Function {name, params, return_type, body, ..} => {
+                let path = type_path.clone().concat(name.clone()).with_span(name.span());
+                match function::declare(scope, path, Some(self_type), params, return_type, body) {
+                    Ok(method) => declared.push(method),
+                    Err(error) => scope.report_error(error),
+                }
+            },
+            // The grammar only allows functions in an impl block
+            _ => unreachable!(),
+        }
+    }
+
+    Ok(declared)
+}
+
+#[cfg(test)]
+mod tests {
+    use codegen::testing::assert_output;
+
+    #[test]
+    fn methods() {
+        assert_output(r#"
+            struct Counter { count: u16, step: u8 }
+            impl Counter {
+                fn advanced(mut self, times: u8) -> Self {
+                    let mut i: u8 = 0;
+                    while i < times {
+                        self.count = self.count + self.step as u16;
+                        i += 1;
+                    }
+                    self
+                }
+
+                fn show(self) {
+                    stdout.print(self.count, b" ");
+                }
+
+                fn ahead_of(self, other: Self) -> bool {
+                    self.count > other.count
+                }
+            }
+
+            let start: Counter = Counter { count: 250, step: 3 };
+            let end: Counter = start.advanced(4);
+            end.show();
+            start.show();
+            if end.ahead_of(start) {
+                stdout.print(b"ahead");
+            }
+        "#, b"", b"262 250 ahead");
+    }
+
+    #[test]
+    fn compound_field_assignment() {
+        assert_output(r#"
+            struct Counter { n: u8 }
+            struct Pair { left: Counter, right: Counter }
+            impl Counter {
+                fn bumped(mut self) -> Self {
+                    self.n += 1;
+                    self
+                }
+            }
+
+            let left: Counter = Counter { n: 5 };
+            let mut pair: Pair = Pair { left: left.bumped(), right: Counter { n: 1 } };
+            pair.right.n *= pair.left.n;
+            pair.left.n -= 2;
+            stdout.print(pair.left.n, b" ", pair.right.n);
+        "#, b"", b"4 6");
+    }
+}
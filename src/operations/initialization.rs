@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use parser::{Identifier, Span};
+
+/// Whether a variable has been given a value on every path that leads to the code currently
+/// being generated
+#[derive(Debug, PartialEq, Clone)]
+pub enum Initialization {
+    Initialized,
+    /// The variable has not been given a value on any path
+    Uninitialized,
+    /// The variable has been given a value on some paths but not on the given path
+    Partial(Vec<PathStep>),
+}
+
+impl Initialization {
+    /// Returns the path on which the variable has no value or None if it always has one
+    pub fn uninitialized_path(&self) -> Option<Vec<PathStep>> {
+        match *self {
+            Initialization::Initialized => None,
+            Initialization::Uninitialized => Some(Vec::new()),
+            Initialization::Partial(ref path) => Some(path.clone()),
+        }
+    }
+
+    /// Combines the initialization of several parts of a value, which only has a value once
+    /// every part has one
+    ///
+    /// The shortest path on which some part has no value is kept since it is the easiest to
+    /// follow. A value without any parts is always initialized.
+    pub fn all<'a, I>(parts: I) -> Initialization
+        where I: IntoIterator<Item=&'a Initialization> {
+
+        let path = parts.into_iter()
+            .filter_map(|init| init.uninitialized_path())
+            .min_by_key(|path| path.len());
+        match path {
+            None => Initialization::Initialized,
+            Some(ref path) if path.is_empty() => Initialization::Uninitialized,
+            Some(path) => Initialization::Partial(path),
+        }
+    }
+}
+
+/// A variable or a field of the struct stored in a variable, like `line` or `line.start.x`
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Place {
+    pub name: Identifier,
+    /// The fields that lead from the variable to this place, empty for the variable itself
+    pub fields: Vec<Identifier>,
+}
+
+/// A decision made by the program that leads to a variable not being initialized
+#[derive(Debug, PartialEq, Clone)]
+pub enum PathStep {
+    /// The condition of an `if` had the given value
+    Condition {
+        value: bool,
+        span: Span,
+    },
+    /// The body of a while loop never ran
+    LoopSkipped {
+        span: Span,
+    },
+    /// The arm of a `match` with the pattern at the given location ran
+    MatchArm {
+        span: Span,
+    },
+}
+
+impl fmt::Display for PathStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PathStep::Condition {value, span} => {
+                write!(f, "the condition on line {} is `{}`", span.line, value)
+            },
+            PathStep::LoopSkipped {span} => {
+                write!(f, "the loop on line {} does not run", span.line)
+            },
+            PathStep::MatchArm {span} => {
+                write!(f, "the `match` arm on line {} runs", span.line)
+            },
+        }
+    }
+}
+
+/// The initialization of every tracked variable (and each of its fields) in each level of the
+/// scope stack
+///
+/// Used to analyze code that may not run (like the body of a branch) by saving the state before
+/// that code and merging it with the state after.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct InitializationState {
+    pub scopes: Vec<HashMap<Place, Initialization>>,
+}
+
+impl InitializationState {
+    /// Merges the states at the end of each body of a branch with the given condition
+    pub fn merge_branches(if_state: InitializationState, else_state: InitializationState, condition: Span) -> InitializationState {
+        merge(if_state, else_state, |if_init, else_init| {
+            // The step into each body comes before any decisions made inside of it
+            let in_body = |value, path: Vec<PathStep>| {
+                Initialization::Partial(Some(PathStep::Condition {value, span: condition}).into_iter().chain(path).collect())
+            };
+
+            match (if_init.uninitialized_path(), else_init.uninitialized_path()) {
+                (None, None) => Initialization::Initialized,
+                (Some(ref if_path), Some(ref else_path)) if if_path.is_empty() && else_path.is_empty() => {
+                    Initialization::Uninitialized
+                },
+                // The shortest path is reported since it is the easiest to follow
+                (Some(if_path), Some(ref else_path)) if if_path.len() <= else_path.len() => in_body(true, if_path),
+                (_, Some(else_path)) => in_body(false, else_path),
+                (Some(if_path), None) => in_body(true, if_path),
+            }
+        })
+    }
+
+    /// Merges the states at the end of each arm of a `match`, each given with the span of the
+    /// pattern of its arm
+    ///
+    /// A `match` without any arms never finishes running, so the state before it is kept.
+    pub fn merge_arms(before: InitializationState, arms: Vec<(InitializationState, Span)>) -> InitializationState {
+        // The step into each arm comes before any decisions made inside of it
+        let in_arm = |span, path: Vec<PathStep>| {
+            Initialization::Partial(Some(PathStep::MatchArm {span}).into_iter().chain(path).collect())
+        };
+
+        let mut arms = arms.into_iter();
+        let (first, first_span) = match arms.next() {
+            Some(arm) => arm,
+            None => return before,
+        };
+        let first = map(first, |init| match init {
+            Initialization::Partial(path) => in_arm(first_span, path),
+            init => init,
+        });
+
+        arms.fold(first, |merged, (arm, span)| merge(merged, arm, |merged_init, arm_init| {
+            match (merged_init.uninitialized_path(), arm_init.uninitialized_path()) {
+                (None, None) => Initialization::Initialized,
+                (Some(ref merged_path), Some(ref arm_path)) if merged_path.is_empty() && arm_path.is_empty() => {
+                    Initialization::Uninitialized
+                },
+                // Every arm before this one left the variable without a value, so the first arm
+                // is the shortest path
+                (Some(ref merged_path), _) if merged_path.is_empty() => in_arm(first_span, Vec::new()),
+                // The shortest path is reported since it is the easiest to follow
+                (Some(merged_path), Some(ref arm_path)) if merged_path.len() <= arm_path.len() + 1 => {
+                    Initialization::Partial(merged_path)
+                },
+                (_, Some(arm_path)) => in_arm(span, arm_path),
+                (Some(merged_path), None) => Initialization::Partial(merged_path),
+            }
+        }))
+    }
+
+    /// Merges the state before a while loop with the state after its body
+    pub fn merge_loop(skipped: InitializationState, ran: InitializationState, loop_span: Span) -> InitializationState {
+        merge(skipped, ran, |skipped_init, ran_init| {
+            match (skipped_init.uninitialized_path(), ran_init) {
+                (None, _) => Initialization::Initialized,
+                (Some(ref path), &Initialization::Uninitialized) if path.is_empty() => Initialization::Uninitialized,
+                // Skipping the loop comes after every decision made before the loop
+                (Some(path), _) => Initialization::Partial(path.into_iter()
+                    .chain(Some(PathStep::LoopSkipped {span: loop_span})).collect()),
+            }
+        })
+    }
+}
+
+/// Merges every variable in the given states using the given function
+///
+/// Both states must come from the same scope stack since they are merged level by level.
+fn merge<F>(first: InitializationState, second: InitializationState, merge_variable: F) -> InitializationState
+    where F: Fn(&Initialization, &Initialization) -> Initialization {
+
+    debug_assert_eq!(first.scopes.len(), second.scopes.len());
+    InitializationState {
+        scopes: first.scopes.into_iter().zip(second.scopes).map(|(first, second)| {
+            first.into_iter().map(|(name, first_init)| {
+                let merged = match second.get(&name) {
+                    Some(second_init) => merge_variable(&first_init, second_init),
+                    None => first_init,
+                };
+                (name, merged)
+            }).collect()
+        }).collect(),
+    }
+}
+
+/// Changes every variable in the given state using the given function
+fn map<F>(state: InitializationState, map_variable: F) -> InitializationState
+    where F: Fn(Initialization) -> Initialization {
+
+    InitializationState {
+        scopes: state.scopes.into_iter().map(|scope| {
+            scope.into_iter().map(|(name, init)| (name, map_variable(init))).collect()
+        }).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place() -> Place {
+        Place {name: Identifier::from("a"), fields: Vec::new()}
+    }
+
+    fn state(init: Initialization) -> InitializationState {
+        let mut scope = HashMap::new();
+        scope.insert(place(), init);
+        InitializationState {scopes: vec![scope]}
+    }
+
+    fn variable(state: InitializationState) -> Initialization {
+        state.scopes[0][&place()].clone()
+    }
+
+    #[test]
+    fn merges_branches() {
+        let condition = Span::new(0, 1, 3, 4);
+        let merged = |if_init, else_init| variable(InitializationState::merge_branches(state(if_init), state(else_init), condition));
+
+        assert_eq!(merged(Initialization::Initialized, Initialization::Initialized), Initialization::Initialized);
+        assert_eq!(merged(Initialization::Uninitialized, Initialization::Uninitialized), Initialization::Uninitialized);
+        assert_eq!(merged(Initialization::Initialized, Initialization::Uninitialized),
+            Initialization::Partial(vec![PathStep::Condition {value: false, span: condition}]));
+
+        let inner = PathStep::LoopSkipped {span: Span::new(2, 3, 4, 5)};
+        assert_eq!(merged(Initialization::Partial(vec![inner.clone()]), Initialization::Initialized),
+            Initialization::Partial(vec![PathStep::Condition {value: true, span: condition}, inner.clone()]));
+        // The shortest path is preferred
+        assert_eq!(merged(Initialization::Partial(vec![inner]), Initialization::Uninitialized),
+            Initialization::Partial(vec![PathStep::Condition {value: false, span: condition}]));
+    }
+
+    #[test]
+    fn merges_loops() {
+        let loop_span = Span::new(0, 1, 5, 1);
+        let merged = |skipped, ran| variable(InitializationState::merge_loop(state(skipped), state(ran), loop_span));
+
+        assert_eq!(merged(Initialization::Initialized, Initialization::Initialized), Initialization::Initialized);
+        assert_eq!(merged(Initialization::Uninitialized, Initialization::Uninitialized), Initialization::Uninitialized);
+        assert_eq!(merged(Initialization::Uninitialized, Initialization::Initialized),
+            Initialization::Partial(vec![PathStep::LoopSkipped {span: loop_span}]));
+
+        let before = PathStep::Condition {value: false, span: Span::new(2, 3, 2, 4)};
+        assert_eq!(merged(Initialization::Partial(vec![before.clone()]), Initialization::Initialized),
+            Initialization::Partial(vec![before, PathStep::LoopSkipped {span: loop_span}]));
+    }
+
+    #[test]
+    fn merges_arms() {
+        let arms = [Span::new(0, 1, 3, 4), Span::new(5, 6, 4, 4), Span::new(7, 8, 5, 4)];
+        let merged = |inits: Vec<Initialization>| variable(InitializationState::merge_arms(
+            state(Initialization::Uninitialized),
+            inits.into_iter().map(state).zip(arms.iter().cloned()).collect(),
+        ));
+
+        assert_eq!(merged(vec![]), Initialization::Uninitialized);
+        assert_eq!(merged(vec![Initialization::Initialized; 3]), Initialization::Initialized);
+        assert_eq!(merged(vec![Initialization::Uninitialized; 3]), Initialization::Uninitialized);
+        assert_eq!(merged(vec![Initialization::Initialized, Initialization::Initialized, Initialization::Uninitialized]),
+            Initialization::Partial(vec![PathStep::MatchArm {span: arms[2]}]));
+        assert_eq!(merged(vec![Initialization::Uninitialized, Initialization::Initialized, Initialization::Uninitialized]),
+            Initialization::Partial(vec![PathStep::MatchArm {span: arms[0]}]));
+
+        let inner = PathStep::LoopSkipped {span: Span::new(9, 10, 6, 5)};
+        assert_eq!(merged(vec![Initialization::Initialized, Initialization::Partial(vec![inner.clone()]), Initialization::Initialized]),
+            Initialization::Partial(vec![PathStep::MatchArm {span: arms[1]}, inner.clone()]));
+        // The shortest path is preferred
+        assert_eq!(merged(vec![Initialization::Partial(vec![inner]), Initialization::Uninitialized, Initialization::Initialized]),
+            Initialization::Partial(vec![PathStep::MatchArm {span: arms[1]}]));
+    }
+
+    #[test]
+    fn combines_parts() {
+        let step = |line| PathStep::LoopSkipped {span: Span::new(0, 1, line, 1)};
+
+        assert_eq!(Initialization::all(&[]), Initialization::Initialized);
+        assert_eq!(Initialization::all(&[Initialization::Initialized, Initialization::Initialized]),
+            Initialization::Initialized);
+        assert_eq!(Initialization::all(&[Initialization::Initialized, Initialization::Uninitialized]),
+            Initialization::Uninitialized);
+        // The shortest path is preferred
+        assert_eq!(Initialization::all(&[
+            Initialization::Partial(vec![step(2), step(3)]),
+            Initialization::Initialized,
+            Initialization::Partial(vec![step(4)]),
+        ]), Initialization::Partial(vec![step(4)]));
+        assert_eq!(Initialization::all(&[Initialization::Partial(vec![step(2)]), Initialization::Uninitialized]),
+            Initialization::Uninitialized);
+    }
+
+    #[test]
+    fn describes_paths() {
+        assert_eq!(PathStep::Condition {value: false, span: Span::new(0, 1, 3, 4)}.to_string(),
+            "the condition on line 3 is `false`");
+        assert_eq!(PathStep::LoopSkipped {span: Span::new(0, 1, 5, 1)}.to_string(),
+            "the loop on line 5 does not run");
+        assert_eq!(PathStep::MatchArm {span: Span::new(0, 1, 7, 9)}.to_string(),
+            "the `match` arm on line 7 runs");
+    }
+}
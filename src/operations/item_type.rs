@@ -1,6 +1,7 @@
 use std::iter::Peekable;
 use std::slice::Iter;
 
+use parser::Identifier;
 use memory::MemSize;
 
 use super::scope::{ScopeStack, TypeId, ArraySize};
@@ -42,6 +43,62 @@ impl FuncArgType {
     }
 }
 
+/// A field of a struct
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: Identifier,
+    pub typ: FieldType,
+}
+
+/// The type of a field of a struct
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    /// A single value of the specified type
+    Value(TypeId),
+
+    /// An array of the specified item type
+    Array {
+        item: TypeId,
+        size: ArraySize,
+    },
+}
+
+impl FieldType {
+    /// Computes the number of cells used by a field of this type
+    pub fn required_size(&self, scope: &ScopeStack) -> MemSize {
+        match *self {
+            FieldType::Value(type_id) => scope.get_type(type_id).required_size(scope),
+            FieldType::Array {item, size} => scope.get_type(item).required_size(scope) * size,
+        }
+    }
+}
+
+/// A variant of an enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub name: Identifier,
+    /// The fields of the variant in the order they are laid out after the tag
+    pub fields: Vec<FieldType>,
+}
+
+impl Variant {
+    /// Returns each field along with the offset of its first cell from the start of the enum,
+    /// which is right after the tag
+    pub fn field_offsets(&self, scope: &ScopeStack) -> Vec<(FieldType, MemSize)> {
+        let mut offset = 1;
+        self.fields.iter().map(|&field| {
+            let field_offset = offset;
+            offset += field.required_size(scope);
+            (field, field_offset)
+        }).collect()
+    }
+
+    /// Computes the number of cells used by the fields of this variant
+    pub fn fields_size(&self, scope: &ScopeStack) -> MemSize {
+        self.fields.iter().map(|field| field.required_size(scope)).sum()
+    }
+}
+
 /// An item is anything that can be declared
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ItemType {
@@ -60,7 +117,15 @@ pub enum ItemType {
     /// Structs can have impls which contain methods for that
     /// struct
     Struct {
-        //TODO: fields, generics, etc.
+        /// The fields in the order they are laid out in memory, each one right after the last
+        fields: Vec<Field>,
+        //TODO: generics, etc.
+    },
+
+    /// An enum is stored as a single tag cell with the index of its variant followed by enough
+    /// cells for the fields of its largest variant
+    Enum {
+        variants: Vec<Variant>,
     },
 
     /// A declaration of an array type, optionally specialized for the given item type
@@ -89,7 +154,10 @@ impl ItemType {
         match *self {
             ItemType::Unit => MemSize::default(),
             ItemType::Primitive(size) => size,
-            ItemType::Struct { .. } => MemSize::default(), //TODO: Update this when fields are supported
+            ItemType::Struct {ref fields} => fields.iter().map(|field| field.typ.required_size(scope)).sum(),
+            ItemType::Enum {ref variants} => {
+                1 + variants.iter().map(|variant| variant.fields_size(scope)).max().unwrap_or(0)
+            },
             ItemType::Array {item: Some(item), size: Some(size)} => {
                 scope.get_type(item).required_size(scope) * size
             },
@@ -98,6 +166,35 @@ impl ItemType {
         }
     }
 
+    /// Returns the type of the field with the given name along with the offset of its first cell
+    /// from the start of the struct
+    /// Returns None if this item type is not a struct or has no such field
+    pub fn field(&self, scope: &ScopeStack, name: &Identifier) -> Option<(FieldType, MemSize)> {
+        match *self {
+            ItemType::Struct {ref fields} => {
+                let mut offset = 0;
+                for field in fields {
+                    if field.name == *name {
+                        return Some((field.typ, offset));
+                    }
+                    offset += field.typ.required_size(scope);
+                }
+                None
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the index of the variant with the given name, which is also the value of its tag,
+    /// along with the variant itself
+    /// Returns None if this item type is not an enum or has no such variant
+    pub fn variant(&self, name: &Identifier) -> Option<(usize, &Variant)> {
+        match *self {
+            ItemType::Enum {ref variants} => variants.iter().enumerate().find(|&(_, variant)| variant.name == *name),
+            _ => None,
+        }
+    }
+
     /// Returns true if this item type matches the given function signature (args, return type)
     /// Returns false if this item type is not a function
     /// Note: Variadic matching is only done one-way
@@ -111,13 +208,23 @@ impl ItemType {
     ///     self = Function {args: [Variadic(1)], return_type: 0}
     ///     expected_args = [Arg(1)]
     ///     return_type = 0
-    pub fn matches_signature(&self, expected_args: &Vec<FuncArgType>, expected_return_type: TypeId) -> bool {
+    pub fn matches_signature(&self, expected_args: &[FuncArgType], expected_return_type: TypeId) -> bool {
+        match *self {
+            ItemType::Function {return_type, ..} => return_type == expected_return_type &&
+                self.matches_args(expected_args),
+            _ => false,
+        }
+    }
+
+    /// Returns true if this item type is a function that can be called with the given args,
+    /// regardless of its return type
+    /// Returns false if this item type is not a function
+    pub fn matches_args(&self, expected_args: &[FuncArgType]) -> bool {
         let mut expected_args = expected_args.iter().peekable();
         match *self {
-            ItemType::Function {ref args, return_type} => (
-                return_type == expected_return_type &&
+            ItemType::Function {ref args, ..} => {
                 // All the args must match an argument in expected_args
-                args.iter().all(|arg| match *arg {
+                let all_match = args.iter().all(|arg| match *arg {
                     FuncArgType::Arg(type_id) => match expected_args.peek() {
                         Some(&&FuncArgType::Arg(arg_id)) if type_id == arg_id => {
                             expected_args.next();
@@ -125,8 +232,10 @@ impl ItemType {
                         },
                         _ => false,
                     },
+                    // An array without a size accepts an array of any size
                     FuncArgType::Array {item, size} => match expected_args.peek() {
-                        Some(&&FuncArgType::Array {item: arg_item, size: arg_size}) if item == arg_item && size == arg_size => {
+                        Some(&&FuncArgType::Array {item: arg_item, size: arg_size})
+                            if item == arg_item && (size.is_none() || size == arg_size) => {
                             expected_args.next();
                             true
                         },
@@ -134,7 +243,7 @@ impl ItemType {
                     },
                     FuncArgType::Variadic(None) => {
                         // Exhaust the entire iterator
-                        while let Some(_) = expected_args.next() {}
+                        for _ in expected_args.by_ref() {}
                         true
                     },
                     FuncArgType::Variadic(Some(type_id)) => match expected_args.peek() {
@@ -145,8 +254,11 @@ impl ItemType {
                         Some(&&FuncArgType::Arg(_)) => matches_variadic(type_id, &mut expected_args),
                         _ => false,
                     },
-                })
-            ),
+                });
+
+                // Every argument in expected_args must be used
+                all_match && expected_args.peek().is_none()
+            },
             _ => false,
         }
     }
@@ -169,6 +281,7 @@ fn matches_variadic(type_id: TypeId, expected_args: &mut Peekable<Iter<FuncArgTy
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::ptr_arg)]
 mod tests {
     use super::*;
 
@@ -285,6 +398,50 @@ mod tests {
             ],
             22,
         ), true);
+
+        // Every argument must be used by the function
+        assert_eq!(test_matches_signature(
+            ItemType::Function {
+                args: vec![
+                    FuncArgType::Arg(0),
+                ],
+                return_type: 22,
+            },
+            &vec![
+                FuncArgType::Arg(0),
+                FuncArgType::Arg(0),
+            ],
+            22,
+        ), false);
+
+        // An array without a size matches any size, otherwise the sizes must be the same
+        assert_eq!(test_matches_signature(
+            ItemType::Function {
+                args: vec![
+                    FuncArgType::Array {item: 0, size: None},
+                    FuncArgType::Array {item: 0, size: Some(3)},
+                ],
+                return_type: 22,
+            },
+            &vec![
+                FuncArgType::Array {item: 0, size: Some(5)},
+                FuncArgType::Array {item: 0, size: Some(3)},
+            ],
+            22,
+        ), true);
+
+        assert_eq!(test_matches_signature(
+            ItemType::Function {
+                args: vec![
+                    FuncArgType::Array {item: 0, size: Some(3)},
+                ],
+                return_type: 22,
+            },
+            &vec![
+                FuncArgType::Array {item: 0, size: Some(4)},
+            ],
+            22,
+        ), false);
     }
 
     fn test_matches_signature(
@@ -7,20 +7,32 @@ pub mod statement;
 pub mod declaration;
 pub mod assignment;
 pub mod while_loop;
+pub mod function;
+pub mod structure;
+pub mod enumeration;
+pub mod implementation;
 pub mod expression;
 
 mod operation;
 mod primitives;
 mod error;
+mod warning;
+mod initialization;
+mod constants;
+mod dispatch;
 
 pub use self::expression::Target;
 pub use self::operation::*;
 pub use self::error::*;
+pub use self::warning::*;
+pub use self::initialization::*;
 
 use parser::Program;
 
 use self::scope::ScopeStack;
 
-pub fn from_ast(global_scope: &mut ScopeStack, ast: Program) -> OperationsResult {
-    program::into_operations(global_scope, ast)
+/// Generates the operations for the given program
+/// Warnings are not returned, they are left in the scope to be taken with `take_warnings()`
+pub fn from_ast(global_scope: &mut ScopeStack, ast: Program) -> Result<Operations, Vec<Error>> {
+    program::into_operations(global_scope, ast).map(constants::fold)
 }
@@ -47,20 +47,16 @@ pub enum Operation {
     },
 
     /// Read bytes into the given memory block
-    /// Note: this generates both read instructions and move right instructions
     Read {
         target: MemoryBlock,
     },
 
-    /// Write bytes into the given memory block
-    /// Note: this generates both write instructions and move right instructions
+    /// Write bytes from the given memory block
     Write {
         target: MemoryBlock,
     },
 
     /// Set the value of every cell inside the given memory block to zero
-    /// Note: this generates instructions to zero the value and move to the right towards each
-    /// consecutive cell
     Zero {
         target: MemoryBlock,
     },
@@ -104,6 +100,34 @@ pub enum Operation {
         source: MemoryBlock,
         target: MemoryBlock,
     },
+
+    /// Move the value of the given cell onto a new entry at the top of the call stack, leaving
+    /// zero in the cell
+    ///
+    /// The call stack is kept on the tape after every cell used by anything else, so it can
+    /// grow for as long as the tape does.
+    Push {
+        source: CellPosition,
+    },
+
+    /// Move the value of the entry at the top of the call stack into the given cell and remove
+    /// that entry from the stack
+    /// The cell must be zero beforehand
+    Pop {
+        target: CellPosition,
+    },
+
+    /// Placeholder for a call of a recursive function from inside of its own body
+    ///
+    /// The body of a recursive function is turned into a dispatch loop which replaces every one
+    /// of these with operations that use the call stack (see `operations::dispatch`). Code
+    /// generation never sees this operation.
+    Recurse {
+        /// The program counter of the dispatch loop that this call belongs to
+        pc: MemoryBlock,
+        /// The memory that the result of the call is stored in
+        target: MemoryBlock,
+    },
 }
 
 impl Operation {
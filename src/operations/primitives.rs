@@ -17,6 +17,12 @@ macro_rules! impl_primitives {
             $($field_name: $field_type,)*
         }
 
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
         impl $name {
             pub fn new() -> Primitives {
                 $name {
@@ -31,14 +37,14 @@ macro_rules! impl_primitives {
                             // Only allowed to define primitive types once
                             // This helps catch bugs where we are accidentally defining special
                             // primitives twice for some reason
-                            debug_assert!(self.$field_name.is_none(), format!("Redefined `{}` primitive in scope", stringify!($field_name)));
+                            debug_assert!(self.$field_name.is_none(), "Redefined `{}` primitive in scope", stringify!($field_name));
 
                             self.check_collision(type_id);
 
                             self.$field_name = Some(type_id)
                         },
                     )*
-                    _ => panic!(format!("Attempt to register unknown primitive: `{}`", name)),
+                    _ => panic!("Attempt to register unknown primitive: `{}`", name),
                 }
             }
 
@@ -47,7 +53,7 @@ macro_rules! impl_primitives {
             fn check_collision(&self, type_id: TypeId) {
                 $(
                     if self.$field_name == Some(type_id) {
-                        panic!(format!("TypeId `{}` is already registered to another primitive: `{}`", type_id, stringify!($field_name)));
+                        panic!("TypeId `{}` is already registered to another primitive: `{}`", type_id, stringify!($field_name));
                     }
                 )*
             }
@@ -61,14 +67,19 @@ macro_rules! impl_primitives {
     }
 }
 
-/// To add a primitive, simply add a field to the following struct
-/// Everything else will be generated for you
+// To add a primitive, simply add a field to the following struct
+// Everything else will be generated for you
 impl_primitives! {
     pub struct Primitives {
         unit: Option<TypeId>,
         array: Option<TypeId>,
         bool: Option<TypeId>,
         u8: Option<TypeId>,
+        u16: Option<TypeId>,
+        u32: Option<TypeId>,
+        i8: Option<TypeId>,
+        i16: Option<TypeId>,
+        usize: Option<TypeId>,
     }
 }
 
@@ -1,16 +1,31 @@
 use parser::Program;
 
-use super::{OperationsResult, module};
+use super::{Operations, Error, module};
 use super::scope::ScopeStack;
 
-pub fn into_operations(scope: &mut ScopeStack, program: Program) -> OperationsResult {
-    module::into_operations(scope, program.root_mod)
+/// Generates the operations for the entire program
+/// Returns every error found in the program if there were any
+pub fn into_operations(scope: &mut ScopeStack, program: Program) -> Result<Operations, Vec<Error>> {
+    let ops = module::into_operations(scope, program.root_mod).unwrap_or_else(|error| {
+        scope.report_error(error);
+        Vec::new()
+    });
+
+    let errors = scope.take_errors();
+    if errors.is_empty() {
+        Ok(ops)
+    }
+    else {
+        Err(errors)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use parser::Program;
+    use operations::{Error, Warning};
+    use prelude;
 
     #[test]
     fn empty_program() {
@@ -20,4 +35,227 @@ mod tests {
         let ops = into_operations(&mut scope, program).unwrap();
         assert_eq!(ops.len(), 1);
     }
+
+    #[test]
+    fn errors_have_spans() {
+        let errors = [
+            ("let a: u8 = 1;\nlet b: u8 = c;\n", (2, 13)),
+            ("let mut a: u8 = 1;\n  a = b\"ab\";\n", (2, 7)),
+            ("let a: u8 = 1;\nlet b: u9 = a;\n", (2, 8)),
+            ("let a: u8 = 1;\nlet b: u8 = 256;\n", (2, 13)),
+            ("let a: u8 = 1;\nstdout.foo(a);\n", (2, 1)),
+            ("let a: u8 = 1;\nlet b: [u8; _] = a as u8;\n", (2, 18)),
+            ("let a: u8 = 1;\nlet b: [u8; _] = match a { _ => b\"a\" };\n", (2, 18)),
+            ("let a: i8 = -128;\nlet b: i8 = -129;\n", (2, 13)),
+            ("let a: u8 = 1;\nlet b: u16 = -1;\n", (2, 14)),
+            ("fn f() {}\nfn f() {}\n", (2, 4)),
+            ("struct P { a: u8 }\nimpl P { fn m(self) {} }\nimpl P { fn m(self) {} }\n", (3, 13)),
+            ("fn f(x: u8) -> u8 { g(x) }\nfn g(x: u8) -> u8 { f(x) }\n", (1, 4)),
+            ("struct P { a: u8 }\nstruct P { b: u8 }\n", (2, 8)),
+            ("enum P { A }\nstruct P { a: u8 }\n", (2, 8)),
+            ("struct P { a: u8 }\nlet p: P = P { a: 1 };\nstdout.print(p.a.b);\n", (3, 18)),
+            ("struct P { a: u8, b: u8 }\nlet mut p: P;\np.a = 1;\nstdout.print(p.b);\n", (4, 14)),
+            ("struct P { a: u8, b: u8 }\nlet mut p: P;\np.a = 1;\nlet q: P = p;\n", (4, 12)),
+            ("struct P { a: u8 }\nlet p: P;\np.a = 1;\n", (3, 1)),
+            ("enum E { N(u8) }\nlet e: E = E::N(1);\nstdout.print(match e { E::N(n) => n });\n", (3, 14)),
+        ];
+
+        for &(source, position) in &errors {
+            let mut scope = ScopeStack::new();
+            prelude::populate_scope(&mut scope);
+            let program: Program = source.parse().unwrap();
+
+            let error = into_operations(&mut scope, program).unwrap_err().remove(0);
+            let span = error.span();
+            assert_eq!((span.line, span.col), position, "wrong span for {:?}", error);
+        }
+
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        let program: Program = "let a: u8 = 256;".parse().unwrap();
+        match into_operations(&mut scope, program).unwrap_err().remove(0) {
+            Error::OverflowingLiteral {span, ..} => assert_eq!((span.start, span.end), (12, 15)),
+            error => panic!("unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let source = "\
+let a: u8 = b;
+let c: u9 = 1;
+stdout.print(c);
+c = 2;
+while d {
+    stdout.print(e, a, f);
+}
+let g: u8 = 1;
+stdout.print(g);
+";
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        let program: Program = source.parse().unwrap();
+
+        let errors = into_operations(&mut scope, program).unwrap_err();
+        let names: Vec<_> = errors.iter().map(|error| match *error {
+            Error::UnresolvedName {ref name, span} => (name.to_string(), span.line),
+            ref error => panic!("unexpected error: {:?}", error),
+        }).collect();
+        // Uses of `c` are not reported since its type could not be found
+        assert_eq!(names, vec![
+            ("b".to_owned(), 1),
+            ("u9".to_owned(), 2),
+            ("d".to_owned(), 5),
+            ("e".to_owned(), 6),
+            ("f".to_owned(), 6),
+        ]);
+    }
+
+    #[test]
+    fn reports_warnings() {
+        let source = "\
+let a: u8 = 1;
+let mut b: u8 = 2;
+let mut c: [u8; 1];
+stdin.read_exact(c);
+let _d: u8 = b;
+stdout.print(c);
+a;
+if false {
+    stdout.print(b\"x\");
+}
+while true {
+    // Comments are not unreachable
+}
+let e: u8 = 1;
+stdout.print(e);
+struct P { a: u8 }
+let mut p: P;
+p.a = 1;
+stdout.print(p.a);
+";
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        let program: Program = source.parse().unwrap();
+
+        into_operations(&mut scope, program).unwrap();
+        let warnings: Vec<_> = scope.take_warnings().into_iter()
+            .map(|warning| (warning.lint().name(), warning.span().line))
+            .collect();
+        assert_eq!(warnings, vec![
+            ("no_effect", 7),
+            ("constant_condition", 8),
+            ("unreachable_code", 14),
+            ("unused_mut", 2),
+        ]);
+    }
+
+    #[test]
+    fn rebinding_reports_unused_variables() {
+        let source = "let a: u8 = 1;\nlet a: u8 = 2;\nstdout.print(a);\n";
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        let program: Program = source.parse().unwrap();
+
+        into_operations(&mut scope, program).unwrap();
+        match scope.take_warnings().as_slice() {
+            [Warning::UnusedVariable {ref name, span}] => assert_eq!((name.to_string(), span.line), ("a".to_owned(), 1)),
+            warnings => panic!("unexpected warnings: {:?}", warnings),
+        }
+    }
+
+    #[test]
+    fn describes_mismatched_operators() {
+        let source = "\
+let a: u16 = 5;
+let b: u8 = a + 1;
+let c: u8 = 2;
+let d: u16 = a + c;
+let e: u8 = 1 + true;
+";
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        let program: Program = source.parse().unwrap();
+
+        let errors = into_operations(&mut scope, program).unwrap_err();
+        let types: Vec<_> = errors.iter().map(|error| match *error {
+            Error::MismatchedTypes {ref expected, ref found, ..} => (expected.as_str(), found.as_str()),
+            ref error => panic!("unexpected error: {:?}", error),
+        }).collect();
+        // The overload that accepts the arguments is the one that is reported
+        assert_eq!(types, vec![
+            ("u8", "u16"),
+            ("fn(u16, u8)", "fn(u16, u16) -> u16"),
+            ("fn({integer}, bool)", "fn(u8, u8) -> u8"),
+        ]);
+    }
+
+    #[test]
+    fn rejects_modifying_immutable_variables() {
+        let source = "\
+let a: u8 = 1;
+a = 2;
+decrement(a);
+let b: [u8; 1];
+stdin.read_exact(b);
+stdin.read_exact(b);
+let mut c: u8 = 3;
+decrement(c);
+c = 4;
+stdout.print(a, b, c);
+";
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        let program: Program = source.parse().unwrap();
+
+        let errors = into_operations(&mut scope, program).unwrap_err();
+        let names: Vec<_> = errors.iter().map(|error| match *error {
+            Error::ImmutableVariable {ref name, span, ..} => (name.to_string(), span.line, span.col),
+            ref error => panic!("unexpected error: {:?}", error),
+        }).collect();
+        // Variables declared without a value can be initialized once and a variable passed to a
+        // function that modifies it is pointed at rather than the function
+        assert_eq!(names, vec![
+            ("a".to_owned(), 2, 1),
+            ("a".to_owned(), 3, 11),
+            ("b".to_owned(), 6, 18),
+        ]);
+    }
+
+    #[test]
+    fn rejects_uninitialized_variables() {
+        let source = "\
+let mut c: bool = true;
+let a: u8;
+stdout.print(a);
+let b: u8;
+if c { b = 1; }
+stdout.print(b);
+let mut d: [u8; 1];
+while c { stdin.read_exact(d); c = false; }
+stdout.print(d);
+let e: u8;
+if c { e = 1; } else { e = 2; }
+let f: [u8; 1];
+stdin.read_exact(f);
+stdout.print(e, f);
+";
+        let mut scope = ScopeStack::new();
+        prelude::populate_scope(&mut scope);
+        let program: Program = source.parse().unwrap();
+
+        let errors = into_operations(&mut scope, program).unwrap_err();
+        let paths: Vec<_> = errors.iter().map(|error| match *error {
+            Error::UninitializedVariable {ref name, ref path, span} => {
+                let path: Vec<_> = path.iter().map(|step| step.to_string()).collect();
+                (name.to_string(), span.line, path)
+            },
+            ref error => panic!("unexpected error: {:?}", error),
+        }).collect();
+        assert_eq!(paths, vec![
+            ("a".to_owned(), 3, vec![]),
+            ("b".to_owned(), 6, vec!["the condition on line 5 is `false`".to_owned()]),
+            ("d".to_owned(), 9, vec!["the loop on line 8 does not run".to_owned()]),
+        ]);
+    }
 }
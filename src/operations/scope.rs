@@ -1,11 +1,11 @@
 use std::rc::Rc;
 use std::collections::{VecDeque, HashMap};
 
-use parser::{Identifier, Number};
+use parser::{Identifier, Number, Span};
 use memory::{StaticAllocator, MemoryBlock};
 
-use super::OperationsResult;
-use super::item_type::ItemType;
+use super::{OperationsResult, Error, Warning, Initialization, InitializationState, Place};
+use super::item_type::{ItemType, FuncArgType, FieldType};
 
 pub use super::primitives::Primitives;
 
@@ -19,6 +19,10 @@ pub type ArraySize = usize;
 /// Arguments are guaranteed by static analysis to match the type defined for the function
 pub type FuncArgs = Vec<ScopeItem>;
 
+/// The code generator of a built-in function
+/// Function should store the result in the memory block represented by the third parameter
+pub type BuiltInFunctionOperations = dyn Fn(&mut ScopeStack, FuncArgs, MemoryBlock) -> OperationsResult;
+
 /// Represents a type declared in a scope
 pub enum ScopeType {
     /// A type, not associated with any memory
@@ -29,6 +33,15 @@ pub enum ScopeType {
     //TODO: Generic types, etc. will all go here
 }
 
+/// Whether a variable can be modified after it is declared
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Mutability {
+    /// Declared with `let mut`, can always be modified
+    Mutable,
+    /// Declared with `let`, can only be assigned to once if it was declared without a value
+    Immutable,
+}
+
 /// Represents a single item in a scope
 #[derive(Clone)]
 pub enum ScopeItem {
@@ -55,6 +68,7 @@ pub enum ScopeItem {
     TypedBlock {
         type_id: TypeId,
         memory: MemoryBlock,
+        mutability: Mutability,
     },
 
     /// A specialization of the generic array type [T; N]
@@ -67,9 +81,11 @@ pub enum ScopeItem {
         /// The block of memory allocated to this array
         /// Size of this block is always sizeof(item) * size
         memory: MemoryBlock,
+        mutability: Mutability,
     },
 
-    /// The implementation of a built-in function
+    /// The implementation of a built-in function or of a function declared with `fn`
+    /// Functions declared with `fn` are inlined wherever they are called
     /// Note that the type signature is stored separately
     BuiltInFunction {
         /// The ID of the type associated with this function
@@ -78,11 +94,24 @@ pub enum ScopeItem {
         /// function with the given arguments
         /// Function should store the result in the memory block represented by the third
         /// parameter
-        operations: Rc<Fn(&mut ScopeStack, FuncArgs, MemoryBlock) -> OperationsResult>,
+        operations: Rc<BuiltInFunctionOperations>,
     },
+
+    /// A name whose declaration failed, usually because its type could not be resolved
+    /// Using this name is an error that is never reported since the error from the declaration
+    /// already was. This prevents a single mistake from causing a flood of errors.
+    Poisoned,
 }
 
 impl ScopeItem {
+    /// Returns whether this item can be modified or None if it is not a variable
+    pub fn mutability(&self) -> Option<Mutability> {
+        match *self {
+            ScopeItem::TypedBlock {mutability, ..} | ScopeItem::Array {mutability, ..} => Some(mutability),
+            _ => None,
+        }
+    }
+
     pub fn numeric_literal_value(&self) -> Number {
         match *self {
             ScopeItem::NumericLiteral(number) => number,
@@ -102,8 +131,43 @@ impl ScopeItem {
             Constant { type_id, .. } => type_id,
             TypedBlock { type_id, .. } => type_id,
             BuiltInFunction { type_id, .. } => type_id,
-            NumericLiteral(..) | ByteLiteral(..) | Array {..} => panic!("Variant does not store its TypeId"),
+            NumericLiteral(..) | ByteLiteral(..) | Array {..} | Poisoned => panic!("Variant does not store its TypeId"),
+        }
+    }
+}
+
+/// How a variable declared with `let` has been used so far
+struct Variable {
+    /// The name as it was written in the declaration
+    name: Identifier,
+    mutable: bool,
+    read: bool,
+    written: bool,
+    initialization: Initialization,
+    /// The initialization of every field of a struct that is not a struct itself, along with the
+    /// fields that lead to it, so that a struct can be given a value one field at a time
+    fields: Vec<(Vec<Identifier>, Initialization)>,
+    /// The number of loops that the declaration is inside of
+    loop_depth: usize,
+}
+
+impl Variable {
+    /// Returns whether the variable has a value, which a struct also has once every one of its
+    /// fields has been given one
+    fn initialization(&self) -> Initialization {
+        self.field_initialization(&[])
+    }
+
+    /// Returns whether the field at the end of the given path (or the whole variable if the path
+    /// is empty) has a value
+    fn field_initialization(&self, path: &[Identifier]) -> Initialization {
+        if self.initialization == Initialization::Initialized || self.fields.is_empty() {
+            return self.initialization.clone();
         }
+
+        Initialization::all(self.fields.iter()
+            .filter(|&(field, _)| field.starts_with(path))
+            .map(|(_, init)| init))
     }
 }
 
@@ -111,6 +175,17 @@ impl ScopeItem {
 pub struct Scope {
     types: HashMap<Identifier, ScopeType>,
     items: HashMap<Identifier, ScopeItem>,
+    /// The variables in items that are tracked to find out whether they are ever used
+    variables: HashMap<Identifier, Variable>,
+    /// True if this is the outermost scope of the body of a function
+    /// The variables of the scopes below this one cannot be used from inside of the function
+    function: bool,
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Scope {
@@ -118,6 +193,8 @@ impl Scope {
         Scope {
             types: HashMap::new(),
             items: HashMap::new(),
+            variables: HashMap::new(),
+            function: false,
         }
     }
 }
@@ -138,6 +215,27 @@ pub struct ScopeStack {
     /// they say they are. For example, if the compiler needs to refer to `bool`, it needs to
     /// be able to lookup that type without worrying about conflicting with user defined types
     primitives: Primitives,
+
+    /// Errors reported so far
+    /// Statements that fail are skipped so that the errors in every statement can be reported
+    /// at once instead of only the first one
+    errors: Vec<Error>,
+
+    /// Warnings reported so far
+    warnings: Vec<Warning>,
+
+    /// The number of loops that the code currently being generated is inside of
+    loop_depth: usize,
+
+    /// True if functions that call themselves are allowed, which compiles them into a dispatch
+    /// loop that uses a call stack instead of inlining them
+    recursion: bool,
+}
+
+impl Default for ScopeStack {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ScopeStack {
@@ -155,22 +253,55 @@ impl ScopeStack {
                 // 0 is the index of the Unit type in the types array declared above
                 primitives.register("unit", 0);
                 primitives
-            }
+            },
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            loop_depth: 0,
+            recursion: false,
         }
     }
 
+    /// Allows functions declared with `fn` to call themselves
+    pub fn enable_recursion(&mut self) {
+        self.recursion = true;
+    }
+
+    /// Returns true if functions declared with `fn` are allowed to call themselves
+    pub fn recursion_enabled(&self) -> bool {
+        self.recursion
+    }
+
     /// Pushes a new level of scope onto the stack
     /// This scope will become the current scope
     pub fn push_scope(&mut self) {
         self.stack.push_back(Scope::new());
     }
 
+    /// Pushes a new level of scope for the body of a function onto the stack
+    /// The variables declared with `let` in the scopes below this one cannot be used from inside
+    /// of the function, everything else (like functions and `stdout`) can
+    pub fn push_function_scope(&mut self) {
+        self.stack.push_back(Scope {
+            function: true,
+            ..Scope::new()
+        });
+    }
+
     /// Removes and returns the top level scope (current scope)
+    /// Variables from that scope that were never used are reported as warnings
     ///
     /// # Panics
     /// Panics if there is no scope in the stack
     pub fn pop_scope(&mut self) -> Scope {
-        self.stack.pop_back().unwrap()
+        let mut scope = self.stack.pop_back().unwrap();
+
+        let mut variables: Vec<_> = scope.variables.drain().map(|(_, variable)| variable).collect();
+        variables.sort_by_key(|variable| variable.name.span().start);
+        for variable in variables {
+            self.check_usage(variable);
+        }
+
+        scope
     }
 
     /// Returns the type name associated with the given TypeId
@@ -187,6 +318,353 @@ impl ScopeStack {
         &self.types.get(type_id).expect("Invalid TypeId used to lookup type").1
     }
 
+    /// Returns the given type as it would be written in the source code
+    pub fn type_name(&self, type_id: TypeId) -> String {
+        match *self.get_type(type_id) {
+            // Primitives, structs and enums are only known by the name they were declared with
+            ItemType::Primitive(..) | ItemType::Struct {..} | ItemType::Enum {..} => {
+                self.get_type_name(type_id).to_string()
+            },
+            ref typ => self.describe_type(typ),
+        }
+    }
+
+    /// Returns the given type as it would be written in the source code
+    ///
+    /// # Panics
+    /// Panics if the type is a primitive, a struct or an enum since those must be described using
+    /// `type_name()` with their TypeId
+    pub fn describe_type(&self, typ: &ItemType) -> String {
+        match *typ {
+            ItemType::Unit => "()".to_owned(),
+            ItemType::Array {item, size} => self.describe_array(item, size),
+            ItemType::Function {ref args, return_type} => {
+                format!("{} -> {}", self.describe_arguments(args), self.type_name(return_type))
+            },
+            ItemType::Primitive(..) | ItemType::Struct {..} | ItemType::Enum {..} => {
+                unreachable!("Named types must be described by their TypeId")
+            },
+        }
+    }
+
+    /// Returns a function signature with the given arguments and no return type, like `fn(u8, bool)`
+    pub fn describe_arguments(&self, args: &[FuncArgType]) -> String {
+        format!("fn({})", args.iter().map(|arg| match *arg {
+            FuncArgType::Arg(type_id) => self.type_name(type_id),
+            FuncArgType::Array {item, size} => self.describe_array(Some(item), size),
+            FuncArgType::Variadic(Some(type_id)) => format!("{}...", self.type_name(type_id)),
+            FuncArgType::Variadic(None) => "...".to_owned(),
+        }).collect::<Vec<_>>().join(", "))
+    }
+
+    fn describe_array(&self, item: Option<TypeId>, size: Option<ArraySize>) -> String {
+        format!("[{}; {}]",
+            item.map_or_else(|| "_".to_owned(), |item| self.type_name(item)),
+            size.map_or_else(|| "_".to_owned(), |size| size.to_string()))
+    }
+
+    /// Records an error so that compilation can continue and report more errors
+    /// Errors caused by using a poisoned name are ignored since the error that poisoned the name
+    /// was already reported
+    pub fn report_error(&mut self, error: Error) {
+        match error {
+            Error::Poisoned {..} => {},
+            error => self.errors.push(error),
+        }
+    }
+
+    /// Removes and returns all of the errors reported so far
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        self.errors.drain(..).collect()
+    }
+
+    /// Records a warning, warnings never stop the program from being compiled
+    pub fn report_warning(&mut self, warning: Warning) {
+        self.warnings.push(warning);
+    }
+
+    /// Removes and returns all of the warnings reported so far
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        self.warnings.drain(..).collect()
+    }
+
+    /// Runs the given function and throws away any errors or warnings that it reports
+    ///
+    /// Used when generating code that was already checked once so that the same problems are not
+    /// reported again.
+    pub fn silently<T, F>(&mut self, f: F) -> T
+        where F: FnOnce(&mut ScopeStack) -> T {
+        let errors = self.errors.len();
+        let warnings = self.warnings.len();
+        let result = f(self);
+        self.errors.truncate(errors);
+        self.warnings.truncate(warnings);
+        result
+    }
+
+    /// Tracks how the variable with the given name is used so that a warning can be reported if
+    /// it is never read (or never assigned to when it is mutable) by the end of its scope
+    /// Also tracks whether the variable has a value so that it is never read before it has one
+    /// The variable must already be declared in the current scope
+    pub fn track_variable(&mut self, name: Identifier, mutable: bool, initialized: bool) {
+        debug_assert!(self.stack.back().is_some_and(|scope| scope.items.contains_key(&name)));
+
+        let loop_depth = self.loop_depth;
+        let initialization = if initialized {
+            Initialization::Initialized
+        }
+        else {
+            Initialization::Uninitialized
+        };
+        let fields = match self.stack.back().and_then(|scope| scope.items.get(&name)) {
+            Some(&ScopeItem::TypedBlock {type_id, ..}) => self.leaf_fields(type_id).into_iter()
+                .map(|path| (path, initialization.clone()))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let scope = self.stack.back_mut()
+            .expect("Attempt to track variable despite having no current scope");
+        scope.variables.insert(name.clone(), Variable {
+            name,
+            mutable,
+            read: false,
+            written: false,
+            initialization,
+            fields,
+            loop_depth,
+        });
+    }
+
+    /// Returns the path to every field of the given type that is not a struct itself, including
+    /// the fields of the structs in its fields, in the order they are declared
+    fn leaf_fields(&self, type_id: TypeId) -> Vec<Vec<Identifier>> {
+        let fields = match *self.get_type(type_id) {
+            ItemType::Struct {ref fields} => fields,
+            _ => return Vec::new(),
+        };
+
+        fields.iter().flat_map(|field| {
+            let nested = match field.typ {
+                FieldType::Value(type_id) => self.leaf_fields(type_id),
+                FieldType::Array {..} => Vec::new(),
+            };
+            if nested.is_empty() {
+                return vec![vec![field.name.clone()]];
+            }
+            nested.into_iter().map(|path| Some(field.name.clone()).into_iter().chain(path).collect()).collect()
+        }).collect()
+    }
+
+    /// Records that the value of the variable with the given name was used
+    pub fn mark_read(&mut self, name: &Identifier) {
+        if let Some(variable) = self.lookup_variable(name) {
+            variable.read = true;
+        }
+    }
+
+    /// Checks that the variable with the given name has a value on every path that leads to the
+    /// code currently being generated
+    pub fn check_initialized(&mut self, name: &Identifier) -> Result<(), Error> {
+        self.check_field_initialized(name, &[])
+    }
+
+    /// Checks that the field at the end of the given path in the variable with the given name has
+    /// a value on every path that leads to the code currently being generated
+    ///
+    /// The rest of the variable does not need to have a value.
+    pub fn check_field_initialized(&mut self, name: &Identifier, fields: &[Identifier]) -> Result<(), Error> {
+        let path = self.lookup_variable(name)
+            .and_then(|variable| variable.field_initialization(fields).uninitialized_path());
+
+        match path {
+            Some(path) => Err(Error::UninitializedVariable {name: name.clone(), path, span: name.span()}),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that the variable stored in the given memory can be given a new value and records
+    /// that it was
+    ///
+    /// Immutable variables can only be assigned to if they have not been given a value on any
+    /// path yet and the assignment is not in a loop that could run it more than once. Memory that
+    /// does not belong to a variable (like a temporary value) can always be assigned to.
+    /// Errors are returned without a span since only the caller knows where the assignment
+    /// happened.
+    pub fn assign(&mut self, memory: MemoryBlock) -> Result<(), Error> {
+        let loop_depth = self.loop_depth;
+        let (scope, name) = match self.lookup_memory(memory) {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+        let first_assignment = match scope.variables.get_mut(&name) {
+            Some(variable) => {
+                let first_assignment = variable.initialization() == Initialization::Uninitialized
+                    && variable.loop_depth == loop_depth;
+                variable.written = true;
+                variable.initialization = Initialization::Initialized;
+                for (_, init) in &mut variable.fields {
+                    *init = Initialization::Initialized;
+                }
+                first_assignment
+            },
+            None => false,
+        };
+
+        match scope.items[&name].mutability() {
+            Some(Mutability::Immutable) if !first_assignment => Err(Error::ImmutableVariable {
+                declared: name.span(),
+                name,
+                span: Span::default(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the variable stored in the given memory can be modified and records that it
+    /// was modified
+    ///
+    /// Unlike `assign()`, the variable must already have a value since its new value is based on
+    /// that value. Memory that does not belong to a variable (like a temporary value) can always
+    /// be modified. Errors are returned without a span since only the caller knows where the
+    /// modification happened.
+    pub fn mutate(&mut self, memory: MemoryBlock) -> Result<(), Error> {
+        let (scope, name) = match self.lookup_memory(memory) {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+        if let Some(variable) = scope.variables.get_mut(&name) {
+            if let Some(path) = variable.initialization().uninitialized_path() {
+                return Err(Error::UninitializedVariable {name, path, span: Span::default()});
+            }
+            variable.written = true;
+        }
+
+        match scope.items[&name].mutability() {
+            Some(Mutability::Immutable) => Err(Error::ImmutableVariable {
+                declared: name.span(),
+                name,
+                span: Span::default(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that a field of the variable stored in the given memory can be given a new value
+    /// and records that it was
+    ///
+    /// Unlike `mutate()`, the rest of the variable does not need to have a value yet, so a
+    /// struct can be given a value one field at a time. The variable must still be mutable.
+    /// Errors are returned without a span since only the caller knows where the assignment
+    /// happened.
+    pub fn assign_field(&mut self, memory: MemoryBlock, fields: &[Identifier]) -> Result<(), Error> {
+        let (scope, name) = match self.lookup_memory(memory) {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+        if let Some(variable) = scope.variables.get_mut(&name) {
+            variable.written = true;
+            for (field, init) in &mut variable.fields {
+                if field.starts_with(fields) {
+                    *init = Initialization::Initialized;
+                }
+            }
+        }
+
+        match scope.items[&name].mutability() {
+            Some(Mutability::Immutable) => Err(Error::ImmutableVariable {
+                declared: name.span(),
+                name,
+                span: Span::default(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns whether every tracked variable (and each of its fields) has a value on every path
+    /// so far
+    pub fn initialization(&self) -> InitializationState {
+        InitializationState {
+            scopes: self.stack.iter().map(|scope| scope.variables.iter().flat_map(|(name, variable)| {
+                let whole = (Place {name: name.clone(), fields: Vec::new()}, variable.initialization.clone());
+                let fields = variable.fields.iter().map(move |(fields, init)| {
+                    (Place {name: name.clone(), fields: fields.clone()}, init.clone())
+                });
+                Some(whole).into_iter().chain(fields)
+            }).collect()).collect(),
+        }
+    }
+
+    /// Replaces whether every tracked variable has a value with the given state
+    /// The state must have come from `initialization()` with the same scopes on the stack
+    pub fn set_initialization(&mut self, state: InitializationState) {
+        debug_assert_eq!(self.stack.len(), state.scopes.len());
+
+        for (scope, variables) in self.stack.iter_mut().zip(state.scopes) {
+            for (Place {name, fields}, initialization) in variables {
+                let variable = match scope.variables.get_mut(&name) {
+                    Some(variable) => variable,
+                    None => continue,
+                };
+                if fields.is_empty() {
+                    variable.initialization = initialization;
+                }
+                else if let Some(field) = variable.fields.iter_mut().find(|field| field.0 == fields) {
+                    field.1 = initialization;
+                }
+            }
+        }
+    }
+
+    /// Records that the code generated until `exit_loop()` is called may run more than once
+    pub fn enter_loop(&mut self) {
+        self.loop_depth += 1;
+    }
+
+    pub fn exit_loop(&mut self) {
+        debug_assert!(self.loop_depth > 0, "Exited a loop without entering one");
+        self.loop_depth -= 1;
+    }
+
+    /// Returns the usage of the latest declaration of the given name if it is a tracked variable
+    fn lookup_variable(&mut self, name: &Identifier) -> Option<&mut Variable> {
+        let function_start = self.function_start();
+        self.stack.iter_mut().skip(function_start).rev()
+            .find(|scope| scope.items.contains_key(name))
+            .and_then(|scope| scope.variables.get_mut(name))
+    }
+
+    /// Returns the scope and the name of the variable stored in the given memory
+    fn lookup_memory(&mut self, memory: MemoryBlock) -> Option<(&mut Scope, Identifier)> {
+        self.stack.iter_mut().rev().filter_map(|scope| {
+            let name = scope.items.iter().find(|&(_, item)| match *item {
+                ScopeItem::TypedBlock {memory: item_memory, ..} |
+                ScopeItem::Array {memory: item_memory, ..} => item_memory == memory,
+                _ => false,
+            }).map(|(name, _)| name.clone());
+
+            name.map(|name| (scope, name))
+        }).next()
+    }
+
+    /// Reports a warning if the given variable was not used the way it was declared
+    fn check_usage(&mut self, variable: Variable) {
+        let Variable {name, mutable, read, written, ..} = variable;
+        let span = name.span();
+
+        // Names that start with an underscore are meant to be unused and a method does not have
+        // to use the instance it was called on
+        if !read && !name.to_string().starts_with('_') && name != Identifier::from("self") {
+            self.report_warning(Warning::UnusedVariable {name: name.clone(), span});
+        }
+        if mutable && !written {
+            self.report_warning(Warning::UnusedMut {name, span});
+        }
+    }
+
     /// Access special primitives
     /// e.g. `scope.primitives().unit()`
     pub fn primitives(&self) -> &Primitives {
@@ -207,7 +685,42 @@ impl ScopeStack {
     /// Definitions are returned in order from latest definition to oldest
     /// Always use the first definition that matches the type you are looking for
     pub fn lookup(&self, name: &Identifier) -> Vec<&ScopeItem> {
-        self.search_stack(name, |sc| &sc.items)
+        let function_start = self.function_start();
+        self.stack.iter().enumerate().rev().filter_map(|(i, sc)| {
+            // Variables declared outside of the current function cannot be used inside of it
+            sc.items.get(name).filter(|_| i >= function_start || !sc.variables.contains_key(name))
+        }).collect()
+    }
+
+    /// Returns the name as it was written in the latest declaration of the given name, which has
+    /// the span of that declaration
+    pub fn declared_name(&self, name: &Identifier) -> Option<&Identifier> {
+        let function_start = self.function_start();
+        self.stack.iter().enumerate().rev().filter_map(|(i, sc)| {
+            sc.items.get_key_value(name).map(|(declared, _)| declared)
+                .filter(|_| i >= function_start || !sc.variables.contains_key(name))
+        }).next()
+    }
+
+    /// Returns true if the given name was declared in the current scope
+    pub fn is_declared_here(&self, name: &Identifier) -> bool {
+        self.stack.back().is_some_and(|sc| sc.items.contains_key(name))
+    }
+
+    /// Returns true if the given type name was declared in the current scope
+    pub fn is_type_declared_here(&self, name: &Identifier) -> bool {
+        self.stack.back().is_some_and(|sc| sc.types.contains_key(name))
+    }
+
+    /// Returns the number of function bodies that the code currently being generated is inside of
+    pub fn function_depth(&self) -> usize {
+        self.stack.iter().filter(|sc| sc.function).count()
+    }
+
+    /// Returns the index of the outermost scope of the current function, or zero if the code
+    /// currently being generated is not in a function
+    fn function_start(&self) -> usize {
+        self.stack.iter().rposition(|sc| sc.function).unwrap_or(0)
     }
 
     pub fn lookup_type(&self, name: &Identifier) -> Vec<&ScopeType> {
@@ -228,9 +741,12 @@ impl ScopeStack {
     /// Declares a type with the given name
     /// Returns the unique identifier of that type
     pub fn declare_type(&mut self, name: Identifier, typ: ItemType) -> TypeId {
-        let type_id = self.insert_type(name, typ);
+        self.insert_type(name, typ)
+    }
 
-        type_id
+    /// Declares another name for an existing type
+    pub fn declare_type_alias(&mut self, name: Identifier, type_id: TypeId) {
+        self.insert_type_into_current(name, ScopeType::Type(type_id));
     }
 
     /// Declares a constant with the given name
@@ -241,7 +757,7 @@ impl ScopeStack {
         });
 
         self.insert_item_into_current(name, ScopeItem::Constant {
-            type_id: type_id,
+            type_id,
             bytes: value,
         });
     }
@@ -249,27 +765,36 @@ impl ScopeStack {
     /// Declares a name with the given type and allocates enough space for that type
     /// The name is declared in the "current" scope which is at the top of the stack
     /// Returns the allocated memory block
-    pub fn declare(&mut self, name: Identifier, type_id: TypeId) -> MemoryBlock {
+    pub fn declare(&mut self, name: Identifier, type_id: TypeId, mutability: Mutability) -> MemoryBlock {
         let mem = self.allocate(type_id);
         self.insert_item_into_current(name, ScopeItem::TypedBlock {
-            type_id: type_id,
+            type_id,
             memory: mem,
+            mutability,
         });
 
         mem
     }
 
+    /// Declares a name whose declaration failed so that uses of that name do not produce any
+    /// more errors
+    /// The name is declared in the "current" scope which is at the top of the stack
+    pub fn declare_poisoned(&mut self, name: Identifier) {
+        self.insert_item_into_current(name, ScopeItem::Poisoned);
+    }
+
     /// Declares a name with an array type with the given item type and allocates enough space for
     /// that type and all its elements
     /// The array is allocated as a single, contiguous block of memory
     /// The name is declared in the "current" scope which is at the top of the stack
     /// Returns the allocated memory block
-    pub fn declare_array(&mut self, name: Identifier, item: TypeId, size: ArraySize) -> MemoryBlock {
+    pub fn declare_array(&mut self, name: Identifier, item: TypeId, size: ArraySize, mutability: Mutability) -> MemoryBlock {
         let mem = self.allocate_array(item, size);
         self.insert_item_into_current(name, ScopeItem::Array {
-            item: item,
-            size: size,
+            item,
+            size,
             memory: mem,
+            mutability,
         });
 
         mem
@@ -296,18 +821,15 @@ impl ScopeStack {
     /// The function is guaranteed to be called with arguments that match its given type signature
     /// Functions that can be called on an instance of a type should have that type as the first
     /// parameter as the "self" of that function
-    pub fn declare_builtin_function<F: 'static>(&mut self, name: Identifier, typ: ItemType, f: F)
-        where F: Fn(&mut ScopeStack, FuncArgs, MemoryBlock) -> OperationsResult {
+    pub fn declare_builtin_function<F>(&mut self, name: Identifier, typ: ItemType, f: F)
+        where F: 'static + Fn(&mut ScopeStack, FuncArgs, MemoryBlock) -> OperationsResult {
 
         // Make sure we are declaring the function as a function type
-        debug_assert!(match typ {
-            ItemType::Function { .. } => true,
-            _ => false,
-        });
+        debug_assert!(matches!(typ, ItemType::Function { .. }));
 
         let type_id = self.insert_type(name.clone(), typ);
         self.insert_item_into_current(name, ScopeItem::BuiltInFunction {
-            type_id: type_id,
+            type_id,
             operations: Rc::new(f),
         });
     }
@@ -328,7 +850,12 @@ impl ScopeStack {
         // It's OK to overwrite existing names because we support rebinding
         let scope = self.stack.back_mut()
             .expect("Attempt to declare item despite having no current scope");
-        scope.items.insert(name, item);
+        scope.items.insert(name.clone(), item);
+
+        // A variable that is rebound can never be used again
+        if let Some(variable) = scope.variables.remove(&name) {
+            self.check_usage(variable);
+        }
     }
 
     /// Inserts a ScopeType into the current scope
@@ -362,22 +889,22 @@ mod tests {
         let type_id = scope.declare_type(Identifier::from("FooType"), ItemType::Primitive(1));
         assert_eq!(scope.lookup(&Identifier::from("foo")).len(), 0);
 
-        scope.declare(Identifier::from("foo"), type_id);
+        scope.declare(Identifier::from("foo"), type_id, Mutability::Immutable);
         assert_eq!(scope.lookup(&Identifier::from("foo")).len(), 1);
 
         // Declaring the same name in the same scope should overwrite the
         // definition
-        scope.declare(Identifier::from("foo"), type_id);
+        scope.declare(Identifier::from("foo"), type_id, Mutability::Immutable);
         assert_eq!(scope.lookup(&Identifier::from("foo")).len(), 1);
 
         scope.push_scope();
         // Declaring foo in another scope should add a definition
-        scope.declare(Identifier::from("foo"), type_id);
+        scope.declare(Identifier::from("foo"), type_id, Mutability::Immutable);
         assert_eq!(scope.lookup(&Identifier::from("foo")).len(), 2);
 
         // Declaring the same name in the same scope should overwrite the
         // definition
-        scope.declare(Identifier::from("foo"), type_id);
+        scope.declare(Identifier::from("foo"), type_id, Mutability::Immutable);
         assert_eq!(scope.lookup(&Identifier::from("foo")).len(), 2);
     }
 }
@@ -1,12 +1,16 @@
-use parser::Statement;
+use parser::{Statement, Expression as Expr};
 use parser::Statement::*;
 use memory::MemoryBlock;
 
 use super::{
+    Error,
+    Warning,
     OperationsResult,
     declaration,
     assignment,
     while_loop,
+    structure,
+    enumeration,
     expression,
     Target,
 };
@@ -14,15 +18,46 @@ use super::scope::ScopeStack;
 
 pub fn into_operations(scope: &mut ScopeStack, node: Statement) -> OperationsResult {
     match node {
-        Comment(_) => Ok(Vec::new()),
-        Declaration {pattern, type_def, expr} => {
-            declaration::into_operations(scope, pattern, type_def, expr)
+        Comment(..) => Ok(Vec::new()),
+        Declaration {pattern, mutable, type_def, expr, ..} => {
+            declaration::into_operations(scope, pattern, mutable, type_def, expr)
         },
-        Assignment {lhs, expr} => {
+        Assignment {lhs, expr, ..} => {
             assignment::into_operations(scope, lhs, expr)
         },
-        WhileLoop {condition, body} => {
-            while_loop::into_operations(scope, condition, body)
+        FieldAssignment {target, fields, expr, ..} => {
+            assignment::field_into_operations(scope, target, fields, expr)
+        },
+        CompoundAssignment {lhs, fields, operator, expr, span} => {
+            assignment::compound_into_operations(scope, lhs, fields, operator, expr, span)
+        },
+        WhileLoop {condition, body, span} => {
+            while_loop::into_operations(scope, condition, body, span)
+        },
+        Struct {name, fields, ..} => {
+            structure::into_operations(scope, name, fields)
+        },
+        Enum {name, variants, ..} => {
+            enumeration::into_operations(scope, name, variants)
+        },
+        // Functions are declared before the rest of the statements in their block so that they
+        // can be called from anywhere in it, see `block::declare_items`
+        Function {..} | Impl {..} => unreachable!(),
+        // The value of a statement is thrown away, so a statement without any side effects
+        // does nothing at all
+        Expression {expr: Expr::Identifier(name)} => {
+            scope.report_warning(Warning::NoEffect {span: name.span()});
+            scope.mark_read(&name);
+            if scope.lookup(&name).is_empty() {
+                return Err(Error::UnresolvedName {span: name.span(), name});
+            }
+            scope.check_initialized(&name)?;
+            Ok(Vec::new())
+        },
+        Expression {expr: Expr::Number(_, span)} |
+        Expression {expr: Expr::ByteLiteral(_, span)} => {
+            scope.report_warning(Warning::NoEffect {span});
+            Ok(Vec::new())
         },
         Expression {expr} => {
             let unit_type = scope.primitives().unit();
@@ -38,11 +73,13 @@ pub fn into_operations(scope: &mut ScopeStack, node: Statement) -> OperationsRes
 mod tests {
     use super::*;
 
+    use parser::Span;
+
     #[test]
     fn comment() {
         // Make sure comments result in no operations
         let mut scope = ScopeStack::new();
-        let ops = into_operations(&mut scope, Statement::Comment("foo".to_string())).unwrap();
+        let ops = into_operations(&mut scope, Statement::Comment("foo".to_string(), Span::default())).unwrap();
         assert_eq!(ops.len(), 0);
     }
 }
@@ -0,0 +1,118 @@
+use parser::{Identifier, FieldDefinition, TypeDefinition, Expression};
+
+use super::{Error, OperationsResult};
+use super::item_type::{ItemType, Field, FieldType};
+use super::scope::{ScopeStack, ArraySize};
+use super::declaration::resolve_type_id;
+
+/// Declares a struct type so that it can be used anywhere in the block it is declared in
+///
+/// The fields are laid out one after the other in the order they are declared, so a struct uses
+/// exactly as many cells as all of its fields together. A struct cannot contain itself since its
+/// name is only declared after its fields. Types are declared in the order they are written, so
+/// the type of a field must be declared before the struct.
+pub fn into_operations(
+    scope: &mut ScopeStack,
+    name: Identifier,
+    field_defs: Vec<FieldDefinition>,
+) -> OperationsResult {
+    if scope.is_type_declared_here(&name) {
+        return Err(Error::DuplicateDefinition {span: name.span(), name});
+    }
+
+    let mut fields: Vec<Field> = Vec::new();
+    for FieldDefinition {name: field_name, type_def} in field_defs {
+        if fields.iter().any(|field| field.name == field_name) {
+            return Err(Error::DuplicateField {span: field_name.span(), field: field_name});
+        }
+
+        let typ = field_type(scope, &field_name, type_def)?;
+        fields.push(Field {name: field_name, typ});
+    }
+
+    scope.declare_type(name, ItemType::Struct {fields});
+    Ok(Vec::new())
+}
+
+/// Resolves the type of a field
+pub fn field_type(scope: &ScopeStack, name: &Identifier, type_def: TypeDefinition) -> Result<FieldType, Error> {
+    match type_def {
+        TypeDefinition::Name {name: ref type_name} => Ok(FieldType::Value(resolve_type_id(scope, type_name)?)),
+        TypeDefinition::Array {type_def, size: Some(Expression::Number(size, _)), span} if size > 0 => match *type_def {
+            TypeDefinition::Name {name: ref item_name} => Ok(FieldType::Array {
+                item: resolve_type_id(scope, item_name)?,
+                size: size as ArraySize,
+            }),
+            TypeDefinition::Array {..} => Err(Error::UnsupportedArrayType {name: name.clone(), span}),
+        },
+        // Fields are never initialized where they are declared, so their size can never be
+        // inferred
+        TypeDefinition::Array {span, ..} => Err(Error::UnsupportedArrayType {name: name.clone(), span}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codegen::testing::assert_output;
+
+    #[test]
+    fn structs() {
+        assert_output(r#"
+            struct Player { lives: u8, score: u16, name: [u8; 3] }
+            fn describe(player: Player) {
+                stdout.print(player.name, b":", player.lives, b":", player.score, b" ");
+            }
+
+            let mut player: Player = Player { name: b"amy", score: 250, lives: 3 };
+            describe(player);
+            while player.lives > 0 {
+                player.lives = player.lives - 1;
+                player.score = player.score + 100;
+            }
+            player.name = b"bea";
+            describe(player);
+            describe(Player { lives: player.lives + 1, score: 7, name: player.name });
+        "#, b"", b"amy:3:250 bea:0:550 bea:1:7 ");
+    }
+
+    #[test]
+    fn nested_fields() {
+        assert_output(r#"
+            struct Point { x: u8, y: u8 }
+            struct Line { start: Point, end: Point, name: [u8; 2] }
+
+            let mut line: Line = Line { start: Point { x: 1, y: 2 }, end: Point { x: 3, y: 4 }, name: b"ab" };
+            line.end.y = line.start.x + 6;
+            line.start = line.end;
+            line.end.x = 9;
+            stdout.print(line.start.x, line.start.y, line.end.x, line.end.y, line.name);
+        "#, b"", b"3797ab");
+    }
+
+    #[test]
+    fn fields_assigned_one_at_a_time() {
+        assert_output(r#"
+            struct Point { x: u8, y: u8 }
+            struct Line { start: Point, end: Point }
+
+            let mut line: Line;
+            line.start.x = 1;
+            stdout.print(line.start.x);
+            let mut i: u8 = 0;
+            while i < 2 {
+                line.end.y = i;
+                i += 1;
+            }
+            if i == 2 {
+                line.end = Point { x: 4, y: 5 };
+            }
+            else {
+                line.end.x = 6;
+                line.end.y = 7;
+            }
+            line.start.y = line.end.y - 3;
+            let copy: Line = line;
+            stdout.print(copy.start.x, copy.start.y, copy.end.x, copy.end.y);
+        "#, b"", b"11245");
+    }
+}
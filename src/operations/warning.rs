@@ -0,0 +1,281 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use parser::{Identifier, Span};
+use diagnostics::{Diagnostic, Level};
+
+/// Code that compiles but is probably a mistake
+///
+/// Unlike errors, warnings do not stop the program from being compiled. Every warning belongs to
+/// a lint which decides whether it is reported at all (see `Lints`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// A variable declared with `let` is never read
+    UnusedVariable {
+        name: Identifier,
+        span: Span,
+    },
+
+    /// A variable declared with `let mut` is never assigned to
+    UnusedMut {
+        name: Identifier,
+        span: Span,
+    },
+
+    /// A statement comes after a `while true` loop and will never run
+    UnreachableCode {
+        /// The span of the infinite loop that comes before the statement
+        infinite_loop: Span,
+        span: Span,
+    },
+
+    /// An expression statement that does nothing, like `a;`
+    NoEffect {
+        span: Span,
+    },
+
+    /// The condition of an `if` is always `value`
+    ConstantCondition {
+        value: bool,
+        span: Span,
+    },
+}
+
+impl Warning {
+    /// Returns the lint that this warning belongs to
+    pub fn lint(&self) -> Lint {
+        use self::Warning::*;
+        match *self {
+            UnusedVariable {..} => Lint::UnusedVariables,
+            UnusedMut {..} => Lint::UnusedMut,
+            UnreachableCode {..} => Lint::UnreachableCode,
+            NoEffect {..} => Lint::NoEffect,
+            ConstantCondition {..} => Lint::ConstantCondition,
+        }
+    }
+
+    /// Returns the location of the code that caused this warning
+    pub fn span(&self) -> Span {
+        use self::Warning::*;
+        match *self {
+            UnusedVariable {span, ..} |
+            UnusedMut {span, ..} |
+            UnreachableCode {span, ..} |
+            NoEffect {span} |
+            ConstantCondition {span, ..} => span,
+        }
+    }
+
+    /// Converts this warning into a diagnostic that can be rendered with the source code
+    pub fn diagnostic(&self) -> Diagnostic {
+        use self::Warning::*;
+        match *self {
+            UnusedVariable {ref name, span} => {
+                Diagnostic::warning(format!("unused variable: `{}`", name), span)
+                    .with_label(format!("help: if this is intentional, prefix it with an underscore: `_{}`", name))
+            },
+            UnusedMut {ref name, span} => {
+                Diagnostic::warning("variable does not need to be mutable", span)
+                    .with_label(format!("`{}` is never assigned to", name))
+                    .with_help("remove the `mut` from its declaration")
+            },
+            UnreachableCode {infinite_loop, span} => {
+                Diagnostic::warning("unreachable statement", span)
+                    .with_label("unreachable statement")
+                    .with_note(format!("any code following the infinite loop on line {} is unreachable",
+                        infinite_loop.line))
+            },
+            NoEffect {span} => {
+                Diagnostic::warning("statement has no effect", span)
+                    .with_label("this statement does nothing")
+            },
+            ConstantCondition {value, span} => {
+                Diagnostic::warning(format!("condition is always `{}`", value), span)
+                    .with_label(format!("always `{}`", value))
+                    .with_help(if value {
+                        "remove the `if` and only keep the code that always runs"
+                    }
+                    else {
+                        "remove the code that never runs"
+                    })
+            },
+        }
+    }
+}
+
+/// A kind of warning that can be allowed, warned about or denied as a group
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Lint {
+    UnusedVariables,
+    UnusedMut,
+    UnreachableCode,
+    NoEffect,
+    ConstantCondition,
+}
+
+impl Lint {
+    /// Every lint in the order that they are documented
+    pub const ALL: [Lint; 5] = [
+        Lint::UnusedVariables,
+        Lint::UnusedMut,
+        Lint::UnreachableCode,
+        Lint::NoEffect,
+        Lint::ConstantCondition,
+    ];
+
+    /// The name used to refer to this lint from the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "unused_variables",
+            Lint::UnusedMut => "unused_mut",
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::NoEffect => "no_effect",
+            Lint::ConstantCondition => "constant_condition",
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Lint {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Lint::ALL.iter().cloned().find(|lint| lint.name() == s).ok_or(())
+    }
+}
+
+/// What happens when a warning from a lint is found
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LintLevel {
+    /// The warning is not reported
+    Allow,
+    /// The warning is reported but the program still compiles
+    Warn,
+    /// The warning is reported as an error
+    Deny,
+}
+
+impl LintLevel {
+    /// The command line flag that sets this level
+    fn flag(self) -> &'static str {
+        match self {
+            LintLevel::Allow => "-A",
+            LintLevel::Warn => "-W",
+            LintLevel::Deny => "-D",
+        }
+    }
+}
+
+/// The level of every lint, every lint is at `LintLevel::Warn` by default
+#[derive(Debug, Default, Clone)]
+pub struct Lints {
+    /// Only lints that were set to a level are stored
+    levels: HashMap<Lint, LintLevel>,
+}
+
+impl Lints {
+    /// The name that refers to every lint at once
+    pub const ALL_WARNINGS: &'static str = "warnings";
+
+    pub fn new() -> Lints {
+        Default::default()
+    }
+
+    /// Sets the level of the lint with the given name
+    /// `warnings` sets the level of every lint at once
+    /// Returns false if there is no lint with that name
+    pub fn set(&mut self, name: &str, level: LintLevel) -> bool {
+        if name == Self::ALL_WARNINGS {
+            for &lint in &Lint::ALL {
+                self.levels.insert(lint, level);
+            }
+            return true;
+        }
+
+        match name.parse() {
+            Ok(lint) => {
+                self.levels.insert(lint, level);
+                true
+            },
+            Err(()) => false,
+        }
+    }
+
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        self.levels.get(&lint).cloned().unwrap_or(LintLevel::Warn)
+    }
+
+    /// Converts the given warnings into diagnostics based on the level of their lint
+    ///
+    /// Allowed warnings are left out and denied warnings become errors. The first diagnostic from
+    /// each lint notes how that lint was enabled so that it is clear how to change its level.
+    pub fn diagnostics(&self, warnings: &[Warning]) -> Vec<Diagnostic> {
+        let mut noted = HashSet::new();
+        warnings.iter().filter_map(|warning| {
+            let lint = warning.lint();
+            let (level, set) = match self.levels.get(&lint) {
+                Some(&LintLevel::Allow) => return None,
+                Some(&LintLevel::Warn) => (Level::Warning, true),
+                Some(&LintLevel::Deny) => (Level::Error, true),
+                None => (Level::Warning, false),
+            };
+
+            let mut diagnostic = warning.diagnostic();
+            diagnostic.level = level;
+            if noted.insert(lint) {
+                diagnostic = diagnostic.with_note(if set {
+                    format!("requested on the command line with `{} {}`", self.level(lint).flag(), lint)
+                }
+                else {
+                    format!("`{} {}` on by default", LintLevel::Warn.flag(), lint)
+                });
+            }
+            Some(diagnostic)
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lint_names() {
+        for &lint in &Lint::ALL {
+            assert_eq!(lint.name().parse(), Ok(lint));
+        }
+        assert_eq!("unused".parse::<Lint>(), Err(()));
+    }
+
+    #[test]
+    fn lint_levels() {
+        let warnings = [
+            Warning::NoEffect {span: Span::default()},
+            Warning::NoEffect {span: Span::default()},
+            Warning::UnusedMut {name: Identifier::from("a"), span: Span::default()},
+            Warning::ConstantCondition {value: true, span: Span::default()},
+        ];
+
+        let mut lints = Lints::new();
+        assert!(lints.set("unused_mut", LintLevel::Allow));
+        assert!(lints.set("constant_condition", LintLevel::Deny));
+        assert!(!lints.set("unused", LintLevel::Deny));
+
+        let diagnostics = lints.diagnostics(&warnings);
+        let levels: Vec<_> = diagnostics.iter().map(|diagnostic| diagnostic.level).collect();
+        assert_eq!(levels, vec![Level::Warning, Level::Warning, Level::Error]);
+        // Only the first diagnostic from each lint explains how it was enabled
+        assert_eq!(diagnostics[0].notes, vec!["`-W no_effect` on by default".to_owned()]);
+        assert!(diagnostics[1].notes.is_empty());
+        assert_eq!(diagnostics[2].notes, vec!["requested on the command line with `-D constant_condition`".to_owned()]);
+
+        assert!(lints.set("warnings", LintLevel::Allow));
+        assert!(lints.diagnostics(&warnings).is_empty());
+    }
+}
@@ -1,28 +1,45 @@
 use std::iter::once;
 
-use parser::{Expression, Block};
+use parser::{Expression, Block, Span};
 use memory::MemoryBlock;
 
-use super::{Operation, OperationsResult, expression, Target, block};
+use super::{Operation, OperationsResult, InitializationState, expression, Target, block};
 use super::scope::ScopeStack;
 
 pub fn into_operations(
     scope: &mut ScopeStack,
     condition: Expression,
     body: Block,
+    span: Span,
 ) -> OperationsResult {
     let unit_type = scope.primitives().unit();
     let bool_type = scope.primitives().bool();
     let cond_mem = scope.allocate(bool_type);
+    let infinite = expression::constant_condition(scope, &condition) == Some(true);
 
+    // The body is still checked when the condition fails so that its errors are reported too
     let cond_ops = expression::into_operations(scope, condition, Target::TypedBlock {
         type_id: bool_type,
         memory: cond_mem,
-    })?;
+    }).unwrap_or_else(|error| {
+        scope.report_error(error);
+        Vec::new()
+    });
+
+    // The body may not run at all, so anything it initializes may still be uninitialized after
+    // the loop. Nothing after an infinite loop runs, so it does not matter what it initializes.
+    let before = scope.initialization();
+    scope.enter_loop();
     let loop_body = block::into_operations(scope, body, Target::TypedBlock {
         type_id: unit_type,
         memory: MemoryBlock::default(),
-    })?;
+    });
+    scope.exit_loop();
+    let after = scope.initialization();
+    if !infinite {
+        scope.set_initialization(InitializationState::merge_loop(before, after, span));
+    }
+    let loop_body = loop_body?;
 
     // While loops need to evaluate the condition both before the loop and at the end
     // of the loop body
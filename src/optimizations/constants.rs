@@ -0,0 +1,205 @@
+use ir::{Ops, Op, Offset};
+use interpreter::{CellWidth, OverflowBehaviour};
+
+use super::known_zeros::{KnownZeros, loop_entry};
+use super::ir::net_amount;
+
+/// The furthest a counter cell can be from the cell being changed
+const MAX_COUNTER_DISTANCE: Offset = 4;
+
+/// Replaces additions of large constants with shorter multiplication loops
+///
+/// Adding 72 to a cell takes 72 `+` instructions, but it can also be done in 24 by adding 8 to
+/// a counter cell and then adding 9 to the cell once for every time the counter is decremented:
+/// `>++++++++[-<+++++++++>]`. The counter must be a nearby cell that is known to be zero, and it
+/// is zero again once the loop is done. The shortest sequence is chosen for each addition, so
+/// small amounts are left as they are.
+///
+/// Amounts are wrapped modulo the cell width first if cells wrap around, so adding 200 to an
+/// 8-bit cell is done by subtracting 56 instead. When overflowing is an error, the loop never
+/// adds more than the amount since the cell could overflow before the remainder is subtracted.
+pub fn generate_constants(ops: &mut Ops, cell_width: CellWidth, overflow: OverflowBehaviour) {
+    generate_constants_with(ops, KnownZeros::all(), cell_width, overflow);
+}
+
+fn generate_constants_with(ops: &mut Ops, mut zeros: KnownZeros, cell_width: CellWidth, overflow: OverflowBehaviour) {
+    let mut generated = Ops::new();
+    for mut op in ops.drain(..) {
+        if let Op::Loop(ref mut body) = op {
+            let entry = loop_entry(&zeros, body);
+            generate_constants_with(body, entry, cell_width, overflow);
+        }
+
+        if let Op::Add(offset, amount) = op {
+            if let Some(constant) = shortest_constant(offset, amount, &zeros, cell_width, overflow) {
+                for op in constant {
+                    zeros.apply(&op);
+                    generated.push(op);
+                }
+                continue;
+            }
+        }
+
+        zeros.apply(&op);
+        generated.push(op);
+    }
+
+    *ops = generated;
+}
+
+/// Returns a multiplication loop that adds the given amount to the cell at offset if it is
+/// shorter than adding the amount directly
+fn shortest_constant(
+    offset: Offset,
+    amount: i32,
+    zeros: &KnownZeros,
+    cell_width: CellWidth,
+    overflow: OverflowBehaviour,
+) -> Option<Vec<Op>> {
+    let amount = net_amount(amount as i64, cell_width, overflow) as i64;
+    let counter = counter_cell(offset, zeros)?;
+    let distance = (counter - offset).abs() as i64;
+
+    // The loop brackets and the `-` on the counter, as well as moving to the target and back to
+    // the counter before the loop and on every iteration
+    let overhead = 3 + 4 * distance;
+
+    let mut best = None;
+    let mut best_cost = amount.abs();
+    let mut count = 2;
+    while count * count <= 4 * amount.abs() {
+        let factor = amount / count;
+        // The factor can be rounded either way, the remainder is added afterwards
+        let factors = match overflow {
+            OverflowBehaviour::Wrap => vec![factor, factor + amount.signum()],
+            OverflowBehaviour::Error => vec![factor],
+        };
+        for factor in factors {
+            let remainder = amount - count * factor;
+            let cost = count + factor.abs() + remainder.abs() + overhead;
+            if factor != 0 && cost < best_cost {
+                best = Some((count, factor, remainder));
+                best_cost = cost;
+            }
+        }
+        count += 1;
+    }
+
+    best.map(|(count, factor, remainder)| {
+        let mut ops = vec![
+            Op::Add(counter, count as i32),
+            Op::MulAdd {src: counter, dst: offset, factor: factor as i32},
+            Op::SetZero(counter),
+        ];
+        if remainder != 0 {
+            ops.push(Op::Add(offset, remainder as i32));
+        }
+        ops
+    })
+}
+
+/// Finds the closest cell to the given offset that is known to be zero
+///
+/// Cells to the left of both the current cell and the target are never used since they may be
+/// before the start of the tape.
+fn counter_cell(offset: Offset, zeros: &KnownZeros) -> Option<Offset> {
+    let leftmost = offset.min(0);
+    (1..MAX_COUNTER_DISTANCE + 1)
+        .flat_map(|distance| vec![offset + distance, offset - distance])
+        .find(|&cell| cell >= leftmost && zeros.is_zero(cell))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(ops: Vec<Op>, cell_width: CellWidth) -> Vec<Op> {
+        generate_for(ops, cell_width, OverflowBehaviour::Wrap)
+    }
+
+    fn generate_for(ops: Vec<Op>, cell_width: CellWidth, overflow: OverflowBehaviour) -> Vec<Op> {
+        let mut ops = ops.into();
+        generate_constants(&mut ops, cell_width, overflow);
+        ops.to_vec()
+    }
+
+    #[test]
+    fn uses_multiplication_for_large_constants() {
+        assert_eq!(generate(vec![Op::Add(0, 72), Op::Write(0)], CellWidth::U8), vec![
+            Op::Add(1, 8),
+            Op::MulAdd {src: 1, dst: 0, factor: 9},
+            Op::SetZero(1),
+            Op::Write(0),
+        ]);
+
+        // 101 is prime so there is always something left over
+        assert_eq!(generate(vec![Op::Add(0, 101)], CellWidth::U8), vec![
+            Op::Add(1, 10),
+            Op::MulAdd {src: 1, dst: 0, factor: 10},
+            Op::SetZero(1),
+            Op::Add(0, 1),
+        ]);
+    }
+
+    #[test]
+    fn never_overshoots_when_overflowing_is_an_error() {
+        // Rounding 200 / 8 up would take the cell past 200 before the remainder is subtracted
+        assert_eq!(generate_for(vec![Op::Add(0, 200)], CellWidth::U8, OverflowBehaviour::Error), vec![
+            Op::Add(1, 10),
+            Op::MulAdd {src: 1, dst: 0, factor: 20},
+            Op::SetZero(1),
+        ]);
+        assert_eq!(generate_for(vec![Op::Add(0, 101)], CellWidth::U8, OverflowBehaviour::Error), vec![
+            Op::Add(1, 10),
+            Op::MulAdd {src: 1, dst: 0, factor: 10},
+            Op::SetZero(1),
+            Op::Add(0, 1),
+        ]);
+    }
+
+    #[test]
+    fn wraps_large_amounts() {
+        assert_eq!(generate(vec![Op::Add(0, 200)], CellWidth::U8), vec![
+            Op::Add(1, 7),
+            Op::MulAdd {src: 1, dst: 0, factor: -8},
+            Op::SetZero(1),
+        ]);
+
+        // Wider cells do not wrap at 256
+        assert_eq!(generate(vec![Op::Add(0, 200)], CellWidth::U16), vec![
+            Op::Add(1, 10),
+            Op::MulAdd {src: 1, dst: 0, factor: 20},
+            Op::SetZero(1),
+        ]);
+    }
+
+    #[test]
+    fn keeps_small_constants() {
+        let ops = vec![Op::Add(0, 10), Op::Add(1, -12)];
+        assert_eq!(generate(ops.clone(), CellWidth::U8), ops);
+    }
+
+    #[test]
+    fn counter_must_be_zero() {
+        // Cells 1 and 2 are not zero, so the counter is placed further away
+        assert_eq!(generate(vec![
+            Op::Read(1),
+            Op::Read(2),
+            Op::Add(0, 72),
+        ], CellWidth::U8), vec![
+            Op::Read(1),
+            Op::Read(2),
+            Op::Add(3, 8),
+            Op::MulAdd {src: 3, dst: 0, factor: 9},
+            Op::SetZero(3),
+        ]);
+
+        // Nothing is known after a loop that moves the pointer
+        let ops = vec![
+            Op::Read(0),
+            Op::Loop(vec![Op::Move(1), Op::Read(0)].into()),
+            Op::Add(0, 72),
+        ];
+        assert_eq!(generate(ops.clone(), CellWidth::U8), ops);
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashSet;
+
+use ir::{Ops, Op, Offset};
+use interpreter::{CellWidth, OverflowBehaviour};
+
+use super::known_zeros::{KnownZeros, loop_entry};
+
+/// Removes operations that have no effect because the cells they use are known to be zero
+///
+/// This removes `SetZero` operations on cells that are already zero, `MulAdd` operations from a
+/// source that is zero, and loops that can never run because the current cell is zero when
+/// they are reached. The tape is assumed to be all zeros when the program starts.
+///
+/// Only operations that would leave a cell unchanged are removed, so every cell that was zero
+/// before this pass (for example a temporary that was just freed) is still zero afterwards.
+pub fn remove_redundant_zeros(ops: &mut Ops, _: CellWidth, _: OverflowBehaviour) {
+    remove_redundant_zeros_with(ops, KnownZeros::all());
+}
+
+fn remove_redundant_zeros_with(ops: &mut Ops, mut zeros: KnownZeros) {
+    let mut kept = Ops::new();
+    for mut op in ops.drain(..) {
+        let redundant = match op {
+            Op::SetZero(offset) => zeros.is_zero(offset),
+            Op::MulAdd {src, ..} => zeros.is_zero(src),
+            Op::Loop(ref mut body) => {
+                if zeros.is_zero(0) {
+                    true
+                }
+                else {
+                    remove_redundant_zeros_with(body, loop_entry(&zeros, body));
+                    false
+                }
+            },
+            Op::Add(..) | Op::Move(_) | Op::Read(_) | Op::Write(_) => false,
+        };
+
+        if !redundant {
+            zeros.apply(&op);
+            kept.push(op);
+        }
+    }
+
+    *ops = kept;
+}
+
+/// Removes additions and zeroing whose results are never observed because the cell is set to
+/// zero again before it is read
+///
+/// For example, the temporary cell in `Add(1, 5), Write(0), SetZero(1)` is never read, so the
+/// addition to it can be removed. The final `SetZero` is always kept, so cells that are expected
+/// to be zero afterwards still are.
+pub fn remove_dead_stores(ops: &mut Ops, _: CellWidth, _: OverflowBehaviour) {
+    remove_dead_stores_with(ops);
+}
+
+fn remove_dead_stores_with(ops: &mut Ops) {
+    // Cells that will be set to zero before they are read again, relative to the position of
+    // the pointer at the current operation. This is computed going backwards from the end.
+    let mut dead: HashSet<Offset> = HashSet::new();
+    let mut keep = vec![true; ops.len()];
+
+    // The SetZero at the end of a run of MulAdds is what allows them to be lowered into a loop
+    let ends_mul_group: Vec<_> = ops.iter().enumerate().map(|(i, op)| match *op {
+        Op::SetZero(offset) => i > 0 && matches!(ops[i - 1], Op::MulAdd {src, ..} if src == offset),
+        _ => false,
+    }).collect();
+
+    for (i, op) in ops.iter_mut().enumerate().rev() {
+        match *op {
+            Op::SetZero(offset) => {
+                // Zeroing a cell that will be zeroed again before it is read does nothing
+                keep[i] = dead.insert(offset) || ends_mul_group[i];
+            },
+            Op::Add(offset, _) => keep[i] = !dead.contains(&offset),
+            Op::MulAdd {src, dst, ..} => {
+                keep[i] = !dead.contains(&dst);
+                if keep[i] {
+                    dead.remove(&src);
+                }
+            },
+            Op::Read(offset) | Op::Write(offset) => {
+                // A read leaves the cell unchanged at EOF on some implementations, so it counts
+                // as observing the previous value
+                dead.remove(&offset);
+            },
+            Op::Move(amount) => {
+                dead = dead.into_iter().map(|offset| offset + amount).collect();
+            },
+            Op::Loop(ref mut body) => {
+                // The loop may observe any cell, and so can its next iteration
+                remove_dead_stores_with(body);
+                dead.clear();
+            },
+        }
+    }
+
+    let mut keep = keep.into_iter();
+    ops.retain(|_| keep.next().unwrap());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redundant_zeros(ops: Vec<Op>) -> Vec<Op> {
+        let mut ops = ops.into();
+        remove_redundant_zeros(&mut ops, CellWidth::default(), OverflowBehaviour::default());
+        ops.to_vec()
+    }
+
+    fn dead_stores(ops: Vec<Op>) -> Vec<Op> {
+        let mut ops = ops.into();
+        remove_dead_stores(&mut ops, CellWidth::default(), OverflowBehaviour::default());
+        ops.to_vec()
+    }
+
+    #[test]
+    fn removes_zeroing_of_zero_cells() {
+        assert_eq!(redundant_zeros(vec![
+            Op::SetZero(0),
+            Op::Add(1, 2),
+            Op::SetZero(1),
+            Op::SetZero(1),
+            Op::Read(2),
+            Op::SetZero(2),
+        ]), vec![
+            Op::Add(1, 2),
+            Op::SetZero(1),
+            Op::Read(2),
+            Op::SetZero(2),
+        ]);
+    }
+
+    #[test]
+    fn removes_loops_and_mul_adds_on_zero_cells() {
+        assert_eq!(redundant_zeros(vec![
+            Op::Loop(vec![Op::Read(0)].into()),
+            Op::MulAdd {src: 0, dst: 1, factor: 1},
+            Op::SetZero(0),
+            Op::Read(0),
+            Op::Loop(vec![Op::Add(0, -1), Op::SetZero(2)].into()),
+            Op::Loop(vec![Op::Read(0)].into()),
+        ]), vec![
+            Op::Read(0),
+            Op::Loop(vec![Op::Add(0, -1)].into()),
+        ]);
+    }
+
+    #[test]
+    fn knowledge_inside_loops() {
+        let ops = vec![
+            Op::Read(0),
+            // Cell 1 is changed in the body, so it is not known to be zero on later iterations
+            // but cell 2 is only ever set to zero so it still is
+            Op::Loop(vec![Op::SetZero(1), Op::SetZero(2), Op::Add(1, 1), Op::Read(0)].into()),
+        ];
+        assert_eq!(redundant_zeros(ops), vec![
+            Op::Read(0),
+            Op::Loop(vec![Op::SetZero(1), Op::Add(1, 1), Op::Read(0)].into()),
+        ]);
+
+        // After a loop that moves the pointer, nothing is known except the current cell
+        let ops = vec![
+            Op::Read(0),
+            Op::Loop(vec![Op::Move(1)].into()),
+            Op::SetZero(1),
+            Op::SetZero(0),
+        ];
+        assert_eq!(redundant_zeros(ops), vec![
+            Op::Read(0),
+            Op::Loop(vec![Op::Move(1)].into()),
+            Op::SetZero(1),
+        ]);
+    }
+
+    #[test]
+    fn removes_dead_stores() {
+        assert_eq!(dead_stores(vec![
+            Op::Add(1, 5),
+            Op::MulAdd {src: 0, dst: 1, factor: 2},
+            Op::SetZero(0),
+            Op::Write(2),
+            Op::SetZero(1),
+            Op::Move(1),
+            Op::SetZero(0),
+        ]), vec![
+            Op::SetZero(0),
+            Op::Write(2),
+            Op::Move(1),
+            Op::SetZero(0),
+        ]);
+    }
+
+    #[test]
+    fn keeps_mul_groups_intact() {
+        let ops = vec![
+            Op::Read(0),
+            Op::MulAdd {src: 0, dst: 1, factor: 3},
+            Op::SetZero(0),
+            Op::Write(1),
+            Op::SetZero(0),
+        ];
+        assert_eq!(dead_stores(ops.clone()), ops);
+    }
+
+    #[test]
+    fn keeps_observed_stores() {
+        let ops = vec![
+            Op::Add(1, 5),
+            Op::Write(1),
+            Op::Add(2, 1),
+            Op::MulAdd {src: 2, dst: 3, factor: 1},
+            Op::SetZero(2),
+            Op::Add(0, 1),
+            Op::Loop(vec![Op::Add(0, -1)].into()),
+            Op::SetZero(0),
+            Op::SetZero(3),
+        ];
+        assert_eq!(dead_stores(ops.clone()), ops);
+    }
+}
@@ -0,0 +1,547 @@
+use ir::{Ops, Op, Offset};
+use interpreter::{CellWidth, OverflowBehaviour};
+
+use super::{Optimize, OptimizationLevel};
+use super::known_zeros::KnownZeros;
+use super::dataflow::{remove_redundant_zeros, remove_dead_stores};
+use super::constants::generate_constants;
+
+impl Optimize for Ops {
+    fn optimize(self, level: OptimizationLevel) -> Ops {
+        self.optimize_for(level, CellWidth::default(), OverflowBehaviour::default())
+    }
+}
+
+impl Ops {
+    /// Optimizes these operations for a brainfuck implementation with cells of the given width
+    /// that do the given thing when they overflow
+    ///
+    /// When cells wrap around, amounts are wrapped to whichever has the smallest magnitude and
+    /// loops that wrap a cell back around to zero are recognized. When overflowing is an error,
+    /// no cell is ever taken past the end of its range unless the original operations did so.
+    pub fn optimize_for(mut self, level: OptimizationLevel, cell_width: CellWidth, overflow: OverflowBehaviour) -> Ops {
+        // Passes are grouped and ordered the same way as the passes over instructions
+        let optimizers: Vec<fn(&mut Ops, CellWidth, OverflowBehaviour)> = match level {
+            OptimizationLevel::Off => vec![],
+            OptimizationLevel::L1 => vec![
+                fold_runs,
+                recognize_multiplication_loops,
+                // Recognized loops no longer stop moves from being folded
+                fold_runs,
+                merge_copies,
+                remove_redundant_zeros,
+                remove_dead_stores,
+                generate_constants,
+            ],
+            OptimizationLevel::L2 => vec![
+                fold_runs,
+                recognize_multiplication_loops,
+                fold_runs,
+                merge_copies,
+                remove_redundant_zeros,
+                remove_dead_stores,
+                generate_constants,
+                truncate_no_side_effects,
+            ],
+        };
+
+        for optimize in optimizers {
+            optimize(&mut self, cell_width, overflow);
+        }
+
+        self
+    }
+}
+
+/// Folds runs of additions and moves into their net effect and replaces clear loops like `[-]`
+/// with a single `SetZero`
+///
+/// Moves are pushed forward past every operation other than loops by adjusting the offsets of
+/// the operations they pass. This leaves a single move before each loop and at the end of each
+/// loop body. Additions to the same cell within a run of consecutive additions are then combined
+/// and wrapped to the smallest amount that has the same effect if cells wrap around.
+fn fold_runs(ops: &mut Ops, cell_width: CellWidth, overflow: OverflowBehaviour) {
+    let mut folded = Ops::new();
+    let mut pending: Offset = 0;
+
+    for op in ops.drain(..) {
+        match op {
+            Op::Move(amount) => pending += amount,
+            Op::Add(offset, amount) => add(&mut folded, offset + pending, amount, cell_width, overflow),
+            Op::SetZero(offset) => folded.push(Op::SetZero(offset + pending)),
+            Op::MulAdd {src, dst, factor} => folded.push(Op::MulAdd {
+                src: src + pending,
+                dst: dst + pending,
+                factor,
+            }),
+            Op::Read(offset) => folded.push(Op::Read(offset + pending)),
+            Op::Write(offset) => folded.push(Op::Write(offset + pending)),
+            Op::Loop(mut body) => {
+                flush_move(&mut folded, &mut pending);
+
+                fold_runs(&mut body, cell_width, overflow);
+                if is_clear_loop(&body, overflow) {
+                    folded.push(Op::SetZero(0));
+                }
+                else {
+                    folded.push(Op::Loop(body));
+                }
+            },
+        }
+    }
+    flush_move(&mut folded, &mut pending);
+
+    *ops = folded;
+}
+
+fn flush_move(ops: &mut Ops, pending: &mut Offset) {
+    if *pending != 0 {
+        ops.push(Op::Move(*pending));
+        *pending = 0;
+    }
+}
+
+/// Adds the given amount to the cell at offset, combining it with any addition to the same cell
+/// in the run of additions at the end of ops
+fn add(ops: &mut Ops, offset: Offset, amount: i32, cell_width: CellWidth, overflow: OverflowBehaviour) {
+    let run_start = ops.iter().rposition(|op| !matches!(*op, Op::Add(..))).map_or(0, |i| i + 1);
+    let existing = ops[run_start..].iter().position(|op| matches!(*op, Op::Add(o, _) if o == offset));
+
+    match existing {
+        Some(i) => {
+            let index = run_start + i;
+            let total = match ops[index] {
+                Op::Add(_, previous) => net_amount(previous as i64 + amount as i64, cell_width, overflow),
+                _ => unreachable!(),
+            };
+
+            if total == 0 {
+                ops.remove(index);
+            }
+            else {
+                ops[index] = Op::Add(offset, total);
+            }
+        },
+        None => {
+            let amount = net_amount(amount as i64, cell_width, overflow);
+            if amount != 0 {
+                ops.push(Op::Add(offset, amount));
+            }
+        },
+    }
+}
+
+/// Returns the amount with the smallest magnitude that has the same effect as adding the given
+/// amount to a cell of the given width
+pub fn wrap(amount: i64, cell_width: CellWidth) -> i32 {
+    let modulus = cell_width.max_value() as i64 + 1;
+    let amount = amount.rem_euclid(modulus);
+    if amount >= modulus / 2 {
+        (amount - modulus) as i32
+    }
+    else {
+        amount as i32
+    }
+}
+
+/// Returns the amount that has the same effect as adding the given amount to a cell of the given
+/// width, which is only wrapped if the cell wraps around when it overflows
+pub fn net_amount(amount: i64, cell_width: CellWidth, overflow: OverflowBehaviour) -> i32 {
+    match overflow {
+        OverflowBehaviour::Wrap => wrap(amount, cell_width),
+        OverflowBehaviour::Error => amount as i32,
+    }
+}
+
+/// Returns true if the given loop body always sets the current cell to zero and does nothing else
+///
+/// Adding an odd amount to a wrapping cell will eventually reach zero no matter what value the
+/// cell starts at. When overflowing is an error, only `[-]` reaches zero without overflowing.
+fn is_clear_loop(body: &Ops, overflow: OverflowBehaviour) -> bool {
+    match (body.as_slice(), overflow) {
+        ([Op::Add(0, amount)], OverflowBehaviour::Wrap) => amount % 2 != 0,
+        ([Op::Add(0, -1)], OverflowBehaviour::Error) => true,
+        _ => false,
+    }
+}
+
+/// Replaces multiplication loops like `[->+>++<<]` with `MulAdd` operations followed by a
+/// `SetZero` of the loop counter
+///
+/// A multiplication loop is a loop whose body only adds constants to cells without moving the
+/// pointer, and adds exactly 1 or -1 to the current cell. The number of iterations of such a loop
+/// is known from the value of the current cell when the loop starts. This pass expects runs to
+/// have already been folded.
+///
+/// A loop that adds 1 to its counter only stops once the counter wraps around, so it is not a
+/// multiplication loop when overflowing is an error.
+fn recognize_multiplication_loops(ops: &mut Ops, cell_width: CellWidth, overflow: OverflowBehaviour) {
+    let mut recognized = Ops::new();
+    for op in ops.drain(..) {
+        match op {
+            Op::Loop(mut body) => {
+                recognize_multiplication_loops(&mut body, cell_width, overflow);
+                match multiplication_loop(&body, cell_width, overflow) {
+                    Some(mul_adds) => recognized.extend(mul_adds),
+                    None => recognized.push(Op::Loop(body)),
+                }
+            },
+            op => recognized.push(op),
+        }
+    }
+
+    *ops = recognized;
+}
+
+/// Returns the MulAdd and SetZero operations equivalent to the given loop body if it is a
+/// multiplication loop
+fn multiplication_loop(body: &Ops, cell_width: CellWidth, overflow: OverflowBehaviour) -> Option<Ops> {
+    let mut counter = None;
+    for op in body.iter() {
+        match *op {
+            Op::Add(0, amount) => counter = Some(amount),
+            Op::Add(..) => {},
+            _ => return None,
+        }
+    }
+
+    // Adding 1 instead of subtracting 1 runs the loop until the counter wraps back around to
+    // zero, which is the same as multiplying by the negated value of the counter
+    let sign = match (counter, overflow) {
+        (Some(-1), _) => 1,
+        (Some(1), OverflowBehaviour::Wrap) => -1,
+        _ => return None,
+    };
+
+    let mut ops: Ops = body.iter().filter_map(|op| match *op {
+        Op::Add(0, _) => None,
+        Op::Add(dst, amount) => Some(Op::MulAdd {
+            src: 0,
+            dst,
+            factor: net_amount(sign * amount as i64, cell_width, overflow),
+        }),
+        _ => unreachable!(),
+    }).collect();
+    ops.push(Op::SetZero(0));
+
+    Some(ops)
+}
+
+/// A run of MulAdd operations from the same source cell followed by a SetZero of that source.
+/// This is what a multiplication loop is represented as in the IR.
+struct MulGroup {
+    src: Offset,
+    dsts: Vec<(Offset, i32)>,
+    /// The number of operations that make up the group
+    len: usize,
+}
+
+impl MulGroup {
+    fn into_ops(self) -> Vec<Op> {
+        let src = self.src;
+        self.dsts.into_iter()
+            .map(|(dst, factor)| Op::MulAdd {src, dst, factor})
+            .chain(Some(Op::SetZero(src)))
+            .collect()
+    }
+
+    /// Returns the factor for the given destination if it appears exactly once in the group
+    fn factor(&self, dst: Offset) -> Option<i32> {
+        let mut factors = self.dsts.iter().filter(|&&(d, _)| d == dst);
+        match (factors.next(), factors.next()) {
+            (Some(&(_, factor)), None) => Some(factor),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the group at the start of the given operations, if any
+fn mul_group(ops: &[Op]) -> Option<MulGroup> {
+    let src = match ops.first() {
+        Some(&Op::MulAdd {src, ..}) => src,
+        _ => return None,
+    };
+
+    let dsts: Vec<_> = ops.iter().map_while(|op| match *op {
+        Op::MulAdd {src: s, dst, factor} if s == src && dst != src => Some((dst, factor)),
+        _ => None,
+    }).collect();
+
+    match ops.get(dsts.len()) {
+        Some(&Op::SetZero(s)) if s == src => Some(MulGroup {src, len: dsts.len() + 1, dsts}),
+        _ => None,
+    }
+}
+
+/// Merges copies of the same cell that happen one after the other into a single loop
+///
+/// Copying a cell `x` leaves two multiplication loops behind: one that moves `x` into its
+/// targets and a temporary cell, and one that restores `x` by moving the temporary back.
+/// When the next copy of `x` comes right after that, its targets can be added by the restore
+/// loop instead since the temporary cell holds the same value that `x` is restored to.
+///
+/// ```text
+/// x[-a+t+]t[-x+] x[-b+u+]u[-x+]   becomes   x[-a+t+]t[-x+b+]
+/// ```
+///
+/// This requires `u` to be zero before the second copy, which is true when it is the same cell
+/// as `t` or is otherwise known to be zero.
+fn merge_copies(ops: &mut Ops, _: CellWidth, _: OverflowBehaviour) {
+    merge_copies_with(ops, KnownZeros::all());
+}
+
+fn merge_copies_with(ops: &mut Ops, mut zeros: KnownZeros) {
+    let mut i = 0;
+    while i < ops.len() {
+        if let Some((len, merged)) = merge_copy(&ops[i..], &zeros) {
+            ops.splice(i..i + len, merged);
+            // Try again in case the next copy can be merged as well
+            continue;
+        }
+
+        if let Op::Loop(ref mut body) = ops[i] {
+            merge_copies_with(body, KnownZeros::none());
+        }
+        zeros.apply(&ops[i]);
+        i += 1;
+    }
+}
+
+/// Attempts to merge a restore loop at the start of ops with the copy that follows it. Returns
+/// the number of operations replaced and the operations to replace them with.
+fn merge_copy(ops: &[Op], zeros: &KnownZeros) -> Option<(usize, Vec<Op>)> {
+    let restore = mul_group(ops)?;
+    let copy = mul_group(&ops[restore.len..])?;
+    let copy_back = mul_group(&ops[restore.len + copy.len..])?;
+
+    let temp = restore.src;
+    let source = copy.src;
+    let copy_temp = copy_back.src;
+
+    // The restore loop must only be restoring source, which must have been moved out already
+    if restore.factor(source) != Some(1) || !zeros.is_zero(source) {
+        return None;
+    }
+    // The copy must go through a temporary that starts at zero and is moved back into source
+    if copy.factor(copy_temp) != Some(1) || copy_back.dsts != [(source, 1)] {
+        return None;
+    }
+    if copy_temp != temp && (!zeros.is_zero(copy_temp) || restore.factor(copy_temp).is_some()) {
+        return None;
+    }
+
+    let targets: Vec<_> = copy.dsts.iter().cloned().filter(|&(dst, _)| dst != copy_temp).collect();
+    if targets.iter().any(|&(dst, _)| dst == temp) {
+        return None;
+    }
+
+    let len = restore.len + copy.len + copy_back.len;
+    let mut merged = restore;
+    merged.dsts.extend(targets);
+    Some((len, merged.into_ops()))
+}
+
+/// Removes operations from the end of the program which do not have any side effects.
+///
+/// This is the same as the pass with the same name that runs on instructions. Loops that
+/// contain a read or write anywhere in their body are kept intact.
+fn truncate_no_side_effects(ops: &mut Ops, _: CellWidth, _: OverflowBehaviour) {
+    while ops.last().is_some_and(|op| !op.has_side_effects()) {
+        ops.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn optimize(ops: Vec<Op>) -> Vec<Op> {
+        Ops::from(ops).optimize(OptimizationLevel::L1).to_vec()
+    }
+
+    #[test]
+    fn folds_mixed_runs() {
+        // +++++-- >>>< +>+<-
+        assert_eq!(optimize(vec![
+            Op::Add(0, 1), Op::Add(0, 1), Op::Add(0, 1), Op::Add(0, 1), Op::Add(0, 1),
+            Op::Add(0, -1), Op::Add(0, -1),
+            Op::Move(1), Op::Move(1), Op::Move(1), Op::Move(-1),
+            Op::Add(0, 1), Op::Move(1), Op::Add(0, 1), Op::Move(-1), Op::Add(0, -1),
+        ]), vec![
+            Op::Add(0, 3),
+            Op::Add(3, 1),
+            Op::Move(2),
+        ]);
+    }
+
+    #[test]
+    fn wraps_modulo_cell_width() {
+        assert_eq!(optimize(vec![Op::Add(0, 255), Op::Add(1, 200), Op::Add(1, 56)]), vec![
+            Op::Add(0, -1),
+        ]);
+
+        // Larger amounts would be turned into multiplication loops by the full pipeline
+        let mut ops: Ops = vec![Op::Add(0, 255), Op::Add(0, 1)].into();
+        fold_runs(&mut ops, CellWidth::U16, OverflowBehaviour::Wrap);
+        assert_eq!(ops.to_vec(), vec![
+            Op::Add(0, 256),
+        ]);
+    }
+
+    #[test]
+    fn does_not_wrap_when_overflowing_is_an_error() {
+        let mut ops: Ops = vec![
+            Op::Add(0, 200), Op::Add(1, 255), Op::Add(1, 1),
+            Op::Loop(vec![Op::Add(0, 1)].into()),
+            Op::Loop(vec![Op::Add(0, -1)].into()),
+        ].into();
+        fold_runs(&mut ops, CellWidth::U8, OverflowBehaviour::Error);
+        assert_eq!(ops.to_vec(), vec![
+            Op::Add(0, 200),
+            Op::Add(1, 256),
+            Op::Loop(vec![Op::Add(0, 1)].into()),
+            Op::SetZero(0),
+        ]);
+    }
+
+    #[test]
+    fn only_counts_down_when_overflowing_is_an_error() {
+        // [-<+>] [+<->]
+        let mut ops: Ops = vec![
+            Op::Loop(vec![Op::Add(0, -1), Op::Add(-1, 1)].into()),
+            Op::Loop(vec![Op::Add(0, 1), Op::Add(-1, -1)].into()),
+        ].into();
+        recognize_multiplication_loops(&mut ops, CellWidth::U8, OverflowBehaviour::Error);
+        assert_eq!(ops.to_vec(), vec![
+            Op::MulAdd {src: 0, dst: -1, factor: 1},
+            Op::SetZero(0),
+            Op::Loop(vec![Op::Add(0, 1), Op::Add(-1, -1)].into()),
+        ]);
+    }
+
+    #[test]
+    fn moves_are_kept_before_loops_and_side_effects_are_ordered() {
+        assert_eq!(optimize(vec![
+            Op::Move(2), Op::Write(0), Op::Move(-1), Op::Add(0, 1),
+            Op::Loop(vec![Op::Add(0, -1), Op::Move(3), Op::Move(-1)].into()),
+            Op::Read(0),
+        ]), vec![
+            Op::Write(2),
+            Op::Add(1, 1),
+            Op::Move(1),
+            Op::Loop(vec![Op::Add(0, -1), Op::Move(2)].into()),
+            Op::Read(0),
+        ]);
+    }
+
+    #[test]
+    fn recognizes_multiplication_loops() {
+        // ,[->+>++<<] >[+<--->]
+        assert_eq!(optimize(vec![
+            Op::Read(0),
+            Op::Loop(vec![
+                Op::Add(0, -1), Op::Move(1), Op::Add(0, 1), Op::Move(1), Op::Add(0, 2), Op::Move(-2),
+            ].into()),
+            Op::Move(1),
+            Op::Loop(vec![Op::Add(0, 1), Op::Move(-1), Op::Add(0, -3), Op::Move(1)].into()),
+            Op::Write(0),
+        ]), vec![
+            Op::Read(0),
+            Op::MulAdd {src: 0, dst: 1, factor: 1},
+            Op::MulAdd {src: 0, dst: 2, factor: 2},
+            Op::SetZero(0),
+            Op::MulAdd {src: 1, dst: 0, factor: 3},
+            Op::SetZero(1),
+            Op::Write(1),
+            Op::Move(1),
+        ]);
+    }
+
+    #[test]
+    fn keeps_other_loops() {
+        let loops = vec![
+            Op::Read(0),
+            // Moves the pointer
+            Op::Loop(vec![Op::Add(0, -1), Op::Move(1)].into()),
+            Op::Read(0),
+            // Counter changes by more than one
+            Op::Loop(vec![Op::Add(0, -2), Op::Add(1, 1)].into()),
+            Op::Read(0),
+            // Has side effects
+            Op::Loop(vec![Op::Add(0, -1), Op::Write(1)].into()),
+        ];
+        assert_eq!(optimize(loops.clone()), loops);
+    }
+
+    #[test]
+    fn merges_consecutive_copies() {
+        // Two copies of cell 0 into cells 1 and 2 through temporary cells 3 and 4
+        let copy = |target, temp| vec![
+            Op::Loop(vec![Op::Add(target, 1), Op::Add(temp, 1), Op::Add(0, -1)].into()),
+            Op::Move(temp),
+            Op::Loop(vec![Op::Add(-temp, 1), Op::Add(0, -1)].into()),
+            Op::Move(-temp),
+        ];
+        let mut ops = vec![Op::Read(0)];
+        ops.extend(copy(1, 3));
+        ops.extend(copy(2, 4));
+        ops.extend(copy(5, 3));
+
+        assert_eq!(optimize(ops), vec![
+            Op::Read(0),
+            Op::MulAdd {src: 0, dst: 1, factor: 1},
+            Op::MulAdd {src: 0, dst: 3, factor: 1},
+            Op::SetZero(0),
+            Op::MulAdd {src: 3, dst: 0, factor: 1},
+            Op::MulAdd {src: 3, dst: 2, factor: 1},
+            Op::MulAdd {src: 3, dst: 5, factor: 1},
+            Op::SetZero(3),
+        ]);
+    }
+
+    #[test]
+    fn does_not_merge_copies_through_unknown_temporaries() {
+        // Cell 4 is not known to be zero inside the loop, so the second copy cannot be merged
+        let body = vec![
+            Op::MulAdd {src: 1, dst: 2, factor: 1},
+            Op::MulAdd {src: 1, dst: 3, factor: 1},
+            Op::SetZero(1),
+            Op::MulAdd {src: 3, dst: 1, factor: 1},
+            Op::SetZero(3),
+            Op::MulAdd {src: 1, dst: 5, factor: 1},
+            Op::MulAdd {src: 1, dst: 4, factor: 1},
+            Op::SetZero(1),
+            Op::MulAdd {src: 4, dst: 1, factor: 1},
+            Op::SetZero(4),
+            Op::Add(0, -1),
+        ];
+        let ops = vec![Op::Read(0), Op::Loop(body.into())];
+        assert_eq!(optimize(ops.clone()), ops);
+    }
+
+    #[test]
+    fn recognizes_clear_loops() {
+        assert_eq!(optimize(vec![
+            Op::Read(0),
+            Op::Loop(vec![Op::Add(0, -1)].into()),
+            Op::Move(1),
+            Op::Read(0),
+            Op::Loop(vec![Op::Add(0, 1)].into()),
+            Op::Read(0),
+            Op::Loop(vec![Op::Add(0, -1), Op::Add(0, -1), Op::Add(0, -1)].into()),
+            Op::Read(0),
+            // Never reaches zero for odd values
+            Op::Loop(vec![Op::Add(0, 2)].into()),
+        ]), vec![
+            Op::Read(0),
+            Op::SetZero(0),
+            Op::Read(1),
+            Op::SetZero(1),
+            Op::Read(1),
+            Op::SetZero(1),
+            Op::Read(1),
+            Op::Move(1),
+            Op::Loop(vec![Op::Add(0, 2)].into()),
+        ]);
+    }
+}
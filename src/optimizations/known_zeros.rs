@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+
+use ir::{Ops, Op, Offset};
+
+/// Tracks which cells are known to be zero at a given point in a sequence of operations
+///
+/// Cells are identified by their offset from the current position of the pointer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KnownZeros {
+    /// Every cell is zero except for the cells in the set, which may or may not be
+    AllExcept(HashSet<Offset>),
+    /// Only the cells in the set are known to be zero
+    Only(HashSet<Offset>),
+}
+
+impl KnownZeros {
+    /// The state at the start of a program: every cell of the tape starts at zero
+    pub fn all() -> KnownZeros {
+        KnownZeros::AllExcept(HashSet::new())
+    }
+
+    /// Nothing is known about any cell
+    pub fn none() -> KnownZeros {
+        KnownZeros::Only(HashSet::new())
+    }
+
+    /// Returns true if the cell at the given offset is definitely zero
+    pub fn is_zero(&self, offset: Offset) -> bool {
+        match *self {
+            KnownZeros::AllExcept(ref cells) => !cells.contains(&offset),
+            KnownZeros::Only(ref cells) => cells.contains(&offset),
+        }
+    }
+
+    /// Updates the state to reflect what is known after the given operation runs
+    pub fn apply(&mut self, op: &Op) {
+        match *op {
+            Op::Add(offset, _) | Op::Read(offset) => self.set_unknown(offset),
+            Op::MulAdd {dst, ..} => self.set_unknown(dst),
+            Op::SetZero(offset) => self.set_zero(offset),
+            Op::Write(_) => {},
+            Op::Move(amount) => self.shift(amount),
+            Op::Loop(ref body) => {
+                if is_stationary(body) {
+                    // Any cell the body may make non-zero is unknown, everything else is as it was
+                    for offset in nonzero_writes(body) {
+                        self.set_unknown(offset);
+                    }
+                }
+                else {
+                    // The pointer could be anywhere relative to where it was before the loop
+                    *self = KnownZeros::none();
+                }
+
+                // A loop only stops once the current cell is zero
+                self.set_zero(0);
+            },
+        }
+    }
+
+    fn set_zero(&mut self, offset: Offset) {
+        match *self {
+            KnownZeros::AllExcept(ref mut cells) => {cells.remove(&offset);},
+            KnownZeros::Only(ref mut cells) => {cells.insert(offset);},
+        }
+    }
+
+    fn set_unknown(&mut self, offset: Offset) {
+        match *self {
+            KnownZeros::AllExcept(ref mut cells) => {cells.insert(offset);},
+            KnownZeros::Only(ref mut cells) => {cells.remove(&offset);},
+        }
+    }
+
+    fn shift(&mut self, amount: Offset) {
+        let cells = match *self {
+            KnownZeros::AllExcept(ref mut cells) | KnownZeros::Only(ref mut cells) => cells,
+        };
+        *cells = cells.drain().map(|offset| offset - amount).collect();
+    }
+}
+
+/// Returns what is known at the start of every iteration of a loop with the given body
+pub fn loop_entry(zeros: &KnownZeros, body: &Ops) -> KnownZeros {
+    if !is_stationary(body) {
+        return KnownZeros::none();
+    }
+
+    // Anything the body makes non-zero may have changed by the time a later iteration starts
+    let mut entry = zeros.clone();
+    for offset in nonzero_writes(body) {
+        entry.apply(&Op::Add(offset, 1));
+    }
+    entry
+}
+
+/// Returns true if the given operations never move the pointer, even inside nested loops
+pub fn is_stationary(ops: &Ops) -> bool {
+    ops.iter().all(|op| match *op {
+        Op::Move(_) => false,
+        Op::Loop(ref body) => is_stationary(body),
+        _ => true,
+    })
+}
+
+/// Returns the offsets of all the cells that may be set to a non-zero value by the given
+/// stationary operations
+///
+/// Cells that are only ever set to zero are not included since that can never make a cell that
+/// was zero non-zero.
+pub fn nonzero_writes(ops: &Ops) -> HashSet<Offset> {
+    let mut cells = HashSet::new();
+    for op in ops.iter() {
+        match *op {
+            Op::Add(offset, _) | Op::Read(offset) => {cells.insert(offset);},
+            Op::MulAdd {dst, ..} => {cells.insert(dst);},
+            Op::Loop(ref body) => cells.extend(nonzero_writes(body)),
+            Op::Move(_) | Op::SetZero(_) | Op::Write(_) => {},
+        }
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_zeros() {
+        let mut zeros = KnownZeros::all();
+        zeros.apply(&Op::Add(1, 3));
+        assert!(zeros.is_zero(0));
+        assert!(!zeros.is_zero(1));
+
+        zeros.apply(&Op::Move(1));
+        assert!(!zeros.is_zero(0));
+        assert!(zeros.is_zero(1));
+
+        zeros.apply(&Op::Loop(vec![Op::Add(0, -1), Op::Add(2, 1)].into()));
+        assert!(zeros.is_zero(0));
+        assert!(!zeros.is_zero(2));
+        assert!(zeros.is_zero(3));
+
+        zeros.apply(&Op::Loop(vec![Op::Move(1)].into()));
+        assert!(zeros.is_zero(0));
+        assert!(!zeros.is_zero(3));
+    }
+}
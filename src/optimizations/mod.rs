@@ -1,4 +1,8 @@
 mod optimize;
 mod instructions;
+mod ir;
+mod known_zeros;
+mod dataflow;
+mod constants;
 
 pub use self::optimize::*;
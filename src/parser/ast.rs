@@ -1,11 +1,12 @@
+use std::fmt;
 use std::iter::FromIterator;
 use std::convert::From;
 use std::str::FromStr;
 
 use pest::prelude::*;
 
-use super::{Rdp, ParseError};
-use operations::{self, OperationsResult};
+use super::{Rdp, Rule, ParseError, Span, recovery};
+use operations::{self, Operations, Error};
 use operations::scope::ScopeStack;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -20,30 +21,57 @@ impl Program {
         }
     }
 
-    pub fn into_operations(self, global_scope: &mut ScopeStack) -> OperationsResult {
+    pub fn into_operations(self, global_scope: &mut ScopeStack) -> Result<Operations, Vec<Error>> {
         operations::from_ast(global_scope, self)
     }
 }
 
 impl FromStr for Program {
-    type Err = ParseError;
+    type Err = Vec<ParseError>;
 
+    /// Parses the entire program and returns every syntax error found in it
+    ///
+    /// After each error, the statement that caused it is skipped and parsing starts again so that
+    /// the errors in the rest of the program can be found too.
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        let mut parser = Rdp::new(StringInput::new(input));
+        let mut source = input.to_owned();
+        let mut errors = Vec::new();
+
+        loop {
+            let mut parser = Rdp::new(StringInput::new(&source));
+            if parser.module() {
+                if errors.is_empty() {
+                    return Ok(Program {
+                        root_mod: parser.module_ast(),
+                    });
+                }
+                return Err(errors);
+            }
 
-        if parser.module() {
-            Ok(Program {
-                root_mod: parser.module_ast(),
-            })
-        }
-        else {
             let (expected, pos) = parser.expected();
             let (line, col) = parser.input().line_col(pos);
-            Err(ParseError {
-                line: line,
-                col: col,
-                expected: expected,
-            })
+
+            // The unexpected token is either a whole word or a single character
+            let rest = &source[pos..];
+            let word = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            let found = match rest.chars().next() {
+                Some(_) if word > 0 => Some(&rest[..word]),
+                Some(c) => Some(&rest[..c.len_utf8()]),
+                None => None,
+            };
+
+            // A comment can go almost anywhere, so it would be listed in every error
+            let expected = expected.into_iter().filter(|rule| *rule != Rule::comment).collect();
+
+            errors.push(ParseError {
+                span: Span::new(pos, pos + found.map_or(0, |found| found.len()), line as u32, col as u32),
+                found: found.map(|found| found.to_owned()),
+                expected,
+            });
+
+            if !recovery::skip_statement(&mut source, pos) {
+                return Err(errors);
+            }
         }
     }
 }
@@ -56,7 +84,7 @@ pub struct Module {
 impl Module {
     pub fn empty() -> Module {
         Module {
-            body: vec![Statement::Expression {expr: Expression::UnitLiteral}],
+            body: vec![Statement::Expression {expr: Expression::UnitLiteral(Span::default())}],
         }
     }
 }
@@ -71,30 +99,172 @@ impl From<Block> for Module {
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Statement {
-    Comment(String),
+    Comment(String, Span),
     Declaration {
         pattern: Pattern,
+        /// Whether the declaration used `let mut`
+        mutable: bool,
         type_def: TypeDefinition,
         expr: Option<Expression>,
+        span: Span,
     },
     Assignment {
         lhs: Identifier,
         expr: Expression,
+        span: Span,
+    },
+    /// An assignment to a single field of a struct, like `a.b = c` or `a.b.c = d`
+    FieldAssignment {
+        target: Identifier,
+        /// The fields that lead from the target to the assigned field, like `[b, c]` in `a.b.c = d`
+        fields: Vec<Identifier>,
+        expr: Expression,
+        span: Span,
+    },
+    /// An assignment like `a += b` that applies an operator to the current value of `lhs`, or to
+    /// one of its fields like in `a.b += c`
+    CompoundAssignment {
+        lhs: Identifier,
+        /// The fields that lead from `lhs` to the assigned field, empty if `lhs` itself is assigned
+        fields: Vec<Identifier>,
+        /// The function that implements the operator, like `std::ops::Add::add` for `+=`
+        operator: Identifier,
+        expr: Expression,
+        span: Span,
     },
     WhileLoop {
         condition: Expression,
         body: Block,
+        span: Span,
+    },
+    /// A function declared with `fn`
+    Function {
+        name: Identifier,
+        params: Vec<Parameter>,
+        /// The function returns `()` if this is None
+        return_type: Option<TypeDefinition>,
+        body: Block,
+        span: Span,
+    },
+    /// A struct type declared with `struct`
+    Struct {
+        name: Identifier,
+        /// The fields in the order they are laid out in memory
+        fields: Vec<FieldDefinition>,
+        span: Span,
+    },
+    /// An enum type declared with `enum`
+    Enum {
+        name: Identifier,
+        /// The variants in order, the index of each one is the value of its tag
+        variants: Vec<VariantDefinition>,
+        span: Span,
+    },
+    /// Methods of a type declared in an `impl` block
+    Impl {
+        type_name: Identifier,
+        /// Always `Statement::Function`
+        methods: Vec<Statement>,
+        span: Span,
     },
     Expression {
         expr: Expression,
     },
 }
 
+impl Statement {
+    /// Returns the location of this statement in the source
+    pub fn span(&self) -> Span {
+        match *self {
+            Statement::Comment(_, span) |
+            Statement::Declaration {span, ..} |
+            Statement::Assignment {span, ..} |
+            Statement::FieldAssignment {span, ..} |
+            Statement::CompoundAssignment {span, ..} |
+            Statement::WhileLoop {span, ..} |
+            Statement::Function {span, ..} |
+            Statement::Struct {span, ..} |
+            Statement::Enum {span, ..} |
+            Statement::Impl {span, ..} => span,
+            Statement::Expression {ref expr} => expr.span(),
+        }
+    }
+}
+
+/// A parameter of a function, like `mut a: u8`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Parameter {
+    pub pattern: Pattern,
+    /// Whether the parameter can be modified in the body of the function
+    pub mutable: bool,
+    pub type_def: TypeDefinition,
+}
+
+/// A field of a struct declaration, like `a: u8`
+#[derive(Debug, PartialEq, Clone)]
+pub struct FieldDefinition {
+    pub name: Identifier,
+    pub type_def: TypeDefinition,
+}
+
+/// A variant of an enum declaration, like `Num(u8)`
+#[derive(Debug, PartialEq, Clone)]
+pub struct VariantDefinition {
+    pub name: Identifier,
+    /// The types of the fields in the order they are laid out in memory
+    pub fields: Vec<TypeDefinition>,
+}
+
+/// An arm of a `match`, like `Op::Num(n) => n + 1`
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchArm {
+    pub pattern: MatchPattern,
+    /// An arm with an expression instead of a block has a block with only that expression
+    pub body: Block,
+    pub span: Span,
+}
+
+/// The pattern of an arm of a `match`
+#[derive(Debug, PartialEq, Clone)]
+pub enum MatchPattern {
+    /// `_`, which matches any value
+    Wildcard(Span),
+    /// A number or `true`/`false`, which matches values equal to it
+    Literal(Expression),
+    /// A variant of an enum, like `Op::Add` or `Op::Num(n)`
+    Variant {
+        path: Identifier,
+        /// The name that each field is stored in, or None if it is ignored with `_`
+        fields: Vec<Option<Identifier>>,
+        span: Span,
+    },
+}
+
+impl MatchPattern {
+    /// Returns the location of this pattern in the source
+    pub fn span(&self) -> Span {
+        match *self {
+            MatchPattern::Wildcard(span) |
+            MatchPattern::Variant {span, ..} => span,
+            MatchPattern::Literal(ref expr) => expr.span(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Pattern {
     Identifier(Identifier),
 }
 
+impl Pattern {
+    /// Returns the location of this pattern in the source
+    pub fn span(&self) -> Span {
+        match *self {
+            Pattern::Identifier(ref name) => name.span(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum TypeDefinition {
     Name {
@@ -103,18 +273,30 @@ pub enum TypeDefinition {
     Array {
         type_def: Box<TypeDefinition>,
         size: Option<Expression>,
+        span: Span,
     },
 }
 
+impl TypeDefinition {
+    /// Returns the location of this type definition in the source
+    pub fn span(&self) -> Span {
+        match *self {
+            TypeDefinition::Name {ref name} => name.span(),
+            TypeDefinition::Array {span, ..} => span,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expression {
-    UnitLiteral,
-    ByteLiteral(Vec<u8>),
+    UnitLiteral(Span),
+    ByteLiteral(Vec<u8>, Span),
     Identifier(Identifier),
-    Number(Number),
+    Number(Number, Span),
     Call {
         method: Box<Expression>,
         args: CallArgs,
+        span: Span,
     },
     Access {
         // target can be another field access, an identifier, or even a literal, etc.
@@ -123,6 +305,14 @@ pub enum Expression {
         // Numbers may also be fields when #40 is implemented
         // https://github.com/brain-lang/brain/issues/40
         field: Identifier,
+        span: Span,
+    },
+    /// Creates a value of a struct type, like `Point {x: 1, y: 2}`
+    StructLiteral {
+        name: Identifier,
+        /// The value of each field in the order they were written
+        fields: Vec<(Identifier, Expression)>,
+        span: Span,
     },
     Branch {
         /// Condition to be executed to determine which block
@@ -132,11 +322,45 @@ pub enum Expression {
         body: Block,
         /// (optional) executed if the condition is zero
         otherwise: Option<Block>,
+        span: Span,
+    },
+    /// Runs the body of the first arm whose pattern matches the value of the expression
+    Match {
+        expr: Box<Expression>,
+        arms: Vec<MatchArm>,
+        span: Span,
+    },
+    /// Converts the value of an expression to another type, like `a as u16`
+    Cast {
+        expr: Box<Expression>,
+        type_def: Box<TypeDefinition>,
+        span: Span,
     },
 }
 
+impl Expression {
+    /// Returns the location of this expression in the source
+    pub fn span(&self) -> Span {
+        match *self {
+            Expression::Identifier(ref name) => name.span(),
+            Expression::UnitLiteral(span) |
+            Expression::ByteLiteral(_, span) |
+            Expression::Number(_, span) |
+            Expression::Call {span, ..} |
+            Expression::Access {span, ..} |
+            Expression::StructLiteral {span, ..} |
+            Expression::Branch {span, ..} |
+            Expression::Match {span, ..} |
+            Expression::Cast {span, ..} => span,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
-pub struct Identifier(Vec<String>);
+pub struct Identifier {
+    path: Vec<String>,
+    span: Span,
+}
 
 impl Identifier {
     // NOTE: Do not implement ::new() for Identifier
@@ -145,14 +369,34 @@ impl Identifier {
     // Concatenates this identifier with another identifier and returns
     // a new identifier
     pub fn concat<T>(self, other: T) -> Identifier where T: IntoIterator<Item=String> {
-        self.into_iter().chain(other).collect()
+        let span = self.span;
+        self.into_iter().chain(other).collect::<Identifier>().with_span(span)
+    }
+
+    /// Returns the location of this identifier in the source
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Returns this identifier with the given location in the source
+    pub fn with_span(self, span: Span) -> Identifier {
+        Identifier {
+            span,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.path.join("::"))
     }
 }
 
 impl FromStr for Identifier {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Identifier(s.split("::").map(|s| s.to_owned()).collect()))
+        Ok(s.split("::").map(|s| s.to_owned()).collect())
     }
 }
 
@@ -167,18 +411,21 @@ impl IntoIterator for Identifier {
     type IntoIter = ::std::vec::IntoIter<String>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.path.into_iter()
     }
 }
 
 impl FromIterator<String> for Identifier {
     fn from_iter<I: IntoIterator<Item=String>>(iter: I) -> Self {
-        Identifier(iter.into_iter().collect())
+        Identifier {
+            path: iter.into_iter().collect(),
+            span: Span::default(),
+        }
     }
 }
 
 pub type Block = Vec<Statement>;
-pub type Number = i32;
+pub type Number = i64;
 pub type CallArgs = Vec<CallArg>;
 pub type CallArg = Expression;
 
@@ -1,23 +1,65 @@
 use std::fmt;
 use std::error::Error;
 
-use super::Rule;
+use diagnostics::Diagnostic;
+
+use super::{Rule, Span};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct ParseError {
-    pub line: usize,
-    pub col: usize,
+    /// The location of the unexpected token
+    pub span: Span,
+    /// The unexpected token that was found or None if the end of the input was reached
+    pub found: Option<String>,
     pub expected: Vec<Rule>,
 }
 
+impl ParseError {
+    /// Describes the tokens that were expected, e.g. "one of `(`, `.`, or `;`"
+    pub fn expected_tokens(&self) -> String {
+        let mut expected: Vec<String> = Vec::new();
+        for rule in &self.expected {
+            let rule = rule.to_string();
+            if !expected.contains(&rule) {
+                expected.push(rule);
+            }
+        }
+
+        match expected.len() {
+            0 => "nothing".to_owned(),
+            1 => expected.remove(0),
+            2 => format!("{} or {}", expected[0], expected[1]),
+            _ => {
+                let last = expected.pop().unwrap();
+                format!("one of {}, or {}", expected.join(", "), last)
+            },
+        }
+    }
+
+    /// Describes the token that was found instead of the expected tokens
+    pub fn found_token(&self) -> String {
+        self.found.as_ref().map_or_else(|| "end of file".to_owned(), |found| format!("`{}`", found))
+    }
+
+    /// Converts this error into a diagnostic that can be rendered with the source code
+    pub fn diagnostic(&self) -> Diagnostic {
+        if self.expected.is_empty() {
+            Diagnostic::error(format!("unexpected {}", self.found_token()), self.span)
+                .with_label("unexpected token")
+        }
+        else {
+            Diagnostic::error(format!("expected {}, found {}", self.expected_tokens(), self.found_token()), self.span)
+                .with_label(format!("expected {}", self.expected_tokens()))
+        }
+    }
+}
+
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.expected.is_empty() {
-            write!(f, "no token expected at line {} col {}", self.line, self.col)
+            write!(f, "unexpected {} at {}", self.found_token(), self.span)
         } else {
-            write!(f, "expected token(s): {} at line {} col {}",
-                self.expected.iter().map(|r| format!("{}", r)).collect::<Vec<String>>().join(", "),
-                self.line, self.col)
+            write!(f, "expected {}, found {} at {}", self.expected_tokens(), self.found_token(), self.span)
         }
     }
 }
@@ -31,3 +73,33 @@ impl Error for ParseError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parser::Program;
+
+    #[test]
+    fn describes_expected_tokens() {
+        let error = "let a: u8 = 1\nlet b: u8 = 2;".parse::<Program>().unwrap_err().remove(0);
+        assert_eq!(error.found, Some("let".to_owned()));
+        assert_eq!((error.span.line, error.span.col, error.span.end - error.span.start), (2, 1, 3));
+        assert!(error.expected.contains(&Rule::semi));
+        assert!(error.expected_tokens().contains("`;`"), "{}", error.expected_tokens());
+        assert!(!error.expected.contains(&Rule::comment));
+        assert!(!error.expected_tokens().contains("comment"), "{}", error.expected_tokens());
+
+        let error = "stdout.print(b\"a\"".parse::<Program>().unwrap_err().remove(0);
+        assert_eq!(error.found, None);
+        assert_eq!(error.found_token(), "end of file");
+    }
+
+    #[test]
+    fn recovers_after_errors() {
+        let source = "let a: u8 = ;\nwhile a { stdout.print(a; }\nlet b: u8 = 1\nstdout.print(b);\nlet c: u8 = 2;\n";
+        let errors = source.parse::<Program>().unwrap_err();
+        let lines: Vec<_> = errors.iter().map(|error| error.span.line).collect();
+        assert_eq!(lines, vec![1, 2, 4]);
+    }
+}
@@ -9,20 +9,46 @@ impl_rdp! {
     grammar! {
         module = _{ soi ~ statement* ~ eoi }
 
-        // conditional is technically an expression too but it can be used as a statement
-        // without a semicolon as well
-        statement = { declaration | assignment | while_loop | conditional | (expr ~ semi) | comment }
+        // conditional and match are technically expressions too but they can be used as
+        // statements without a semicolon as well
+        statement = { function | struct_def | enum_def | impl_block | declaration | field_assignment | assignment | compound_assignment | while_loop | conditional | match_expr | (expr ~ semi) | comment }
 
         comment = @{ block_comment | line_comment }
         line_comment = _{ ["//"] ~ (!(["\r"] | ["\n"]) ~ any)* ~ (["\n"] | ["\r\n"] | ["\r"] | eoi) }
         block_comment = _{ ["/*"] ~ ((!(["*/"]) ~ any) | block_comment)* ~ ["*/"] }
 
         assignment = { identifier ~ op_assign ~ expr ~ semi}
-        declaration = { ["let"] ~ ["mut"]? ~ pattern ~ op_declare_type ~ type_def ~ (op_assign ~ expr)? ~ semi}
+        field_assignment = { (self_value | identifier) ~ (op_access ~ identifier)+ ~ op_assign ~ expr ~ semi }
+        compound_assignment = { (self_value | identifier) ~ (op_access ~ identifier)* ~ (op_add_assign | op_sub_assign | op_mul_assign | op_div_assign | op_rem_assign) ~ expr ~ semi }
+        declaration = { ["let"] ~ mutable? ~ pattern ~ op_declare_type ~ type_def ~ (op_assign ~ expr)? ~ semi}
+        mutable = { ["mut"] }
         op_declare_type = { [":"] }
         op_assign = { ["="] }
+        op_add_assign = { ["+="] }
+        op_sub_assign = { ["-="] }
+        op_mul_assign = { ["*="] }
+        op_div_assign = { ["/="] }
+        op_rem_assign = { ["%="] }
         pattern = { identifier }
 
+        function = { ["fn"] ~ identifier ~ params ~ (op_return_type ~ type_def)? ~ block }
+        // This allows () and (param, param) and (param) and (param,)
+        params = _{ func_args_start ~ (param ~ [","])* ~ param? ~ func_args_end }
+        // `self` is only valid as the first parameter of a method, its type is always `Self`
+        param = { mutable? ~ (self_value | (pattern ~ op_declare_type ~ type_def)) }
+        op_return_type = { ["->"] }
+
+        // This allows {} and {field, field} and {field} and {field,}
+        struct_def = { ["struct"] ~ identifier ~ block_start ~ (field_def ~ [","])* ~ field_def? ~ block_end }
+        field_def = { identifier ~ op_declare_type ~ type_def }
+
+        // This allows {} and {variant, variant} and {variant} and {variant,}
+        enum_def = { ["enum"] ~ identifier ~ block_start ~ (variant_def ~ [","])* ~ variant_def? ~ block_end }
+        // A variant may have fields, like `Num(u8)` or `Pair(u8, [u8; 2])`
+        variant_def = { identifier ~ (func_args_start ~ (type_def ~ [","])* ~ type_def? ~ func_args_end)? }
+
+        impl_block = { ["impl"] ~ identifier ~ block_start ~ (function | comment)* ~ block_end }
+
         type_def = _{ identifier | array_type }
         array_type = { ["["] ~ type_def ~ semi ~ array_size ~ ["]"] }
         array_size = _{ unspecified | expr }
@@ -31,13 +57,19 @@ impl_rdp! {
         while_loop = { ["while"] ~ expr ~ block }
 
         expr = {
-            { bool_not | func_call | field_access | string_literal | bool_literal | identifier | conditional | number }
+            { cast | bool_not | group | func_call | struct_literal | method_call | field_access | string_literal | bool_literal | path | identifier | self_value | conditional | match_expr | number }
 
             // Ordered from lowest precedence to highest precedence
             bool_or = { op_bool_or }
             bool_and = { op_bool_and }
             // NOTE: Order matters! { ["<"] | ["<="] } will never match "<="
             comparison = { op_eq | op_ne | op_ge | op_le | op_gt | op_lt }
+            bit_or = { op_bit_or }
+            bit_xor = { op_bit_xor }
+            bit_and = { op_bit_and }
+            shift = { op_shl | op_shr }
+            sum = { op_add | op_sub }
+            product = { op_mul | op_div | op_rem }
         }
         op_bool_or = { ["||"] }
         op_bool_and = { ["&&"] }
@@ -45,23 +77,61 @@ impl_rdp! {
         op_ne = { ["!="] }
         op_ge = { [">="] }
         op_le = { ["<="] }
-        op_gt = { [">"] }
-        op_lt = { ["<"] }
+        // The lookahead stops these from matching the first half of a shift
+        op_gt = { [">"] ~ ![">"] }
+        op_lt = { ["<"] ~ !["<"] }
+        // The lookahead stops these from matching the first half of `||` and `&&`
+        op_bit_or = { ["|"] ~ !["|"] }
+        op_bit_xor = { ["^"] }
+        op_bit_and = { ["&"] ~ !["&"] }
+        op_shl = { ["<<"] }
+        op_shr = { [">>"] }
+        op_add = { ["+"] }
+        op_sub = { ["-"] }
+        op_mul = { ["*"] }
+        op_div = { ["/"] }
+        op_rem = { ["%"] }
+
+        // Casts bind tighter than every binary operator, so only these can be cast without
+        // parentheses
+        cast = { cast_operand ~ cast_types }
+        cast_operand = _{ group | func_call | method_call | field_access | bool_literal | identifier | self_value | number }
+        // The types are grouped in their own token so that a cast inside of a group never takes the
+        // types of the cast around it
+        cast_types = { (op_as ~ type_def)+ }
+        op_as = @{ ["as"] ~ !(alphanumeric | ["_"]) }
 
         bool_not = _{ op_bool_not ~ expr }
+        group = _{ ["("] ~ expr ~ [")"] }
         op_bool_not = { ["!"] }
 
         conditional = { ["if"] ~ expr ~ block ~ (op_else_if ~ expr ~ block)* ~ (op_else ~ block)? }
         op_else_if = { ["else if"] }
         op_else = { ["else"] }
 
+        // This allows {} and {arm, arm} and {arm} and {arm,}, the comma after an arm is optional
+        match_expr = { ["match"] ~ expr ~ block_start ~ match_arm* ~ block_end }
+        match_arm = { match_pattern ~ op_arrow ~ (block | expr) ~ [","]? }
+        match_pattern = _{ wildcard | variant_pattern | number | bool_literal }
+        // The fields of a variant can be stored in names or ignored with `_`
+        variant_pattern = { path ~ (func_args_start ~ (field_pattern ~ [","])* ~ field_pattern? ~ func_args_end)? }
+        field_pattern = _{ wildcard | identifier }
+        wildcard = @{ ["_"] ~ !(alphanumeric | ["_"]) }
+        op_arrow = { ["=>"] }
+
         // This allows {} and {statement; statement; statement;} and {statement; expr} and {expr}
         block = _{ block_start ~ statement* ~ expr? ~ block_end }
         block_start = { ["{"] }
         block_end = { ["}"] }
 
-        func_call = { identifier ~ func_args }
-        field_access = { identifier ~ op_access ~ identifier ~ func_args? }
+        func_call = { (path | identifier) ~ func_args }
+        // At least one field is required so that the block of a conditional or a loop is never
+        // mistaken for a struct literal, like in `if a {}`
+        struct_literal = { identifier ~ block_start ~ field_init ~ ([","] ~ field_init)* ~ [","]? ~ block_end }
+        field_init = { identifier ~ op_declare_type ~ expr }
+        method_call = { (self_value | identifier) ~ op_access ~ identifier ~ func_args }
+        // Fields can be nested, like `line.start.x`
+        field_access = { (self_value | identifier) ~ (op_access ~ identifier)+ }
         op_access = { ["."] }
 
         // This allows () and (func_arg, func_arg) and (func_arg) and (func_arg,)
@@ -77,10 +147,17 @@ impl_rdp! {
         bool_literal = @{ ["true"] | ["false"] }
 
         identifier = @{ !keyword ~ (alpha | ["_"]) ~ (alphanumeric | ["_"])* }
+        // A name declared inside of a type, like the variant `Op::Add` of an enum
+        path = @{ path_segment ~ (["::"] ~ path_segment)+ }
+        path_segment = _{ (alpha | ["_"]) ~ (alphanumeric | ["_"])* }
+        // The instance that a method was called on
+        self_value = @{ ["self"] ~ !(alphanumeric | ["_"]) }
         alpha = _{ ['a'..'z'] | ['A'..'Z'] }
         alphanumeric = _{ alpha | ['0'..'9'] }
 
-        number = @{ ["0"] | (nonzero ~ digit*) }
+        // A leading minus is part of the literal so that the smallest value of a signed type can
+        // be written, like `-128`
+        number = @{ ["-"]? ~ (["0"] | (nonzero ~ digit*)) }
         // Allow "_" in numbers for grouping: 1_000_000 == 1000000
         digit = _{ ["0"] | nonzero | ["_"] }
         nonzero = _{ ['1'..'9'] }
@@ -88,7 +165,8 @@ impl_rdp! {
         whitespace = _{ [" "] | ["\t"] | ["\u{000C}"] | ["\r"] | ["\n"] }
         // NOTE: When changing this code, make sure you don't have a subset of a word before
         // another word. For example: { ["type"] | ["typeof"] } will never match "typeof"
-        keyword = @{
+        // The lookahead allows identifiers that start with a keyword, like `double` or `format`
+        keyword = @{ (
             ["abstract"] | ["as"] | ["become"] | ["break"] | ["byte"] | ["class"] | ["clear"] |
             ["const"] | ["continue"] | ["do"] | ["else"] | ["enum"] | ["eval"] | ["export"] |
             ["extern"] | ["false"] | ["final"] | ["fn"] | ["for"] | ["if"] | ["impl"] | ["import"] |
@@ -96,7 +174,7 @@ impl_rdp! {
             ["out"] | ["pub"] | ["raw"] | ["ref"] | ["return"] | ["self"] | ["static"] |
             ["struct"] | ["super"] | ["trait"] | ["true"] | ["typeof"] | ["type"] | ["unsafe"] |
             ["use"] | ["where"] | ["while"] | ["yield"]
-        }
+        ) ~ !(alphanumeric | ["_"]) }
 
         // These are separate rules because we can use the generated rules and tokens to provide
         // better error messages
@@ -121,41 +199,186 @@ impl_rdp! {
 
                 tail
             },
-            (&text: comment, mut tail: _module()) => {
-                tail.push_front(Statement::Comment(text.into()));
+            (text: comment, mut tail: _module()) => {
+                tail.push_front(self.comment_ast(&text));
 
                 tail
             },
             () => {
                 let mut tail = VecDeque::new();
                 // We do this so the last statement in a block always represents its return type
-                tail.push_front(Statement::Expression {expr: Expression::UnitLiteral});
+                let end = self.input().len();
+                tail.push_front(Statement::Expression {expr: Expression::UnitLiteral(self.span_at(end, end))});
                 tail
             },
         }
 
         _statement(&self) -> Statement {
-            (&text: comment) => {
-                Statement::Comment(text.into())
+            (text: comment) => {
+                self.comment_ast(&text)
+            },
+            (decl: declaration, mutable: _mutable(), pattern: _pattern(), _: op_declare_type, type_def: _type_def(), _: op_assign, _: expr, expr: _expr(), _: semi) => {
+                Statement::Declaration {pattern, mutable, type_def, expr: Some(expr), span: self.span(&decl)}
             },
-            (_: declaration, pattern: _pattern(), _: op_declare_type, type_def: _type_def(), _: op_assign, _: expr, expr: _expr(), _: semi) => {
-                Statement::Declaration {pattern: pattern, type_def: type_def, expr: Some(expr)}
+            (decl: declaration, mutable: _mutable(), pattern: _pattern(), _: op_declare_type, type_def: _type_def(), _: semi) => {
+                Statement::Declaration {pattern, mutable, type_def, expr: None, span: self.span(&decl)}
             },
-            (_: declaration, pattern: _pattern(), _: op_declare_type, type_def: _type_def(), _: semi) => {
-                Statement::Declaration {pattern: pattern, type_def: type_def, expr: None}
+            (assign: assignment, ident: _identifier(), _: op_assign, _: expr, expr: _expr(), _: semi) => {
+                Statement::Assignment {lhs: ident, expr, span: self.span(&assign)}
             },
-            (_: assignment, ident: _identifier(), _: op_assign, _: expr, expr: _expr(), _: semi) => {
-                Statement::Assignment {lhs: ident, expr: expr}
+            (assign: field_assignment, target: _identifier(), fields: _field_path(), _: op_assign, _: expr, expr: _expr(), _: semi) => {
+                Statement::FieldAssignment {target, fields: fields.into_iter().collect(), expr, span: self.span(&assign)}
             },
-            (_: while_loop, _: expr, condition: _expr(), body: _block()) => {
-                Statement::WhileLoop {condition: condition, body: body}
+            (assign: compound_assignment, ident: _identifier(), fields: _field_path(), op_token, _: expr, expr: _expr(), _: semi) => {
+                Statement::CompoundAssignment {
+                    lhs: ident,
+                    fields: fields.into_iter().collect(),
+                    operator: Identifier::from(operator_method(op_token.rule)).with_span(self.span(op_token)),
+                    expr,
+                    span: self.span(&assign),
+                }
+            },
+            (while_token: while_loop, _: expr, condition: _expr(), body: _block()) => {
+                Statement::WhileLoop {condition, body, span: self.span(&while_token)}
+            },
+            (func: function, name: _identifier(), _: func_args_start, params: _params(), return_type: _return_type(), body: _block()) => {
+                Statement::Function {name, params: params.into_iter().collect(), return_type, body, span: self.span(&func)}
+            },
+            (def: struct_def, name: _identifier(), _: block_start, fields: _fields()) => {
+                Statement::Struct {name, fields: fields.into_iter().collect(), span: self.span(&def)}
             },
-            (_: conditional, expr: _conditional()) => {
-                Statement::Expression {expr: expr}
+            (def: enum_def, name: _identifier(), _: block_start, variants: _variants()) => {
+                Statement::Enum {name, variants: variants.into_iter().collect(), span: self.span(&def)}
+            },
+            (block: impl_block, type_name: _identifier(), _: block_start, methods: _methods()) => {
+                Statement::Impl {type_name, methods: methods.into_iter().collect(), span: self.span(&block)}
             },
-            // This should always be lmodule_ast as it will catch pretty much any cases that weren't caught above
             (_: expr, expr: _expr(), _: semi) => {
-                Statement::Expression {expr: expr}
+                Statement::Expression {expr}
+            },
+            (m: match_expr, _: expr, value: _expr(), _: block_start, arms: _match_arms()) => {
+                Statement::Expression {
+                    expr: Expression::Match {expr: Box::new(value), arms: arms.into_iter().collect(), span: self.span(&m)},
+                }
+            },
+            // This should always be last since _conditional() panics if it cannot match
+            (expr: _conditional()) => {
+                Statement::Expression {expr}
+            },
+        }
+
+        _mutable(&self) -> bool {
+            (_: mutable) => {
+                true
+            },
+            () => {
+                false
+            },
+        }
+
+        _params(&self) -> VecDeque<Parameter> {
+            (_: func_args_end) => {
+                VecDeque::new()
+            },
+            // Must come before the other parameters since _pattern() panics if it cannot match
+            (_: param, mutable: _mutable(), self_token: self_value, mut tail: _params()) => {
+                let span = self.span(&self_token);
+                tail.push_front(Parameter {
+                    pattern: Pattern::Identifier(Identifier::from("self").with_span(span)),
+                    mutable,
+                    type_def: TypeDefinition::Name {name: Identifier::from("Self").with_span(span)},
+                });
+
+                tail
+            },
+            (_: param, mutable: _mutable(), pattern: _pattern(), _: op_declare_type, type_def: _type_def(), mut tail: _params()) => {
+                tail.push_front(Parameter {pattern, mutable, type_def});
+
+                tail
+            },
+        }
+
+        _fields(&self) -> VecDeque<FieldDefinition> {
+            (_: block_end) => {
+                VecDeque::new()
+            },
+            (_: field_def, name: _identifier(), _: op_declare_type, type_def: _type_def(), mut tail: _fields()) => {
+                tail.push_front(FieldDefinition {name, type_def});
+
+                tail
+            },
+        }
+
+        _variants(&self) -> VecDeque<VariantDefinition> {
+            (_: block_end) => {
+                VecDeque::new()
+            },
+            (_: variant_def, name: _identifier(), _: func_args_start, fields: _variant_fields(), mut tail: _variants()) => {
+                tail.push_front(VariantDefinition {name, fields: fields.into_iter().collect()});
+
+                tail
+            },
+            (_: variant_def, name: _identifier(), mut tail: _variants()) => {
+                tail.push_front(VariantDefinition {name, fields: Vec::new()});
+
+                tail
+            },
+        }
+
+        _variant_fields(&self) -> VecDeque<TypeDefinition> {
+            (_: func_args_end) => {
+                VecDeque::new()
+            },
+            (type_def: _type_def(), mut tail: _variant_fields()) => {
+                tail.push_front(type_def);
+
+                tail
+            },
+        }
+
+        _methods(&self) -> VecDeque<Statement> {
+            (_: block_end) => {
+                VecDeque::new()
+            },
+            // Comments between the methods are not kept
+            (_: comment, tail: _methods()) => {
+                tail
+            },
+            (head: _statement(), mut tail: _methods()) => {
+                tail.push_front(head);
+
+                tail
+            },
+        }
+
+        _field_inits(&self) -> VecDeque<(Identifier, Expression)> {
+            (_: block_end) => {
+                VecDeque::new()
+            },
+            (_: field_init, name: _identifier(), _: op_declare_type, _: expr, value: _expr(), mut tail: _field_inits()) => {
+                tail.push_front((name, value));
+
+                tail
+            },
+        }
+
+        _field_path(&self) -> VecDeque<Identifier> {
+            (_: op_access, field: _identifier(), mut tail: _field_path()) => {
+                tail.push_front(field);
+
+                tail
+            },
+            () => {
+                VecDeque::new()
+            },
+        }
+
+        _return_type(&self) -> Option<TypeDefinition> {
+            (_: op_return_type, type_def: _type_def()) => {
+                Some(type_def)
+            },
+            () => {
+                None
             },
         }
 
@@ -166,11 +389,11 @@ impl_rdp! {
         }
 
         _type_def(&self) -> TypeDefinition {
-            (_: array_type, type_def: _type_def(), _: semi, _: unspecified) => {
-                TypeDefinition::Array {type_def: Box::new(type_def), size: None}
+            (array: array_type, type_def: _type_def(), _: semi, _: unspecified) => {
+                TypeDefinition::Array {type_def: Box::new(type_def), size: None, span: self.span(&array)}
             },
-            (_: array_type, type_def: _type_def(), _: semi, _: expr, size: _expr()) => {
-                TypeDefinition::Array {type_def: Box::new(type_def), size: Some(size)}
+            (array: array_type, type_def: _type_def(), _: semi, _: expr, size: _expr()) => {
+                TypeDefinition::Array {type_def: Box::new(type_def), size: Some(size), span: self.span(&array)}
             },
             (ident: _identifier()) => {
                 TypeDefinition::Name {name: ident}
@@ -178,136 +401,214 @@ impl_rdp! {
         }
 
         _expr(&self) -> Expression {
-            (_: op_bool_not, _:expr, expr: _expr()) => {
+            (not: op_bool_not, _:expr, expr: _expr()) => {
+                let span = self.span(&not).to(expr.span());
                 Expression::Call {
-                    method: Box::new(Expression::Identifier(Identifier::from("std::ops::Not"))),
+                    method: Box::new(Expression::Identifier(Identifier::from("std::ops::Not").with_span(self.span(&not)))),
                     args: vec![expr],
+                    span,
                 }
             },
-            (_: func_call, method: _identifier(), args: _call_args()) => {
+            (_: expr, expr: _expr()) => {
+                expr
+            },
+            (call: func_call, method: _identifier(), args: _call_args()) => {
                 Expression::Call {
                     method: Box::new(Expression::Identifier(method)),
-                    args: args,
+                    args,
+                    span: self.span(&call),
                 }
             },
-            (_: field_access, expr: _field_access()) => {
-                expr
+            (_: cast, expr: _expr(), _: cast_types, types: _cast_types()) => {
+                types.into_iter().fold(expr, |expr, type_def| {
+                    let span = expr.span().to(type_def.span());
+                    Expression::Cast {expr: Box::new(expr), type_def: Box::new(type_def), span}
+                })
             },
-            (_: conditional, expr: _conditional()) => {
-                expr
+            (literal: struct_literal, name: _identifier(), _: block_start, fields: _field_inits()) => {
+                Expression::StructLiteral {name, fields: fields.into_iter().collect(), span: self.span(&literal)}
+            },
+            (call: method_call, target: _identifier(), _: op_access, field: _identifier(), args: _call_args()) => {
+                let method_span = target.span().to(field.span());
+                Expression::Call {
+                    method: Box::new(Expression::Access {
+                        target: Box::new(Expression::Identifier(target)),
+                        field,
+                        span: method_span,
+                    }),
+                    args,
+                    span: self.span(&call),
+                }
             },
-            (_: bool_or, lhs: _expr(), _: op_bool_or, rhs: _expr()) => {
+            (_: field_access, target: _identifier(), fields: _field_path()) => {
+                fields.into_iter().fold(Expression::Identifier(target), |target, field| {
+                    let span = target.span().to(field.span());
+                    Expression::Access {target: Box::new(target), field, span}
+                })
+            },
+            (or: bool_or, lhs: _expr(), op_token: op_bool_or, rhs: _expr()) => {
                 Expression::Call {
-                    method: Box::new(Expression::Identifier(Identifier::from("operator||"))),
+                    method: Box::new(Expression::Identifier(Identifier::from("operator||").with_span(self.span(&op_token)))),
                     args: vec![lhs, rhs],
+                    span: self.span(&or),
                 }
             },
-            (_: bool_and, lhs: _expr(), _: op_bool_and, rhs: _expr()) => {
+            (and: bool_and, lhs: _expr(), op_token: op_bool_and, rhs: _expr()) => {
                 Expression::Call {
-                    method: Box::new(Expression::Identifier(Identifier::from("operator&&"))),
+                    method: Box::new(Expression::Identifier(Identifier::from("operator&&").with_span(self.span(&op_token)))),
                     args: vec![lhs, rhs],
+                    span: self.span(&and),
                 }
             },
-            (_: comparison, lhs: _expr(), op_token, rhs: _expr()) => {
+            (cmp: comparison, lhs: _expr(), op_token, rhs: _expr()) => {
                 Expression::Call {
-                    method: Box::new(Expression::Identifier(Identifier::from(match op_token.rule {
-                        Rule::op_eq => "std::cmp::PartialEq::eq",
-                        Rule::op_ne => "std::cmp::PartialEq::ne",
-                        Rule::op_ge => "std::cmp::PartialOrd::ge",
-                        Rule::op_le => "std::cmp::PartialOrd::le",
-                        Rule::op_gt => "std::cmp::PartialOrd::gt",
-                        Rule::op_lt => "std::cmp::PartialOrd::lt",
-                        _ => unreachable!(),
-                    }))),
+                    method: Box::new(Expression::Identifier(Identifier::from(operator_method(op_token.rule)).with_span(self.span(op_token)))),
                     args: vec![lhs, rhs],
+                    span: self.span(&cmp),
                 }
             },
-            (&ident: bool_literal) => {
-                Expression::Identifier(ident.into())
+            (sum: sum, lhs: _expr(), op_token, rhs: _expr()) => {
+                Expression::Call {
+                    method: Box::new(Expression::Identifier(Identifier::from(operator_method(op_token.rule)).with_span(self.span(op_token)))),
+                    args: vec![lhs, rhs],
+                    span: self.span(&sum),
+                }
             },
-            (&ident: identifier) => {
-                Expression::Identifier(ident.into())
+            (bit_or: bit_or, lhs: _expr(), op_token, rhs: _expr()) => {
+                Expression::Call {
+                    method: Box::new(Expression::Identifier(Identifier::from(operator_method(op_token.rule)).with_span(self.span(op_token)))),
+                    args: vec![lhs, rhs],
+                    span: self.span(&bit_or),
+                }
             },
-            (_: string_literal, s: _literal_chars()) => {
-                Expression::ByteLiteral(s.into_iter().collect())
+            (bit_xor: bit_xor, lhs: _expr(), op_token, rhs: _expr()) => {
+                Expression::Call {
+                    method: Box::new(Expression::Identifier(Identifier::from(operator_method(op_token.rule)).with_span(self.span(op_token)))),
+                    args: vec![lhs, rhs],
+                    span: self.span(&bit_xor),
+                }
             },
-            (&s: number) => {
-                // If our grammar is correct, we are guarenteed that this will work
-                Expression::Number(s.replace("_", "").parse().unwrap())
+            (bit_and: bit_and, lhs: _expr(), op_token, rhs: _expr()) => {
+                Expression::Call {
+                    method: Box::new(Expression::Identifier(Identifier::from(operator_method(op_token.rule)).with_span(self.span(op_token)))),
+                    args: vec![lhs, rhs],
+                    span: self.span(&bit_and),
+                }
             },
-        }
-
-        _field_access(&self) -> Expression {
-            (target: _identifier(), _: op_access, field: _identifier(), args: _call_args()) => {
+            (shift: shift, lhs: _expr(), op_token, rhs: _expr()) => {
                 Expression::Call {
-                    method: Box::new(Expression::Access {
-                        target: Box::new(Expression::Identifier(target)),
-                        field: field,
-                    }),
-                    args: args,
+                    method: Box::new(Expression::Identifier(Identifier::from(operator_method(op_token.rule)).with_span(self.span(op_token)))),
+                    args: vec![lhs, rhs],
+                    span: self.span(&shift),
                 }
             },
-            (target: _identifier(), _: op_access, field: _identifier()) => {
-                Expression::Access {
-                    target: Box::new(Expression::Identifier(target)),
-                    field: field,
+            (product: product, lhs: _expr(), op_token, rhs: _expr()) => {
+                Expression::Call {
+                    method: Box::new(Expression::Identifier(Identifier::from(operator_method(op_token.rule)).with_span(self.span(op_token)))),
+                    args: vec![lhs, rhs],
+                    span: self.span(&product),
                 }
             },
+            (ident: bool_literal) => {
+                Expression::Identifier(Identifier::from(self.text(&ident)).with_span(self.span(&ident)))
+            },
+            (ident: identifier) => {
+                Expression::Identifier(Identifier::from(self.text(&ident)).with_span(self.span(&ident)))
+            },
+            (ident: self_value) => {
+                Expression::Identifier(Identifier::from(self.text(&ident)).with_span(self.span(&ident)))
+            },
+            (ident: path) => {
+                Expression::Identifier(Identifier::from(self.text(&ident)).with_span(self.span(&ident)))
+            },
+            (m: match_expr, _: expr, value: _expr(), _: block_start, arms: _match_arms()) => {
+                Expression::Match {expr: Box::new(value), arms: arms.into_iter().collect(), span: self.span(&m)}
+            },
+            (literal: string_literal, s: _literal_chars()) => {
+                Expression::ByteLiteral(s.into_iter().collect(), self.span(&literal))
+            },
+            (n: number) => {
+                // If our grammar is correct, we are guarenteed that this will work
+                Expression::Number(self.text(&n).replace("_", "").parse().unwrap(), self.span(&n))
+            },
+            // This should always be last since _conditional() panics if it cannot match
+            (expr: _conditional()) => {
+                expr
+            },
         }
 
         _conditional(&self) -> Expression {
-            (_: expr, expr: _expr(), block: _block(), _: op_else_if, branches: _branches(), _: op_else, else_block: _block()) => {
+            (cond: conditional, _: expr, expr: _expr(), block: _block(), _: op_else_if, branches: _branches(), _: op_else, else_block: _block()) => {
                 Expression::Branch {
                     condition: Box::new(expr),
                     body: block,
-                    otherwise: Some(nest_else_ifs(branches, Some(else_block))),
+                    otherwise: Some(nest_else_ifs(branches, Some(else_block), self.span(&cond))),
+                    span: self.span(&cond),
                 }
             },
-            (_: expr, expr: _expr(), block: _block(), _: op_else_if, branches: _branches()) => {
+            (cond: conditional, _: expr, expr: _expr(), block: _block(), _: op_else_if, branches: _branches()) => {
                 Expression::Branch {
                     condition: Box::new(expr),
                     body: block,
-                    otherwise: Some(nest_else_ifs(branches, None)),
+                    otherwise: Some(nest_else_ifs(branches, None, self.span(&cond))),
+                    span: self.span(&cond),
                 }
             },
-            (_: expr, expr: _expr(), block: _block(), _: op_else, else_block: _block()) => {
+            (cond: conditional, _: expr, expr: _expr(), block: _block(), _: op_else, else_block: _block()) => {
                 Expression::Branch {
                     condition: Box::new(expr),
                     body: block,
                     otherwise: Some(else_block),
+                    span: self.span(&cond),
                 }
             },
-            (_: expr, expr: _expr(), block: _block()) => {
+            (cond: conditional, _: expr, expr: _expr(), block: _block()) => {
                 Expression::Branch {
                     condition: Box::new(expr),
                     body: block,
                     otherwise: None,
+                    span: self.span(&cond),
                 }
             },
         }
 
         _branches(&self) -> VecDeque<Expression> {
             (_: expr, expr: _expr(), block: _block(), _: op_else_if, mut tail: _branches()) => {
+                let span = expr.span();
                 tail.push_front(Expression::Branch {
                     condition: Box::new(expr),
                     body: block,
                     otherwise: None,
+                    span,
                 });
 
                 tail
             },
             (_: expr, expr: _expr(), block: _block()) => {
+                let span = expr.span();
                 let mut queue = VecDeque::new();
                 queue.push_front(Expression::Branch {
                     condition: Box::new(expr),
                     body: block,
                     otherwise: None,
+                    span,
                 });
 
                 queue
             },
         }
 
+        _cast_types(&self) -> VecDeque<TypeDefinition> {
+            (_: op_as, type_def: _type_def(), mut tail: _cast_types()) => {
+                tail.push_front(type_def);
+
+                tail
+            },
+            () => {
+                VecDeque::new()
+            },
+        }
+
         _call_args(&self) -> CallArgs {
             (_: func_args_start, deque: _expr_deque()) => {
                 deque.into_iter().collect()
@@ -325,6 +626,56 @@ impl_rdp! {
             },
         }
 
+        _match_arms(&self) -> VecDeque<MatchArm> {
+            (_: block_end) => {
+                VecDeque::new()
+            },
+            (arm: match_arm, pattern: _match_pattern(), _: op_arrow, _: expr, value: _expr(), mut tail: _match_arms()) => {
+                tail.push_front(MatchArm {pattern, body: vec![Statement::Expression {expr: value}], span: self.span(&arm)});
+
+                tail
+            },
+            (arm: match_arm, pattern: _match_pattern(), _: op_arrow, body: _block(), mut tail: _match_arms()) => {
+                tail.push_front(MatchArm {pattern, body, span: self.span(&arm)});
+
+                tail
+            },
+        }
+
+        _match_pattern(&self) -> MatchPattern {
+            (w: wildcard) => {
+                MatchPattern::Wildcard(self.span(&w))
+            },
+            (n: number) => {
+                MatchPattern::Literal(Expression::Number(self.text(&n).replace("_", "").parse().unwrap(), self.span(&n)))
+            },
+            (b: bool_literal) => {
+                MatchPattern::Literal(Expression::Identifier(Identifier::from(self.text(&b)).with_span(self.span(&b))))
+            },
+            (pattern: variant_pattern, path: _identifier(), _: func_args_start, fields: _field_patterns()) => {
+                MatchPattern::Variant {path, fields: fields.into_iter().collect(), span: self.span(&pattern)}
+            },
+            (pattern: variant_pattern, path: _identifier()) => {
+                MatchPattern::Variant {path, fields: Vec::new(), span: self.span(&pattern)}
+            },
+        }
+
+        _field_patterns(&self) -> VecDeque<Option<Identifier>> {
+            (_: func_args_end) => {
+                VecDeque::new()
+            },
+            (_: wildcard, mut tail: _field_patterns()) => {
+                tail.push_front(None);
+
+                tail
+            },
+            (name: _identifier(), mut tail: _field_patterns()) => {
+                tail.push_front(Some(name));
+
+                tail
+            },
+        }
+
         _block(&self) -> Block {
             (_: block_start, deque: _block_deque()) => {
                 deque.into_iter().collect()
@@ -333,12 +684,21 @@ impl_rdp! {
 
         _block_deque(&self) -> VecDeque<Statement> {
             (_: statement, head: _statement(), mut tail: _block_deque()) => {
+                // A conditional or match at the end of a block is the value of the block, so it
+                // replaces the unit literal that would otherwise be returned
+                let unit_only = tail.len() == 1 &&
+                    matches!(tail[0], Statement::Expression {expr: Expression::UnitLiteral(_)});
+                match head {
+                    Statement::Expression {expr: Expression::Branch {..}} |
+                    Statement::Expression {expr: Expression::Match {..}} if unit_only => tail.clear(),
+                    _ => {},
+                }
                 tail.push_front(head);
 
                 tail
             },
-            (&text: comment, mut tail: _block_deque()) => {
-                tail.push_front(Statement::Comment(text.into()));
+            (text: comment, mut tail: _block_deque()) => {
+                tail.push_front(self.comment_ast(&text));
 
                 tail
             },
@@ -348,10 +708,10 @@ impl_rdp! {
 
                 tail
             },
-            (_: block_end) => {
+            (end: block_end) => {
                 let mut tail = VecDeque::new();
                 // We do this so the last statement in a block always represents its return type
-                tail.push_front(Statement::Expression {expr: Expression::UnitLiteral});
+                tail.push_front(Statement::Expression {expr: Expression::UnitLiteral(self.span(&end))});
                 tail
             },
         }
@@ -360,7 +720,7 @@ impl_rdp! {
             (&c: literal_char, mut tail: _literal_chars()) => {
                 if c.len() == 2 {
                     debug_assert!(c.bytes().next().unwrap() == b'\\');
-                    tail.push_front(match c.bytes().nth(1).unwrap() {
+                    tail.push_front(match c.as_bytes()[1] {
                         b'\\' => b'\\',
                         b'"' => b'"',
                         b'\'' => b'\'',
@@ -385,13 +745,69 @@ impl_rdp! {
         }
 
         _identifier(&self) -> Identifier {
-            (&ident: identifier) => {
-                ident.into()
+            (ident: identifier) => {
+                Identifier::from(self.text(&ident)).with_span(self.span(&ident))
+            },
+            (ident: self_value) => {
+                Identifier::from(self.text(&ident)).with_span(self.span(&ident))
+            },
+            (ident: path) => {
+                Identifier::from(self.text(&ident)).with_span(self.span(&ident))
             },
         }
     }
 }
 
+impl<'input, T: Input<'input>> Rdp<T> {
+    /// Returns the source code matched by the given token
+    fn text(&self, token: &Token<Rule>) -> &'input str {
+        self.input().slice(token.start, token.end)
+    }
+
+    /// Returns the comment matched by the given token
+    fn comment_ast(&self, token: &Token<Rule>) -> Statement {
+        let text = self.text(token);
+        // Line comments include the newline that ends them but their span does not
+        let end = token.start + text.trim_end_matches(['\n', '\r']).len();
+        Statement::Comment(text.into(), self.span_at(token.start, end))
+    }
+
+    /// Returns the location of the source code matched by the given token
+    fn span(&self, token: &Token<Rule>) -> Span {
+        self.span_at(token.start, token.end)
+    }
+
+    fn span_at(&self, start: usize, end: usize) -> Span {
+        let (line, col) = self.input().line_col(start);
+        Span::new(start, end, line as u32, col as u32)
+    }
+}
+
+/// Returns the name of the function that implements the given binary operator
+///
+/// Compound assignment operators like `+=` use the same function as their binary operator.
+fn operator_method(rule: Rule) -> &'static str {
+    match rule {
+        Rule::op_eq => "std::cmp::PartialEq::eq",
+        Rule::op_ne => "std::cmp::PartialEq::ne",
+        Rule::op_ge => "std::cmp::PartialOrd::ge",
+        Rule::op_le => "std::cmp::PartialOrd::le",
+        Rule::op_gt => "std::cmp::PartialOrd::gt",
+        Rule::op_lt => "std::cmp::PartialOrd::lt",
+        Rule::op_bit_or => "std::ops::BitOr::bitor",
+        Rule::op_bit_xor => "std::ops::BitXor::bitxor",
+        Rule::op_bit_and => "std::ops::BitAnd::bitand",
+        Rule::op_shl => "std::ops::Shl::shl",
+        Rule::op_shr => "std::ops::Shr::shr",
+        Rule::op_add | Rule::op_add_assign => "std::ops::Add::add",
+        Rule::op_sub | Rule::op_sub_assign => "std::ops::Sub::sub",
+        Rule::op_mul | Rule::op_mul_assign => "std::ops::Mul::mul",
+        Rule::op_div | Rule::op_div_assign => "std::ops::Div::div",
+        Rule::op_rem | Rule::op_rem_assign => "std::ops::Rem::rem",
+        _ => unreachable!(),
+    }
+}
+
 /// Given a series of branch expressions, this will nest them together
 /// so that they result in a single nested branch expression
 ///
@@ -403,13 +819,17 @@ impl_rdp! {
 ///
 /// Results in:
 /// if foo1 { body1 } else { if foo2 { body2 } else { if foo3 { body3 } else {} } }
-fn nest_else_ifs(branches: VecDeque<Expression>, else_block: Option<Block>) -> Block {
+///
+/// Each nested branch goes on until the end of the entire conditional since the branches after
+/// it end up inside of its else block.
+fn nest_else_ifs(branches: VecDeque<Expression>, else_block: Option<Block>, conditional: Span) -> Block {
     branches.into_iter().rev().fold(else_block, |acc, mut br| {
         Some(vec![Statement::Expression {
             expr: {
                 match br {
-                    Expression::Branch {ref mut otherwise, ..} => {
+                    Expression::Branch {ref mut otherwise, ref mut span, ..} => {
                         *otherwise = acc;
+                        *span = span.to(conditional);
                     },
                     _ => unreachable!(),
                 };
@@ -436,11 +856,23 @@ impl fmt::Display for Rule {
             any => "any character",
 
             unspecified => "`_`",
+            mutable => "`mut`",
             semi => "`;`",
 
             bool_or => "`or`",
             bool_and => "`and`",
             conditional => "`if`",
+            while_loop => "`while`",
+            function => "`fn`",
+            struct_def => "`struct`",
+            enum_def => "`enum`",
+            match_expr => "`match`",
+            wildcard => "`_`",
+            path => "path",
+            impl_block => "`impl`",
+            self_value => "`self`",
+            declaration => "`let`",
+            array_type => "`[`",
             op_else_if => "`else if`",
             op_else => "`else`",
 
@@ -454,8 +886,26 @@ impl fmt::Display for Rule {
             op_le => "`<=`",
             op_gt => "`>`",
             op_lt => "`<`",
+            op_bit_or => "`|`",
+            op_bit_xor => "`^`",
+            op_bit_and => "`&`",
+            op_shl => "`<<`",
+            op_shr => "`>>`",
+            op_add => "`+`",
+            op_sub => "`-`",
+            op_mul => "`*`",
+            op_div => "`/`",
+            op_rem => "`%`",
+            op_add_assign => "`+=`",
+            op_sub_assign => "`-=`",
+            op_mul_assign => "`*=`",
+            op_div_assign => "`/=`",
+            op_rem_assign => "`%=`",
             op_access => "`.`",
+            op_as => "`as`",
             op_declare_type => "`:`",
+            op_return_type => "`->`",
+            op_arrow => "`=>`",
 
             block_start => "`{`",
             block_end => "`}`",
@@ -467,8 +917,10 @@ impl fmt::Display for Rule {
             // this method is meant to be used for formatting errors
             // We don't want to use the "_" wildcard because we want Rust
             // to tell us when a new rule has to be added here
-            statement | assignment | declaration | pattern | array_type | while_loop | comparison |
-            func_call | field_access | expr | soi => unreachable!("{:?}", *self),
+            statement | assignment | field_assignment | compound_assignment | pattern | param | field_def |
+            comparison | bit_or | bit_xor | bit_and | shift | sum | product | func_call | struct_literal |
+            field_init | method_call | field_access | cast | cast_types | expr | variant_def | match_arm |
+            variant_pattern | soi => unreachable!("{:?}", *self),
         })
     }
 }
@@ -517,7 +969,12 @@ mod tests {
             Token::new(Rule::number, 0, 24),
         ]);
 
+        test_parse(r#"-128"#, |p| p.number(), vec![
+            Token::new(Rule::number, 0, 4),
+        ]);
+
         test_fail(r#"_1_000_000"#, |p| p.number());
+        test_fail(r#"- 1"#, |p| p.number());
     }
 
     #[test]
@@ -528,38 +985,64 @@ mod tests {
             Token::new(Rule::op_access, 3, 4),
             Token::new(Rule::identifier, 4, 7),
         ]);
+
+        test_method(r#"q.p.a"#, |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            Expression::Access {
+                target: Box::new(Expression::Access {
+                    target: Box::new(Expression::Identifier(Identifier::from("q"))),
+                    field: Identifier::from("p"),
+                    span: Span::default(),
+                }),
+                field: Identifier::from("a"),
+                span: Span::default(),
+            }
+        );
+
+        test_method(r#"self.p.a = 7;"#, |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::FieldAssignment {
+                target: Identifier::from("self"),
+                fields: vec![Identifier::from("p"), Identifier::from("a")],
+                expr: Expression::Number(7, Span::default()),
+                span: Span::default(),
+            }
+        );
     }
 
     #[test]
+    #[allow(clippy::inconsistent_digit_grouping)]
     fn numeric_literal() {
         test_method(r#"0"#, |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
-            Expression::Number(0)
+            Expression::Number(0, Span::default())
         );
 
         test_method(r#"100"#, |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
-            Expression::Number(100)
+            Expression::Number(100, Span::default())
         );
 
         test_method(r#"1_000_000"#, |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
-            Expression::Number(1_000_000)
+            Expression::Number(1_000_000, Span::default())
         );
 
         test_method(r#"1_000_000_"#, |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
-            Expression::Number(1_000_000_)
+            Expression::Number(1_000_000_, Span::default())
         );
 
         test_method(r#"1____0_0__0______000____"#, |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
-            Expression::Number(1____0_0__0______000____)
+            Expression::Number(1____0_0__0______000____, Span::default())
+        );
+
+        test_method(r#"-1_000"#, |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            Expression::Number(-1_000, Span::default())
         );
     }
 
     #[test]
     fn string_literal_escapes() {
         test_method(r#"b"foo""#, |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
-            Expression::ByteLiteral(b"foo".to_vec()));
+            Expression::ByteLiteral(b"foo".to_vec(), Span::default()));
 
         test_method(r#"b"\\ \" \' \n \r \t \0""#, |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
-            Expression::ByteLiteral(b"\\ \" \' \n \r \t \0".to_vec()));
+            Expression::ByteLiteral(b"\\ \" \' \n \r \t \0".to_vec(), Span::default()));
     }
 
     #[test]
@@ -568,10 +1051,11 @@ mod tests {
             Expression::Call {
                 method: Box::new(Expression::Identifier(Identifier::from("func"))),
                 args: vec![
-                    Expression::Number(1),
-                    Expression::ByteLiteral(b"foo".to_vec()),
-                    Expression::Number(3),
+                    Expression::Number(1, Span::default()),
+                    Expression::ByteLiteral(b"foo".to_vec(), Span::default()),
+                    Expression::Number(3, Span::default()),
                 ],
+                span: Span::default(),
             }
         );
 
@@ -580,12 +1064,14 @@ mod tests {
                 method: Box::new(Expression::Access {
                     target: Box::new(Expression::Identifier(Identifier::from("thing"))),
                     field: Identifier::from("prop"),
+                    span: Span::default(),
                 }),
                 args: vec![
-                    Expression::Number(1),
-                    Expression::ByteLiteral(b"foo".to_vec()),
-                    Expression::Number(3),
+                    Expression::Number(1, Span::default()),
+                    Expression::ByteLiteral(b"foo".to_vec(), Span::default()),
+                    Expression::Number(3, Span::default()),
                 ],
+                span: Span::default(),
             }
         );
     }
@@ -616,9 +1102,10 @@ mod tests {
                     expr: Expression::Call {
                         method: Box::new(Expression::Identifier(Identifier::from("foo"))),
                         args: vec![],
+                        span: Span::default(),
                     },
                 },
-                Statement::Expression {expr: Expression::UnitLiteral},
+                Statement::Expression {expr: Expression::UnitLiteral(Span::default())},
             ])
         );
     }
@@ -654,48 +1141,56 @@ mod tests {
                     expr: Expression::Call {
                         method: Box::new(Expression::Identifier(Identifier::from("operator||"))),
                         args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
                     expr: Expression::Call {
                         method: Box::new(Expression::Identifier(Identifier::from("operator&&"))),
                         args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
                     expr: Expression::Call {
                         method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialEq::eq"))),
                         args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
                     expr: Expression::Call {
                         method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialEq::ne"))),
                         args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
                     expr: Expression::Call {
                         method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::ge"))),
                         args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
                     expr: Expression::Call {
                         method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::le"))),
                         args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
                     expr: Expression::Call {
                         method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::gt"))),
                         args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
                     expr: Expression::Call {
                         method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::lt"))),
                         args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -705,9 +1200,11 @@ mod tests {
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("operator&&"))),
                                 args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                span: Span::default(),
                             },
                             Expression::Identifier(Identifier::from("c")),
                         ],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -717,12 +1214,15 @@ mod tests {
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("operator&&"))),
                                 args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                span: Span::default(),
                             },
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("operator&&"))),
                                 args: vec![Expression::Identifier(Identifier::from("c")), Expression::Identifier(Identifier::from("d"))],
+                                span: Span::default(),
                             },
                         ],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -732,12 +1232,15 @@ mod tests {
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialEq::eq"))),
                                 args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                span: Span::default(),
                             },
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("operator&&"))),
                                 args: vec![Expression::Identifier(Identifier::from("c")), Expression::Identifier(Identifier::from("d"))],
+                                span: Span::default(),
                             },
                         ],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -747,12 +1250,15 @@ mod tests {
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialEq::eq"))),
                                 args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                span: Span::default(),
                             },
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialEq::ne"))),
                                 args: vec![Expression::Identifier(Identifier::from("c")), Expression::Identifier(Identifier::from("d"))],
+                                span: Span::default(),
                             },
                         ],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -762,12 +1268,15 @@ mod tests {
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("operator&&"))),
                                 args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                span: Span::default(),
                             },
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::ge"))),
                                 args: vec![Expression::Identifier(Identifier::from("c")), Expression::Identifier(Identifier::from("d"))],
+                                span: Span::default(),
                             },
                         ],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -777,12 +1286,15 @@ mod tests {
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::le"))),
                                 args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                span: Span::default(),
                             },
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::ge"))),
                                 args: vec![Expression::Identifier(Identifier::from("c")), Expression::Identifier(Identifier::from("d"))],
+                                span: Span::default(),
                             },
                         ],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -792,12 +1304,15 @@ mod tests {
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::lt"))),
                                 args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                span: Span::default(),
                             },
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::gt"))),
                                 args: vec![Expression::Identifier(Identifier::from("c")), Expression::Identifier(Identifier::from("d"))],
+                                span: Span::default(),
                             },
                         ],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -807,9 +1322,11 @@ mod tests {
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("operator&&"))),
                                 args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                span: Span::default(),
                             },
                             Expression::Identifier(Identifier::from("c")),
                         ],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -822,12 +1339,15 @@ mod tests {
                                     Expression::Call {
                                         method: Box::new(Expression::Identifier(Identifier::from("operator&&"))),
                                         args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                        span: Span::default(),
                                     },
                                     Expression::Identifier(Identifier::from("c")),
                                 ],
+                                span: Span::default(),
                             },
                             Expression::Identifier(Identifier::from("d")),
                         ],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -840,12 +1360,15 @@ mod tests {
                                     Expression::Call {
                                         method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialEq::eq"))),
                                         args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                        span: Span::default(),
                                     },
                                     Expression::Identifier(Identifier::from("c")),
                                 ],
+                                span: Span::default(),
                             },
                             Expression::Identifier(Identifier::from("d")),
                         ],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -855,12 +1378,15 @@ mod tests {
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialEq::eq"))),
                                 args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                span: Span::default(),
                             },
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialEq::ne"))),
                                 args: vec![Expression::Identifier(Identifier::from("c")), Expression::Identifier(Identifier::from("d"))],
+                                span: Span::default(),
                             },
                         ],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -870,12 +1396,15 @@ mod tests {
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("operator&&"))),
                                 args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                span: Span::default(),
                             },
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::ge"))),
                                 args: vec![Expression::Identifier(Identifier::from("c")), Expression::Identifier(Identifier::from("d"))],
+                                span: Span::default(),
                             },
                         ],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -885,12 +1414,15 @@ mod tests {
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::le"))),
                                 args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                span: Span::default(),
                             },
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::ge"))),
                                 args: vec![Expression::Identifier(Identifier::from("c")), Expression::Identifier(Identifier::from("d"))],
+                                span: Span::default(),
                             },
                         ],
+                        span: Span::default(),
                     },
                 },
                 Statement::Expression {
@@ -900,15 +1432,18 @@ mod tests {
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::lt"))),
                                 args: vec![Expression::Identifier(Identifier::from("a")), Expression::Identifier(Identifier::from("b"))],
+                                span: Span::default(),
                             },
                             Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("std::cmp::PartialOrd::gt"))),
                                 args: vec![Expression::Identifier(Identifier::from("c")), Expression::Identifier(Identifier::from("d"))],
+                                span: Span::default(),
                             },
                         ],
+                        span: Span::default(),
                     },
                 },
-                Statement::Expression {expr: Expression::UnitLiteral},
+                Statement::Expression {expr: Expression::UnitLiteral(Span::default())},
             ])
         );
     }
@@ -929,11 +1464,13 @@ mod tests {
                             expr: Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("a"))),
                                 args: vec![],
+                                span: Span::default(),
                             },
                         },
-                        Statement::Expression {expr: Expression::UnitLiteral},
+                        Statement::Expression {expr: Expression::UnitLiteral(Span::default())},
                     ],
                     otherwise: None,
+                    span: Span::default(),
                 },
             }
         );
@@ -955,19 +1492,22 @@ mod tests {
                             expr: Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("a"))),
                                 args: vec![],
+                                span: Span::default(),
                             },
                         },
-                        Statement::Expression {expr: Expression::UnitLiteral},
+                        Statement::Expression {expr: Expression::UnitLiteral(Span::default())},
                     ],
                     otherwise: Some(vec![
                         Statement::Expression {
                             expr: Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("b"))),
                                 args: vec![],
+                                span: Span::default(),
                             }
                         },
-                        Statement::Expression {expr: Expression::UnitLiteral},
+                        Statement::Expression {expr: Expression::UnitLiteral(Span::default())},
                     ]),
+                    span: Span::default(),
                 },
             }
         );
@@ -995,9 +1535,10 @@ mod tests {
                             expr: Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("a"))),
                                 args: vec![],
+                                span: Span::default(),
                             },
                         },
-                        Statement::Expression {expr: Expression::UnitLiteral},
+                        Statement::Expression {expr: Expression::UnitLiteral(Span::default())},
                     ],
                     otherwise: Some(vec![
                         Statement::Expression {
@@ -1008,9 +1549,10 @@ mod tests {
                                         expr: Expression::Call {
                                             method: Box::new(Expression::Identifier(Identifier::from("c"))),
                                             args: vec![],
+                                            span: Span::default(),
                                         },
                                     },
-                                    Statement::Expression {expr: Expression::UnitLiteral},
+                                    Statement::Expression {expr: Expression::UnitLiteral(Span::default())},
                                 ],
                                 otherwise: Some(vec![
                                     Statement::Expression {
@@ -1021,25 +1563,30 @@ mod tests {
                                                     expr: Expression::Call {
                                                         method: Box::new(Expression::Identifier(Identifier::from("d"))),
                                                         args: vec![],
+                                                        span: Span::default(),
                                                     },
                                                 },
-                                                Statement::Expression {expr: Expression::UnitLiteral},
+                                                Statement::Expression {expr: Expression::UnitLiteral(Span::default())},
                                             ],
                                             otherwise: Some(vec![
                                                 Statement::Expression {
                                                     expr: Expression::Call {
                                                         method: Box::new(Expression::Identifier(Identifier::from("b"))),
                                                         args: vec![],
+                                                        span: Span::default(),
                                                     }
                                                 },
-                                                Statement::Expression {expr: Expression::UnitLiteral},
+                                                Statement::Expression {expr: Expression::UnitLiteral(Span::default())},
                                             ]),
+                                            span: Span::default(),
                                         },
                                     },
                                 ]),
+                                span: Span::default(),
                             },
                         },
                     ]),
+                    span: Span::default(),
                 },
             }
         );
@@ -1064,9 +1611,10 @@ mod tests {
                             expr: Expression::Call {
                                 method: Box::new(Expression::Identifier(Identifier::from("a"))),
                                 args: vec![],
+                                span: Span::default(),
                             },
                         },
-                        Statement::Expression {expr: Expression::UnitLiteral},
+                        Statement::Expression {expr: Expression::UnitLiteral(Span::default())},
                     ],
                     otherwise: Some(vec![
                         Statement::Expression {
@@ -1077,9 +1625,10 @@ mod tests {
                                         expr: Expression::Call {
                                             method: Box::new(Expression::Identifier(Identifier::from("c"))),
                                             args: vec![],
+                                            span: Span::default(),
                                         },
                                     },
-                                    Statement::Expression {expr: Expression::UnitLiteral},
+                                    Statement::Expression {expr: Expression::UnitLiteral(Span::default())},
                                 ],
                                 otherwise: Some(vec![
                                     Statement::Expression {
@@ -1090,17 +1639,21 @@ mod tests {
                                                     expr: Expression::Call {
                                                         method: Box::new(Expression::Identifier(Identifier::from("d"))),
                                                         args: vec![],
+                                                        span: Span::default(),
                                                     },
                                                 },
-                                                Statement::Expression {expr: Expression::UnitLiteral},
+                                                Statement::Expression {expr: Expression::UnitLiteral(Span::default())},
                                             ],
                                             otherwise: None,
+                                            span: Span::default(),
                                         },
                                     },
                                 ]),
+                                span: Span::default(),
                             },
                         },
                     ]),
+                    span: Span::default(),
                 },
             }
         );
@@ -1119,6 +1672,7 @@ mod tests {
         "#.trim(), |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
             Statement::Declaration {
                 pattern: Pattern::Identifier(Identifier::from("a")),
+                mutable: false,
                 type_def: TypeDefinition::Name {
                     name: Identifier::from("u8"),
                 },
@@ -1126,7 +1680,7 @@ mod tests {
                     condition: Box::new(Expression::Identifier(Identifier::from("foo"))),
                     body: vec![
                         Statement::Expression {
-                            expr: Expression::Number(1),
+                            expr: Expression::Number(1, Span::default()),
                         },
                     ],
                     otherwise: Some(vec![
@@ -1135,20 +1689,625 @@ mod tests {
                                 condition: Box::new(Expression::Identifier(Identifier::from("bar7"))),
                                 body: vec![
                                     Statement::Expression {
-                                        expr: Expression::Number(2)
+                                        expr: Expression::Number(2, Span::default())
                                     },
                                 ],
                                 otherwise: Some(vec![
                                     Statement::Expression {
-                                        expr: Expression::Number(3)
+                                        expr: Expression::Number(3, Span::default())
                                     },
                                 ]),
+                                span: Span::default(),
                             },
                         },
                     ]),
+                    span: Span::default(),
                 }),
+                span: Span::default(),
             }
         );
+
+        // A conditional at the end of a block is the value of that block
+        test_method(r#"
+        if foo {
+            a();
+            if bar { 1 } else { 2 }
+        }
+        "#.trim(), |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::Expression {
+                expr: Expression::Branch {
+                    condition: Box::new(Expression::Identifier(Identifier::from("foo"))),
+                    body: vec![
+                        Statement::Expression {
+                            expr: Expression::Call {
+                                method: Box::new(Expression::Identifier(Identifier::from("a"))),
+                                args: vec![],
+                                span: Span::default(),
+                            },
+                        },
+                        Statement::Expression {
+                            expr: Expression::Branch {
+                                condition: Box::new(Expression::Identifier(Identifier::from("bar"))),
+                                body: vec![
+                                    Statement::Expression {expr: Expression::Number(1, Span::default())},
+                                ],
+                                otherwise: Some(vec![
+                                    Statement::Expression {expr: Expression::Number(2, Span::default())},
+                                ]),
+                                span: Span::default(),
+                            },
+                        },
+                    ],
+                    otherwise: None,
+                    span: Span::default(),
+                },
+            }
+        );
+
+        // A statement after a conditional is still part of the block
+        test_method(r#"
+        if foo {
+            if bar { a(); }
+            b
+        }
+        "#.trim(), |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::Expression {
+                expr: Expression::Branch {
+                    condition: Box::new(Expression::Identifier(Identifier::from("foo"))),
+                    body: vec![
+                        Statement::Expression {
+                            expr: Expression::Branch {
+                                condition: Box::new(Expression::Identifier(Identifier::from("bar"))),
+                                body: vec![
+                                    Statement::Expression {
+                                        expr: Expression::Call {
+                                            method: Box::new(Expression::Identifier(Identifier::from("a"))),
+                                            args: vec![],
+                                            span: Span::default(),
+                                        },
+                                    },
+                                    Statement::Expression {expr: Expression::UnitLiteral(Span::default())},
+                                ],
+                                otherwise: None,
+                                span: Span::default(),
+                            },
+                        },
+                        Statement::Expression {expr: Expression::Identifier(Identifier::from("b"))},
+                    ],
+                    otherwise: None,
+                    span: Span::default(),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn mutable_declarations() {
+        test_method("let mut a: u8 = 1;", |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::Declaration {
+                pattern: Pattern::Identifier(Identifier::from("a")),
+                mutable: true,
+                type_def: TypeDefinition::Name {
+                    name: Identifier::from("u8"),
+                },
+                expr: Some(Expression::Number(1, Span::default())),
+                span: Span::default(),
+            }
+        );
+
+        test_method("let a: u8;", |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::Declaration {
+                pattern: Pattern::Identifier(Identifier::from("a")),
+                mutable: false,
+                type_def: TypeDefinition::Name {
+                    name: Identifier::from("u8"),
+                },
+                expr: None,
+                span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn arithmetic_operators() {
+        fn call(method: &str, args: Vec<Expression>) -> Expression {
+            Expression::Call {
+                method: Box::new(Expression::Identifier(Identifier::from(method))),
+                args,
+                span: Span::default(),
+            }
+        }
+        fn ident(name: &str) -> Expression {
+            Expression::Identifier(Identifier::from(name))
+        }
+
+        // Multiplication binds tighter than addition which binds tighter than comparison
+        test_method("a + b * c == d", |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            call("std::cmp::PartialEq::eq", vec![
+                call("std::ops::Add::add", vec![
+                    ident("a"),
+                    call("std::ops::Mul::mul", vec![ident("b"), ident("c")]),
+                ]),
+                ident("d"),
+            ])
+        );
+
+        // Operators with the same precedence are left associative
+        test_method("a - b % c / d", |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            call("std::ops::Sub::sub", vec![
+                ident("a"),
+                call("std::ops::Div::div", vec![
+                    call("std::ops::Rem::rem", vec![ident("b"), ident("c")]),
+                    ident("d"),
+                ]),
+            ])
+        );
+
+        test_method("(a + b) * 2", |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            call("std::ops::Mul::mul", vec![
+                call("std::ops::Add::add", vec![ident("a"), ident("b")]),
+                Expression::Number(2, Span::default()),
+            ])
+        );
+    }
+
+    #[test]
+    fn bitwise_operators() {
+        fn call(method: &str, args: Vec<Expression>) -> Expression {
+            Expression::Call {
+                method: Box::new(Expression::Identifier(Identifier::from(method))),
+                args,
+                span: Span::default(),
+            }
+        }
+        fn ident(name: &str) -> Expression {
+            Expression::Identifier(Identifier::from(name))
+        }
+
+        // Shifts bind tighter than `&` which binds tighter than `^` and then `|`
+        test_method("a | b ^ c & d << e", |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            call("std::ops::BitOr::bitor", vec![
+                ident("a"),
+                call("std::ops::BitXor::bitxor", vec![
+                    ident("b"),
+                    call("std::ops::BitAnd::bitand", vec![
+                        ident("c"),
+                        call("std::ops::Shl::shl", vec![ident("d"), ident("e")]),
+                    ]),
+                ]),
+            ])
+        );
+
+        // Shifts are not confused with comparisons and bitwise operators are not confused with
+        // boolean operators
+        test_method("a >> b < c || d & e && f", |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            call("operator||", vec![
+                call("std::cmp::PartialOrd::lt", vec![
+                    call("std::ops::Shr::shr", vec![ident("a"), ident("b")]),
+                    ident("c"),
+                ]),
+                call("operator&&", vec![
+                    call("std::ops::BitAnd::bitand", vec![ident("d"), ident("e")]),
+                    ident("f"),
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn casts() {
+        fn cast(expr: Expression, type_name: &str) -> Expression {
+            Expression::Cast {
+                expr: Box::new(expr),
+                type_def: Box::new(TypeDefinition::Name {name: Identifier::from(type_name)}),
+                span: Span::default(),
+            }
+        }
+        fn add(lhs: Expression, rhs: Expression) -> Expression {
+            Expression::Call {
+                method: Box::new(Expression::Identifier(Identifier::from("std::ops::Add::add"))),
+                args: vec![lhs, rhs],
+                span: Span::default(),
+            }
+        }
+        fn ident(name: &str) -> Expression {
+            Expression::Identifier(Identifier::from(name))
+        }
+
+        // Casts bind tighter than binary operators and apply from left to right
+        test_method("a as u16 + b as u8 as u32", |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            add(cast(ident("a"), "u16"), cast(cast(ident("b"), "u8"), "u32"))
+        );
+
+        // A cast inside of a group does not take the type of the cast around the group
+        test_method("(a + b as u8) as u16", |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            cast(add(ident("a"), cast(ident("b"), "u8")), "u16")
+        );
+
+        // `as` must be a whole word
+        test_fail("let b: u16 = a asu16;", |p| p.module());
+    }
+
+    #[test]
+    fn functions() {
+        test_method("fn add(a: u8, mut b: [u8; 2],) -> u16 { a }", |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::Function {
+                name: Identifier::from("add"),
+                params: vec![
+                    Parameter {
+                        pattern: Pattern::Identifier(Identifier::from("a")),
+                        mutable: false,
+                        type_def: TypeDefinition::Name {name: Identifier::from("u8")},
+                    },
+                    Parameter {
+                        pattern: Pattern::Identifier(Identifier::from("b")),
+                        mutable: true,
+                        type_def: TypeDefinition::Array {
+                            type_def: Box::new(TypeDefinition::Name {name: Identifier::from("u8")}),
+                            size: Some(Expression::Number(2, Span::default())),
+                            span: Span::default(),
+                        },
+                    },
+                ],
+                return_type: Some(TypeDefinition::Name {name: Identifier::from("u16")}),
+                body: vec![
+                    Statement::Expression {expr: Expression::Identifier(Identifier::from("a"))},
+                ],
+                span: Span::default(),
+            }
+        );
+
+        test_method("fn nothing() {}", |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::Function {
+                name: Identifier::from("nothing"),
+                params: Vec::new(),
+                return_type: None,
+                body: vec![Statement::Expression {expr: Expression::UnitLiteral(Span::default())}],
+                span: Span::default(),
+            }
+        );
+
+        // Names may start with a keyword as long as they are not the whole keyword
+        test_method("double(format)", |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            Expression::Call {
+                method: Box::new(Expression::Identifier(Identifier::from("double"))),
+                args: vec![Expression::Identifier(Identifier::from("format"))],
+                span: Span::default(),
+            }
+        );
+        test_fail("fn fn() {}", |p| p.module());
+    }
+
+    #[test]
+    fn structs() {
+        test_method("struct Point { x: u8, tag: [u8; 4], }", |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::Struct {
+                name: Identifier::from("Point"),
+                fields: vec![
+                    FieldDefinition {
+                        name: Identifier::from("x"),
+                        type_def: TypeDefinition::Name {name: Identifier::from("u8")},
+                    },
+                    FieldDefinition {
+                        name: Identifier::from("tag"),
+                        type_def: TypeDefinition::Array {
+                            type_def: Box::new(TypeDefinition::Name {name: Identifier::from("u8")}),
+                            size: Some(Expression::Number(4, Span::default())),
+                            span: Span::default(),
+                        },
+                    },
+                ],
+                span: Span::default(),
+            }
+        );
+
+        test_method("struct Empty {}", |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::Struct {name: Identifier::from("Empty"), fields: Vec::new(), span: Span::default()}
+        );
+
+        // Field accesses and nested struct literals are allowed as the values of fields
+        test_method("Line { start: Point { x: 1 }, x: p.x }", |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            Expression::StructLiteral {
+                name: Identifier::from("Line"),
+                fields: vec![
+                    (Identifier::from("start"), Expression::StructLiteral {
+                        name: Identifier::from("Point"),
+                        fields: vec![(Identifier::from("x"), Expression::Number(1, Span::default()))],
+                        span: Span::default(),
+                    }),
+                    (Identifier::from("x"), Expression::Access {
+                        target: Box::new(Expression::Identifier(Identifier::from("p"))),
+                        field: Identifier::from("x"),
+                        span: Span::default(),
+                    }),
+                ],
+                span: Span::default(),
+            }
+        );
+
+        test_method("p.x = f(p.x);", |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::FieldAssignment {
+                target: Identifier::from("p"),
+                fields: vec![Identifier::from("x")],
+                expr: Expression::Call {
+                    method: Box::new(Expression::Identifier(Identifier::from("f"))),
+                    args: vec![Expression::Access {
+                        target: Box::new(Expression::Identifier(Identifier::from("p"))),
+                        field: Identifier::from("x"),
+                        span: Span::default(),
+                    }],
+                    span: Span::default(),
+                },
+                span: Span::default(),
+            }
+        );
+
+        // The block of a conditional is never a struct literal
+        test_method("if a {}", |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::Expression {expr: Expression::Branch {
+                condition: Box::new(Expression::Identifier(Identifier::from("a"))),
+                body: vec![Statement::Expression {expr: Expression::UnitLiteral(Span::default())}],
+                otherwise: None,
+                span: Span::default(),
+            }}
+        );
+    }
+
+    #[test]
+    fn impl_blocks() {
+        test_method(r#"
+        impl Point {
+            // Comments are allowed between methods
+            fn shift(mut self, by: u8) -> Self {
+                self.x = self.x + by;
+                self
+            }
+        }
+        "#.trim(), |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::Impl {
+                type_name: Identifier::from("Point"),
+                methods: vec![
+                    Statement::Function {
+                        name: Identifier::from("shift"),
+                        params: vec![
+                            Parameter {
+                                pattern: Pattern::Identifier(Identifier::from("self")),
+                                mutable: true,
+                                type_def: TypeDefinition::Name {name: Identifier::from("Self")},
+                            },
+                            Parameter {
+                                pattern: Pattern::Identifier(Identifier::from("by")),
+                                mutable: false,
+                                type_def: TypeDefinition::Name {name: Identifier::from("u8")},
+                            },
+                        ],
+                        return_type: Some(TypeDefinition::Name {name: Identifier::from("Self")}),
+                        body: vec![
+                            Statement::FieldAssignment {
+                                target: Identifier::from("self"),
+                                fields: vec![Identifier::from("x")],
+                                expr: Expression::Call {
+                                    method: Box::new(Expression::Identifier(Identifier::from("std::ops::Add::add"))),
+                                    args: vec![
+                                        Expression::Access {
+                                            target: Box::new(Expression::Identifier(Identifier::from("self"))),
+                                            field: Identifier::from("x"),
+                                            span: Span::default(),
+                                        },
+                                        Expression::Identifier(Identifier::from("by")),
+                                    ],
+                                    span: Span::default(),
+                                },
+                                span: Span::default(),
+                            },
+                            Statement::Expression {expr: Expression::Identifier(Identifier::from("self"))},
+                        ],
+                        span: Span::default(),
+                    },
+                ],
+                span: Span::default(),
+            }
+        );
+
+        test_method("self.show(1)", |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            Expression::Call {
+                method: Box::new(Expression::Access {
+                    target: Box::new(Expression::Identifier(Identifier::from("self"))),
+                    field: Identifier::from("show"),
+                    span: Span::default(),
+                }),
+                args: vec![Expression::Number(1, Span::default())],
+                span: Span::default(),
+            }
+        );
+
+        // `self` is a keyword, so it cannot be used as a name
+        test_fail("let self: u8 = 1;", |p| p.module());
+        test_fail("fn f(self: u8) {}", |p| p.module());
+    }
+
+    #[test]
+    fn enums_and_match() {
+        test_method("enum Op { Add, Num(u8), Pair(u8, [u8; 2]), }", |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::Enum {
+                name: Identifier::from("Op"),
+                variants: vec![
+                    VariantDefinition {name: Identifier::from("Add"), fields: vec![]},
+                    VariantDefinition {
+                        name: Identifier::from("Num"),
+                        fields: vec![TypeDefinition::Name {name: Identifier::from("u8")}],
+                    },
+                    VariantDefinition {
+                        name: Identifier::from("Pair"),
+                        fields: vec![
+                            TypeDefinition::Name {name: Identifier::from("u8")},
+                            TypeDefinition::Array {
+                                type_def: Box::new(TypeDefinition::Name {name: Identifier::from("u8")}),
+                                size: Some(Expression::Number(2, Span::default())),
+                                span: Span::default(),
+                            },
+                        ],
+                    },
+                ],
+                span: Span::default(),
+            }
+        );
+
+        test_method("Op::Num(1)", |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            Expression::Call {
+                method: Box::new(Expression::Identifier(Identifier::from("Op::Num"))),
+                args: vec![Expression::Number(1, Span::default())],
+                span: Span::default(),
+            }
+        );
+
+        test_method(r#"
+        match op {
+            Op::Add => 1,
+            Op::Pair(_, b) => { b }
+            3 => 2,
+            _ => 0,
+        }
+        "#.trim(), |p| p.expr(), |p| {p.inc_queue_index(); p._expr()},
+            Expression::Match {
+                expr: Box::new(Expression::Identifier(Identifier::from("op"))),
+                arms: vec![
+                    MatchArm {
+                        pattern: MatchPattern::Variant {path: Identifier::from("Op::Add"), fields: vec![], span: Span::default()},
+                        body: vec![Statement::Expression {expr: Expression::Number(1, Span::default())}],
+                        span: Span::default(),
+                    },
+                    MatchArm {
+                        pattern: MatchPattern::Variant {
+                            path: Identifier::from("Op::Pair"),
+                            fields: vec![None, Some(Identifier::from("b"))],
+                            span: Span::default(),
+                        },
+                        body: vec![Statement::Expression {expr: Expression::Identifier(Identifier::from("b"))}],
+                        span: Span::default(),
+                    },
+                    MatchArm {
+                        pattern: MatchPattern::Literal(Expression::Number(3, Span::default())),
+                        body: vec![Statement::Expression {expr: Expression::Number(2, Span::default())}],
+                        span: Span::default(),
+                    },
+                    MatchArm {
+                        pattern: MatchPattern::Wildcard(Span::default()),
+                        body: vec![Statement::Expression {expr: Expression::Number(0, Span::default())}],
+                        span: Span::default(),
+                    },
+                ],
+                span: Span::default(),
+            }
+        );
+
+        // A statement after a match is still part of the block
+        test_method(r#"
+        if foo {
+            match a { _ => {} }
+            b
+        }
+        "#.trim(), |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::Expression {
+                expr: Expression::Branch {
+                    condition: Box::new(Expression::Identifier(Identifier::from("foo"))),
+                    body: vec![
+                        Statement::Expression {
+                            expr: Expression::Match {
+                                expr: Box::new(Expression::Identifier(Identifier::from("a"))),
+                                arms: vec![
+                                    MatchArm {
+                                        pattern: MatchPattern::Wildcard(Span::default()),
+                                        body: vec![Statement::Expression {expr: Expression::UnitLiteral(Span::default())}],
+                                        span: Span::default(),
+                                    },
+                                ],
+                                span: Span::default(),
+                            },
+                        },
+                        Statement::Expression {expr: Expression::Identifier(Identifier::from("b"))},
+                    ],
+                    otherwise: None,
+                    span: Span::default(),
+                },
+            }
+        );
+
+        // Only names and `_` can be used for the fields of a variant in a pattern
+        test_fail("match a { Op::Add(1) => {} }", |p| p.module());
+    }
+
+    #[test]
+    fn compound_assignment() {
+        test_method("a *= b + 1;", |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::CompoundAssignment {
+                lhs: Identifier::from("a"),
+                fields: Vec::new(),
+                operator: Identifier::from("std::ops::Mul::mul"),
+                expr: Expression::Call {
+                    method: Box::new(Expression::Identifier(Identifier::from("std::ops::Add::add"))),
+                    args: vec![Expression::Identifier(Identifier::from("b")), Expression::Number(1, Span::default())],
+                    span: Span::default(),
+                },
+                span: Span::default(),
+            }
+        );
+
+        test_method("self.p.n -= 1;", |p| p.statement(), |p| {p.inc_queue_index(); p._statement()},
+            Statement::CompoundAssignment {
+                lhs: Identifier::from("self"),
+                fields: vec![Identifier::from("p"), Identifier::from("n")],
+                operator: Identifier::from("std::ops::Sub::sub"),
+                expr: Expression::Number(1, Span::default()),
+                span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn spans() {
+        let input = "// hello\nlet x: [u8; 2] = b\"ab\";\nwhile a == b {\n    stdout.print(x);\n}\n";
+        let mut parser = parser_from(input);
+        assert!(parser.module(), "Parsing failed");
+        let module = parser.module_ast();
+
+        let text = |span: Span| &input[span.start..span.end];
+        let position = |span: Span| (span.line, span.col);
+
+        let comment = &module.body[0];
+        assert_eq!(text(comment.span()), "// hello");
+        assert_eq!(position(comment.span()), (1, 1));
+
+        let declaration = &module.body[1];
+        assert_eq!(text(declaration.span()), "let x: [u8; 2] = b\"ab\";");
+        assert_eq!(position(declaration.span()), (2, 1));
+        match *declaration {
+            Statement::Declaration {ref pattern, ref type_def, expr: Some(ref expr), ..} => {
+                assert_eq!(position(pattern.span()), (2, 5));
+                assert_eq!(text(type_def.span()), "[u8; 2]");
+                assert_eq!(text(expr.span()), "b\"ab\"");
+                assert_eq!(position(expr.span()), (2, 18));
+            },
+            _ => unreachable!(),
+        }
+
+        let while_loop = &module.body[2];
+        assert_eq!(position(while_loop.span()), (3, 1));
+        match *while_loop {
+            Statement::WhileLoop {ref condition, ref body, ..} => {
+                assert_eq!(text(condition.span()), "a == b");
+                assert_eq!(text(body[0].span()), "stdout.print(x)");
+                assert_eq!(position(body[0].span()), (4, 5));
+                match body[0] {
+                    Statement::Expression {expr: Expression::Call {ref method, ref args, ..}} => {
+                        assert_eq!(text(method.span()), "stdout.print");
+                        assert_eq!(position(args[0].span()), (4, 18));
+                    },
+                    _ => unreachable!(),
+                }
+            },
+            _ => unreachable!(),
+        }
     }
 
     fn test_parse<F>(input: &'static str, parse: F, tokens: Vec<Token<Rule>>)
@@ -1182,7 +2341,7 @@ mod tests {
         assert!(parser.queue().is_empty(), "Queue was not empty despite expecting to fail");
     }
 
-    fn parser_from(s: &'static str) -> Rdp<StringInput> {
+    fn parser_from(s: &'static str) -> Rdp<StringInput<'static>> {
         Rdp::new(StringInput::new(s))
     }
 }
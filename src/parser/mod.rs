@@ -1,7 +1,10 @@
 mod ast;
 mod grammar;
 mod error;
+mod span;
+mod recovery;
 
 pub use self::ast::*;
 pub use self::grammar::*;
 pub use self::error::*;
+pub use self::span::*;
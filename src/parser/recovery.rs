@@ -0,0 +1,145 @@
+/// Removes the statement that contains the given position so that parsing can continue after it
+///
+/// Statements end at a `;` or at the `}` of the block they are in. A statement that contains a
+/// block (like a while loop) is removed along with its entire block. Everything removed is
+/// replaced with spaces (line breaks are kept) so that the positions of the rest of the source
+/// stay the same.
+///
+/// Returns false if there was nothing left to remove.
+pub fn skip_statement(source: &mut String, pos: usize) -> bool {
+    let boundaries = boundaries(source);
+
+    let start = boundaries.iter().rev().find(|&&(i, _)| i < pos).map_or(0, |&(i, _)| i + 1);
+    let mut end = source.len();
+    for (index, &(i, c)) in boundaries.iter().enumerate() {
+        if i < pos {
+            continue;
+        }
+
+        end = match c {
+            ';' => i + 1,
+            '{' => matching_block_end(&boundaries[index..]).map_or(source.len(), |end| end + 1),
+            // The end of the block is only removed if the statement itself is empty
+            '}' if source[start..i].trim().is_empty() => i + 1,
+            _ => i,
+        };
+        break;
+    }
+
+    if source[start..end].trim().is_empty() {
+        return false;
+    }
+
+    let blank: String = source[start..end].chars()
+        .map(|c| if c == '\n' || c == '\r' { c.to_string() } else { " ".repeat(c.len_utf8()) })
+        .collect();
+    source.replace_range(start..end, &blank);
+    true
+}
+
+/// Returns the position of the `}` that closes the `{` at the start of the given boundaries
+fn matching_block_end(boundaries: &[(usize, char)]) -> Option<usize> {
+    let mut depth = 0;
+    for &(i, c) in boundaries {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+/// Returns the position of every `;`, `{` and `}` that is not part of a string or a comment
+fn boundaries(source: &str) -> Vec<(usize, char)> {
+    let bytes = source.as_bytes();
+    let mut boundaries = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+            },
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            },
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                // Block comments can be nested
+                let mut depth = 0;
+                while i < bytes.len() {
+                    if bytes[i..].starts_with(b"/*") {
+                        depth += 1;
+                        i += 1;
+                    }
+                    else if bytes[i..].starts_with(b"*/") {
+                        depth -= 1;
+                        i += 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    i += 1;
+                }
+            },
+            c @ b';' | c @ b'{' | c @ b'}' => boundaries.push((i, c as char)),
+            _ => {},
+        }
+        i += 1;
+    }
+    boundaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skip(source: &str, pos: usize) -> Option<String> {
+        let mut source = source.to_owned();
+        if skip_statement(&mut source, pos) {
+            Some(source)
+        }
+        else {
+            None
+        }
+    }
+
+    #[test]
+    fn skips_to_semicolon() {
+        assert_eq!(skip("a();\nb(;\nc();", 7).unwrap(), "a();\n   \nc();");
+        // Missing semicolons take the next statement with them
+        assert_eq!(skip("a()\nb();\nc();", 4).unwrap(), "   \n    \nc();");
+    }
+
+    #[test]
+    fn skips_to_end_of_block() {
+        assert_eq!(skip("while a { b( }\nc();", 13).unwrap(), "while a {    }\nc();");
+        // A stray `}` is removed by itself
+        assert_eq!(skip("a();\n}\nb();", 5).unwrap(), "a();\n \nb();");
+    }
+
+    #[test]
+    fn skips_blocks() {
+        assert_eq!(skip("while a b { c(); }\nd();", 8).unwrap(), "                  \nd();");
+    }
+
+    #[test]
+    fn ignores_strings_and_comments() {
+        assert_eq!(skip("a(b\";}\" c); // ;\nd();", 8).unwrap(), "            // ;\nd();");
+        assert_eq!(skip("a( /* ; /* } */ */ b);", 19).unwrap(), "                      ");
+    }
+
+    #[test]
+    fn stops_when_nothing_is_left() {
+        assert_eq!(skip("a();\n  ", 7), None);
+    }
+}
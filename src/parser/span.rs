@@ -0,0 +1,76 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// The location of a piece of source code
+///
+/// Spans never affect equality or hashing. Two syntax trees are equal if they represent the same
+/// code no matter where that code came from. The default span is used for code that does not
+/// come from the source at all, like the names of built-in items.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Span {
+    /// The byte offset of the first character
+    pub start: usize,
+    /// The byte offset just past the last character
+    pub end: usize,
+    /// The line of the first character, starting at 1
+    pub line: u32,
+    /// The column of the first character, starting at 1
+    pub col: u32,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: u32, col: u32) -> Span {
+        Span {start, end, line, col}
+    }
+
+    /// Returns a span that starts at the start of this span and ends at the end of the other span
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end,
+            ..self
+        }
+    }
+
+    /// Returns true if this span does not refer to any code in the source
+    pub fn is_unknown(&self) -> bool {
+        self.line == 0
+    }
+}
+
+impl PartialEq for Span {
+    fn eq(&self, _other: &Span) -> bool {
+        true
+    }
+}
+
+impl Eq for Span {}
+
+impl Hash for Span {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {} col {}", self.line, self.col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joined_spans() {
+        let start = Span::new(4, 7, 2, 1);
+        let end = Span::new(10, 12, 2, 7);
+        let span = start.to(end);
+        assert_eq!((span.start, span.end, span.line, span.col), (4, 12, 2, 1));
+    }
+
+    #[test]
+    fn spans_do_not_affect_equality() {
+        assert_eq!(Span::new(0, 1, 1, 1), Span::new(5, 9, 3, 2));
+        assert!(Span::default().is_unknown());
+        assert!(!Span::new(0, 1, 1, 1).is_unknown());
+    }
+}
@@ -12,10 +12,17 @@ pub fn populate_scope(scope: &mut ScopeStack) {
     // https://github.com/brain-lang/brain/issues/37
     scope.push_scope();
 
-    array::define_array(scope);
-
     let bool_type = boolean::define_boolean(scope);
     let u8_type = u8::define_u8(scope, bool_type);
+    integer::define_integer(scope, "u16", 2, false, bool_type);
+    integer::define_integer(scope, "u32", 4, false, bool_type);
+    integer::define_integer(scope, "i8", 1, true, bool_type);
+    integer::define_integer(scope, "i16", 2, true, bool_type);
+    // The tape is never large enough to need more than two cells to index it
+    let usize_type = integer::define_integer(scope, "usize", 2, false, bool_type);
+
+    cast::define_casts(scope);
+    array::define_array(scope, usize_type);
 
     stdio::define_stdin(scope, u8_type);
     stdio::define_stdout(scope);